@@ -3,6 +3,7 @@ pub mod packages;
 mod typst;
 
 pub use typst::{
-    autocomplete_at, compile_to_pdf, compile_to_svg, install_package, resolve_click,
-    take_missing_packages, CompletionItem,
+    autocomplete_at, compile_to_pdf, compile_to_svg, count_font_faces, document_counts,
+    document_headings, document_labels, install_fonts, install_package, page_svg,
+    render_style_sample, resolve_click, take_missing_packages, CompletionItem,
 };
@@ -4,11 +4,16 @@ use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use typst::diag::{FileError, FileResult};
-use typst::foundations::Bytes;
+use typst::foundations::{Bytes, NativeElement, Selector, Smart, StyleChain};
 use typst::layout::PagedDocument;
+use typst::math::EquationElem;
+use typst::model::{CiteGroup, FigureElem, FigureKind, HeadingElem, TableElem};
+use typst::text::Font;
 use typst::syntax::package::PackageSpec;
 use typst::syntax::{FileId, Source, Span, VirtualPath};
 use typst_as_lib::file_resolver::FileResolver;
+use crate::utils::lint::DocLabels;
+use crate::utils::stats::ElementCounts;
 use typst_as_lib::{
     typst_kit_options::TypstKitFontOptions, TypstAsLibError, TypstEngine,
 };
@@ -43,7 +48,7 @@ fn decode_image(base64_data: &str) -> Option<Vec<u8>> {
 
 /// Mutable inputs served to the persistent engine: the main source, any
 /// additional project `.typ` files (resolved by `#include`/`#import`), plus the
-/// bibliography and referenced images.
+/// bibliography files and referenced images.
 #[derive(Default)]
 struct ResolverState {
     main: Option<Source>,
@@ -127,6 +132,30 @@ struct CompilerSession {
     /// Last successfully compiled document, retained for IDE features
     /// (label/citation completion) and preview click-to-jump.
     last_doc: RefCell<Option<PagedDocument>>,
+    /// User font files installed on top of the embedded fonts.
+    fonts: Vec<Bytes>,
+}
+
+/// Build an engine serving `state` through a [`DynamicResolver`], with the
+/// embedded fonts plus any user `fonts` (which take precedence in the book).
+fn build_engine(state: &Arc<Mutex<ResolverState>>, main_id: FileId, fonts: &[Bytes]) -> TypstEngine {
+    let resolver = DynamicResolver {
+        state: Arc::clone(state),
+        main_id,
+    };
+
+    let mut builder = TypstEngine::builder()
+        .add_file_resolver(resolver)
+        .fonts(fonts.iter().cloned())
+        .search_fonts_with(
+            TypstKitFontOptions::default()
+                .include_system_fonts(false) // not available under WASM
+                .include_embedded_fonts(true),
+        );
+    // Retain the incremental cache across compilations instead of evicting
+    // everything after each run (the crate default is `Some(0)`).
+    builder.comemo_evict_max_age(Some(10));
+    builder.build()
 }
 
 impl CompilerSession {
@@ -135,25 +164,13 @@ impl CompilerSession {
         // `.main_file(&str)` path did, so relative paths resolve identically.
         let main_id = Source::detached(String::new()).id();
         let state = Arc::new(Mutex::new(ResolverState::default()));
-        let resolver = DynamicResolver {
-            state: Arc::clone(&state),
-            main_id,
-        };
-
-        let mut builder = TypstEngine::builder().add_file_resolver(resolver).search_fonts_with(
-            TypstKitFontOptions::default()
-                .include_system_fonts(false) // not available under WASM
-                .include_embedded_fonts(true),
-        );
-        // Retain the incremental cache across compilations instead of evicting
-        // everything after each run (the crate default is `Some(0)`).
-        builder.comemo_evict_max_age(Some(10));
 
         Self {
-            engine: builder.build(),
+            engine: build_engine(&state, main_id, &[]),
             state,
             main_id,
             last_doc: RefCell::new(None),
+            fonts: Vec::new(),
         }
     }
 
@@ -161,14 +178,18 @@ impl CompilerSession {
     ///
     /// `extra_files` are additional project `.typ` files keyed by their virtual
     /// path (e.g. `chapter1.typ`), reachable from the main file via
-    /// `#include`/`#import`.
+    /// `#include`/`#import`. `bibliographies` are `(name, content)` pairs
+    /// served under their own name (`refs.yml`, `zotero.bib`, …); Typst picks
+    /// the parser from the extension. `assets` are arbitrary data files keyed
+    /// by their virtual path, served to `#read`/`#csv`/`#json`/… as-is.
     fn set_inputs(
         &self,
         source: &str,
         settings: &str,
-        bibliography: Option<&str>,
+        bibliographies: &[(String, String)],
         images: &HashMap<String, String>,
         extra_files: &[(String, String)],
+        assets: &HashMap<String, Vec<u8>>,
     ) {
         let mut state = self.state.lock().expect("resolver state poisoned");
         // Reset the per-compile record of unsatisfied packages (installed
@@ -196,16 +217,19 @@ impl CompilerSession {
             state.sources.insert(id, Source::new(id, content.clone()));
         }
         state.binaries.clear();
-        if let Some(bib) = bibliography {
+        for (name, content) in bibliographies {
             state
                 .binaries
-                .insert(file_id("refs.yml"), Bytes::new(bib.as_bytes().to_vec()));
+                .insert(file_id(name), Bytes::new(content.as_bytes().to_vec()));
         }
         for (id, data) in images {
             if let Some(bytes) = decode_image(data) {
                 state.binaries.insert(file_id(id), Bytes::new(bytes));
             }
         }
+        for (path, bytes) in assets {
+            state.binaries.insert(file_id(path), Bytes::new(bytes.clone()));
+        }
     }
 
     fn compile(&self) -> Result<PagedDocument, String> {
@@ -291,20 +315,22 @@ fn format_error(err: TypstAsLibError, main: Option<&Source>, preamble_lines: usi
 /// Compile Typst source to a combined multi-page SVG string.
 ///
 /// `extra_files` are additional project `.typ` files reachable from `source`
-/// via `#include`/`#import`.
+/// via `#include`/`#import`; `bibliographies` and `assets` are served under
+/// their virtual path.
 pub fn compile_to_svg(
     source: &str,
     settings: &str,
-    bibliography: Option<&str>,
+    bibliographies: &[(String, String)],
     images: &HashMap<String, String>,
     extra_files: &[(String, String)],
+    assets: &HashMap<String, Vec<u8>>,
 ) -> Result<String, String> {
     if source.trim().is_empty() {
         return Err("Source code is empty".to_string());
     }
 
     with_session(|session| {
-        session.set_inputs(source, settings, bibliography, images, extra_files);
+        session.set_inputs(source, settings, bibliographies, images, extra_files, assets);
         let doc = session.compile()?;
 
         let mut combined = String::new();
@@ -322,20 +348,22 @@ pub fn compile_to_svg(
 /// Compile Typst source to PDF bytes.
 ///
 /// `extra_files` are additional project `.typ` files reachable from `source`
-/// via `#include`/`#import`.
+/// via `#include`/`#import`; `bibliographies` and `assets` are served under
+/// their virtual path.
 pub fn compile_to_pdf(
     source: &str,
     settings: &str,
-    bibliography: Option<&str>,
+    bibliographies: &[(String, String)],
     images: &HashMap<String, String>,
     extra_files: &[(String, String)],
+    assets: &HashMap<String, Vec<u8>>,
 ) -> Result<Vec<u8>, String> {
     if source.trim().is_empty() {
         return Err("Source code is empty".to_string());
     }
 
     with_session(|session| {
-        session.set_inputs(source, settings, bibliography, images, extra_files);
+        session.set_inputs(source, settings, bibliographies, images, extra_files, assets);
         let doc = session.compile()?;
         typst_pdf::pdf(&doc, &typst_pdf::PdfOptions::default())
             .map_err(|e| format!("PDF generation error: {e:?}"))
@@ -358,6 +386,36 @@ pub fn install_package(spec: &PackageSpec, files: Vec<(String, Vec<u8>)>) {
     typst::comemo::evict(0);
 }
 
+/// Number of font faces in a font file (0 if it isn't a readable TTF/OTF/TTC).
+pub fn count_font_faces(data: &[u8]) -> usize {
+    Font::iter(Bytes::new(data.to_vec())).count()
+}
+
+/// Install user font files into the persistent engine and return how many
+/// new files were added. Fonts are fixed when an engine is built, so this
+/// rebuilds it around the same resolver state: installed packages and the
+/// current inputs are kept. Files already installed, or without any readable
+/// face, are ignored.
+pub fn install_fonts(files: Vec<Vec<u8>>) -> usize {
+    SESSION.with(|cell| {
+        let mut slot = cell.borrow_mut();
+        let session = slot.get_or_insert_with(CompilerSession::new);
+        let mut added = 0;
+        for data in files {
+            let bytes = Bytes::new(data);
+            if session.fonts.contains(&bytes) || Font::iter(bytes.clone()).next().is_none() {
+                continue;
+            }
+            session.fonts.push(bytes);
+            added += 1;
+        }
+        if added > 0 {
+            session.engine = build_engine(&session.state, session.main_id, &session.fonts);
+        }
+        added
+    })
+}
+
 /// Drain the set of package specs the last compile could not resolve.
 pub fn take_missing_packages() -> Vec<PackageSpec> {
     with_session(|session| {
@@ -491,6 +549,195 @@ pub fn resolve_click(page: usize, x_pt: f64, y_pt: f64) -> Option<usize> {
     })
 }
 
+/// A heading of the last compiled document, for the outline panel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineHeading {
+    pub level: usize,
+    /// Plain text of the heading body.
+    pub text: String,
+    /// 1-based page the heading is on.
+    pub page: usize,
+    /// Where the heading is written: the project file (`None` for the main
+    /// file) and the byte offset in it, in user coordinates. `None` for
+    /// headings produced outside the project, e.g. by a package template.
+    pub source: Option<(Option<String>, usize)>,
+}
+
+/// Every heading of the last successfully compiled document, in order.
+pub fn document_headings() -> Vec<OutlineHeading> {
+    with_session(|session| {
+        let doc = session.last_doc.borrow();
+        let Some(doc) = doc.as_ref() else {
+            return Vec::new();
+        };
+        let state = session.state.lock().expect("resolver state poisoned");
+        doc.introspector
+            .query(&Selector::Elem(HeadingElem::elem(), None))
+            .iter()
+            .filter_map(|content| {
+                let heading = content.to_packed::<HeadingElem>()?;
+                let page = doc.introspector.page(content.location()?).get();
+                Some(OutlineHeading {
+                    // The level is synthesized during layout, so no styles are needed.
+                    level: heading.resolve_level(StyleChain::default()).get(),
+                    text: heading.body.plain_text().trim().to_string(),
+                    page,
+                    source: span_source(&state, session.main_id, content.span()),
+                })
+            })
+            .collect()
+    })
+}
+
+/// Map an element's span to its project file and user-coordinate offset.
+fn span_source(state: &ResolverState, main_id: FileId, span: Span) -> Option<(Option<String>, usize)> {
+    let id = span.id()?;
+    if id == main_id {
+        let start = state.main.as_ref()?.range(span)?.start;
+        // Elements in the settings preamble have no place in the editor.
+        return Some((None, start.checked_sub(state.preamble_bytes)?));
+    }
+    let start = state.sources.get(&id)?.range(span)?.start;
+    let name = id.vpath().as_rootless_path().to_string_lossy().into_owned();
+    Some((Some(name), start))
+}
+
+/// Page count and element tallies of the last successfully compiled
+/// document, read from its introspector, so figures or citations produced by
+/// loops, templates or included files are all counted.
+pub fn document_counts() -> Option<(usize, ElementCounts)> {
+    with_session(|session| {
+        let doc = session.last_doc.borrow();
+        let doc = doc.as_ref()?;
+        let query = |elem| doc.introspector.query(&Selector::Elem(elem, None));
+        // Kinds and block flags are set on the elements themselves (synthesized
+        // or written), so no styles are needed.
+        let styles = StyleChain::default();
+        let mut counts = ElementCounts::default();
+        for content in query(FigureElem::elem()).iter() {
+            let Some(figure) = content.to_packed::<FigureElem>() else {
+                continue;
+            };
+            match figure.kind(styles) {
+                Smart::Custom(FigureKind::Elem(elem)) if elem == TableElem::elem() => counts.tables += 1,
+                _ => counts.figures += 1,
+            }
+        }
+        counts.equations = query(EquationElem::elem())
+            .iter()
+            .filter_map(|c| c.to_packed::<EquationElem>())
+            .filter(|eq| eq.block(styles))
+            .count();
+        // Citations are located through the groups Typst forms from them.
+        let keys: Vec<_> = query(CiteGroup::elem())
+            .iter()
+            .filter_map(|c| c.to_packed::<CiteGroup>())
+            .flat_map(|group| group.children.iter().map(|cite| cite.key).collect::<Vec<_>>())
+            .collect();
+        counts.citations = keys.len();
+        counts.references = keys.iter().collect::<HashSet<_>>().len();
+        Some((doc.pages.len(), counts))
+    })
+}
+
+/// Labels attached to elements of the last successfully compiled document,
+/// and the bibliography keys it cites, for the reference linter.
+pub fn document_labels() -> Option<DocLabels> {
+    with_session(|session| {
+        let doc = session.last_doc.borrow();
+        let doc = doc.as_ref()?;
+        let state = session.state.lock().expect("resolver state poisoned");
+        let labels = doc
+            .introspector
+            .all()
+            .filter_map(|content| {
+                let label = content.label()?.resolve().to_string();
+                Some((label, span_source(&state, session.main_id, content.span())))
+            })
+            .collect();
+        let cited = doc
+            .introspector
+            .query(&Selector::Elem(CiteGroup::elem(), None))
+            .iter()
+            .filter_map(|c| c.to_packed::<CiteGroup>())
+            .flat_map(|group| group.children.iter().map(|cite| cite.key.resolve().to_string()).collect::<Vec<_>>())
+            .collect();
+        Some(DocLabels { labels, cited })
+    })
+}
+
+/// Render a single page (0-based) of the last successfully compiled document
+/// as a standalone SVG, e.g. for rasterizing one page to PNG.
+pub fn page_svg(page: usize) -> Option<String> {
+    with_session(|session| {
+        let doc = session.last_doc.borrow();
+        doc.as_ref()?.pages.get(page).map(typst_svg::svg)
+    })
+}
+
+/// Bibliography used by [`render_style_sample`]: a journal article and a book.
+const SAMPLE_BIB: &str = "sample-article:
+  type: article
+  title: Literate Programming
+  author: Knuth, Donald E.
+  date: 1984
+  page-range: 97-111
+  parent:
+    type: periodical
+    title: The Computer Journal
+    volume: 27
+sample-book:
+  type: book
+  title: A Document Preparation System
+  author: [Lamport, Leslie]
+  date: 1994
+  publisher: Addison-Wesley
+";
+
+/// Render two citations and their bibliography in `style` (a built-in name
+/// or the path of a `.csl` asset) as one SVG page, for the style picker.
+///
+/// Runs on the persistent engine but restores the project's inputs and last
+/// document afterwards, so IDE features keep pointing at the real project.
+pub fn render_style_sample(style: &str, assets: &HashMap<String, Vec<u8>>) -> Result<String, String> {
+    let source = format!(
+        "#set page(width: 12cm, height: auto, margin: 8pt)\n\
+         #set text(size: 9pt)\n\
+         Typesetting matters @sample-article, especially for long documents @sample-book.\n\
+         #bibliography(\"__style-sample.yml\", title: none, style: {style:?})"
+    );
+    let styles: HashMap<String, Vec<u8>> = assets
+        .iter()
+        .filter(|(path, _)| path.to_ascii_lowercase().ends_with(".csl"))
+        .map(|(path, bytes)| (path.clone(), bytes.clone()))
+        .collect();
+    let bibs = [("__style-sample.yml".to_string(), SAMPLE_BIB.to_string())];
+
+    with_session(|session| {
+        let saved = {
+            let mut state = session.state.lock().expect("resolver state poisoned");
+            (
+                state.main.take(),
+                std::mem::take(&mut state.sources),
+                std::mem::take(&mut state.binaries),
+                state.preamble_lines,
+                state.preamble_bytes,
+            )
+        };
+        let last_doc = session.last_doc.take();
+
+        session.set_inputs(&source, "", &bibs, &HashMap::new(), &[], &styles);
+        let result = session
+            .compile()
+            .map(|doc| doc.pages.first().map(typst_svg::svg).unwrap_or_default());
+
+        let mut state = session.state.lock().expect("resolver state poisoned");
+        (state.main, state.sources, state.binaries, state.preamble_lines, state.preamble_bytes) = saved;
+        session.last_doc.replace(last_doc);
+        result
+    })
+}
+
 /// Whether a package's files are already installed (entrypoint or otherwise).
 #[cfg(test)]
 pub fn is_package_installed(spec: &PackageSpec) -> bool {
@@ -508,11 +755,16 @@ mod tests {
         HashMap::new()
     }
 
+    fn no_assets() -> HashMap<String, Vec<u8>> {
+        HashMap::new()
+    }
+
     const NO_FILES: &[(String, String)] = &[];
+    const NO_BIBS: &[(String, String)] = &[];
 
     #[test]
     fn empty_source_is_rejected() {
-        assert!(compile_to_svg("   ", "", None, &no_images(), NO_FILES).is_err());
+        assert!(compile_to_svg("   ", "", NO_BIBS, &no_images(), NO_FILES, &no_assets()).is_err());
     }
 
     #[test]
@@ -520,9 +772,9 @@ mod tests {
         // Both compilations share the per-thread session, so this exercises the
         // reused engine + retained comemo cache. The second result must reflect
         // the new source, not a cached copy of the first (the key R1 risk).
-        let first = compile_to_svg("= Hello", "", None, &no_images(), NO_FILES).expect("first compile");
+        let first = compile_to_svg("= Hello", "", NO_BIBS, &no_images(), NO_FILES, &no_assets()).expect("first compile");
         let second =
-            compile_to_svg("= Goodbye", "", None, &no_images(), NO_FILES).expect("second compile");
+            compile_to_svg("= Goodbye", "", NO_BIBS, &no_images(), NO_FILES, &no_assets()).expect("second compile");
 
         assert!(!first.is_empty());
         assert!(!second.is_empty());
@@ -531,16 +783,25 @@ mod tests {
 
         // Recompiling the original source is deterministic.
         let first_again =
-            compile_to_svg("= Hello", "", None, &no_images(), NO_FILES).expect("recompile");
+            compile_to_svg("= Hello", "", NO_BIBS, &no_images(), NO_FILES, &no_assets()).expect("recompile");
         assert_eq!(first, first_again);
     }
 
+    #[test]
+    fn page_svg_renders_single_pages_of_last_document() {
+        compile_to_svg("A\n#pagebreak()\nB", "", NO_BIBS, &no_images(), NO_FILES, &no_assets()).expect("compile");
+        let first = page_svg(0).expect("first page");
+        assert!(first.starts_with("<svg"));
+        assert!(page_svg(1).is_some());
+        assert_eq!(page_svg(2), None);
+    }
+
     #[test]
     fn source_error_is_prefixed_with_line_and_column() {
         // `#undefined_fn()` on the second line is an unknown-variable error whose
         // span resolves against the main source, so the message must carry a
         // `line:col` prefix (here line 2).
-        let err = compile_to_svg("Hello\n#undefined_fn()", "", None, &no_images(), NO_FILES)
+        let err = compile_to_svg("Hello\n#undefined_fn()", "", NO_BIBS, &no_images(), NO_FILES, &no_assets())
             .expect_err("undefined function should fail");
         assert!(
             err.starts_with("2:"),
@@ -555,9 +816,10 @@ mod tests {
         let err = compile_to_svg(
             "Hello\n#undefined_fn()",
             "#set page(numbering: \"1\")",
-            None,
+            NO_BIBS,
             &no_images(),
             NO_FILES,
+            &no_assets(),
         )
         .expect_err("undefined function should fail");
         assert!(
@@ -570,26 +832,155 @@ mod tests {
     fn bibliography_resolves_on_persistent_engine() {
         let bib = "key:\n  type: article\n  title: Title\n  author: Author\n  date: 2020\n";
         let source = "Cite @key. #bibliography(\"refs.yml\")";
+        let bibs = [("refs.yml".to_string(), bib.to_string())];
         let svg =
-            compile_to_svg(source, "", Some(bib), &no_images(), NO_FILES).expect("bib compile");
+            compile_to_svg(source, "", &bibs, &no_images(), NO_FILES, &no_assets()).expect("bib compile");
         assert!(!svg.is_empty());
     }
 
+    #[test]
+    fn biblatex_and_yaml_bibliographies_resolve_by_name() {
+        let yaml = "hay:\n  type: book\n  title: From YAML\n  author: Author\n  date: 2020\n";
+        let bib = "@book{tex, title = {From BibLaTeX}, author = {Knuth, Donald}, year = {1984}}";
+        let bibs = [
+            ("refs.yml".to_string(), yaml.to_string()),
+            ("lit/zotero.bib".to_string(), bib.to_string()),
+        ];
+        let source = "@hay @tex\n#bibliography((\"refs.yml\", \"lit/zotero.bib\"))";
+        compile_to_svg(source, "", &bibs, &no_images(), NO_FILES, &no_assets())
+            .expect("mixed-format bibliography");
+
+        // A .bib file is parsed as BibLaTeX, so YAML content under that name fails.
+        let wrong = [("refs.bib".to_string(), yaml.to_string())];
+        assert!(compile_to_svg("@hay #bibliography(\"refs.bib\")", "", &wrong, &no_images(), NO_FILES, &no_assets())
+            .is_err());
+    }
+
     #[test]
     fn included_file_is_resolved() {
         // The main file pulls in a second project file via `#include`; the
         // resolver must serve it from `extra_files` by its virtual path.
         let main = "= Main\n#include \"chapter1.typ\"";
         let extra = vec![("chapter1.typ".to_string(), "== Chapter One".to_string())];
-        let svg = compile_to_svg(main, "", None, &no_images(), &extra).expect("multi-file compile");
+        let svg = compile_to_svg(main, "", NO_BIBS, &no_images(), &extra, &no_assets()).expect("multi-file compile");
         assert!(!svg.is_empty());
 
         // Without the extra file the include fails, proving it was really used.
-        let err = compile_to_svg(main, "", None, &no_images(), NO_FILES)
+        let err = compile_to_svg(main, "", NO_BIBS, &no_images(), NO_FILES, &no_assets())
             .expect_err("missing included file should fail");
         assert!(err.to_lowercase().contains("chapter1"), "got: {err}");
     }
 
+    #[test]
+    fn data_assets_are_readable_by_path() {
+        let main = "#let rows = csv(\"data/sales.csv\")\n#rows.at(1).at(1)\n#json(\"cfg.json\").title";
+        let mut assets = no_assets();
+        assets.insert("data/sales.csv".to_string(), b"region,total\nnorth,42\n".to_vec());
+        assets.insert("cfg.json".to_string(), br#"{"title": "Q1"}"#.to_vec());
+        compile_to_svg(main, "", NO_BIBS, &no_images(), NO_FILES, &assets).expect("asset compile");
+
+        let err = compile_to_svg(main, "", NO_BIBS, &no_images(), NO_FILES, &no_assets())
+            .expect_err("missing asset should fail");
+        assert!(err.contains("sales.csv"), "got: {err}");
+    }
+
+    #[test]
+    fn style_sample_renders_builtin_and_uploaded_styles() {
+        let main = "= Project";
+        compile_to_svg(main, "#set text(size: 12pt)", NO_BIBS, &no_images(), NO_FILES, &no_assets()).expect("compile");
+
+        assert!(render_style_sample("ieee", &no_assets()).expect("builtin").contains("<svg"));
+        let mut assets = no_assets();
+        assets.insert("styles/test.csl".to_string(), crate::utils::csl::TEST_CSL.as_bytes().to_vec());
+        assert!(render_style_sample("styles/test.csl", &assets).expect("uploaded").contains("<svg"));
+        let err = render_style_sample("styles/missing.csl", &no_assets()).expect_err("missing style");
+        assert!(err.contains("missing.csl"), "got: {err}");
+
+        // The project's inputs are back in place for IDE features.
+        with_session(|s| {
+            let state = s.state.lock().unwrap();
+            assert_eq!(state.main.as_ref().map(|m| m.text()), Some("#set text(size: 12pt)\n= Project"));
+            assert_eq!(state.preamble_lines, 1);
+        });
+    }
+
+    #[test]
+    fn document_headings_carry_level_page_and_source() {
+        let main = "= Intro\n#pagebreak()\n#include \"chapter.typ\"";
+        let extra = vec![("chapter.typ".to_string(), "Text\n== Details *here*".to_string())];
+        compile_to_svg(main, "#set heading(numbering: \"1.\")", NO_BIBS, &no_images(), &extra, &no_assets())
+            .expect("compile");
+        assert_eq!(
+            document_headings(),
+            vec![
+                OutlineHeading {
+                    level: 1,
+                    text: "Intro".to_string(),
+                    page: 1,
+                    source: Some((None, 0)),
+                },
+                OutlineHeading {
+                    level: 2,
+                    text: "Details here".to_string(),
+                    page: 2,
+                    source: Some((Some("chapter.typ".to_string()), 5)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn document_counts_include_generated_and_included_elements() {
+        let bibs = [(
+            "refs.yml".to_string(),
+            "knuth:\n  type: book\n  title: TAOCP\n  author: Knuth, Donald\n  date: 1968\n\
+             lamport:\n  type: book\n  title: LaTeX\n  author: Lamport, Leslie\n  date: 1986\n"
+                .to_string(),
+        )];
+        let main = "= Intro\n@knuth @knuth $x$\n#for i in range(2) [#figure(rect(), caption: [#i])]\n\
+                    #figure(table[a])\n$ x + y $\n#pagebreak()\n#include \"ch.typ\"\n#bibliography(\"refs.yml\")";
+        let extra = vec![("ch.typ".to_string(), "== Method\nSee #cite(<lamport>).\n$ z $".to_string())];
+        compile_to_svg(main, "", &bibs, &no_images(), &extra, &no_assets()).expect("compile");
+
+        let (pages, counts) = document_counts().expect("counts");
+        assert_eq!(pages, 2);
+        assert_eq!(
+            counts,
+            ElementCounts {
+                figures: 2,
+                tables: 1,
+                equations: 2,
+                citations: 3,
+                references: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn document_labels_carry_sources_and_citations() {
+        let bibs = [(
+            "refs.yml".to_string(),
+            "knuth:\n  type: book\n  title: TAOCP\n  author: Knuth, Donald\n  date: 1968\n".to_string(),
+        )];
+        let main = "= Intro <intro>\n#for i in range(2) [#figure(rect()) <loop>]\n@intro @knuth\n#bibliography(\"refs.yml\")";
+        compile_to_svg(main, "#set heading(numbering: \"1.\")", &bibs, &no_images(), NO_FILES, &no_assets())
+            .expect("compile");
+
+        let doc = document_labels().expect("labels");
+        let mut labels: Vec<_> = doc.labels.iter().map(|(name, source)| (name.as_str(), source.clone())).collect();
+        labels.sort();
+        let loop_at = main.find("#figure").unwrap() + 1;
+        assert_eq!(
+            labels,
+            vec![
+                ("intro", Some((None, 0))),
+                ("loop", Some((None, loop_at))),
+                ("loop", Some((None, loop_at))),
+            ]
+        );
+        assert_eq!(doc.cited, vec!["knuth".to_string()]);
+    }
+
     #[test]
     fn missing_package_recorded_then_resolves_after_install() {
         let spec: PackageSpec = "@preview/testpkg:0.1.0".parse().unwrap();
@@ -597,7 +988,7 @@ mod tests {
 
         // First compile: the package isn't installed → compile fails and the
         // resolver records the missing spec for the retry loop to fetch.
-        let _ = compile_to_svg(main, "", None, &no_images(), NO_FILES)
+        let _ = compile_to_svg(main, "", NO_BIBS, &no_images(), NO_FILES, &no_assets())
             .expect_err("missing package should fail");
         let missing = take_missing_packages();
         assert!(
@@ -620,7 +1011,7 @@ mod tests {
         install_package(&spec, files);
         assert!(is_package_installed(&spec));
 
-        let svg = compile_to_svg(main, "", None, &no_images(), NO_FILES)
+        let svg = compile_to_svg(main, "", NO_BIBS, &no_images(), NO_FILES, &no_assets())
             .expect("compiles after package install");
         assert!(!svg.is_empty());
     }
@@ -646,7 +1037,7 @@ mod tests {
             None
         }
 
-        compile_to_svg("Hello world.", "", None, &no_images(), NO_FILES).expect("compile");
+        compile_to_svg("Hello world.", "", NO_BIBS, &no_images(), NO_FILES, &no_assets()).expect("compile");
         let (x, y) = with_session(|s| {
             let doc = s.last_doc.borrow();
             let frame = &doc.as_ref().unwrap().pages[0].frame;
@@ -678,7 +1069,7 @@ mod tests {
         // reference `@i` — the label `intro` must be offered.
         let settings = "#set heading(numbering: \"1.\")";
         let doc_src = "= Introduction <intro>\n\nSee @intro.";
-        compile_to_svg(doc_src, settings, None, &no_images(), NO_FILES)
+        compile_to_svg(doc_src, settings, NO_BIBS, &no_images(), NO_FILES, &no_assets())
             .expect("compile with label");
 
         let src = "= Introduction <intro>\n\nSee @i";
@@ -691,6 +1082,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn user_fonts_install_once_and_engine_keeps_working() {
+        let font = typst_assets::fonts().next().expect("embedded font").to_vec();
+        assert!(count_font_faces(&font) >= 1);
+        assert_eq!(count_font_faces(b"not a font"), 0);
+
+        assert_eq!(install_fonts(vec![b"not a font".to_vec()]), 0);
+        assert_eq!(install_fonts(vec![font.clone()]), 1);
+        // Re-installing the same file (e.g. restore after import) is a no-op.
+        assert_eq!(install_fonts(vec![font]), 0);
+        compile_to_svg("= After fonts", "", NO_BIBS, &no_images(), NO_FILES, &no_assets()).expect("compile");
+    }
+
     #[test]
     fn bundled_templates_compile() {
         // Every template the picker offers must compile out of the box.
@@ -699,14 +1103,14 @@ mod tests {
         let ieee = include_str!("../../templates/ieee.typ");
         let ieee_bib = include_str!("../../examples/refs.yml");
 
-        assert!(!compile_to_svg(blank, "", None, &no_images(), NO_FILES)
+        assert!(!compile_to_svg(blank, "", NO_BIBS, &no_images(), NO_FILES, &no_assets())
             .expect("blank template")
             .is_empty());
-        assert!(!compile_to_svg(article, "", None, &no_images(), NO_FILES)
+        assert!(!compile_to_svg(article, "", NO_BIBS, &no_images(), NO_FILES, &no_assets())
             .expect("article template")
             .is_empty());
         // The IEEE template cites entries from the bundled bibliography.
-        assert!(!compile_to_svg(ieee, "", Some(ieee_bib), &no_images(), NO_FILES)
+        assert!(!compile_to_svg(ieee, "", &[("refs.yml".to_string(), ieee_bib.to_string())], &no_images(), NO_FILES, &no_assets())
             .expect("ieee template")
             .is_empty());
     }
@@ -0,0 +1,428 @@
+use crate::utils::assets::{csv_preview, format_size, load_snippet, normalize_asset_path, AssetStorage};
+use crate::utils::csl::{inspect_csl, is_csl_path};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use std::collections::HashMap;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+/// Rows shown in the CSV preview table.
+const PREVIEW_ROWS: usize = 20;
+/// Characters shown in the plain-text preview.
+const PREVIEW_CHARS: usize = 4000;
+
+/// Reload every asset from IndexedDB into the compiler cache.
+async fn reload_assets(set_asset_cache: WriteSignal<HashMap<String, Vec<u8>>>) {
+    match AssetStorage::new().list_all().await {
+        Ok(list) => set_asset_cache.set(list.into_iter().collect()),
+        Err(e) => log::error!("Failed to load assets: {}", e),
+    }
+}
+
+/// Project assets drawer: data files (CSV, JSON, TOML, XML, YAML, text)
+/// readable from Typst by their path, with upload, rename, preview and delete.
+#[component]
+pub fn AssetDrawer(
+    show: ReadSignal<bool>,
+    set_show: WriteSignal<bool>,
+    asset_cache: ReadSignal<HashMap<String, Vec<u8>>>,
+    set_asset_cache: WriteSignal<HashMap<String, Vec<u8>>>,
+) -> impl IntoView {
+    let (uploading, set_uploading) = signal(false);
+    let (toast_message, set_toast_message) = signal(Option::<String>::None);
+    let (dragover, set_dragover) = signal(false);
+    // Folder prefix for uploads, so files land at e.g. `data/sales.csv`.
+    let folder = RwSignal::new(String::new());
+    // Asset being renamed and the path typed for it.
+    let renaming = RwSignal::new(Option::<String>::None);
+    let rename_input = RwSignal::new(String::new());
+    let preview = RwSignal::new(Option::<String>::None);
+
+    let paths = Memo::new(move |_| {
+        let mut paths: Vec<String> = asset_cache.with(|c| c.keys().cloned().collect());
+        paths.sort();
+        paths
+    });
+
+    // Store every picked/dropped file under `<folder>/<file name>`; an existing
+    // asset at the same path is replaced.
+    let upload_files = move |files: Vec<web_sys::File>| {
+        if files.is_empty() {
+            return;
+        }
+        let prefix = folder.get_untracked();
+        set_uploading.set(true);
+        spawn_local(async move {
+            let storage = AssetStorage::new();
+            let mut stored = Vec::new();
+            for file in files {
+                let path = match normalize_asset_path(&format!("{}/{}", prefix, file.name())) {
+                    Ok(path) => path,
+                    Err(e) => {
+                        set_toast_message.set(Some(format!("{}: {}", file.name(), e)));
+                        continue;
+                    }
+                };
+                let Ok(buffer) = JsFuture::from(file.array_buffer()).await else { continue };
+                let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+                // Catch styles Typst can't load now rather than at compile time.
+                if is_csl_path(&path) {
+                    if let Err(e) = inspect_csl(&bytes) {
+                        set_toast_message.set(Some(format!("{}: {}", file.name(), e)));
+                        continue;
+                    }
+                }
+                match storage.store(&path, &bytes).await {
+                    Ok(()) => stored.push(path),
+                    Err(e) => {
+                        log::error!("Upload failed: {}", e);
+                        set_toast_message.set(Some(format!("Upload failed: {}", e)));
+                    }
+                }
+            }
+            if !stored.is_empty() {
+                set_toast_message.set(Some(format!("Uploaded: {}", stored.join(", "))));
+            }
+            reload_assets(set_asset_cache).await;
+            set_uploading.set(false);
+        });
+    };
+
+    let handle_upload = move |ev: web_sys::Event| {
+        let Some(input) = ev.target()
+            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+        else { return };
+        if let Some(list) = input.files() {
+            upload_files((0..list.length()).filter_map(|i| list.get(i)).collect());
+        }
+        // Allow picking the same file again after a delete.
+        input.set_value("");
+    };
+
+    let handle_drop = move |ev: web_sys::DragEvent| {
+        ev.prevent_default();
+        set_dragover.set(false);
+        if let Some(list) = ev.data_transfer().and_then(|dt| dt.files()) {
+            upload_files((0..list.length()).filter_map(|i| list.get(i)).collect());
+        }
+    };
+
+    let copy_snippet = move |path: String| {
+        let code = load_snippet(&path);
+        let Some(window) = web_sys::window() else { return };
+        let promise = window.navigator().clipboard().write_text(&code);
+        spawn_local(async move {
+            match JsFuture::from(promise).await {
+                Ok(_) => set_toast_message.set(Some(format!("Copied: {}", code))),
+                Err(e) => log::error!("Failed to copy: {:?}", e),
+            }
+        });
+    };
+
+    // Move an asset to a new path (store under the new key, drop the old one).
+    let confirm_rename = move || {
+        let Some(old) = renaming.get_untracked() else { return };
+        let new = match normalize_asset_path(&rename_input.get_untracked()) {
+            Ok(new) => new,
+            Err(e) => {
+                set_toast_message.set(Some(e));
+                return;
+            }
+        };
+        renaming.set(None);
+        if new == old {
+            return;
+        }
+        if asset_cache.with_untracked(|c| c.contains_key(&new)) {
+            set_toast_message.set(Some(format!("An asset named {} already exists", new)));
+            return;
+        }
+        let Some(bytes) = asset_cache.with_untracked(|c| c.get(&old).cloned()) else { return };
+        spawn_local(async move {
+            let storage = AssetStorage::new();
+            let result = match storage.store(&new, &bytes).await {
+                Ok(()) => storage.delete(&old).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => {
+                    if preview.get_untracked().as_deref() == Some(old.as_str()) {
+                        preview.set(Some(new.clone()));
+                    }
+                    set_toast_message.set(Some(format!("Renamed to {}", new)));
+                }
+                Err(e) => set_toast_message.set(Some(format!("Rename failed: {}", e))),
+            }
+            reload_assets(set_asset_cache).await;
+        });
+    };
+
+    let delete_asset = move |path: String| {
+        spawn_local(async move {
+            match AssetStorage::new().delete(&path).await {
+                Ok(()) => {
+                    if preview.get_untracked().as_deref() == Some(path.as_str()) {
+                        preview.set(None);
+                    }
+                    set_toast_message.set(Some(format!("Deleted: {}", path)));
+                }
+                Err(e) => {
+                    log::error!("Delete failed: {}", e);
+                    set_toast_message.set(Some(format!("Delete failed: {}", e)));
+                }
+            }
+            reload_assets(set_asset_cache).await;
+        });
+    };
+
+    // Preview of the selected asset: a table for CSV, text otherwise.
+    let preview_view = move || {
+        let path = preview.get()?;
+        let bytes = asset_cache.with(|c| c.get(&path).cloned())?;
+        let body = match String::from_utf8(bytes) {
+            Ok(text) if path.to_ascii_lowercase().ends_with(".csv") => {
+                let rows = csv_preview(&text, PREVIEW_ROWS);
+                view! {
+                    <div class="overflow-x-auto max-h-80">
+                        <table class="table table-xs table-zebra">
+                            <tbody>
+                                {rows
+                                    .into_iter()
+                                    .map(|row| {
+                                        view! {
+                                            <tr>
+                                                {row.into_iter().map(|cell| view! { <td>{cell}</td> }).collect::<Vec<_>>()}
+                                            </tr>
+                                        }
+                                    })
+                                    .collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    </div>
+                }
+                .into_any()
+            }
+            Ok(text) => {
+                let shown: String = text.chars().take(PREVIEW_CHARS).collect();
+                view! {
+                    <pre class="text-xs bg-base-200 rounded p-2 max-h-80 overflow-auto whitespace-pre-wrap">{shown}</pre>
+                }
+                .into_any()
+            }
+            Err(_) => view! {
+                <p class="text-sm text-base-content/60">"Binary file — no preview available. Load it with #read(..., encoding: none)."</p>
+            }
+            .into_any(),
+        };
+        Some(view! {
+            <div class="mt-6">
+                <div class="flex items-center justify-between mb-2">
+                    <h3 class="font-semibold truncate" title=path.clone()>{path.clone()}</h3>
+                    <button
+                        class="btn btn-xs btn-circle btn-ghost"
+                        aria-label="Close preview"
+                        on:click=move |_| preview.set(None)
+                    >
+                        <span class="icon-[lucide--x] text-sm"></span>
+                    </button>
+                </div>
+                {body}
+            </div>
+        })
+    };
+
+    view! {
+        <Show when=move || show.get()>
+            <div class="drawer-overlay" on:click=move |_| set_show.set(false)></div>
+
+            <div class="drawer-container" role="dialog" aria-modal="true" aria-label="Project assets">
+                <div class="drawer-header">
+                    <div class="flex items-center gap-2">
+                        <span class="icon-[lucide--database] text-2xl text-primary"></span>
+                        <h2 class="text-xl font-bold">"Assets"</h2>
+                    </div>
+                    <button
+                        class="btn btn-sm btn-circle btn-ghost"
+                        aria-label="Close assets"
+                        on:click=move |_| set_show.set(false)
+                    >
+                        <span class="icon-[lucide--x] text-xl"></span>
+                    </button>
+                </div>
+
+                <div class="drawer-content">
+                    <div
+                        class="upload-zone"
+                        class:dragover=move || dragover.get()
+                        on:dragover=move |ev: web_sys::DragEvent| {
+                            ev.prevent_default();
+                            set_dragover.set(true);
+                        }
+                        on:dragleave=move |_| set_dragover.set(false)
+                        on:drop=handle_drop
+                    >
+                        <label class="input input-sm w-full max-w-xs mb-3">
+                            <span class="icon-[lucide--folder] opacity-60"></span>
+                            <input
+                                type="text"
+                                placeholder="Folder (optional), e.g. data"
+                                prop:value=move || folder.get()
+                                on:input=move |ev| folder.set(event_target_value(&ev))
+                            />
+                        </label>
+                        <label class="btn btn-primary gap-2 cursor-pointer">
+                            <input
+                                type="file"
+                                multiple
+                                class="hidden"
+                                on:change=handle_upload
+                                disabled=uploading
+                            />
+                            <Show
+                                when=move || uploading.get()
+                                fallback=move || view! {
+                                    <span class="icon-[lucide--upload] text-xl"></span>
+                                    "Upload Files"
+                                }
+                            >
+                                <span class="loading loading-spinner"></span>
+                                "Uploading..."
+                            </Show>
+                        </label>
+                        <p class="text-sm text-base-content/60 mt-2">
+                            "CSV, JSON, TOML, XML, YAML or text files. Load them with #csv(\"path\"), #json(\"path\"), #read(\"path\")…"
+                        </p>
+                    </div>
+
+                    <div class="mt-6">
+                        <h3 class="text-lg font-semibold mb-4">
+                            {move || format!("Assets ({})", paths.with(|p| p.len()))}
+                        </h3>
+
+                        <ul class="flex flex-col gap-2">
+                            <For
+                                each=move || paths.get()
+                                key=|path| path.clone()
+                                children=move |path: String| {
+                                    let size = {
+                                        let path = path.clone();
+                                        move || asset_cache.with(|c| c.get(&path).map(|b| format_size(b.len())).unwrap_or_default())
+                                    };
+                                    let is_renaming = {
+                                        let path = path.clone();
+                                        move || renaming.get().as_deref() == Some(path.as_str())
+                                    };
+                                    let (p_preview, p_copy, p_rename, p_delete) =
+                                        (path.clone(), path.clone(), path.clone(), path.clone());
+
+                                    view! {
+                                        <li class="flex flex-col gap-1 rounded-box bg-base-200 p-2">
+                                            <Show
+                                                when=is_renaming
+                                                fallback={
+                                                    let path = path.clone();
+                                                    move || view! {
+                                                        <div class="flex items-center gap-2 min-w-0">
+                                                            <span class="icon-[lucide--file] opacity-60 shrink-0"></span>
+                                                            <code class="text-sm truncate" title=path.clone()>{path.clone()}</code>
+                                                            <span class="text-xs text-base-content/60 ml-auto shrink-0 tabular-nums">{size.clone()}</span>
+                                                        </div>
+                                                    }
+                                                }
+                                            >
+                                                <div class="join w-full">
+                                                    <input
+                                                        type="text"
+                                                        class="input input-sm join-item flex-1"
+                                                        aria-label="New asset path"
+                                                        prop:value=move || rename_input.get()
+                                                        on:input=move |ev| rename_input.set(event_target_value(&ev))
+                                                        on:keydown=move |ev: web_sys::KeyboardEvent| {
+                                                            if ev.key() == "Enter" {
+                                                                confirm_rename();
+                                                            }
+                                                        }
+                                                    />
+                                                    <button class="btn btn-sm btn-primary join-item" on:click=move |_| confirm_rename()>
+                                                        "Save"
+                                                    </button>
+                                                    <button class="btn btn-sm join-item" on:click=move |_| renaming.set(None)>
+                                                        "Cancel"
+                                                    </button>
+                                                </div>
+                                            </Show>
+                                            <div class="flex gap-1 justify-end">
+                                                <button
+                                                    class="btn btn-xs btn-ghost gap-1"
+                                                    on:click=move |_| preview.set(Some(p_preview.clone()))
+                                                    title="Preview"
+                                                >
+                                                    <span class="icon-[lucide--eye] text-sm"></span>
+                                                    "Preview"
+                                                </button>
+                                                <button
+                                                    class="btn btn-xs btn-success gap-1"
+                                                    on:click=move |_| copy_snippet(p_copy.clone())
+                                                    title="Copy loading code"
+                                                    aria-label="Copy asset code"
+                                                >
+                                                    <span class="icon-[lucide--copy] text-sm"></span>
+                                                    "Copy"
+                                                </button>
+                                                <button
+                                                    class="btn btn-xs btn-ghost gap-1"
+                                                    on:click=move |_| {
+                                                        rename_input.set(p_rename.clone());
+                                                        renaming.set(Some(p_rename.clone()));
+                                                    }
+                                                    title="Rename or move"
+                                                >
+                                                    <span class="icon-[lucide--pencil] text-sm"></span>
+                                                    "Rename"
+                                                </button>
+                                                <button
+                                                    class="btn btn-xs btn-error gap-1"
+                                                    on:click=move |_| delete_asset(p_delete.clone())
+                                                    title="Delete asset"
+                                                    aria-label="Delete asset"
+                                                >
+                                                    <span class="icon-[lucide--trash-2] text-sm"></span>
+                                                    "Delete"
+                                                </button>
+                                            </div>
+                                        </li>
+                                    }
+                                }
+                            />
+                        </ul>
+
+                        <Show when=move || paths.with(|p| p.is_empty())>
+                            <div class="text-center py-12 text-base-content/50">
+                                <span class="icon-[lucide--database] text-5xl block mb-4 opacity-30"></span>
+                                <p>"No assets uploaded yet"</p>
+                                <p class="text-sm">"Upload a CSV or JSON file to use its data in your document"</p>
+                            </div>
+                        </Show>
+
+                        {preview_view}
+                    </div>
+                </div>
+            </div>
+
+            <Show when=move || toast_message.get().is_some()>
+                <div class="toast toast-top toast-end">
+                    <div class="alert alert-success">
+                        <span>{toast_message.get().unwrap_or_default()}</span>
+                        <button
+                            class="btn btn-xs btn-circle btn-ghost"
+                            aria-label="Dismiss notification"
+                            on:click=move |_| set_toast_message.set(None)
+                        >
+                            "×"
+                        </button>
+                    </div>
+                </div>
+            </Show>
+        </Show>
+    }
+}
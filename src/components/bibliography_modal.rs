@@ -0,0 +1,778 @@
+use crate::utils::bib_convert::{bib_to_yaml, yaml_to_bib, Conversion};
+use crate::utils::bib_entries::{
+    check_bibliography, citation_counts, cross_file_duplicates, delete_entry, entry_types,
+    upsert_entry, validate_draft, BibCheck, EntryDraft,
+};
+use crate::utils::bibliography::{format_mismatch, validate_bib_name, BibFile, BibFormat};
+use crate::utils::download::download_bytes;
+use crate::utils::project::TypstFile;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+/// A destructive action waiting for confirmation in the manager's dialog.
+#[derive(Clone)]
+enum Confirm {
+    DeleteFile(usize),
+    /// An entry key and how often the project cites it.
+    DeleteEntry(String, usize),
+    /// Converted `.bib` imports whose `.yml` name is already taken.
+    ReplaceConverted(Vec<Converted>),
+}
+
+#[derive(Clone)]
+struct Converted {
+    source: String,
+    name: String,
+    conversion: Conversion,
+}
+
+/// Bibliography manager: the project's bibliography files (Hayagriva YAML and
+/// BibLaTeX `.bib`) as tabs, each served to the compiler under its own name.
+/// Entries are listed in a table with add/edit/delete forms, validated while
+/// typing, and counted against the citations in the project `files`.
+#[component]
+pub fn BibliographyModal(
+    show: ReadSignal<bool>,
+    set_show: WriteSignal<bool>,
+    bibliographies: RwSignal<Vec<BibFile>>,
+    files: RwSignal<Vec<TypstFile>>,
+) -> impl IntoView {
+    let selected = RwSignal::new(0usize);
+    // Name typed for a new file; `Some` while the "add" row is open.
+    let new_name = RwSignal::new(Option::<String>::None);
+    let (message, set_message) = signal(Option::<String>::None);
+    // Outcome of the last import/export: a summary line and the dropped fields.
+    let report = RwSignal::new(Option::<(String, Vec<String>)>::None);
+    let confirm = RwSignal::new(Option::<Confirm>::None);
+
+    let current = move || bibliographies.with(|b| b.get(selected.get()).cloned());
+    let format = move || bibliographies.with(|b| b.get(selected.get()).and_then(|f| BibFormat::from_name(&f.name)));
+    // Memoized so typing in the textarea doesn't rebuild the tab strip.
+    let names = Memo::new(move |_| bibliographies.with(|b| b.iter().map(|f| f.name.clone()).collect::<Vec<_>>()));
+
+    // `true` shows the raw file instead of the entries table.
+    let source_view = RwSignal::new(false);
+    // Entry form: `None` closed, `Some(None)` adding, `Some(Some(key))` editing.
+    let editing = RwSignal::new(Option::<Option<String>>::None);
+    let draft = RwSignal::new(EntryDraft::default());
+
+    // Parsing only runs while the dialog is open.
+    let check = Memo::new(move |_| {
+        if !show.get() {
+            return BibCheck::default();
+        }
+        current().map(|f| check_bibliography(&f.name, &f.content)).unwrap_or_default()
+    });
+    let shared_keys = Memo::new(move |_| {
+        if !show.get() {
+            return Vec::new();
+        }
+        let checks: Vec<_> = bibliographies.with(|b| {
+            b.iter().map(|f| (f.name.clone(), check_bibliography(&f.name, &f.content))).collect()
+        });
+        cross_file_duplicates(&checks)
+    });
+    let usage = Memo::new(move |_| {
+        if !show.get() {
+            return Default::default();
+        }
+        files.with(|f| citation_counts(f.iter().map(|file| file.content.as_str())))
+    });
+    let draft_errors = Memo::new(move |_| {
+        let original = editing.get().flatten();
+        check.with(|c| draft.with(|d| validate_draft(d, &c.entries, original.as_deref())))
+    });
+
+    // Write a form result back into the selected file.
+    let apply = move |result: Result<String, String>| match result {
+        Ok(content) => {
+            let index = selected.get_untracked();
+            bibliographies.update(|b| {
+                if let Some(f) = b.get_mut(index) {
+                    f.content = content;
+                }
+            });
+            set_message.set(None);
+            true
+        }
+        Err(e) => {
+            set_message.set(Some(e));
+            false
+        }
+    };
+
+    let save_entry = move || {
+        let Some(file) = current() else { return };
+        let original = editing.get_untracked().flatten();
+        if apply(upsert_entry(&file, original.as_deref(), &draft.get_untracked())) {
+            editing.set(None);
+        }
+    };
+
+    let remove_entry = move |key: String| {
+        let Some(file) = current() else { return };
+        apply(delete_entry(&file, &key));
+    };
+
+    let add_file = move |name: String, content: String| {
+        let index = bibliographies.with_untracked(|b| b.len());
+        bibliographies.update(|b| b.push(BibFile { name, content }));
+        selected.set(index);
+    };
+
+    let confirm_new = move || {
+        let Some(name) = new_name.get_untracked() else { return };
+        match bibliographies.with_untracked(|b| validate_bib_name(&name, b)) {
+            Ok(name) => {
+                add_file(name, String::new());
+                new_name.set(None);
+                set_message.set(None);
+            }
+            Err(e) => set_message.set(Some(e)),
+        }
+    };
+
+    // Load `.bib` / `.yml` files (e.g. a Zotero or JabRef export); a file with
+    // an existing name replaces that file's content.
+    let handle_upload = move |ev: web_sys::Event| {
+        let Some(input) = ev.target()
+            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+        else { return };
+        let Some(list) = input.files() else { return };
+        let picked: Vec<web_sys::File> = (0..list.length()).filter_map(|i| list.get(i)).collect();
+        input.set_value("");
+        spawn_local(async move {
+            for file in picked {
+                let name = file.name();
+                if BibFormat::from_name(&name).is_none() {
+                    set_message.set(Some(format!("{name}: bibliography files must end in .yml, .yaml or .bib")));
+                    continue;
+                }
+                let Ok(text) = JsFuture::from(file.text()).await else { continue };
+                let content = text.as_string().unwrap_or_default();
+                let existing = bibliographies.with_untracked(|b| b.iter().position(|f| f.name == name));
+                match existing {
+                    Some(index) => {
+                        bibliographies.update(|b| b[index].content = content);
+                        selected.set(index);
+                    }
+                    None => add_file(name, content),
+                }
+            }
+        });
+    };
+
+    // Add or overwrite the `.yml` file produced by a `.bib` import.
+    let store_converted = move |Converted { source, name, conversion }: Converted| {
+        let Conversion { content, lossy } = conversion;
+        match bibliographies.with_untracked(|b| b.iter().position(|f| f.name == name)) {
+            Some(index) => {
+                bibliographies.update(|b| b[index].content = content);
+                selected.set(index);
+            }
+            None => add_file(name.clone(), content),
+        }
+        source_view.set(false);
+        report.set(Some((format!("Imported {source} as {name}."), lossy)));
+    };
+
+    // Convert BibTeX/BibLaTeX files to Hayagriva YAML; `x.bib` becomes `x.yml`.
+    // Files that would overwrite an existing one wait for confirmation.
+    let handle_bib_import = move |ev: web_sys::Event| {
+        let Some(input) = ev.target()
+            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+        else { return };
+        let Some(list) = input.files() else { return };
+        let picked: Vec<web_sys::File> = (0..list.length()).filter_map(|i| list.get(i)).collect();
+        input.set_value("");
+        spawn_local(async move {
+            let mut conflicts = Vec::new();
+            for file in picked {
+                let source = file.name();
+                let Ok(text) = JsFuture::from(file.text()).await else { continue };
+                let conversion = match bib_to_yaml(&text.as_string().unwrap_or_default()) {
+                    Ok(conversion) => conversion,
+                    Err(e) => {
+                        set_message.set(Some(format!("{source}: {e}")));
+                        continue;
+                    }
+                };
+                let name = format!("{}.yml", file_stem(&source));
+                let converted = Converted { source, name, conversion };
+                if bibliographies.with_untracked(|b| b.iter().any(|f| f.name == converted.name)) {
+                    conflicts.push(converted);
+                } else {
+                    store_converted(converted);
+                }
+            }
+            if !conflicts.is_empty() {
+                confirm.set(Some(Confirm::ReplaceConverted(conflicts)));
+            }
+        });
+    };
+
+    // Download the selected YAML file converted to BibLaTeX.
+    let export_bib = move || {
+        let Some(file) = current() else { return };
+        match yaml_to_bib(&file.content) {
+            Ok(Conversion { content, lossy }) => {
+                let name = format!("{}.bib", file_stem(&file.name));
+                download_bytes(&name, "application/x-bibtex", content.as_bytes());
+                report.set(Some((format!("Exported {} as {name}.", file.name), lossy)));
+                set_message.set(None);
+            }
+            Err(e) => set_message.set(Some(format!("{}: {e}", file.name))),
+        }
+    };
+
+    let remove_file = move |index: usize| {
+        bibliographies.update(|b| {
+            if index < b.len() {
+                b.remove(index);
+            }
+        });
+        selected.set(index.saturating_sub(1));
+    };
+
+    // Empty files go straight away; anything else asks first.
+    let delete_current = move || {
+        let index = selected.get_untracked();
+        let Some(empty) = bibliographies.with_untracked(|b| b.get(index).map(|f| f.content.trim().is_empty()))
+        else { return };
+        if empty {
+            remove_file(index);
+        } else {
+            confirm.set(Some(Confirm::DeleteFile(index)));
+        }
+    };
+
+    let apply_confirm = move || {
+        let Some(action) = confirm.get_untracked() else { return };
+        confirm.set(None);
+        match action {
+            Confirm::DeleteFile(index) => remove_file(index),
+            Confirm::DeleteEntry(key, _) => remove_entry(key),
+            Confirm::ReplaceConverted(conflicts) => conflicts.into_iter().for_each(store_converted),
+        }
+    };
+
+    let close = move || {
+        new_name.set(None);
+        report.set(None);
+        confirm.set(None);
+        editing.set(None);
+        set_message.set(None);
+        set_show.set(false);
+    };
+
+    view! {
+        <Show when=move || show.get()>
+            <div class="modal modal-open" role="dialog" aria-modal="true">
+                <div class="modal-box max-w-4xl">
+                    <h3 class="font-bold text-lg flex items-center gap-2">
+                        <span class="icon-[lucide--book-open] text-xl"></span>
+                        "Bibliography Manager"
+                    </h3>
+                    <p class="py-2 text-sm text-base-content/70">
+                        "Hayagriva YAML (.yml) and BibLaTeX (.bib) files. Each is available to your documents under its file name."
+                    </p>
+
+                    <div class="flex flex-wrap items-center gap-2">
+                        <div class="tabs tabs-box tabs-sm">
+                            {move || {
+                                names
+                                    .get()
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(i, name)| {
+                                        view! {
+                                            <button
+                                                class="tab"
+                                                class:tab-active=move || selected.get() == i
+                                                on:click=move |_| {
+                                                    selected.set(i);
+                                                    editing.set(None);
+                                                }
+                                            >
+                                                {name}
+                                            </button>
+                                        }
+                                    })
+                                    .collect::<Vec<_>>()
+                            }}
+                        </div>
+                        <button
+                            class="btn btn-xs btn-ghost gap-1"
+                            on:click=move |_| new_name.set(Some("references.bib".to_string()))
+                        >
+                            <span class="icon-[lucide--plus] text-sm"></span>
+                            "New file"
+                        </button>
+                        <label class="btn btn-xs btn-ghost gap-1 cursor-pointer">
+                            <input
+                                type="file"
+                                accept=".bib,.yml,.yaml"
+                                multiple
+                                class="hidden"
+                                on:change=handle_upload
+                            />
+                            <span class="icon-[lucide--upload] text-sm"></span>
+                            "Load file"
+                        </label>
+                        <label class="btn btn-xs btn-ghost gap-1 cursor-pointer" title="Convert BibTeX/BibLaTeX to Hayagriva YAML">
+                            <input
+                                type="file"
+                                accept=".bib"
+                                multiple
+                                class="hidden"
+                                on:change=handle_bib_import
+                            />
+                            <span class="icon-[lucide--file-input] text-sm"></span>
+                            "Import .bib"
+                        </label>
+                        <button
+                            class="btn btn-xs btn-ghost gap-1"
+                            title="Download this YAML file converted to BibLaTeX"
+                            disabled=move || format() != Some(BibFormat::Hayagriva)
+                            on:click=move |_| export_bib()
+                        >
+                            <span class="icon-[lucide--file-output] text-sm"></span>
+                            "Export as .bib"
+                        </button>
+                    </div>
+
+                    <Show when=move || new_name.get().is_some()>
+                        <div class="join mt-2">
+                            <input
+                                type="text"
+                                class="input input-sm join-item"
+                                aria-label="New bibliography file name"
+                                prop:value=move || new_name.get().unwrap_or_default()
+                                on:input=move |ev| new_name.set(Some(event_target_value(&ev)))
+                                on:keydown=move |ev: web_sys::KeyboardEvent| {
+                                    if ev.key() == "Enter" {
+                                        confirm_new();
+                                    }
+                                }
+                            />
+                            <button class="btn btn-sm btn-primary join-item" on:click=move |_| confirm_new()>
+                                "Add"
+                            </button>
+                            <button class="btn btn-sm join-item" on:click=move |_| new_name.set(None)>
+                                "Cancel"
+                            </button>
+                        </div>
+                    </Show>
+
+                    {move || message.get().map(|msg| view! { <p class="text-sm text-error mt-2">{msg}</p> })}
+                    {move || {
+                        report.get().map(|(summary, lossy)| {
+                            let clean = lossy.is_empty();
+                            view! {
+                                <div
+                                    class="alert mt-2 py-2"
+                                    class=(["alert-success"], clean)
+                                    class=(["alert-warning"], !clean)
+                                    role="status"
+                                >
+                                    <span class="icon-[lucide--arrow-left-right] text-lg"></span>
+                                    <div class="text-sm">
+                                        <p>{summary}</p>
+                                        <Show when=move || !clean>
+                                            <p>"Not carried over:"</p>
+                                        </Show>
+                                        <ul class="list-disc list-inside font-mono text-xs">
+                                            {lossy.iter().map(|note| view! { <li>{note.clone()}</li> }).collect::<Vec<_>>()}
+                                        </ul>
+                                    </div>
+                                    <button class="btn btn-ghost btn-xs" aria-label="Dismiss" on:click=move |_| report.set(None)>
+                                        <span class="icon-[lucide--x] text-sm"></span>
+                                    </button>
+                                </div>
+                            }
+                        })
+                    }}
+
+                    <Show
+                        when=move || bibliographies.with(|b| selected.get() < b.len())
+                        fallback=|| view! {
+                            <p class="text-center py-12 text-base-content/50">
+                                "No bibliography files. Create one or load a .bib / .yml export."
+                            </p>
+                        }
+                    >
+                        <div class="flex items-center gap-2 mt-3">
+                            <span class="font-mono text-sm">{move || current().map(|f| f.name)}</span>
+                            <span class="badge badge-sm badge-outline">
+                                {move || format().map(BibFormat::label).unwrap_or("Unknown format")}
+                            </span>
+                            <div class="join ml-auto">
+                                <button
+                                    class="btn btn-xs join-item"
+                                    class:btn-active=move || !source_view.get()
+                                    on:click=move |_| source_view.set(false)
+                                >
+                                    <span class="icon-[lucide--table] text-sm"></span>
+                                    "Entries"
+                                </button>
+                                <button
+                                    class="btn btn-xs join-item"
+                                    class:btn-active=move || source_view.get()
+                                    on:click=move |_| source_view.set(true)
+                                >
+                                    <span class="icon-[lucide--code] text-sm"></span>
+                                    "Source"
+                                </button>
+                            </div>
+                        </div>
+
+                        // Parser diagnostics, updated while typing.
+                        {move || {
+                            let diagnostics = check.with(|c| c.diagnostics.clone());
+                            (!diagnostics.is_empty()).then(|| view! {
+                                <div class="alert alert-error mt-2 py-2" role="alert">
+                                    <span class="icon-[lucide--circle-x] text-lg"></span>
+                                    <ul class="text-sm">
+                                        {diagnostics
+                                            .into_iter()
+                                            .map(|d| {
+                                                let prefix = d.line.map(|l| format!("Line {l}: ")).unwrap_or_default();
+                                                view! { <li>{prefix}{d.message}</li> }
+                                            })
+                                            .collect::<Vec<_>>()}
+                                    </ul>
+                                </div>
+                            })
+                        }}
+                        {move || {
+                            shared_keys
+                                .get()
+                                .into_iter()
+                                .map(|(key, files)| view! {
+                                    <div class="alert alert-warning mt-2 py-2" role="alert">
+                                        <span class="icon-[lucide--triangle-alert] text-lg"></span>
+                                        <span class="text-sm">
+                                            {format!("Key `{key}` is defined in {} — loading these files together fails.", files.join(" and "))}
+                                        </span>
+                                    </div>
+                                })
+                                .collect::<Vec<_>>()
+                        }}
+
+                        // Entries: table + add/edit form.
+                        <div class:hidden=move || source_view.get()>
+                            <Show when=move || editing.get().is_some()>
+                                <div class="card bg-base-200 mt-3">
+                                    <div class="card-body p-4 gap-2">
+                                        <h4 class="font-semibold">
+                                            {move || match editing.get().flatten() {
+                                                Some(key) => format!("Edit {key}"),
+                                                None => "New entry".to_string(),
+                                            }}
+                                        </h4>
+                                        <div class="grid grid-cols-1 sm:grid-cols-3 gap-2">
+                                            <label class="form-control">
+                                                <span class="label-text text-xs">"Key"</span>
+                                                <input
+                                                    type="text"
+                                                    class="input input-sm font-mono"
+                                                    prop:value=move || draft.with(|d| d.key.clone())
+                                                    on:input=move |ev| draft.update(|d| d.key = event_target_value(&ev))
+                                                />
+                                            </label>
+                                            <label class="form-control">
+                                                <span class="label-text text-xs">"Type"</span>
+                                                <input
+                                                    type="text"
+                                                    class="input input-sm"
+                                                    list="bib-entry-types"
+                                                    prop:value=move || draft.with(|d| d.entry_type.clone())
+                                                    on:input=move |ev| draft.update(|d| d.entry_type = event_target_value(&ev))
+                                                />
+                                                <datalist id="bib-entry-types">
+                                                    {move || {
+                                                        format()
+                                                            .map(entry_types)
+                                                            .unwrap_or_default()
+                                                            .iter()
+                                                            .map(|t| view! { <option value=*t></option> })
+                                                            .collect::<Vec<_>>()
+                                                    }}
+                                                </datalist>
+                                            </label>
+                                            <label class="form-control">
+                                                <span class="label-text text-xs">"Year"</span>
+                                                <input
+                                                    type="text"
+                                                    inputmode="numeric"
+                                                    class="input input-sm"
+                                                    prop:value=move || draft.with(|d| d.year.clone())
+                                                    on:input=move |ev| draft.update(|d| d.year = event_target_value(&ev))
+                                                />
+                                            </label>
+                                        </div>
+                                        <label class="form-control">
+                                            <span class="label-text text-xs">"Title"</span>
+                                            <input
+                                                type="text"
+                                                class="input input-sm w-full"
+                                                prop:value=move || draft.with(|d| d.title.clone())
+                                                on:input=move |ev| draft.update(|d| d.title = event_target_value(&ev))
+                                            />
+                                        </label>
+                                        <label class="form-control">
+                                            <span class="label-text text-xs">"Authors (one per line, \"Last, First\")"</span>
+                                            <textarea
+                                                class="textarea textarea-sm w-full h-20"
+                                                prop:value=move || draft.with(|d| d.authors.join("\n"))
+                                                on:input=move |ev| {
+                                                    let text = event_target_value(&ev);
+                                                    draft.update(|d| d.authors = text.split('\n').map(str::to_string).collect());
+                                                }
+                                            />
+                                        </label>
+                                        {move || {
+                                            draft_errors
+                                                .get()
+                                                .into_iter()
+                                                .map(|e| view! { <p class="text-xs text-error">{e}</p> })
+                                                .collect::<Vec<_>>()
+                                        }}
+                                        <div class="card-actions justify-end">
+                                            <button class="btn btn-sm btn-ghost" on:click=move |_| editing.set(None)>
+                                                "Cancel"
+                                            </button>
+                                            <button
+                                                class="btn btn-sm btn-primary"
+                                                disabled=move || !draft_errors.with(Vec::is_empty)
+                                                on:click=move |_| save_entry()
+                                            >
+                                                "Save entry"
+                                            </button>
+                                        </div>
+                                    </div>
+                                </div>
+                            </Show>
+
+                            <div class="overflow-x-auto max-h-80 mt-3">
+                                <table class="table table-xs table-pin-rows">
+                                    <thead>
+                                        <tr>
+                                            <th>"Key"</th>
+                                            <th>"Type"</th>
+                                            <th>"Title"</th>
+                                            <th>"Authors"</th>
+                                            <th>"Year"</th>
+                                            <th title="Citations across project files">"Cited"</th>
+                                            <th></th>
+                                        </tr>
+                                    </thead>
+                                    <tbody>
+                                        {move || {
+                                            let counts = usage.get();
+                                            check
+                                                .get()
+                                                .entries
+                                                .into_iter()
+                                                .map(|entry| {
+                                                    let cited = counts.get(&entry.key).copied().unwrap_or(0);
+                                                    let badge_class = if cited > 0 {
+                                                        "badge badge-xs badge-primary"
+                                                    } else {
+                                                        "badge badge-xs badge-ghost"
+                                                    };
+                                                    let edit = entry.clone();
+                                                    let key = entry.key.clone();
+                                                    view! {
+                                                        <tr class="hover">
+                                                            <td class="font-mono">{entry.key.clone()}</td>
+                                                            <td>{entry.entry_type.clone()}</td>
+                                                            <td class="max-w-xs truncate" title=entry.title.clone()>{entry.title.clone()}</td>
+                                                            <td class="max-w-40 truncate">{entry.authors.join("; ")}</td>
+                                                            <td>{entry.year.clone()}</td>
+                                                            <td>
+                                                                <span class=badge_class>
+                                                                    {cited}
+                                                                </span>
+                                                            </td>
+                                                            <td class="whitespace-nowrap">
+                                                                <button
+                                                                    class="btn btn-ghost btn-xs btn-square"
+                                                                    title="Edit entry"
+                                                                    aria-label="Edit entry"
+                                                                    on:click=move |_| {
+                                                                        draft.set(EntryDraft::from(&edit));
+                                                                        editing.set(Some(Some(edit.key.clone())));
+                                                                    }
+                                                                >
+                                                                    <span class="icon-[lucide--pencil] text-sm"></span>
+                                                                </button>
+                                                                <button
+                                                                    class="btn btn-ghost btn-xs btn-square text-error"
+                                                                    title="Delete entry"
+                                                                    aria-label="Delete entry"
+                                                                    on:click=move |_| confirm.set(Some(Confirm::DeleteEntry(key.clone(), cited)))
+                                                                >
+                                                                    <span class="icon-[lucide--trash-2] text-sm"></span>
+                                                                </button>
+                                                            </td>
+                                                        </tr>
+                                                    }
+                                                })
+                                                .collect::<Vec<_>>()
+                                        }}
+                                    </tbody>
+                                </table>
+                            </div>
+                            <button
+                                class="btn btn-sm btn-ghost gap-1 mt-2"
+                                disabled=move || check.with(|c| !c.diagnostics.is_empty() && c.entries.is_empty())
+                                title="Fix the errors in the Source view first"
+                                on:click=move |_| {
+                                    draft.set(EntryDraft {
+                                        entry_type: "article".to_string(),
+                                        ..Default::default()
+                                    });
+                                    editing.set(Some(None));
+                                }
+                            >
+                                <span class="icon-[lucide--plus] text-sm"></span>
+                                "Add entry"
+                            </button>
+                        </div>
+
+                        // Source: the raw file, kept mounted so typing never loses focus.
+                        <div class="form-control mt-3" class:hidden=move || !source_view.get()>
+                            <textarea
+                                class="textarea textarea-bordered h-80 w-full font-mono text-sm"
+                                aria-label="Bibliography file content"
+                                prop:value=move || current().map(|f| f.content).unwrap_or_default()
+                                on:input=move |ev| {
+                                    let index = selected.get_untracked();
+                                    let text = event_target_value(&ev);
+                                    bibliographies.update(|b| {
+                                        if let Some(f) = b.get_mut(index) {
+                                            f.content = text;
+                                        }
+                                    });
+                                }
+                                placeholder=move || match format() {
+                                    Some(BibFormat::BibLatex) => "@article{key,\n  title = {Title},\n  author = {Author},\n  year = {2024},\n}",
+                                    _ => "key:\n  type: article\n  title: \"Title\"\n  author: [\"Author\"]\n  date: 2024",
+                                }
+                            />
+                            {move || {
+                                current()
+                                    .and_then(|f| format_mismatch(&f.name, &f.content))
+                                    .map(|warning| view! {
+                                        <div class="alert alert-warning mt-2 py-2" role="alert">
+                                            <span class="icon-[lucide--triangle-alert] text-lg"></span>
+                                            <span class="text-sm">{warning}</span>
+                                        </div>
+                                    })
+                            }}
+                        </div>
+                    </Show>
+
+                    <div class="alert alert-info mt-4">
+                        <span class="icon-[lucide--info] text-xl"></span>
+                        <div class="text-sm">
+                            <p class="font-bold">"Usage:"</p>
+                            <ul class="list-disc list-inside mt-1">
+                                <li>"Use @key to cite entries in your document"</li>
+                                <li>
+                                    {move || {
+                                        let names = names.get();
+                                        match names.as_slice() {
+                                            [] => "Add a bibliography file, then #bibliography(\"file\") at the end of your document".to_string(),
+                                            [one] => format!("Add #bibliography(\"{one}\") at the end of your document"),
+                                            many => format!(
+                                                "Add #bibliography(({})) at the end of your document",
+                                                many.iter().map(|n| format!("\"{n}\"")).collect::<Vec<_>>().join(", ")
+                                            ),
+                                        }
+                                    }}
+                                </li>
+                                <li>"Changes are saved automatically to localStorage"</li>
+                            </ul>
+                        </div>
+                    </div>
+
+                    <div class="modal-action">
+                        <button
+                            class="btn btn-error btn-outline gap-2 mr-auto"
+                            disabled=move || current().is_none()
+                            on:click=move |_| delete_current()
+                        >
+                            <span class="icon-[lucide--trash-2] text-lg"></span>
+                            "Delete file"
+                        </button>
+                        <button class="btn btn-primary" on:click=move |_| close()>
+                            "Done"
+                        </button>
+                    </div>
+                </div>
+                <div class="modal-backdrop" on:click=move |_| close()></div>
+                {move || {
+                    confirm
+                        .get()
+                        .map(|action| {
+                            let (title, confirm_label, confirm_class, message) = match action {
+                                Confirm::DeleteFile(index) => (
+                                    "Delete file",
+                                    "Delete",
+                                    "btn btn-error",
+                                    bibliographies.with_untracked(|b| {
+                                        b.get(index).map(|f| format!("{} will be permanently removed.", f.name))
+                                    }).unwrap_or_default(),
+                                ),
+                                Confirm::DeleteEntry(key, cited) => (
+                                    "Delete entry",
+                                    "Delete",
+                                    "btn btn-error",
+                                    if cited > 0 {
+                                        format!("Delete {key}? It is cited {cited} time(s) in the project.")
+                                    } else {
+                                        format!("Delete {key}?")
+                                    },
+                                ),
+                                Confirm::ReplaceConverted(conflicts) => (
+                                    "Replace files",
+                                    "Replace",
+                                    "btn btn-warning",
+                                    conflicts
+                                        .iter()
+                                        .map(|c| format!("Replace {} with the converted {}?", c.name, c.source))
+                                        .collect::<Vec<_>>()
+                                        .join(" "),
+                                ),
+                            };
+                            view! {
+                                <div class="modal modal-open" role="alertdialog" aria-modal="true">
+                                    <div class="modal-box">
+                                        <h3 class="font-bold text-lg">{title}</h3>
+                                        <p class="py-4">{message}</p>
+                                        <div class="modal-action">
+                                            <button class=confirm_class on:click=move |_| apply_confirm()>
+                                                {confirm_label}
+                                            </button>
+                                            <button class="btn btn-ghost" on:click=move |_| confirm.set(None)>
+                                                "Cancel"
+                                            </button>
+                                        </div>
+                                    </div>
+                                    <div class="modal-backdrop" on:click=move |_| confirm.set(None)></div>
+                                </div>
+                            }
+                        })
+                }}
+            </div>
+        </Show>
+    }
+}
+
+fn file_stem(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
@@ -0,0 +1,239 @@
+use crate::utils::bibliography::BibFile;
+use crate::utils::citations::{candidates, citation_markup, search, CiteForm};
+use leptos::html::Input;
+use leptos::prelude::*;
+use std::rc::Rc;
+
+type InsertFn = Rc<dyn Fn(&str, Option<&str>)>;
+
+/// Citation picker: fuzzy search over every entry of the project's
+/// bibliography files, inserting `@key` or `#cite(<key>, supplement: …)` at the
+/// caret. Entries come straight from the files, so no compile is needed.
+///
+/// Enter inserts the checked entries (or the highlighted one); Shift+Enter or
+/// a click toggles an entry into the selection.
+#[component]
+pub fn CitationPicker(
+    show: ReadSignal<bool>,
+    set_show: WriteSignal<bool>,
+    bibliographies: RwSignal<Vec<BibFile>>,
+    insert_at_cursor: InsertFn,
+) -> impl IntoView {
+    let insert = StoredValue::new_local(insert_at_cursor);
+    let query = RwSignal::new(String::new());
+    let highlighted = RwSignal::new(0usize);
+    // Checked keys, in the order they were picked.
+    let chosen = RwSignal::new(Vec::<String>::new());
+    let form = RwSignal::new(CiteForm::At);
+    let supplement = RwSignal::new(String::new());
+    let search_ref = NodeRef::<Input>::new();
+
+    let all = Memo::new(move |_| {
+        if show.get() {
+            bibliographies.with(|b| candidates(b))
+        } else {
+            Vec::new()
+        }
+    });
+    let results = Memo::new(move |_| {
+        let q = query.get();
+        all.with(|all| search(all, &q).into_iter().cloned().collect::<Vec<_>>())
+    });
+
+    // Start fresh and focus the search field each time the picker opens.
+    Effect::new(move |_| {
+        if show.get() {
+            query.set(String::new());
+            chosen.set(Vec::new());
+            highlighted.set(0);
+            supplement.set(String::new());
+            if let Some(el) = search_ref.get() {
+                let _ = el.focus();
+            }
+        }
+    });
+
+    let toggle = move |key: String| {
+        chosen.update(|c| match c.iter().position(|k| *k == key) {
+            Some(i) => {
+                c.remove(i);
+            }
+            None => c.push(key),
+        });
+    };
+
+    let commit = move || {
+        let mut keys = chosen.get_untracked();
+        if keys.is_empty() {
+            let current = results.with_untracked(|r| r.get(highlighted.get_untracked()).map(|c| c.entry.key.clone()));
+            keys.extend(current);
+        }
+        if keys.is_empty() {
+            return;
+        }
+        let refs: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let text = citation_markup(&refs, form.get_untracked(), &supplement.get_untracked());
+        set_show.set(false);
+        insert.with_value(|f| f(&text, None));
+    };
+
+    let on_keydown = move |ev: web_sys::KeyboardEvent| {
+        let len = results.with_untracked(|r| r.len());
+        match ev.key().as_str() {
+            "ArrowDown" if len > 0 => {
+                ev.prevent_default();
+                highlighted.update(|i| *i = (*i + 1) % len);
+            }
+            "ArrowUp" if len > 0 => {
+                ev.prevent_default();
+                highlighted.update(|i| *i = (*i + len - 1) % len);
+            }
+            "Enter" if ev.shift_key() => {
+                ev.prevent_default();
+                let key = results.with_untracked(|r| r.get(highlighted.get_untracked()).map(|c| c.entry.key.clone()));
+                if let Some(key) = key {
+                    toggle(key);
+                }
+            }
+            "Enter" => {
+                ev.prevent_default();
+                commit();
+            }
+            _ => {}
+        }
+    };
+
+    view! {
+        <Show when=move || show.get()>
+            <div class="modal modal-open" role="dialog" aria-modal="true" aria-label="Insert citation">
+                <div class="modal-box max-w-3xl">
+                    <h3 class="font-bold text-lg flex items-center gap-2">
+                        <span class="icon-[lucide--quote] text-xl"></span>
+                        "Insert Citation"
+                    </h3>
+
+                    <input
+                        node_ref=search_ref
+                        type="search"
+                        class="input input-bordered w-full mt-3"
+                        placeholder="Search by key, title, author or year"
+                        aria-label="Search bibliography"
+                        prop:value=move || query.get()
+                        on:input=move |ev| {
+                            query.set(event_target_value(&ev));
+                            highlighted.set(0);
+                        }
+                        on:keydown=on_keydown
+                    />
+
+                    <div class="overflow-y-auto max-h-80 mt-2" role="listbox" aria-multiselectable="true">
+                        <Show
+                            when=move || all.with(|a| !a.is_empty())
+                            fallback=|| view! {
+                                <p class="text-sm text-base-content/70 p-4">
+                                    "No bibliography entries yet. Add some in the Bibliography manager."
+                                </p>
+                            }
+                        >
+                            {move || {
+                                results
+                                    .get()
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(i, c)| {
+                                        let key = c.entry.key.clone();
+                                        let checked_key = key.clone();
+                                        let is_checked = Signal::derive(move || chosen.with(|ch| ch.contains(&checked_key)));
+                                        let authors = c.entry.authors.join("; ");
+                                        view! {
+                                            <div
+                                                class="flex items-start gap-3 px-2 py-1.5 rounded cursor-pointer"
+                                                class:bg-base-200=move || highlighted.get() == i
+                                                role="option"
+                                                aria-selected=move || is_checked.get().to_string()
+                                                on:mouseenter=move |_| highlighted.set(i)
+                                                on:click=move |_| toggle(key.clone())
+                                            >
+                                                <input
+                                                    type="checkbox"
+                                                    class="checkbox checkbox-sm mt-0.5"
+                                                    tabindex="-1"
+                                                    prop:checked=is_checked
+                                                />
+                                                <div class="min-w-0 flex-1">
+                                                    <div class="flex items-baseline gap-2">
+                                                        <span class="font-mono text-sm">{c.entry.key.clone()}</span>
+                                                        <span class="text-xs text-base-content/60">{c.entry.year.clone()}</span>
+                                                        <span class="badge badge-ghost badge-xs ml-auto">{c.file.clone()}</span>
+                                                    </div>
+                                                    <div class="text-sm truncate">{c.entry.title.clone()}</div>
+                                                    <div class="text-xs text-base-content/60 truncate">{authors}</div>
+                                                </div>
+                                            </div>
+                                        }
+                                    })
+                                    .collect::<Vec<_>>()
+                            }}
+                            <Show when=move || results.with(|r| r.is_empty())>
+                                <p class="text-sm text-base-content/70 p-4">"No matching entries."</p>
+                            </Show>
+                        </Show>
+                    </div>
+
+                    <div class="flex flex-wrap items-center gap-3 mt-3">
+                        <div class="join">
+                            <button
+                                class="btn btn-sm join-item font-mono"
+                                class:btn-active=move || form.get() == CiteForm::At
+                                on:click=move |_| form.set(CiteForm::At)
+                            >
+                                "@key"
+                            </button>
+                            <button
+                                class="btn btn-sm join-item font-mono"
+                                class:btn-active=move || form.get() == CiteForm::Cite
+                                on:click=move |_| form.set(CiteForm::Cite)
+                            >
+                                "#cite(<key>)"
+                            </button>
+                        </div>
+                        <Show when=move || form.get() == CiteForm::Cite>
+                            <input
+                                type="text"
+                                class="input input-sm input-bordered w-40"
+                                placeholder="Supplement, e.g. p. 7"
+                                aria-label="Citation supplement"
+                                prop:value=move || supplement.get()
+                                on:input=move |ev| supplement.set(event_target_value(&ev))
+                                on:keydown=move |ev: web_sys::KeyboardEvent| {
+                                    if ev.key() == "Enter" {
+                                        ev.prevent_default();
+                                        commit();
+                                    }
+                                }
+                            />
+                        </Show>
+                        <span class="text-xs text-base-content/60 ml-auto">
+                            "↑↓ move · Shift+Enter select · Enter insert"
+                        </span>
+                    </div>
+
+                    <div class="modal-action">
+                        <button class="btn btn-ghost" on:click=move |_| set_show.set(false)>"Cancel"</button>
+                        <button
+                            class="btn btn-primary"
+                            disabled=move || results.with(|r| r.is_empty()) && chosen.with(Vec::is_empty)
+                            on:click=move |_| commit()
+                        >
+                            {move || match chosen.with(Vec::len) {
+                                0 => "Insert".to_string(),
+                                n => format!("Insert {n}"),
+                            }}
+                        </button>
+                    </div>
+                </div>
+                <div class="modal-backdrop" on:click=move |_| set_show.set(false)></div>
+            </div>
+        </Show>
+    }
+}
@@ -0,0 +1,100 @@
+use crate::compiler::render_style_sample;
+use crate::utils::csl::{builtin_styles, inspect_csl, is_csl_path, style_from_settings, with_style};
+use leptos::prelude::*;
+use std::collections::HashMap;
+
+/// Style Typst uses when no `style:` is given.
+const DEFAULT_STYLE: &str = "ieee";
+
+/// Citation style picker for the document settings: built-in CSL styles and
+/// `.csl` files uploaded as assets, with a rendered sample of the selection.
+/// The choice is written to `settings` as a `#set bibliography(style: …)` rule.
+#[component]
+pub fn CitationStylePicker(
+    settings: ReadSignal<String>,
+    set_settings: WriteSignal<String>,
+    asset_cache: ReadSignal<HashMap<String, Vec<u8>>>,
+) -> impl IntoView {
+    let builtins = builtin_styles();
+    let selected = Memo::new(move |_| settings.with(|s| style_from_settings(s)));
+    let uploaded = Memo::new(move |_| {
+        asset_cache.with(|assets| {
+            let mut paths: Vec<String> = assets.keys().filter(|p| is_csl_path(p)).cloned().collect();
+            paths.sort();
+            paths
+        })
+    });
+
+    // Title of the selected uploaded style, or why Typst can't load it.
+    let uploaded_info = move || {
+        let style = selected.get()?;
+        asset_cache.with(|assets| assets.get(&style).map(|bytes| inspect_csl(bytes)))
+    };
+
+    let sample = Memo::new(move |_| {
+        let style = selected.get().unwrap_or_else(|| DEFAULT_STYLE.to_string());
+        asset_cache.with(|assets| render_style_sample(&style, assets))
+    });
+
+    view! {
+        <div class="form-control my-3">
+            <label class="label" for="citation-style">
+                <span class="label-text font-semibold">"Citation style"</span>
+            </label>
+            <select
+                id="citation-style"
+                class="select select-bordered select-sm w-full"
+                on:change=move |ev| {
+                    let value = event_target_value(&ev);
+                    let style = (!value.is_empty()).then_some(value.as_str());
+                    set_settings.update(|s| *s = with_style(s, style));
+                }
+            >
+                <option value="" selected=move || selected.get().is_none()>
+                    "Document default (IEEE unless #bibliography sets a style)"
+                </option>
+                <optgroup label="Uploaded (.csl assets)">
+                    {move || {
+                        uploaded
+                            .get()
+                            .into_iter()
+                            .map(|path| {
+                                let is_selected = selected.get().as_deref() == Some(path.as_str());
+                                view! {
+                                    <option value=path.clone() selected=is_selected>{path.clone()}</option>
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    }}
+                </optgroup>
+                <optgroup label="Built-in">
+                    {builtins
+                        .into_iter()
+                        .map(|(name, label)| {
+                            view! {
+                                <option value=name selected=move || selected.get().as_deref() == Some(name)>
+                                    {label}
+                                </option>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                </optgroup>
+            </select>
+            <p class="text-xs text-base-content/60 mt-1">
+                "Upload a journal's .csl file in the Assets drawer to list it here. A style: passed to #bibliography(...) directly takes precedence."
+            </p>
+            {move || {
+                uploaded_info().map(|info| match info {
+                    Ok(title) => view! { <p class="text-xs mt-1">{title}</p> }.into_any(),
+                    Err(e) => view! { <p class="text-xs text-error mt-1">{e}</p> }.into_any(),
+                })
+            }}
+            <div class="mt-2 rounded-box border border-base-300 bg-white p-2 overflow-x-auto">
+                {move || match sample.get() {
+                    Ok(svg) => view! { <div aria-label="Sample citation" inner_html=svg></div> }.into_any(),
+                    Err(e) => view! { <p class="text-xs text-error">{e}</p> }.into_any(),
+                }}
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,139 @@
+use crate::utils::keymap::{chord, palette_matches, CommandSpec, Keymap};
+use leptos::html::Input;
+use leptos::prelude::*;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type Actions = HashMap<&'static str, Rc<dyn Fn()>>;
+
+/// The actions behind the named commands of `utils::keymap`. The component
+/// that owns an action (the app, the editor) registers it; the palette and
+/// the shortcut handlers run commands by id.
+#[derive(Clone, Copy)]
+pub struct CommandRegistry(StoredValue<Actions, LocalStorage>);
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self(StoredValue::new_local(HashMap::new()))
+    }
+}
+
+impl CommandRegistry {
+    pub fn register(&self, id: &'static str, action: impl Fn() + 'static) {
+        self.0.update_value(|actions| {
+            actions.insert(id, Rc::new(action));
+        });
+    }
+
+    /// Run a command; false if nothing registered it.
+    pub fn run(&self, id: &str) -> bool {
+        let action = self.0.with_value(|actions| actions.get(id).cloned());
+        action.map(|action| action()).is_some()
+    }
+}
+
+/// The keymap chord of a key press (Cmd counts as Ctrl).
+pub fn event_chord(ev: &web_sys::KeyboardEvent) -> Option<String> {
+    chord(&ev.key(), &ev.code(), ev.ctrl_key() || ev.meta_key(), ev.shift_key(), ev.alt_key())
+}
+
+/// Ctrl+Shift+P palette: fuzzy-filters every command by title and runs the
+/// chosen one, showing its current shortcuts.
+#[component]
+pub fn CommandPalette(show: RwSignal<bool>, keymap: ReadSignal<Keymap>, commands: CommandRegistry) -> impl IntoView {
+    let query = RwSignal::new(String::new());
+    let index = RwSignal::new(0usize);
+    let input_ref = NodeRef::<Input>::new();
+    let matches = Memo::new(move |_| query.with(|q| palette_matches(q)));
+
+    // Start empty, with the input focused, each time the palette opens.
+    Effect::new(move |_| {
+        if show.get() {
+            query.set(String::new());
+            index.set(0);
+            if let Some(el) = input_ref.get() {
+                let _ = el.focus();
+            }
+        }
+    });
+
+    let run = move |i: usize| {
+        if let Some(spec) = matches.with_untracked(|m| m.get(i).copied()) {
+            show.set(false);
+            commands.run(spec.id);
+        }
+    };
+
+    view! {
+        <Show when=move || show.get()>
+            <div class="modal modal-open items-start pt-24" role="dialog" aria-modal="true" aria-label="Command palette">
+                <div class="modal-box max-w-xl p-0">
+                    <input
+                        node_ref=input_ref
+                        class="input input-ghost w-full border-0 border-b border-base-300 rounded-none focus:outline-none"
+                        placeholder="Type a command…"
+                        aria-label="Command"
+                        prop:value=move || query.get()
+                        on:input=move |ev| {
+                            query.set(event_target_value(&ev));
+                            index.set(0);
+                        }
+                        on:keydown=move |ev| {
+                            let len = matches.with_untracked(Vec::len).max(1);
+                            match ev.key().as_str() {
+                                "ArrowDown" => {
+                                    ev.prevent_default();
+                                    index.update(|i| *i = (*i + 1) % len);
+                                }
+                                "ArrowUp" => {
+                                    ev.prevent_default();
+                                    index.update(|i| *i = (*i + len - 1) % len);
+                                }
+                                "Enter" => {
+                                    ev.prevent_default();
+                                    run(index.get_untracked());
+                                }
+                                _ => {}
+                            }
+                        }
+                    />
+                    <ul class="menu w-full max-h-96 overflow-y-auto flex-nowrap p-2">
+                        {move || {
+                            let selected = index.get();
+                            matches
+                                .get()
+                                .into_iter()
+                                .enumerate()
+                                .map(|(i, spec): (usize, &'static CommandSpec)| {
+                                    let keys = keymap.with(|k| k.keys(spec.id));
+                                    view! {
+                                        <li>
+                                            <button
+                                                class="flex justify-between"
+                                                class:menu-active=i == selected
+                                                on:mousemove=move |_| index.set(i)
+                                                on:click=move |_| run(i)
+                                            >
+                                                <span>{spec.title}</span>
+                                                <span class="flex gap-1">
+                                                    {keys
+                                                        .into_iter()
+                                                        .map(|key| view! { <kbd class="kbd kbd-xs">{key}</kbd> })
+                                                        .collect::<Vec<_>>()}
+                                                </span>
+                                            </button>
+                                        </li>
+                                    }
+                                })
+                                .collect::<Vec<_>>()
+                        }}
+                        <Show when=move || matches.with(Vec::is_empty)>
+                            <li class="p-2 text-sm text-base-content/60">"No matching commands"</li>
+                        </Show>
+                    </ul>
+                </div>
+                <div class="modal-backdrop" on:click=move |_| show.set(false)></div>
+            </div>
+        </Show>
+    }
+}
@@ -1,27 +1,44 @@
 use leptos::prelude::*;
 use leptos::task::spawn_local;
-use leptos::html::{Input, Textarea};
+use leptos::html::{Code, Input, Textarea};
+use super::command_palette::{event_chord, CommandRegistry};
 use crate::compiler::{autocomplete_at, CompletionItem};
-use crate::utils::highlight_typst;
+use crate::utils::carets::{line_col, offset_at, Caret, Carets, Motion};
+use crate::utils::highlight::{LineHighlighter, LinePatch};
 use crate::utils::editing::{
-    auto_pair_close, byte_to_utf16, find_matches, indent_block, insert_text, outdent_block,
-    selection, set_selection, utf16_to_byte, INDENT,
+    auto_pair_close, byte_to_utf16, find_matches, indent_block, insert_text, list_enter, minimal_edit,
+    outdent_block, renest_item, replace_changed, selection, set_selection, sync_value, undo, utf16_to_byte,
+    ListEnter, INDENT,
 };
+use crate::utils::folding::{fold, fold_ranges, shift_folds, FoldRange, Folded};
+use crate::utils::format::{format_range, format_source};
+use crate::utils::keymap::{Keymap, Scope};
+use crate::utils::vim::{Command, Mode, Vim};
+use crate::utils::wrap::{logical_position, row_starts, visual_position};
 use gloo_timers::future::sleep;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::Range;
 use std::time::Duration;
+use typst_syntax::Source;
+use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
 use web_sys::HtmlTextAreaElement;
 use std::rc::Rc;
 
 type InsertFn = Rc<dyn Fn(&str, Option<&str>)>;
 
+/// Editor line height (1.6 × 14px, matches the CSS) and textarea padding.
+const LINE_HEIGHT: f64 = 22.4;
+const PAD: f64 = 8.0;
+
 // Cached monospace character width (px) of the editor font.
 thread_local! {
     static CHAR_WIDTH: std::cell::Cell<Option<f64>> = const { std::cell::Cell::new(None) };
 }
 
 /// Width of one editor character, measured once via a hidden span. The editor
-/// is monospace + no-wrap, so caret position is an exact `(col*cw, line*lh)` grid.
+/// is monospace, so caret position is an exact `(col*cw, row*lh)` grid.
 fn char_width() -> f64 {
     if let Some(w) = CHAR_WIDTH.with(|c| c.get()) {
         return w;
@@ -48,20 +65,206 @@ fn char_width() -> f64 {
     measured
 }
 
-/// Caret pixel position within the editor scroll area. Exact for a monospace,
-/// no-wrap textarea: column × char-width and line × line-height, minus scroll.
-fn caret_xy(ta: &HtmlTextAreaElement, source: &str, cursor_byte: usize) -> (f64, f64) {
-    let before = &source[..cursor_byte.min(source.len())];
-    let line = before.matches('\n').count();
-    let col = before.rsplit('\n').next().unwrap_or("").chars().count();
-    let cw = char_width();
-    let lh = 22.4; // 1.6 × 14px, matches the CSS line-height
-    let pad = 8.0; // textarea padding
-    let x = pad + col as f64 * cw - ta.scroll_left() as f64;
-    let y = pad + (line as f64 + 1.0) * lh - ta.scroll_top() as f64;
+/// Characters per visual row of the textarea when soft wrap is on, or
+/// `usize::MAX` (one row per line) when it is off.
+fn wrap_columns(ta: &HtmlTextAreaElement, wrap: bool) -> usize {
+    if !wrap {
+        return usize::MAX;
+    }
+    ((ta.client_width() as f64 - 2.0 * PAD) / char_width()).floor().max(1.0) as usize
+}
+
+/// Caret pixel position within the editor scroll area. Exact for the
+/// monospace textarea: visual column × char-width and visual row ×
+/// line-height (see `utils::wrap`), minus scroll.
+fn caret_xy(ta: &HtmlTextAreaElement, text: &str, cursor_byte: usize, cols: usize) -> (f64, f64) {
+    let (row, col) = visual_position(text, cursor_byte.min(text.len()), cols);
+    let x = PAD + col as f64 * char_width() - ta.scroll_left() as f64;
+    let y = PAD + (row as f64 + 1.0) * LINE_HEIGHT - ta.scroll_top() as f64;
     (x, y)
 }
 
+/// Scroll the textarea so the visual row holding byte `offset` of its text
+/// sits near the top.
+pub fn scroll_to_offset(ta: &HtmlTextAreaElement, text: &str, offset: usize, wrap: bool) {
+    let (row, _) = visual_position(text, offset.min(text.len()), wrap_columns(ta, wrap));
+    ta.set_scroll_top(((row as f64 * LINE_HEIGHT) - 60.0).max(0.0) as i32);
+}
+
+/// Scroll the textarea just enough to show the visual row holding byte
+/// `offset` of its text.
+fn keep_in_view(ta: &HtmlTextAreaElement, text: &str, offset: usize, wrap: bool) {
+    let (row, _) = visual_position(text, offset.min(text.len()), wrap_columns(ta, wrap));
+    let top = row as f64 * LINE_HEIGHT;
+    let height = ta.client_height() as f64 - 2.0 * PAD;
+    let scroll = ta.scroll_top() as f64;
+    if top < scroll {
+        ta.set_scroll_top(top as i32);
+    } else if top + LINE_HEIGHT > scroll + height {
+        ta.set_scroll_top((top + LINE_HEIGHT - height) as i32);
+    }
+}
+
+/// Logical `(line, column)` under the mouse, on the same monospace grid.
+fn point_at(ta: &HtmlTextAreaElement, ev: &web_sys::MouseEvent, cols: usize) -> (usize, usize) {
+    let rect = ta.get_bounding_client_rect();
+    let x = ev.client_x() as f64 - rect.left() - PAD + ta.scroll_left() as f64;
+    let y = ev.client_y() as f64 - rect.top() - PAD + ta.scroll_top() as f64;
+    let row = (y / LINE_HEIGHT).max(0.0) as usize;
+    let col = (x / char_width()).round().max(0.0) as usize;
+    logical_position(&ta.value(), row, col, cols)
+}
+
+/// Markup for the extra carets and their selections, positioned in the
+/// overlay's content box (the primary caret is the native one).
+fn caret_marks(text: &str, carets: &Carets, cols: usize) -> String {
+    let cw = char_width();
+    let mut html = String::new();
+    for (i, caret) in carets.list.iter().enumerate() {
+        if i == carets.primary {
+            continue;
+        }
+        let range = caret.range();
+        let (first, first_col) = line_col(text, range.start);
+        let (last, last_col) = line_col(text, range.end);
+        for line in first..=last {
+            let start = offset_at(text, line, 0);
+            let end = offset_at(text, line, usize::MAX);
+            let len = line_col(text, end).1;
+            let from = if line == first { first_col } else { 0 };
+            // Selected line breaks show as one extra column.
+            let to = if line == last { last_col } else { len + 1 };
+            let top_row = visual_position(text, start, cols).0;
+            let starts = row_starts(&text[start..end], cols);
+            for (row, &row_start) in starts.iter().enumerate() {
+                let row_end = starts.get(row + 1).copied().unwrap_or(len + 1);
+                let (a, b) = (from.max(row_start), to.min(row_end));
+                if b > a {
+                    let _ = write!(
+                        html,
+                        "<div class=\"caret-selection\" style=\"left:{}px;top:{}px;width:{}px\"></div>",
+                        PAD + (a - row_start) as f64 * cw,
+                        PAD + (top_row + row) as f64 * LINE_HEIGHT,
+                        (b - a) as f64 * cw,
+                    );
+                }
+            }
+        }
+        let (row, col) = visual_position(text, caret.head, cols);
+        let _ = write!(
+            html,
+            "<div class=\"caret-extra\" style=\"left:{}px;top:{}px\"></div>",
+            PAD + col as f64 * cw,
+            PAD + row as f64 * LINE_HEIGHT,
+        );
+    }
+    html
+}
+
+/// Overlay markup for highlighted lines: one block per source line.
+fn line_divs(lines: &[String]) -> String {
+    lines.iter().fold(String::new(), |mut html, line| {
+        html.push_str("<div class=\"hl-line\">");
+        html.push_str(line);
+        html.push_str("</div>");
+        html
+    })
+}
+
+/// Swap the patched lines of the overlay in place.
+fn apply_patch(code: &web_sys::Element, patch: &LinePatch) {
+    let lines = code.children();
+    for _ in 0..patch.removed {
+        if let Some(line) = lines.item(patch.start as u32) {
+            line.remove();
+        }
+    }
+    let html = line_divs(&patch.inserted);
+    match lines.item(patch.start as u32) {
+        Some(next) => {
+            let _ = next.insert_adjacent_html("beforebegin", &html);
+        }
+        None => {
+            let _ = code.insert_adjacent_html("beforeend", &html);
+        }
+    }
+}
+
+/// Overlay class of `line`: hidden inside a fold, starting one, or plain.
+fn line_class(line: usize, hidden: &[Range<usize>]) -> &'static str {
+    if hidden.iter().any(|h| h.contains(&line)) {
+        "hl-line hl-hidden"
+    } else if hidden.iter().any(|h| h.start == line + 1) {
+        "hl-line hl-folded"
+    } else {
+        "hl-line"
+    }
+}
+
+/// Set the fold classes of the given overlay lines.
+fn mark_lines(code: &web_sys::Element, lines: Range<usize>, hidden: &[Range<usize>]) {
+    let children = code.children();
+    for line in lines {
+        if let Some(el) = children.item(line as u32) {
+            let class = line_class(line, hidden);
+            if el.class_name() != class {
+                el.set_class_name(class);
+            }
+        }
+    }
+}
+
+/// Re-mark every folded region of the overlay after the folds changed.
+fn mark_folds(code: &web_sys::Element, hidden: &[Range<usize>]) {
+    for class in ["hl-hidden", "hl-folded"] {
+        // Live collection: each reset element drops out of it.
+        let marked = code.get_elements_by_class_name(class);
+        while let Some(el) = marked.item(0) {
+            el.set_class_name("hl-line");
+        }
+    }
+    for h in hidden {
+        mark_lines(code, h.start - 1..h.end, hidden);
+    }
+}
+
+/// Gutter markup: the number of each visible source line, with a fold
+/// marker (`data-fold` = its 0-based line) where a foldable region starts.
+/// With soft wrap, `rows` holds each line's visual row count and a number's
+/// row is as tall as its line.
+fn gutter_html(count: usize, regions: &[FoldRange], hidden: &[Range<usize>], rows: &[usize]) -> String {
+    let mut html = String::new();
+    let mut line = 0;
+    while line < count {
+        if let Some(h) = hidden.iter().find(|h| h.start == line) {
+            line = h.end;
+            continue;
+        }
+        let marker = if hidden.iter().any(|h| h.start == line + 1) {
+            format!("<span class=\"fold-marker icon-[lucide--chevron-right]\" data-fold=\"{line}\" title=\"Unfold\"></span>")
+        } else if regions.iter().any(|r| r.line == line) {
+            format!("<span class=\"fold-marker icon-[lucide--chevron-down]\" data-fold=\"{line}\" title=\"Fold\"></span>")
+        } else {
+            "<span class=\"fold-marker\"></span>".to_string()
+        };
+        match rows.get(line).filter(|&&r| r > 1) {
+            Some(r) => {
+                let _ = write!(
+                    html,
+                    "<div class=\"gutter-row\" style=\"height:{:.1}em\">{}{marker}</div>",
+                    *r as f64 * 1.6,
+                    line + 1
+                );
+            }
+            None => {
+                let _ = write!(html, "<div class=\"gutter-row\">{}{marker}</div>", line + 1);
+            }
+        }
+        line += 1;
+    }
+    html
+}
+
 #[component]
 pub fn Editor(
     source: ReadSignal<String>,
@@ -70,8 +273,26 @@ pub fn Editor(
     textarea_ref: NodeRef<Textarea>,
     insert_at_cursor: InsertFn,
     set_show_settings: WriteSignal<bool>,
+    /// Opens the citation picker (toolbar "Citation" button).
+    set_show_cite_picker: WriteSignal<bool>,
     /// Invoked on Ctrl/Cmd+S to persist the project.
     on_save: Callback<()>,
+    /// Opens "Rename symbol" (F2) for the byte offset of the caret.
+    on_rename: Callback<usize>,
+    /// "Format on save" toggle; the formatting itself happens in `on_save`.
+    format_on_save: RwSignal<bool>,
+    /// Name of the open file; fold state is kept per file.
+    file_name: Signal<String>,
+    /// Folded regions per file name, by the (0-based) line each starts on.
+    folds: RwSignal<HashMap<String, Vec<usize>>>,
+    /// Soft-wrap long lines instead of scrolling horizontally.
+    soft_wrap: RwSignal<bool>,
+    /// Vim keybindings (normal / insert / visual modes).
+    vim_mode: RwSignal<bool>,
+    /// Shortcuts; the editor dispatches those of editor-scoped commands.
+    keymap: ReadSignal<Keymap>,
+    /// Where the editor registers its commands.
+    commands: CommandRegistry,
 ) -> impl IntoView {
     // Sync scroll between textarea, overlay and the line-number gutter.
     let sync_scroll = move |_| {
@@ -101,36 +322,267 @@ pub fn Editor(
         }
     };
 
-    // Line numbers for the gutter; recomputed only when the line count changes.
-    let line_numbers = Memo::new(move |_| {
-        let count = source.get().lines().count().max(1);
-        // A trailing newline starts a new (empty) visual line in the textarea.
-        let count = if source.with(|s| s.ends_with('\n')) {
-            count + 1
-        } else {
-            count
-        };
-        (1..=count).fold(String::new(), |mut acc, n| {
-            if !acc.is_empty() {
-                acc.push('\n');
+    let overlay_ref = NodeRef::<Code>::new();
+    let highlighter = StoredValue::new(None::<LineHighlighter>);
+
+    // ----- Folding -----
+    // The textarea shows the source with folded lines cut out; `view` is
+    // that projection, and edits made in it are mapped back in `on:input`.
+    let folded_lines = Memo::new(move |_| {
+        let name = file_name.get();
+        folds.with(|f| f.get(&name).cloned().unwrap_or_default())
+    });
+    let view = Memo::new(move |_| {
+        let lines = folded_lines.get();
+        source.with(|text| {
+            if lines.is_empty() {
+                Folded::unfolded(text)
+            } else {
+                let parsed = Source::detached(text.as_str());
+                fold(&parsed, &fold_ranges(&parsed), &lines)
             }
-            acc.push_str(&n.to_string());
-            acc
         })
     });
+    let hidden_lines = Memo::new(move |_| view.with(|v| v.hidden_lines.clone()));
+    // Foldable regions, from the highlighter's tree (set by its Effect).
+    let fold_regions = RwSignal::new(Vec::<FoldRange>::new());
 
-    // Keep the textarea value in sync with `source` WITHOUT clobbering the undo
-    // stack: setting `.value` (what `prop:value` does on every change) wipes
-    // browser undo, so only write when the DOM actually differs from the signal.
-    // Our own edits go through execCommand and leave `.value == source`, so this
-    // becomes a no-op for them and Ctrl+Z survives.
+    let set_fold = move |line: usize, folded: bool| {
+        let name = file_name.get_untracked();
+        folds.update(|f| {
+            let lines = f.entry(name).or_default();
+            lines.retain(|&l| l != line);
+            if folded {
+                lines.push(line);
+            }
+        });
+    };
+    // Show the whole source again, synchronously, so callers can work with
+    // source offsets right away.
+    let unfold_all = move || {
+        if folded_lines.with_untracked(|l| !l.is_empty()) {
+            let name = file_name.get_untracked();
+            folds.update(|f| {
+                f.remove(&name);
+            });
+        }
+        if let Some(ta) = textarea_ref.get_untracked() {
+            view.with_untracked(|v| sync_value(&ta, &v.visible));
+        }
+    };
+    // Source byte offset of a textarea (UTF-16) offset, and back; a hidden
+    // offset maps to the end of its fold line.
+    let to_source = move |utf16: usize| view.with_untracked(|v| v.to_source(utf16_to_byte(&v.visible, utf16)));
+    let to_view = move |byte: usize| view.with_untracked(|v| byte_to_utf16(&v.visible, v.nearest_visible(byte)));
+    // Textarea offsets of a source range, unfolding first if it is hidden.
+    let reveal = move |range: Range<usize>| {
+        if view.with_untracked(|v| v.to_visible(range.start).is_none() || v.to_visible(range.end).is_none()) {
+            unfold_all();
+        }
+        (to_view(range.start), to_view(range.end))
+    };
+    // Source line of the caret.
+    let caret_line = move |ta: &HtmlTextAreaElement| {
+        let byte = to_source(selection(ta).0);
+        highlighter.with_value(|h| h.as_ref().and_then(|h| h.source().byte_to_line(byte)))
+    };
+
+    // Characters per visual row with soft wrap; follows the textarea's size.
+    let wrap_cols = RwSignal::new(usize::MAX);
     Effect::new(move |_| {
-        let s = source.get();
+        let Some(ta) = textarea_ref.get() else {
+            return;
+        };
+        let measure = Closure::<dyn FnMut()>::new(move || {
+            if let Some(ta) = textarea_ref.get_untracked() {
+                let cols = wrap_columns(&ta, true);
+                if wrap_cols.get_untracked() != cols {
+                    wrap_cols.set(cols);
+                }
+            }
+        });
+        if let Ok(observer) = web_sys::ResizeObserver::new(measure.as_ref().unchecked_ref()) {
+            observer.observe(&ta);
+        }
+        measure.forget();
+    });
+    let cols = move || if soft_wrap.get() { wrap_cols.get() } else { usize::MAX };
+
+    // Gutter rows; without soft wrap, recomputed only when the line count or
+    // the folds change.
+    let line_count = Memo::new(move |_| source.with(|s| s.split('\n').count()));
+    let line_rows = Memo::new(move |_| {
+        if !soft_wrap.get() {
+            return Vec::new();
+        }
+        let cols = wrap_cols.get();
+        source.with(|s| s.split('\n').map(|line| row_starts(line, cols).len()).collect::<Vec<_>>())
+    });
+    let gutter = Memo::new(move |_| {
+        fold_regions.with(|regions| {
+            hidden_lines.with(|hidden| line_rows.with(|rows| gutter_html(line_count.get(), regions, hidden, rows)))
+        })
+    });
+    let on_gutter_click = move |ev: web_sys::MouseEvent| {
+        let line = ev
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok())
+            .and_then(|el| el.closest("[data-fold]").ok().flatten())
+            .and_then(|el| el.get_attribute("data-fold"))
+            .and_then(|line| line.parse::<usize>().ok());
+        if let Some(line) = line {
+            set_fold(line, !folded_lines.get_untracked().contains(&line));
+        }
+    };
+
+    // ----- Multiple carets -----
+    // Set while there are several carets (byte offsets into the textarea's
+    // text); the primary one is also the native selection.
+    let carets = RwSignal::new(None::<Carets>);
+    // True while a multi-caret edit is written, so `on:input` can tell it
+    // from typing, paste or undo, which go back to a single caret.
+    let multi_edit = StoredValue::new(false);
+    // Where an Alt+drag column selection started, as `(line, column)`.
+    let column_from = StoredValue::new(None::<(usize, usize)>);
+
+    let show_carets = move |ta: &HtmlTextAreaElement, next: Carets| {
+        let text = ta.value();
+        let primary = next.primary().range();
+        set_selection(ta, byte_to_utf16(&text, primary.start), byte_to_utf16(&text, primary.end));
+        carets.set((next.list.len() > 1).then_some(next));
+    };
+    // Write a multi-caret edit back as one undoable change.
+    let commit_carets = move |ta: &HtmlTextAreaElement, old: &str, (new, next): (String, Carets)| {
+        multi_edit.set_value(true);
+        replace_changed(ta, old, &new);
+        multi_edit.set_value(false);
+        show_carets(ta, next);
+    };
+    // The carets to extend: the current ones, or the native selection.
+    let current_carets = move |ta: &HtmlTextAreaElement| {
+        carets.get_untracked().unwrap_or_else(|| {
+            let text = ta.value();
+            let (s, e) = selection(ta);
+            Carets::new(Caret { anchor: utf16_to_byte(&text, s), head: utf16_to_byte(&text, e) })
+        })
+    };
+
+    // Keys that act on every caret while there are several; returns whether
+    // the key was handled.
+    let multi_key = move |ta: &HtmlTextAreaElement, ev: &web_sys::KeyboardEvent, current: Carets| {
+        let text = ta.value();
+        let key = ev.key();
+        let motion = match key.as_str() {
+            "ArrowLeft" => Some(Motion::Left),
+            "ArrowRight" => Some(Motion::Right),
+            "ArrowUp" => Some(Motion::Up),
+            "ArrowDown" => Some(Motion::Down),
+            "Home" => Some(Motion::Home),
+            "End" => Some(Motion::End),
+            _ => None,
+        };
+        if ev.ctrl_key() || ev.meta_key() || ev.alt_key() {
+            return false;
+        }
+        if let Some(motion) = motion {
+            show_carets(ta, current.moved(&text, motion, ev.shift_key()));
+            return true;
+        }
+        let edit = match key.as_str() {
+            "Escape" => {
+                show_carets(ta, Carets::new(current.primary()));
+                return true;
+            }
+            "Backspace" => current.backspace(&text),
+            "Delete" => current.delete(&text),
+            "Enter" => current.insert(&text, "\n"),
+            "Tab" if ev.shift_key() || current.list.iter().any(|c| text[c.range()].contains('\n')) => {
+                current.indent(&text, ev.shift_key())
+            }
+            "Tab" => current.insert(&text, INDENT),
+            _ => {
+                let mut chars = key.chars();
+                let (Some(ch), None) = (chars.next(), chars.next()) else {
+                    return false;
+                };
+                // Re-typing a closer every caret sits before skips over it.
+                if matches!(ch, ')' | ']' | '}') || auto_pair_close(ch) == Some(ch) {
+                    if let Some(next) = current.skip(&text, ch) {
+                        show_carets(ta, next);
+                        return true;
+                    }
+                }
+                match auto_pair_close(ch) {
+                    Some(close) => current.pair(&text, ch, close),
+                    None => current.insert(&text, &key),
+                }
+            }
+        };
+        commit_carets(ta, &text, edit);
+        true
+    };
+
+    // Keep the textarea value in sync with the folded view WITHOUT clobbering
+    // the undo stack: setting `.value` wipes browser undo, so only write when
+    // the DOM actually differs. Our own edits go through execCommand and leave
+    // `.value == visible`, so this becomes a no-op for them and Ctrl+Z
+    // survives; folding or unfolding does reset it.
+    Effect::new(move |prev: Option<(String, Folded)>| {
+        let current = view.get();
+        let name = file_name.get_untracked();
         if let Some(ta) = textarea_ref.get() {
-            if ta.value() != s {
-                ta.set_value(&s);
+            if ta.value() != current.visible {
+                // Folds changed in the same file: keep the caret where it was.
+                let caret = prev.filter(|(n, p)| *n == name && p.visible == ta.value()).map(|(_, p)| {
+                    let value = ta.value();
+                    p.to_source(utf16_to_byte(&value, selection(&ta).0))
+                });
+                ta.set_value(&current.visible);
+                carets.set(None);
+                if let Some(caret) = caret {
+                    let at = byte_to_utf16(&current.visible, current.nearest_visible(caret));
+                    set_selection(&ta, at, at);
+                }
             }
         }
+        (name, current)
+    });
+
+    // Highlight incrementally: only the lines an edit reparsed are re-rendered
+    // and swapped in the overlay, so typing stays fast in long files. The
+    // overlay keeps every source line; folded ones are hidden by class.
+    let marked = StoredValue::new(Vec::<Range<usize>>::new());
+    Effect::new(move |_| {
+        let text = source.get();
+        let hidden = hidden_lines.get();
+        let Some(code) = overlay_ref.get() else {
+            return;
+        };
+        let mut patched = None;
+        highlighter.update_value(|highlighter| match highlighter {
+            Some(highlighter) => {
+                if let Some(patch) = highlighter.update(&text) {
+                    apply_patch(&code, &patch);
+                    patched = Some(patch.start..patch.start + patch.inserted.len());
+                }
+            }
+            None => {
+                let fresh = LineHighlighter::new(&text);
+                code.set_inner_html(&line_divs(fresh.lines()));
+                patched = Some(0..fresh.lines().len());
+                *highlighter = Some(fresh);
+            }
+        });
+        let regions = highlighter.with_value(|h| h.as_ref().map(|h| fold_ranges(h.source())).unwrap_or_default());
+        if fold_regions.with_untracked(|r| *r != regions) {
+            fold_regions.set(regions);
+        }
+        if marked.with_value(|m| *m != hidden) {
+            mark_folds(&code, &hidden);
+            marked.set_value(hidden);
+        } else if let Some(lines) = patched {
+            mark_lines(&code, lines, &hidden);
+        }
     });
 
     // ----- Find / replace bar state -----
@@ -161,14 +613,12 @@ pub fn Editor(
         let i = idx % ms.len();
         match_idx.set(i);
         let (bs, be) = ms[i];
-        let cur = source.get_untracked();
-        let (u_s, u_e) = (byte_to_utf16(&cur, bs), byte_to_utf16(&cur, be));
         if let Some(ta) = textarea_ref.get() {
+            let (u_s, u_e) = reveal(bs..be);
             let _ = ta.focus();
             set_selection(&ta, u_s, u_e);
-            // Approximate scroll: line index * line-height (1.6 * 14px).
-            let line = cur[..bs].matches('\n').count() as f64;
-            ta.set_scroll_top(((line * 22.4) - 60.0).max(0.0) as i32);
+            let visible = ta.value();
+            scroll_to_offset(&ta, &visible, utf16_to_byte(&visible, u_s), soft_wrap.get_untracked());
         }
     };
 
@@ -190,9 +640,8 @@ pub fn Editor(
         }
         let i = match_idx.get_untracked().min(ms.len() - 1);
         let (bs, be) = ms[i];
-        let cur = source.get_untracked();
-        let (u_s, u_e) = (byte_to_utf16(&cur, bs), byte_to_utf16(&cur, be));
         if let Some(ta) = textarea_ref.get() {
+            let (u_s, u_e) = reveal(bs..be);
             set_selection(&ta, u_s, u_e);
             insert_text(&ta, &replace_query.get_untracked());
         }
@@ -204,6 +653,7 @@ pub fn Editor(
         if q.is_empty() {
             return;
         }
+        unfold_all();
         let cur = source.get_untracked();
         let new = cur.replace(&q, &replace_query.get_untracked());
         if let Some(ta) = textarea_ref.get() {
@@ -213,6 +663,33 @@ pub fn Editor(
         }
     };
 
+    // ----- Formatter -----
+    // Why the last format request was refused (syntax errors), until dismissed.
+    let format_error = RwSignal::new(Option::<String>::None);
+
+    // Format the selection, or the whole document when nothing is selected,
+    // as one undoable edit.
+    let format = move || {
+        let Some(ta) = textarea_ref.get() else {
+            return;
+        };
+        let (s, e) = selection(&ta);
+        let range = (s != e).then(|| to_source(s)..to_source(e));
+        unfold_all();
+        let cur = source.get_untracked();
+        let formatted = match range {
+            None => format_source(&cur),
+            Some(range) => format_range(&cur, range),
+        };
+        match formatted {
+            Ok(new) => {
+                format_error.set(None);
+                replace_changed(&ta, &cur, &new);
+            }
+            Err(message) => format_error.set(Some(message)),
+        }
+    };
+
     // ----- Autocomplete state (typst-ide) -----
     let completions = RwSignal::new(Vec::<CompletionItem>::new());
     let ac_open = RwSignal::new(false);
@@ -232,17 +709,19 @@ pub fn Editor(
             return;
         }
         let (s, e) = selection(&ta);
-        if s != e {
+        if s != e || carets.with_untracked(Option::is_some) {
             ac_open.set(false);
             return;
         }
-        let cursor_byte = utf16_to_byte(&cur, s);
+        let cursor_byte = to_source(s);
         let items = autocomplete_at(&cur, &settings.get_untracked(), cursor_byte, explicit);
         if items.is_empty() {
             ac_open.set(false);
             return;
         }
-        ac_pos.set(caret_xy(&ta, &cur, cursor_byte));
+        let visible = ta.value();
+        let cols = wrap_columns(&ta, soft_wrap.get_untracked());
+        ac_pos.set(caret_xy(&ta, &visible, utf16_to_byte(&visible, s), cols));
         completions.set(items);
         ac_index.set(0);
         ac_open.set(true);
@@ -258,9 +737,8 @@ pub fn Editor(
         let Some(ta) = textarea_ref.get() else {
             return;
         };
-        let cur = source.get_untracked();
         let (_, caret_now) = selection(&ta);
-        let from_u16 = byte_to_utf16(&cur, item.replace_from.min(cur.len()));
+        let from_u16 = to_view(item.replace_from);
         // Replace the already-typed prefix with the completion.
         set_selection(&ta, from_u16, caret_now);
         insert_text(&ta, &item.apply);
@@ -270,9 +748,143 @@ pub fn Editor(
         ac_open.set(false);
     };
 
+    // ----- Vim mode -----
+    // The modal state machine lives in `utils::vim`; the editor feeds it keys
+    // and writes back what it returns. Turning the mode off or on starts over
+    // in normal mode.
+    let vim = StoredValue::new(Vim::default());
+    let vim_status = RwSignal::new(String::new());
+    Effect::new(move |_| {
+        if vim_mode.get() {
+            vim.set_value(Vim::default());
+            vim_status.set(vim.with_value(Vim::status));
+        }
+    });
+    // Run a key through Vim; true if it took it.
+    let vim_key = move |ta: &HtmlTextAreaElement, key: &str| {
+        let text = ta.value();
+        let (s, e) = selection(ta);
+        let range = utf16_to_byte(&text, s)..utf16_to_byte(&text, e);
+        let Some(response) = vim.try_update_value(|v| v.key(&text, range, key)).flatten() else {
+            return false;
+        };
+        if let Some(new) = &response.text {
+            replace_changed(ta, &text, new);
+        }
+        let shown = response.text.as_deref().unwrap_or(&text);
+        let sel = response.selection;
+        set_selection(ta, byte_to_utf16(shown, sel.start), byte_to_utf16(shown, sel.end));
+        keep_in_view(ta, shown, sel.start, soft_wrap.get_untracked());
+        match response.command {
+            Some(Command::Save) => on_save.run(()),
+            Some(Command::Undo) => undo(ta, false),
+            Some(Command::Redo) => undo(ta, true),
+            None => {}
+        }
+        vim_status.set(vim.with_value(Vim::status));
+        true
+    };
+
+    // ----- Commands -----
+    // Fold the innermost region around the caret (the next one out if that
+    // is folded already), or unfold the caret's line.
+    let fold_at_caret = move |unfold: bool| {
+        let Some(line) = textarea_ref.get_untracked().and_then(|ta| caret_line(&ta)) else {
+            return;
+        };
+        if unfold {
+            set_fold(line, false);
+            return;
+        }
+        let folded = folded_lines.get_untracked();
+        let region = fold_regions.with_untracked(|regions| {
+            regions
+                .iter()
+                .filter(|r| r.line <= line && line <= r.end && !folded.contains(&r.line))
+                .map(|r| r.line)
+                .max()
+        });
+        if let Some(start) = region {
+            set_fold(start, true);
+        }
+    };
+    // Indent (or outdent) the selected lines. In a list item with no
+    // selection, re-nest the item, keeping the caret; otherwise with no
+    // multi-line selection, indent inserts spaces.
+    let indent = move |outdent: bool| {
+        let Some(textarea) = textarea_ref.get_untracked() else {
+            return;
+        };
+        let (s, e) = selection(&textarea);
+        let cur = textarea.value();
+        let bs = utf16_to_byte(&cur, s);
+        let be = utf16_to_byte(&cur, e);
+        if let Some((line, new_line, caret)) = renest_item(&cur, bs, outdent).filter(|_| s == e) {
+            let u_ls = byte_to_utf16(&cur, line.start);
+            set_selection(&textarea, u_ls, byte_to_utf16(&cur, line.end));
+            insert_text(&textarea, &new_line);
+            let u_caret = u_ls + new_line[..caret - line.start].encode_utf16().count();
+            set_selection(&textarea, u_caret, u_caret);
+            return;
+        }
+        let multiline = cur[bs..be].contains('\n');
+        if !multiline && !outdent {
+            insert_text(&textarea, INDENT);
+        } else {
+            let block_start = cur[..bs].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let block_end = cur[be..].find('\n').map(|i| be + i).unwrap_or(cur.len());
+            let (new_full, _) = if outdent {
+                outdent_block(&cur, bs, be)
+            } else {
+                indent_block(&cur, bs, be)
+            };
+            let suffix_len = cur.len() - block_end;
+            let new_block = &new_full[block_start..new_full.len() - suffix_len];
+            let u_bs = byte_to_utf16(&cur, block_start);
+            let u_be = byte_to_utf16(&cur, block_end);
+            set_selection(&textarea, u_bs, u_be);
+            insert_text(&textarea, new_block);
+            let new_len = new_block.encode_utf16().count();
+            set_selection(&textarea, u_bs, u_bs + new_len);
+        }
+    };
+    {
+        let bold = insert_at_cursor.clone();
+        let italic = insert_at_cursor.clone();
+        commands.register("editor.find", move || show_find.set(true));
+        commands.register("editor.complete", move || run_autocomplete(true));
+        // Select the word at the caret, then add the next occurrence of the
+        // selection as another caret.
+        commands.register("editor.addNextOccurrence", move || {
+            if let Some(ta) = textarea_ref.get_untracked() {
+                let mut next = current_carets(&ta);
+                if next.add_next_occurrence(&ta.value()) {
+                    show_carets(&ta, next);
+                }
+            }
+        });
+        commands.register("editor.bold", move || bold("*text*", Some("text")));
+        commands.register("editor.italic", move || italic("_text_", Some("text")));
+        commands.register("editor.indent", move || indent(false));
+        commands.register("editor.outdent", move || indent(true));
+        commands.register("editor.format", format);
+        commands.register("editor.rename", move || {
+            if let Some(ta) = textarea_ref.get_untracked() {
+                let (start, _) = selection(&ta);
+                on_rename.run(to_source(start));
+            }
+        });
+        commands.register("editor.fold", move || fold_at_caret(false));
+        commands.register("editor.unfold", move || fold_at_caret(true));
+        commands.register("editor.citation", move || set_show_cite_picker.set(true));
+        commands.register("editor.settings", move || set_show_settings.set(true));
+        commands.register("editor.toggleSoftWrap", move || soft_wrap.update(|on| *on = !*on));
+        commands.register("editor.toggleVim", move || vim_mode.update(|on| *on = !*on));
+        commands.register("editor.toggleFormatOnSave", move || format_on_save.update(|on| *on = !*on));
+    }
+
     // ----- Keyboard shortcuts on the textarea -----
     let on_keydown = {
-        let insert = insert_at_cursor.clone();
         move |ev: web_sys::KeyboardEvent| {
             let Some(textarea) = textarea_ref.get() else {
                 return;
@@ -310,60 +922,61 @@ pub fn Editor(
                 }
             }
 
+            // Vim takes the keys of its normal and visual modes, and Escape
+            // in insert mode; Ctrl+R redoes and Ctrl+[ is Escape.
+            if vim_mode.get_untracked() && !ev.alt_key() {
+                let key = match (ctrl, key.as_str()) {
+                    (false, _) => Some(key.as_str()),
+                    (true, "r") => Some("<C-r>"),
+                    (true, "[") => Some("Escape"),
+                    _ => None,
+                };
+                if key.is_some_and(|key| vim_key(&textarea, key)) {
+                    ev.prevent_default();
+                    return;
+                }
+            }
+
+            if let Some(current) = carets.get_untracked() {
+                if multi_key(&textarea, &ev, current) {
+                    ev.prevent_default();
+                    return;
+                }
+            }
+
+            // Commands bound in the keymap; global ones are left to the
+            // app's handler.
+            if let Some(spec) = event_chord(&ev).and_then(|c| keymap.with_untracked(|k| k.command_for(&c, Scope::Editor))) {
+                ev.prevent_default();
+                commands.run(spec.id);
+                return;
+            }
+
             if ctrl {
-                match key.as_str() {
-                    "s" | "S" => {
-                        ev.prevent_default();
-                        on_save.run(());
-                    }
-                    " " => {
-                        // Ctrl+Space: explicit completion request.
-                        ev.prevent_default();
-                        run_autocomplete(true);
-                    }
-                    "f" | "F" => {
-                        ev.prevent_default();
-                        show_find.set(true);
-                    }
-                    "b" | "B" => {
-                        ev.prevent_default();
-                        insert("*text*", Some("text"));
-                    }
-                    "i" | "I" => {
-                        ev.prevent_default();
-                        insert("_text_", Some("text"));
-                    }
-                    // Leave native shortcuts (undo/copy/paste/...) untouched.
-                    _ => {}
+                // Ctrl+A selects everything as one selection.
+                if key.eq_ignore_ascii_case("a") {
+                    carets.set(None);
                 }
+                // Leave native shortcuts (undo/copy/paste/...) untouched.
                 return;
             }
 
-            if key == "Tab" {
-                ev.prevent_default();
+            // Enter in a list, enum or term item continues it; on an empty
+            // item it ends the list.
+            if key == "Enter" && !ev.shift_key() && !ev.alt_key() {
                 let (s, e) = selection(&textarea);
-                let cur = source.get_untracked();
-                let bs = utf16_to_byte(&cur, s);
-                let be = utf16_to_byte(&cur, e);
-                let multiline = cur[bs..be].contains('\n');
-                if !multiline && !ev.shift_key() {
-                    insert_text(&textarea, INDENT);
-                } else {
-                    let block_start = cur[..bs].rfind('\n').map(|i| i + 1).unwrap_or(0);
-                    let block_end = cur[be..].find('\n').map(|i| be + i).unwrap_or(cur.len());
-                    let (new_full, _) = if ev.shift_key() {
-                        outdent_block(&cur, bs, be)
-                    } else {
-                        indent_block(&cur, bs, be)
-                    };
-                    let suffix_len = cur.len() - block_end;
-                    let new_block = &new_full[block_start..new_full.len() - suffix_len];
-                    let u_bs = byte_to_utf16(&cur, block_start);
-                    let u_be = byte_to_utf16(&cur, block_end);
-                    set_selection(&textarea, u_bs, u_be);
-                    insert_text(&textarea, new_block);
-                    let new_len = new_block.encode_utf16().count();
-                    set_selection(&textarea, u_bs, u_bs + new_len);
+                let cur = textarea.value();
+                match list_enter(&cur, utf16_to_byte(&cur, s)).filter(|_| s == e) {
+                    Some(ListEnter::Continue(text)) => {
+                        ev.prevent_default();
+                        insert_text(&textarea, &text);
+                    }
+                    Some(ListEnter::End(line)) => {
+                        ev.prevent_default();
+                        set_selection(&textarea, byte_to_utf16(&cur, line.start), byte_to_utf16(&cur, line.end));
+                        insert_text(&textarea, "");
+                    }
+                    None => {}
                 }
                 return;
             }
@@ -374,7 +987,7 @@ pub fn Editor(
                 return;
             };
             let (s, e) = selection(&textarea);
-            let cur = source.get_untracked();
+            let cur = textarea.value();
             let bs = utf16_to_byte(&cur, s);
 
             // Skip over a closing bracket the user re-types in front of.
@@ -534,12 +1147,9 @@ pub fn Editor(
                     </button>
                     <button
                         class="btn btn-xs join-item"
-                        title="Citation (@ref)"
+                        title="Citation (@key from the bibliography)"
                         aria-label="Citation"
-                        on:click={
-                            let insert = insert_at_cursor.clone();
-                            move |_| insert("@citation", Some("citation"))
-                        }
+                        on:click=move |_| set_show_cite_picker.set(true)
                     >
                         <span class="icon-[lucide--quote] text-sm"></span>
                     </button>
@@ -554,10 +1164,68 @@ pub fn Editor(
                     >
                         <span class="icon-[lucide--link] text-sm"></span>
                     </button>
+                    <button
+                        class="btn btn-xs join-item"
+                        title="Rename symbol at the cursor (F2)"
+                        aria-label="Rename symbol"
+                        on:click=move |_| {
+                            if let Some(ta) = textarea_ref.get() {
+                                let (start, _) = selection(&ta);
+                                on_rename.run(to_source(start));
+                            }
+                        }
+                    >
+                        <span class="icon-[lucide--text-cursor-input] text-sm"></span>
+                    </button>
                 </div>
 
                 <div class="divider divider-horizontal mx-0"></div>
 
+                // Formatter group
+                <div class="join join-horizontal">
+                    <button
+                        class="btn btn-xs join-item"
+                        title="Format document, or the selection (Shift+Alt+F)"
+                        aria-label="Format document"
+                        on:click=move |_| format()
+                    >
+                        <span class="icon-[lucide--wand-sparkles] text-sm"></span>
+                    </button>
+                    <button
+                        class="btn btn-xs join-item"
+                        class:btn-active=move || format_on_save.get()
+                        title="Format on save"
+                        aria-label="Format on save"
+                        aria-pressed=move || format_on_save.get().to_string()
+                        on:click=move |_| format_on_save.update(|on| *on = !*on)
+                    >
+                        <span class="icon-[lucide--save] text-sm"></span>
+                    </button>
+                </div>
+
+                <div class="divider divider-horizontal mx-0"></div>
+
+                <button
+                    class="btn btn-xs"
+                    class:btn-active=move || soft_wrap.get()
+                    title="Soft wrap long lines"
+                    aria-label="Soft wrap"
+                    aria-pressed=move || soft_wrap.get().to_string()
+                    on:click=move |_| soft_wrap.update(|on| *on = !*on)
+                >
+                    <span class="icon-[lucide--wrap-text] text-sm"></span>
+                </button>
+                <button
+                    class="btn btn-xs"
+                    class:btn-active=move || vim_mode.get()
+                    title="Vim keybindings"
+                    aria-label="Vim mode"
+                    aria-pressed=move || vim_mode.get().to_string()
+                    on:click=move |_| vim_mode.update(|on| *on = !*on)
+                >
+                    <span class="icon-[lucide--terminal] text-sm"></span>
+                </button>
+
                 // Document settings (hidden #set preamble) popup
                 <button
                     class="btn btn-xs"
@@ -569,6 +1237,22 @@ pub fn Editor(
                 </button>
             </div>
 
+            // Why the formatter refused to run (the source has syntax errors).
+            {move || format_error.get().map(|message| view! {
+                <div role="alert" class="alert alert-warning alert-soft rounded-none py-1 px-3 text-sm">
+                    <span class="icon-[lucide--triangle-alert]"></span>
+                    <span class="flex-1">{format!("Not formatted: {message}")}</span>
+                    <button
+                        class="btn btn-ghost btn-xs btn-square"
+                        title="Dismiss"
+                        aria-label="Dismiss"
+                        on:click=move |_| format_error.set(None)
+                    >
+                        <span class="icon-[lucide--x]"></span>
+                    </button>
+                </div>
+            })}
+
             // Find / replace bar (Ctrl+F). Escape (handled on the field) closes
             // only this bar and returns focus to the editor.
             {move || {
@@ -658,23 +1342,27 @@ pub fn Editor(
 
             // Editor container with syntax highlighting
             <div class="flex-1 min-h-0 relative bg-base-100 overflow-hidden">
-                <div class="editor-container h-full flex">
+                <div class="editor-container h-full flex" class:soft-wrap=move || soft_wrap.get()>
                     // Line-number gutter (scroll-synced with the textarea).
-                    <div class="editor-gutter" aria-hidden="true">
-                        {move || line_numbers.get()}
-                    </div>
+                    // Fold markers are clickable; the numbers are source lines.
+                    <div class="editor-gutter" aria-hidden="true" inner_html=move || gutter.get() on:click=on_gutter_click></div>
                     // Scroll area holding the overlay + transparent textarea.
                     <div class="editor-scroll relative flex-1">
                         // Overlay con syntax highlighting.
                         // aria-hidden: the overlay only mirrors the textarea's text for
                         // visual highlighting; exposing it to the a11y tree made screen
                         // readers announce the whole document twice.
-                        <div
-                            class="syntax-overlay"
-                            aria-hidden="true"
-                            role="presentation"
-                            inner_html=move || highlight_typst(&source.get())
-                        />
+                        <div class="syntax-overlay" aria-hidden="true" role="presentation">
+                            <pre class="typst-highlighted"><code node_ref=overlay_ref></code></pre>
+                            <div
+                                class="caret-layer"
+                                inner_html=move || {
+                                    carets.with(|c| {
+                                        c.as_ref().map(|c| view.with(|v| caret_marks(&v.visible, c, cols()))).unwrap_or_default()
+                                    })
+                                }
+                            ></div>
+                        </div>
                         // Transparent textarea for editing
                         // `prop:value` is intentionally omitted: the guarded
                         // Effect above writes `.value` only when it differs, so
@@ -684,8 +1372,39 @@ pub fn Editor(
                             class="typst-editor"
                             aria-label="Typst source editor"
                             on:input=move |ev| {
-                                set_source.set(event_target_value(&ev));
-                                // Debounced (200ms) autocomplete on typing.
+                                if !multi_edit.get_value() && carets.with_untracked(Option::is_some) {
+                                    carets.set(None);
+                                }
+                                let visible = event_target_value(&ev);
+                                let lines = folded_lines.get_untracked();
+                                if lines.is_empty() {
+                                    set_source.set(visible);
+                                } else {
+                                    // Map the edit onto the full source and move
+                                    // folds below it along with their lines.
+                                    let old = source.get_untracked();
+                                    let (new, (range, text)) = view.with_untracked(|v| {
+                                        if v.is_folded() {
+                                            v.apply(&old, &visible)
+                                        } else {
+                                            let edit = minimal_edit(&old, &visible);
+                                            (visible, edit)
+                                        }
+                                    });
+                                    let moved = shift_folds(&lines, &old, range, &text);
+                                    if moved != lines {
+                                        let name = file_name.get_untracked();
+                                        folds.update(|f| {
+                                            f.insert(name, moved);
+                                        });
+                                    }
+                                    set_source.set(new);
+                                }
+                                // Debounced (200ms) autocomplete on typing (not
+                                // on Vim's normal-mode edits).
+                                if vim_mode.get_untracked() && vim.with_value(|v| v.mode != Mode::Insert) {
+                                    return;
+                                }
                                 let id = ac_debounce.get_untracked() + 1;
                                 ac_debounce.set(id);
                                 spawn_local(async move {
@@ -697,10 +1416,52 @@ pub fn Editor(
                             }
                             on:scroll=sync_scroll
                             on:keydown=on_keydown
+                            // Alt+click adds or removes a caret, Alt+drag selects a
+                            // column; a plain click goes back to one caret.
+                            on:mousedown=move |ev| {
+                                let Some(ta) = textarea_ref.get() else {
+                                    return;
+                                };
+                                if ev.button() != 0 {
+                                    return;
+                                }
+                                if !ev.alt_key() {
+                                    carets.set(None);
+                                    return;
+                                }
+                                ev.prevent_default();
+                                let _ = ta.focus();
+                                let cols = wrap_columns(&ta, soft_wrap.get_untracked());
+                                column_from.set_value(Some(point_at(&ta, &ev, cols)));
+                            }
+                            on:mousemove=move |ev| {
+                                let (Some(from), Some(ta)) = (column_from.get_value(), textarea_ref.get()) else {
+                                    return;
+                                };
+                                if ev.buttons() & 1 == 0 {
+                                    column_from.set_value(None);
+                                    return;
+                                }
+                                let to = point_at(&ta, &ev, wrap_columns(&ta, soft_wrap.get_untracked()));
+                                if to != from {
+                                    show_carets(&ta, Carets::column(&ta.value(), from, to));
+                                }
+                            }
+                            on:mouseup=move |ev| {
+                                let (Some(from), Some(ta)) = (column_from.get_value(), textarea_ref.get()) else {
+                                    return;
+                                };
+                                column_from.set_value(None);
+                                if point_at(&ta, &ev, wrap_columns(&ta, soft_wrap.get_untracked())) == from {
+                                    let mut next = current_carets(&ta);
+                                    next.toggle(offset_at(&ta.value(), from.0, from.1));
+                                    show_carets(&ta, next);
+                                }
+                            }
                             on:blur=move |_| ac_open.set(false)
                             placeholder="Write Typst markup here..."
                             spellcheck="false"
-                            wrap="off"
+                            wrap=move || if soft_wrap.get() { "soft" } else { "off" }
                         />
                         // Autocomplete dropdown (absolute, positioned at the caret).
                         {move || {
@@ -753,6 +1514,18 @@ pub fn Editor(
                     </div>
                 </div>
             </div>
+            // Vim mode line: the mode, a pending command, `/` or `:` input.
+            {move || {
+                vim_mode
+                    .get()
+                    .then(|| {
+                        view! {
+                            <div class="vim-status px-3 py-0.5 text-xs font-mono bg-base-200 border-t border-base-300" role="status">
+                                {move || vim_status.get()}
+                            </div>
+                        }
+                    })
+            }}
         </div>
     }
 }
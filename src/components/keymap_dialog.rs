@@ -0,0 +1,179 @@
+use super::command_palette::event_chord;
+use crate::utils::keymap::{command, fuzzy_score, Keymap, COMMANDS};
+use leptos::html::Div;
+use leptos::prelude::*;
+
+/// "Keyboard shortcuts": every command with its chords, which can be
+/// removed, recorded (click +, then press the keys) or reset to the
+/// defaults. Chords bound to several commands are flagged.
+#[component]
+pub fn KeymapDialog(show: RwSignal<bool>, keymap: RwSignal<Keymap>) -> impl IntoView {
+    let filter = RwSignal::new(String::new());
+    // The command a chord is being recorded for.
+    let recording = RwSignal::new(None::<&'static str>);
+    let box_ref = NodeRef::<Div>::new();
+    let conflicts = Memo::new(move |_| keymap.with(Keymap::conflicts));
+
+    let close = move || {
+        recording.set(None);
+        show.set(false);
+    };
+    // While recording, the next key press (other than a lone Escape, which
+    // cancels) becomes the chord; it goes no further, so it runs nothing.
+    let on_keydown = move |ev: web_sys::KeyboardEvent| {
+        let Some(id) = recording.get_untracked() else {
+            return;
+        };
+        let Some(chord) = event_chord(&ev) else {
+            return;
+        };
+        ev.prevent_default();
+        ev.stop_propagation();
+        if chord != "Escape" {
+            keymap.update(|k| k.add(id, &chord));
+        }
+        recording.set(None);
+    };
+
+    view! {
+        <Show when=move || show.get()>
+            <div class="modal modal-open" role="dialog" aria-modal="true" aria-label="Keyboard shortcuts">
+                <div class="modal-box max-w-3xl" tabindex="-1" node_ref=box_ref on:keydown=on_keydown>
+                    <h3 class="font-bold text-lg flex items-center gap-2">
+                        <span class="icon-[lucide--keyboard] text-xl"></span>
+                        "Keyboard Shortcuts"
+                    </h3>
+                    <input
+                        class="input input-bordered input-sm w-full mt-3"
+                        placeholder="Filter commands"
+                        aria-label="Filter commands"
+                        prop:value=move || filter.get()
+                        on:input=move |ev| filter.set(event_target_value(&ev))
+                    />
+                    {move || {
+                        let conflicts = conflicts.get();
+                        (!conflicts.is_empty())
+                            .then(|| {
+                                view! {
+                                    <div class="alert alert-warning mt-3 text-sm items-start">
+                                        <span class="icon-[lucide--triangle-alert] text-lg"></span>
+                                        <ul>
+                                            {conflicts
+                                                .into_iter()
+                                                .map(|(key, ids)| {
+                                                    let titles = ids
+                                                        .iter()
+                                                        .filter_map(|id| command(id).map(|c| c.title))
+                                                        .collect::<Vec<_>>()
+                                                        .join(", ");
+                                                    view! {
+                                                        <li>
+                                                            <kbd class="kbd kbd-xs">{key}</kbd>
+                                                            {format!(" is bound to {titles}")}
+                                                        </li>
+                                                    }
+                                                })
+                                                .collect::<Vec<_>>()}
+                                        </ul>
+                                    </div>
+                                }
+                            })
+                    }}
+                    <div class="overflow-y-auto max-h-[60vh] mt-3">
+                        <table class="table table-sm">
+                            <tbody>
+                                {move || {
+                                    let query = filter.get();
+                                    let clashing: Vec<String> = conflicts.get().into_iter().map(|(key, _)| key).collect();
+                                    COMMANDS
+                                        .iter()
+                                        .filter(|c| fuzzy_score(&query, c.title).is_some())
+                                        .map(|spec| {
+                                            let id = spec.id;
+                                            let (keys, is_default) = keymap.with(|k| (k.keys(id), k.is_default(id)));
+                                            view! {
+                                                <tr>
+                                                    <td>{spec.title}</td>
+                                                    <td class="flex flex-wrap items-center gap-1">
+                                                        {keys
+                                                            .into_iter()
+                                                            .map(|key| {
+                                                                let class = if clashing.contains(&key) {
+                                                                    "badge badge-sm badge-warning gap-1 font-mono"
+                                                                } else {
+                                                                    "badge badge-sm gap-1 font-mono"
+                                                                };
+                                                                let chord = key.clone();
+                                                                view! {
+                                                                    <span class=class>
+                                                                        {key}
+                                                                        <button
+                                                                            class="icon-[lucide--x] text-xs opacity-60 hover:opacity-100"
+                                                                            title="Remove shortcut"
+                                                                            aria-label="Remove shortcut"
+                                                                            on:click=move |_| keymap.update(|k| k.remove(id, &chord))
+                                                                        ></button>
+                                                                    </span>
+                                                                }
+                                                            })
+                                                            .collect::<Vec<_>>()}
+                                                        {move || {
+                                                            if recording.get() == Some(id) {
+                                                                view! {
+                                                                    <span class="badge badge-sm badge-primary">"Press a shortcut…"</span>
+                                                                }
+                                                                    .into_any()
+                                                            } else {
+                                                                view! {
+                                                                    <button
+                                                                        class="btn btn-ghost btn-xs"
+                                                                        title="Add shortcut"
+                                                                        aria-label="Add shortcut"
+                                                                        on:click=move |_| {
+                                                                            recording.set(Some(id));
+                                                                            if let Some(el) = box_ref.get_untracked() {
+                                                                                let _ = el.focus();
+                                                                            }
+                                                                        }
+                                                                    >
+                                                                        <span class="icon-[lucide--plus] text-xs"></span>
+                                                                    </button>
+                                                                }
+                                                                    .into_any()
+                                                            }
+                                                        }}
+                                                    </td>
+                                                    <td class="text-right">
+                                                        {(!is_default)
+                                                            .then(|| {
+                                                                view! {
+                                                                    <button
+                                                                        class="btn btn-ghost btn-xs"
+                                                                        title="Reset to default"
+                                                                        on:click=move |_| keymap.update(|k| k.reset(id))
+                                                                    >
+                                                                        "Reset"
+                                                                    </button>
+                                                                }
+                                                            })}
+                                                    </td>
+                                                </tr>
+                                            }
+                                        })
+                                        .collect::<Vec<_>>()
+                                }}
+                            </tbody>
+                        </table>
+                    </div>
+                    <div class="modal-action">
+                        <button class="btn btn-ghost" on:click=move |_| keymap.set(Keymap::default())>
+                            "Reset all"
+                        </button>
+                        <button class="btn btn-primary" on:click=move |_| close()>"Close"</button>
+                    </div>
+                </div>
+                <div class="modal-backdrop" on:click=move |_| close()></div>
+            </div>
+        </Show>
+    }
+}
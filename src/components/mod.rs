@@ -1,7 +1,31 @@
+mod asset_drawer;
+mod bibliography_modal;
+mod citation_picker;
+mod citation_style_picker;
+mod command_palette;
 mod editor;
+mod outline_panel;
 mod preview;
+mod problems_panel;
 mod image_gallery;
+mod keymap_dialog;
+mod qr_dialog;
+mod rename_symbol_dialog;
+mod search_panel;
+mod stats_panel;
 
-pub use editor::Editor;
+pub use asset_drawer::AssetDrawer;
+pub use bibliography_modal::BibliographyModal;
+pub use citation_picker::CitationPicker;
+pub use citation_style_picker::CitationStylePicker;
+pub use command_palette::{event_chord, CommandPalette, CommandRegistry};
+pub use editor::{scroll_to_offset, Editor};
+pub use outline_panel::OutlinePanel;
 pub use preview::Preview;
+pub use problems_panel::ProblemsPanel;
 pub use image_gallery::ImageGalleryDrawer;
+pub use keymap_dialog::KeymapDialog;
+pub use qr_dialog::QrDialog;
+pub use rename_symbol_dialog::RenameSymbolDialog;
+pub use search_panel::SearchPanel;
+pub use stats_panel::StatsPanel;
@@ -0,0 +1,107 @@
+use super::preview::scroll_to_page;
+use crate::compiler::document_headings;
+use crate::utils::outline::{syntax_outline, OutlineEntry};
+use crate::utils::project::TypstFile;
+use leptos::prelude::*;
+
+/// Outline sidebar: the headings of the compiled document with their page,
+/// or — until the project compiles — the `=` headings found in its files.
+/// Clicking an entry scrolls the preview to its page and calls `on_open`
+/// with the `(file index, byte offset)` to show in the editor.
+#[component]
+pub fn OutlinePanel(
+    files: RwSignal<Vec<TypstFile>>,
+    output: ReadSignal<String>,
+    error: ReadSignal<Option<String>>,
+    on_close: Callback<()>,
+    on_open: Callback<(usize, usize)>,
+) -> impl IntoView {
+    // `(entries, from_document)`; document headings are re-read after each
+    // successful compile, the syntax fallback on every edit.
+    let outline = Memo::new(move |_| {
+        let compiled = error.with(Option::is_none) && !output.with(String::is_empty);
+        if compiled {
+            let entries: Vec<OutlineEntry> = files.with_untracked(|files| {
+                document_headings()
+                    .into_iter()
+                    .map(|h| OutlineEntry {
+                        level: h.level,
+                        text: h.text,
+                        page: Some(h.page),
+                        target: h.source.and_then(|(file, offset)| match file {
+                            None => Some((0, offset)),
+                            Some(name) => files.iter().position(|f| f.name == name).map(|i| (i, offset)),
+                        }),
+                    })
+                    .collect()
+            });
+            (entries, true)
+        } else {
+            (files.with(|f| syntax_outline(f)), false)
+        }
+    });
+
+    view! {
+        <aside class="w-56 flex-shrink-0 flex flex-col bg-base-100 border-r border-base-300 overflow-hidden" aria-label="Document outline">
+            <div class="flex items-center gap-2 px-3 py-2 bg-base-200 border-b border-base-300">
+                <span class="icon-[lucide--list-tree] text-base"></span>
+                <h2 class="text-sm font-semibold uppercase tracking-wide text-base-content/70">"Outline"</h2>
+                <Show when=move || !outline.with(|o| o.1)>
+                    <span class="badge badge-ghost badge-xs" title="Headings read from the source; pages appear after a successful compile">
+                        "source"
+                    </span>
+                </Show>
+                <button
+                    class="btn btn-ghost btn-xs btn-square ml-auto"
+                    title="Close outline"
+                    aria-label="Close outline"
+                    on:click=move |_| on_close.run(())
+                >
+                    <span class="icon-[lucide--x] text-sm"></span>
+                </button>
+            </div>
+            <nav class="flex-1 overflow-y-auto py-1 text-sm">
+                <Show
+                    when=move || outline.with(|o| !o.0.is_empty())
+                    fallback=|| view! {
+                        <p class="px-3 py-2 text-xs text-base-content/60">
+                            "No headings yet. Start a line with = to add one."
+                        </p>
+                    }
+                >
+                    {move || {
+                        outline
+                            .get()
+                            .0
+                            .into_iter()
+                            .map(|entry| {
+                                let indent = format!("padding-left: {}rem", 0.75 + (entry.level.saturating_sub(1)) as f64 * 0.75);
+                                let OutlineEntry { level, text, page, target } = entry;
+                                let title = text.clone();
+                                view! {
+                                    <button
+                                        class="w-full flex items-baseline gap-2 pr-3 py-1 text-left hover:bg-base-200"
+                                        class:font-semibold=level == 1
+                                        style=indent
+                                        title=title
+                                        on:click=move |_| {
+                                            if let Some(page) = page {
+                                                scroll_to_page(page - 1);
+                                            }
+                                            if let Some(target) = target {
+                                                on_open.run(target);
+                                            }
+                                        }
+                                    >
+                                        <span class="truncate flex-1">{text}</span>
+                                        {page.map(|p| view! { <span class="text-xs text-base-content/50 tabular-nums">{p}</span> })}
+                                    </button>
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    }}
+                </Show>
+            </nav>
+        </aside>
+    }
+}
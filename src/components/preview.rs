@@ -28,6 +28,17 @@ fn click_to_byte(ev: &web_sys::MouseEvent) -> Option<usize> {
     resolve_click(page, x_pt, y_pt)
 }
 
+/// Scroll the preview so the page with 0-based index `page` is in view.
+pub fn scroll_to_page(page: usize) {
+    let Some(document) = web_sys::window().and_then(|w| w.document()) else {
+        return;
+    };
+    let selector = format!(".preview-page[data-page=\"{page}\"]");
+    if let Ok(Some(el)) = document.query_selector(&selector) {
+        el.scroll_into_view();
+    }
+}
+
 #[component]
 pub fn Preview(
     output: ReadSignal<String>,
@@ -125,9 +136,32 @@ pub fn Preview(
                     (!output.get().is_empty())
                         .then(|| {
                             view! {
-                                <span class="text-sm text-base-content/60 tabular-nums">
-                                    {move || format!("p. {} / {}", current_page.get(), page_count.get())}
-                                </span>
+                                <div class="flex items-center gap-1">
+                                    <button
+                                        class="btn btn-xs btn-ghost btn-square"
+                                        title="Previous page"
+                                        aria-label="Previous page"
+                                        disabled=move || current_page.get() <= 1
+                                        on:click=move |_| {
+                                            scroll_to_page(current_page.get_untracked().saturating_sub(2))
+                                        }
+                                    >
+                                        <span class="icon-[lucide--chevron-left] text-sm"></span>
+                                    </button>
+                                    <span class="text-sm text-base-content/60 tabular-nums">
+                                        {move || format!("p. {} / {}", current_page.get(), page_count.get())}
+                                    </span>
+                                    <button
+                                        class="btn btn-xs btn-ghost btn-square"
+                                        title="Next page"
+                                        aria-label="Next page"
+                                        disabled=move || current_page.get() >= page_count.get()
+                                        // `current_page` is 1-based, so it is also the next page's index.
+                                        on:click=move |_| scroll_to_page(current_page.get_untracked())
+                                    >
+                                        <span class="icon-[lucide--chevron-right] text-sm"></span>
+                                    </button>
+                                </div>
                             }
                         })
                 }}
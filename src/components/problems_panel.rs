@@ -0,0 +1,155 @@
+use crate::compiler::document_labels;
+use crate::utils::bibliography::BibFile;
+use crate::utils::lint::{lint_project, FixAction, Lint, LintKind};
+use crate::utils::project::TypstFile;
+use leptos::prelude::*;
+
+/// Problems sidebar: label and reference warnings for the whole project —
+/// duplicate labels, references to nothing, unused labels, citation keys
+/// missing from the bibliography and entries never cited — each with a
+/// quick-fix where one is obvious. Clicking a warning calls `on_open` with
+/// the `(file index, byte offset)` it points at.
+#[component]
+pub fn ProblemsPanel(
+    files: RwSignal<Vec<TypstFile>>,
+    bibliographies: RwSignal<Vec<BibFile>>,
+    output: ReadSignal<String>,
+    error: ReadSignal<Option<String>>,
+    on_close: Callback<()>,
+    on_open: Callback<(usize, usize)>,
+    on_fix: Callback<FixAction>,
+) -> impl IntoView {
+    // Re-linted on every edit; labels and citations produced by code come
+    // from the last successful compile.
+    let lints = Memo::new(move |_| {
+        let compiled = error.with(Option::is_none) && !output.with(String::is_empty);
+        let doc = compiled.then(document_labels).flatten();
+        files.with(|files| bibliographies.with(|bibs| lint_project(files, bibs, doc.as_ref())))
+    });
+
+    // "main.typ:12" for a file location.
+    let location = move |(file, offset): (usize, usize)| {
+        files.with_untracked(|files| {
+            files.get(file).map(|f| {
+                let line = f.content[..offset.min(f.content.len())].matches('\n').count() + 1;
+                format!("{}:{line}", f.name)
+            })
+        })
+    };
+
+    // "Delete entry" fixes ask under their warning before running.
+    let pending = RwSignal::new(Option::<FixAction>::None);
+    let apply = move |action: FixAction| {
+        if matches!(action, FixAction::DeleteEntry { .. }) {
+            pending.set(Some(action));
+        } else {
+            on_fix.run(action);
+        }
+    };
+    let confirm = move || {
+        if let Some(action) = pending.get_untracked() {
+            pending.set(None);
+            on_fix.run(action);
+        }
+    };
+
+    view! {
+        <aside class="w-64 flex-shrink-0 flex flex-col bg-base-100 border-r border-base-300 overflow-hidden" aria-label="Problems">
+            <div class="flex items-center gap-2 px-3 py-2 bg-base-200 border-b border-base-300">
+                <span class="icon-[lucide--triangle-alert] text-base"></span>
+                <h2 class="text-sm font-semibold uppercase tracking-wide text-base-content/70">"Problems"</h2>
+                <span class="badge badge-warning badge-xs" class:hidden=move || lints.with(Vec::is_empty)>
+                    {move || lints.with(Vec::len)}
+                </span>
+                <button
+                    class="btn btn-ghost btn-xs btn-square ml-auto"
+                    title="Close problems"
+                    aria-label="Close problems"
+                    on:click=move |_| on_close.run(())
+                >
+                    <span class="icon-[lucide--x] text-sm"></span>
+                </button>
+            </div>
+            <div class="flex-1 overflow-y-auto py-1 text-sm">
+                <Show
+                    when=move || lints.with(|l| !l.is_empty())
+                    fallback=|| view! {
+                        <p class="flex items-center gap-2 px-3 py-2 text-xs text-base-content/60">
+                            <span class="icon-[lucide--circle-check] text-success"></span>
+                            "Labels, references and citations all check out."
+                        </p>
+                    }
+                >
+                    {move || {
+                        lints
+                            .get()
+                            .into_iter()
+                            .map(|lint| {
+                                let Lint { kind, message, target, bib, fix } = lint;
+                                let place = target.and_then(location).or(bib);
+                                let icon = match kind {
+                                    LintKind::UnusedLabel | LintKind::UncitedEntry => "icon-[lucide--info] text-info mt-0.5 flex-shrink-0",
+                                    _ => "icon-[lucide--triangle-alert] text-warning mt-0.5 flex-shrink-0",
+                                };
+                                view! {
+                                    <div class="px-3 py-1.5 border-b border-base-200 hover:bg-base-200">
+                                        <button
+                                            class="flex items-start gap-2 w-full text-left"
+                                            disabled=target.is_none()
+                                            on:click=move |_| {
+                                                if let Some(target) = target {
+                                                    on_open.run(target);
+                                                }
+                                            }
+                                        >
+                                            <span class=icon></span>
+                                            <span class="flex-1 min-w-0">
+                                                <span class="block break-words">{message}</span>
+                                                <span class="block text-xs text-base-content/50 font-mono truncate">{place}</span>
+                                            </span>
+                                        </button>
+                                        {fix.map(|fix| {
+                                            let question = match &fix.action {
+                                                FixAction::DeleteEntry { key, bib } => format!("Delete {key} from {bib}?"),
+                                                FixAction::Edit { .. } => String::new(),
+                                            };
+                                            let action = fix.action.clone();
+                                            let title = fix.title.clone();
+                                            view! {
+                                                <Show
+                                                    when=move || pending.with(|p| p.as_ref() == Some(&fix.action))
+                                                    fallback=move || {
+                                                        let action = action.clone();
+                                                        view! {
+                                                            <button
+                                                                class="btn btn-xs btn-ghost text-primary ml-6 mt-0.5"
+                                                                on:click=move |_| apply(action.clone())
+                                                            >
+                                                                <span class="icon-[lucide--wand-sparkles] text-xs"></span>
+                                                                {title.clone()}
+                                                            </button>
+                                                        }
+                                                    }
+                                                >
+                                                    <div class="flex flex-wrap items-center gap-1 ml-6 mt-0.5 text-xs" role="alert">
+                                                        <span class="w-full">{question.clone()}</span>
+                                                        <button class="btn btn-xs btn-error" on:click=move |_| confirm()>
+                                                            "Delete"
+                                                        </button>
+                                                        <button class="btn btn-xs btn-ghost" on:click=move |_| pending.set(None)>
+                                                            "Cancel"
+                                                        </button>
+                                                    </div>
+                                                </Show>
+                                            }
+                                        })}
+                                    </div>
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    }}
+                </Show>
+            </div>
+        </aside>
+    }
+}
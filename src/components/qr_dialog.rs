@@ -0,0 +1,188 @@
+use crate::utils::image_manager::ImageManager;
+use crate::utils::qr::{self, EcLevel};
+use crate::utils::{download_bytes, download_svg_as_png};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use std::collections::HashMap;
+
+/// Quiet zone around the symbol, in modules (the standard minimum).
+const QR_BORDER: usize = 4;
+
+/// Share-link QR code dialog: renders the editor or viewer link at a chosen
+/// error-correction level, with SVG/PNG downloads and "add to gallery".
+#[component]
+pub fn QrDialog(
+    show: ReadSignal<bool>,
+    set_show: WriteSignal<bool>,
+    /// Builds the share URL; `true` requests a read-only viewer link.
+    share_url: Callback<bool, Option<String>>,
+    set_image_cache: WriteSignal<HashMap<String, String>>,
+) -> impl IntoView {
+    // Printed handouts go to readers, so default to the viewer link.
+    let view_only = RwSignal::new(true);
+    let level = RwSignal::new(EcLevel::default());
+    let (status, set_status) = signal(Option::<String>::None);
+
+    // Rebuilt whenever the dialog opens (the project may have changed since).
+    let url = Memo::new(move |_| {
+        if !show.get() {
+            return String::new();
+        }
+        share_url.run(view_only.get()).unwrap_or_default()
+    });
+    let code = Memo::new(move |_| {
+        let url = url.get();
+        if url.is_empty() {
+            return Err("No share link available".to_string());
+        }
+        qr::encode(url.as_bytes(), level.get()).map(|qr| (qr.to_svg(QR_BORDER), qr.version()))
+    });
+    let svg = move || code.get().map(|(svg, _)| svg).ok();
+
+    let add_to_gallery = move |_| {
+        let Some(svg) = svg() else { return };
+        let data_url = format!("data:image/svg+xml;base64,{}", STANDARD.encode(svg));
+        spawn_local(async move {
+            let manager = ImageManager::new();
+            match manager.store_image(&data_url, "share-qr.svg").await {
+                Ok(id) => {
+                    set_status.set(Some(format!("Added to the gallery — use #image(\"{id}\")")));
+                    // Keep the compiler cache in sync so the image works at once.
+                    if let Ok(imgs) = manager.list_all_images().await {
+                        set_image_cache.set(
+                            imgs.into_iter().map(|img| (img.id, img.data)).collect(),
+                        );
+                    }
+                }
+                Err(e) => set_status.set(Some(format!("Could not add to gallery: {e}"))),
+            }
+        });
+    };
+
+    let close = move || {
+        set_status.set(None);
+        set_show.set(false);
+    };
+
+    view! {
+        <Show when=move || show.get()>
+            <div class="modal modal-open" role="dialog" aria-modal="true">
+                <div class="modal-box max-w-lg">
+                    <h3 class="font-bold text-lg flex items-center gap-2">
+                        <span class="icon-[lucide--qr-code] text-xl"></span>
+                        "Share as QR code"
+                    </h3>
+
+                    <div class="flex flex-wrap items-center gap-4 my-3">
+                        <div class="join">
+                            <button
+                                class="btn btn-xs join-item"
+                                class:btn-active=move || view_only.get()
+                                on:click=move |_| view_only.set(true)
+                            >
+                                "Viewer link"
+                            </button>
+                            <button
+                                class="btn btn-xs join-item"
+                                class:btn-active=move || !view_only.get()
+                                on:click=move |_| view_only.set(false)
+                            >
+                                "Editor link"
+                            </button>
+                        </div>
+                        <div class="join" title="Error correction: higher levels survive damage but hold less">
+                            {EcLevel::ALL
+                                .into_iter()
+                                .map(|l| {
+                                    view! {
+                                        <button
+                                            class="btn btn-xs join-item"
+                                            class:btn-active=move || level.get() == l
+                                            on:click=move |_| level.set(l)
+                                        >
+                                            {l.label()}
+                                        </button>
+                                    }
+                                })
+                                .collect::<Vec<_>>()}
+                        </div>
+                    </div>
+
+                    {move || match code.get() {
+                        Ok((svg, version)) => {
+                            let len = url.with(|u| u.len());
+                            view! {
+                                <div class="flex flex-col items-center gap-2">
+                                    <div class="w-64 h-64 bg-white p-1 rounded" inner_html=svg></div>
+                                    <p class="text-xs text-base-content/60 tabular-nums">
+                                        {format!(
+                                            "Version {version} · {len} / {} bytes",
+                                            qr::max_bytes(level.get()),
+                                        )}
+                                    </p>
+                                </div>
+                            }
+                            .into_any()
+                        }
+                        Err(e) => {
+                            view! {
+                                <div class="alert alert-warning" role="alert">
+                                    <span class="icon-[lucide--triangle-alert] text-xl"></span>
+                                    <div class="text-sm">
+                                        <p>{e}</p>
+                                        <p class="opacity-70">
+                                            "Try a lower error-correction level, or shrink the project (e.g. fewer files)."
+                                        </p>
+                                    </div>
+                                </div>
+                            }
+                            .into_any()
+                        }
+                    }}
+
+                    {move || status.get().map(|msg| view! { <p class="text-sm text-success mt-2">{msg}</p> })}
+
+                    <div class="modal-action">
+                        <button
+                            class="btn btn-sm gap-2"
+                            disabled=move || code.with(|c| c.is_err())
+                            on:click=move |_| {
+                                if let Some(svg) = svg() {
+                                    download_bytes("share-qr.svg", "image/svg+xml", svg.as_bytes());
+                                }
+                            }
+                        >
+                            <span class="icon-[lucide--download] text-lg"></span>
+                            "SVG"
+                        </button>
+                        <button
+                            class="btn btn-sm gap-2"
+                            disabled=move || code.with(|c| c.is_err())
+                            on:click=move |_| {
+                                if let Some(svg) = svg() {
+                                    download_svg_as_png("share-qr.png", &svg, 1.0);
+                                }
+                            }
+                        >
+                            <span class="icon-[lucide--image-down] text-lg"></span>
+                            "PNG"
+                        </button>
+                        <button
+                            class="btn btn-sm btn-primary gap-2"
+                            disabled=move || code.with(|c| c.is_err())
+                            on:click=add_to_gallery
+                        >
+                            <span class="icon-[lucide--image-plus] text-lg"></span>
+                            "Add to gallery"
+                        </button>
+                        <button class="btn btn-sm btn-ghost" on:click=move |_| close()>
+                            "Close"
+                        </button>
+                    </div>
+                </div>
+                <div class="modal-backdrop" on:click=move |_| close()></div>
+            </div>
+        </Show>
+    }
+}
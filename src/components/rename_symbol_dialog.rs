@@ -0,0 +1,151 @@
+use crate::utils::project::TypstFile;
+use crate::utils::rename::{plan_rename, preview, symbol_at, RenamePlan, SymbolKind};
+use leptos::html::Input;
+use leptos::prelude::*;
+
+/// "Rename symbol" dialog for the `(file index, byte offset)` in `target`:
+/// asks for the new name and previews every changed line across the project
+/// before `on_apply` runs the rename. Closes by clearing `target`.
+#[component]
+pub fn RenameSymbolDialog(
+    target: RwSignal<Option<(usize, usize)>>,
+    files: RwSignal<Vec<TypstFile>>,
+    on_apply: Callback<RenamePlan>,
+) -> impl IntoView {
+    let new_name = RwSignal::new(String::new());
+    let input_ref = NodeRef::<Input>::new();
+
+    let symbol = Memo::new(move |_| {
+        let (file, offset) = target.get()?;
+        files.with_untracked(|f| symbol_at(f, file, offset))
+    });
+
+    // Start from the current name, selected, each time the dialog opens.
+    Effect::new(move |_| {
+        if let Some((_, name)) = symbol.get() {
+            new_name.set(name);
+            if let Some(el) = input_ref.get() {
+                let _ = el.focus();
+                el.select();
+            }
+        }
+    });
+
+    // `None` until a different name is typed.
+    let plan = Memo::new(move |_| {
+        let (file, offset) = target.get()?;
+        let (_, old) = symbol.get()?;
+        let name = new_name.get();
+        if name.trim().is_empty() || name.trim() == old {
+            return None;
+        }
+        Some(files.with_untracked(|f| plan_rename(f, file, offset, &name)))
+    });
+    let lines = Memo::new(move |_| match plan.get() {
+        Some(Ok(plan)) => files.with_untracked(|f| {
+            preview(f, &plan)
+                .into_iter()
+                .map(|line| (f[line.file].name.clone(), line))
+                .collect::<Vec<_>>()
+        }),
+        _ => Vec::new(),
+    });
+
+    let close = move || target.set(None);
+    let apply = move || {
+        if let Some(Ok(plan)) = plan.get_untracked() {
+            close();
+            on_apply.run(plan);
+        }
+    };
+
+    view! {
+        <Show when=move || target.with(Option::is_some)>
+            <div class="modal modal-open" role="dialog" aria-modal="true" aria-label="Rename symbol">
+                <div class="modal-box max-w-3xl">
+                    <h3 class="font-bold text-lg flex items-center gap-2">
+                        <span class="icon-[lucide--text-cursor-input] text-xl"></span>
+                        "Rename Symbol"
+                    </h3>
+                    <Show
+                        when=move || symbol.with(Option::is_some)
+                        fallback=|| view! {
+                            <p class="text-sm text-base-content/70 mt-3">
+                                "Place the cursor on a variable, function, parameter or label to rename it."
+                            </p>
+                        }
+                    >
+                        <label class="form-control w-full mt-3">
+                            <span class="label-text text-sm mb-1">
+                                {move || match symbol.get() {
+                                    Some((SymbolKind::Label, old)) => format!("Rename label <{old}> to"),
+                                    Some((SymbolKind::Binding, old)) => format!("Rename {old} to"),
+                                    None => String::new(),
+                                }}
+                            </span>
+                            <input
+                                node_ref=input_ref
+                                class="input input-bordered w-full font-mono"
+                                aria-label="New name"
+                                prop:value=move || new_name.get()
+                                on:input=move |ev| new_name.set(event_target_value(&ev))
+                                on:keydown=move |ev| {
+                                    if ev.key() == "Enter" {
+                                        ev.prevent_default();
+                                        apply();
+                                    }
+                                }
+                            />
+                        </label>
+                        {move || match plan.get() {
+                            Some(Err(message)) => Some(view! {
+                                <p class="flex items-center gap-2 text-sm text-error mt-2">
+                                    <span class="icon-[lucide--circle-alert]"></span>
+                                    {message}
+                                </p>
+                            }.into_any()),
+                            Some(Ok(plan)) => Some(view! {
+                                <p class="text-sm text-base-content/70 mt-2">
+                                    {format!(
+                                        "{} change(s) in {} line(s). Strings and comments are left alone.",
+                                        plan.edits.len(),
+                                        lines.with(Vec::len),
+                                    )}
+                                </p>
+                            }.into_any()),
+                            None => None,
+                        }}
+                        <div class="overflow-y-auto max-h-80 mt-2 font-mono text-xs">
+                            {move || {
+                                lines
+                                    .get()
+                                    .into_iter()
+                                    .map(|(name, line)| {
+                                        view! {
+                                            <div class="py-1 border-b border-base-200">
+                                                <div class="text-base-content/50">{format!("{name}:{}", line.line)}</div>
+                                                <div class="whitespace-pre overflow-x-auto text-error">{format!("- {}", line.before)}</div>
+                                                <div class="whitespace-pre overflow-x-auto text-success">{format!("+ {}", line.after)}</div>
+                                            </div>
+                                        }
+                                    })
+                                    .collect::<Vec<_>>()
+                            }}
+                        </div>
+                    </Show>
+                    <div class="modal-action">
+                        <button class="btn btn-ghost" on:click=move |_| close()>"Cancel"</button>
+                        <button
+                            class="btn btn-primary"
+                            disabled=move || !matches!(plan.get(), Some(Ok(_)))
+                            on:click=move |_| apply()
+                        >
+                            "Rename"
+                        </button>
+                    </div>
+                </div>
+                <div class="modal-backdrop" on:click=move |_| close()></div>
+            </div>
+        </Show>
+    }
+}
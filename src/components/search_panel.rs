@@ -0,0 +1,197 @@
+use crate::utils::project::TypstFile;
+use crate::utils::search::{replace_all, search_project, SearchMatch, SearchOptions, SearchScope};
+use leptos::html::Input;
+use leptos::prelude::*;
+
+/// Search sidebar: find across every project file with regex, case and
+/// whole-word toggles and a markup/code/math filter, results grouped per
+/// file. Clicking a result calls `on_open` with its `(file index, byte
+/// offset)`; "Replace all" hands `on_replace` a summary and the new content
+/// of each changed file.
+#[component]
+pub fn SearchPanel(
+    files: RwSignal<Vec<TypstFile>>,
+    on_close: Callback<()>,
+    on_open: Callback<(usize, usize)>,
+    on_replace: Callback<(String, Vec<(usize, String)>)>,
+) -> impl IntoView {
+    let options = RwSignal::new(SearchOptions::default());
+    let replacement = RwSignal::new(String::new());
+    let query_ref = NodeRef::<Input>::new();
+
+    let results = Memo::new(move |_| options.with(|o| files.with(|f| search_project(f, o))));
+    // `(file index, file name, matches)` in project order.
+    let grouped = Memo::new(move |_| {
+        let mut groups: Vec<(usize, String, Vec<SearchMatch>)> = Vec::new();
+        if let Ok(matches) = results.get() {
+            files.with_untracked(|f| {
+                for m in matches {
+                    match groups.last_mut() {
+                        Some((file, _, list)) if *file == m.file => list.push(m),
+                        _ => groups.push((m.file, f[m.file].name.clone(), vec![m])),
+                    }
+                }
+            });
+        }
+        groups
+    });
+    let total = move || results.with(|r| r.as_ref().map_or(0, Vec::len));
+
+    Effect::new(move |_| {
+        if let Some(el) = query_ref.get() {
+            let _ = el.focus();
+        }
+    });
+
+    let run_replace = move || {
+        let replaced = files.with_untracked(|f| options.with_untracked(|o| replace_all(f, o, &replacement.get_untracked())));
+        if let Some(changed) = replaced.ok().filter(|c| !c.is_empty()) {
+            let message = format!("Replaced {} match(es) in {} file(s)", total(), changed.len());
+            on_replace.run((message, changed));
+        }
+    };
+
+    // An on/off option button; `flag` picks the field it toggles.
+    let toggle = move |title: &'static str, text: &'static str, flag: fn(&mut SearchOptions) -> &mut bool| {
+        let on = move || options.with(|o| *flag(&mut o.clone()));
+        view! {
+            <button
+                class="btn btn-xs join-item font-mono"
+                class:btn-active=on
+                title=title
+                aria-label=title
+                aria-pressed=move || on().to_string()
+                on:click=move |_| options.update(|o| *flag(o) = !*flag(o))
+            >
+                {text}
+            </button>
+        }
+    };
+
+    view! {
+        <aside class="w-72 flex-shrink-0 flex flex-col bg-base-100 border-r border-base-300 overflow-hidden" aria-label="Search">
+            <div class="flex items-center gap-2 px-3 py-2 bg-base-200 border-b border-base-300">
+                <span class="icon-[lucide--search] text-base"></span>
+                <h2 class="text-sm font-semibold uppercase tracking-wide text-base-content/70">"Search"</h2>
+                <span class="badge badge-ghost badge-xs" class:hidden=move || total() == 0>{total}</span>
+                <button
+                    class="btn btn-ghost btn-xs btn-square ml-auto"
+                    title="Close search"
+                    aria-label="Close search"
+                    on:click=move |_| on_close.run(())
+                >
+                    <span class="icon-[lucide--x] text-sm"></span>
+                </button>
+            </div>
+            <div class="flex flex-col gap-1.5 px-3 py-2 border-b border-base-300">
+                <input
+                    node_ref=query_ref
+                    class="input input-xs input-bordered w-full font-mono"
+                    placeholder="Find in all files"
+                    aria-label="Find in all files"
+                    prop:value=move || options.with(|o| o.query.clone())
+                    on:input=move |ev| options.update(|o| o.query = event_target_value(&ev))
+                />
+                <div class="flex items-center gap-1">
+                    <div class="join">
+                        {toggle("Match case", "Aa", |o| &mut o.case_sensitive)}
+                        {toggle("Whole word", "ab", |o| &mut o.whole_word)}
+                        {toggle("Regular expression", ".*", |o| &mut o.regex)}
+                    </div>
+                    <select
+                        class="select select-xs select-bordered flex-1 min-w-0"
+                        aria-label="Search in"
+                        on:change=move |ev| {
+                            let picked = event_target_value(&ev);
+                            if let Some(scope) = SearchScope::ALL.into_iter().find(|s| s.label() == picked) {
+                                options.update(|o| o.scope = scope);
+                            }
+                        }
+                    >
+                        {SearchScope::ALL
+                            .into_iter()
+                            .map(|scope| view! {
+                                <option value=scope.label() selected=move || options.with(|o| o.scope == scope)>
+                                    {scope.label()}
+                                </option>
+                            })
+                            .collect::<Vec<_>>()}
+                    </select>
+                </div>
+                <div class="flex items-center gap-1">
+                    <input
+                        class="input input-xs input-bordered flex-1 min-w-0 font-mono"
+                        placeholder="Replace"
+                        aria-label="Replace with"
+                        prop:value=move || replacement.get()
+                        on:input=move |ev| replacement.set(event_target_value(&ev))
+                        on:keydown=move |ev| {
+                            if ev.key() == "Enter" {
+                                ev.prevent_default();
+                                run_replace();
+                            }
+                        }
+                    />
+                    <button
+                        class="btn btn-xs"
+                        title="Replace every match in every file"
+                        disabled=move || total() == 0
+                        on:click=move |_| run_replace()
+                    >
+                        "Replace all"
+                    </button>
+                </div>
+                {move || results.get().err().map(|message| view! {
+                    <p class="flex items-center gap-1 text-xs text-error">
+                        <span class="icon-[lucide--circle-alert]"></span>
+                        {message}
+                    </p>
+                })}
+            </div>
+            <div class="flex-1 overflow-y-auto py-1 text-sm">
+                {move || {
+                    grouped
+                        .get()
+                        .into_iter()
+                        .map(|(_, name, matches)| {
+                            let count = matches.len();
+                            view! {
+                                <div class="flex items-center gap-1 px-3 pt-2 pb-0.5 text-xs font-semibold text-base-content/70">
+                                    <span class="icon-[lucide--file-text]"></span>
+                                    <span class="truncate flex-1">{name}</span>
+                                    <span class="badge badge-ghost badge-xs">{count}</span>
+                                </div>
+                                {matches
+                                    .into_iter()
+                                    .map(|m| {
+                                        let target = (m.file, m.range.start);
+                                        let text = &m.line_text;
+                                        let before = text[..m.in_line.start].trim_start().to_string();
+                                        let found = text[m.in_line.clone()].to_string();
+                                        let after = text[m.in_line.end..].to_string();
+                                        view! {
+                                            <button
+                                                class="w-full flex items-baseline gap-2 px-3 py-0.5 text-left hover:bg-base-200"
+                                                on:click=move |_| on_open.run(target)
+                                            >
+                                                <span class="text-xs text-base-content/50 tabular-nums w-6 text-right flex-shrink-0">{m.line}</span>
+                                                <span class="truncate font-mono text-xs">
+                                                    {before}
+                                                    <mark class="bg-warning/40 text-base-content rounded-sm">{found}</mark>
+                                                    {after}
+                                                </span>
+                                            </button>
+                                        }
+                                    })
+                                    .collect::<Vec<_>>()}
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                }}
+                <Show when=move || options.with(|o| !o.query.is_empty()) && total() == 0 && results.with(Result::is_ok)>
+                    <p class="px-3 py-2 text-xs text-base-content/60">"No matches."</p>
+                </Show>
+            </div>
+        </aside>
+    }
+}
@@ -0,0 +1,121 @@
+use crate::compiler::document_counts;
+use crate::utils::project::TypstFile;
+use crate::utils::stats::{syntax_stats, DocStats};
+use leptos::prelude::*;
+
+/// Statistics sidebar: words and characters of the project as written (the
+/// main file plus everything it `#include`s), words per heading section, and
+/// pages, figures, tables, equations and citations of the compiled document.
+/// Until the project compiles, the element counts are estimated from the
+/// source.
+#[component]
+pub fn StatsPanel(
+    files: RwSignal<Vec<TypstFile>>,
+    output: ReadSignal<String>,
+    error: ReadSignal<Option<String>>,
+    on_close: Callback<()>,
+) -> impl IntoView {
+    // `(stats, from_document)`; the compiled counts are re-read after each
+    // successful compile, the text counts on every edit.
+    let stats = Memo::new(move |_| {
+        let mut stats = files.with(|f| syntax_stats(f));
+        let compiled = error.with(Option::is_none) && !output.with(String::is_empty);
+        let counts = compiled.then(document_counts).flatten();
+        let from_document = counts.is_some();
+        if let Some((pages, elements)) = counts {
+            stats.pages = Some(pages);
+            stats.elements = elements;
+        }
+        (stats, from_document)
+    });
+
+    let row = |label: &'static str, value: Signal<String>| {
+        view! {
+            <div class="flex items-baseline justify-between gap-2 px-3 py-0.5">
+                <span class="text-base-content/70">{label}</span>
+                <span class="font-mono tabular-nums">{move || value.get()}</span>
+            </div>
+        }
+    };
+    let count = move |f: fn(&DocStats) -> usize| {
+        Signal::derive(move || stats.with(|(s, _)| f(s)).to_string())
+    };
+
+    view! {
+        <aside class="w-56 flex-shrink-0 flex flex-col bg-base-100 border-r border-base-300 overflow-hidden" aria-label="Document statistics">
+            <div class="flex items-center gap-2 px-3 py-2 bg-base-200 border-b border-base-300">
+                <span class="icon-[lucide--chart-column] text-base"></span>
+                <h2 class="text-sm font-semibold uppercase tracking-wide text-base-content/70">"Statistics"</h2>
+                <Show when=move || !stats.with(|s| s.1)>
+                    <span class="badge badge-ghost badge-xs" title="Element counts estimated from the source; exact after a successful compile">
+                        "source"
+                    </span>
+                </Show>
+                <button
+                    class="btn btn-ghost btn-xs btn-square ml-auto"
+                    title="Close statistics"
+                    aria-label="Close statistics"
+                    on:click=move |_| on_close.run(())
+                >
+                    <span class="icon-[lucide--x] text-sm"></span>
+                </button>
+            </div>
+            <div class="flex-1 overflow-y-auto py-2 text-sm">
+                {row("Words", count(|s| s.words))}
+                {row("Characters", count(|s| s.characters))}
+                {row("With spaces", count(|s| s.characters_with_spaces))}
+                {row("Pages", Signal::derive(move || stats.with(|(s, _)| s.pages.map_or("–".to_string(), |p| p.to_string()))))}
+                {row("Reading time", Signal::derive(move || stats.with(|(s, _)| format!("~{} min", s.reading_minutes()))))}
+
+                <div class="divider my-1 px-3"></div>
+                {row("Figures", count(|s| s.elements.figures))}
+                {row("Tables", count(|s| s.elements.tables))}
+                {row("Equations", count(|s| s.elements.equations))}
+                {row("Citations", count(|s| s.elements.citations))}
+                {row("References", count(|s| s.elements.references))}
+
+                <div class="divider my-1 px-3"></div>
+                <h3 class="px-3 pb-1 text-xs font-semibold uppercase tracking-wide text-base-content/60">
+                    "Words per section"
+                </h3>
+                <Show
+                    when=move || stats.with(|(s, _)| !s.sections.is_empty())
+                    fallback=|| view! {
+                        <p class="px-3 text-xs text-base-content/60">"No headings yet."</p>
+                    }
+                >
+                    <Show when=move || stats.with(|(s, _)| s.front_words > 0)>
+                        <div class="flex items-baseline gap-2 px-3 py-0.5 italic text-base-content/70">
+                            <span class="truncate flex-1">"Before first heading"</span>
+                            <span class="font-mono tabular-nums">{move || stats.with(|(s, _)| s.front_words)}</span>
+                        </div>
+                    </Show>
+                    {move || {
+                        stats
+                            .with(|(s, _)| s.sections.clone())
+                            .into_iter()
+                            .map(|section| {
+                                let indent = format!("padding-left: {}rem", 0.75 + section.level.saturating_sub(1) as f64 * 0.75);
+                                let title = section.title.clone();
+                                view! {
+                                    <div
+                                        class="flex items-baseline gap-2 pr-3 py-0.5"
+                                        class:font-semibold=section.level == 1
+                                        style=indent
+                                        title=title
+                                    >
+                                        <span class="truncate flex-1">{section.title}</span>
+                                        <span class="font-mono tabular-nums">{section.words}</span>
+                                    </div>
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    }}
+                </Show>
+                <p class="px-3 pt-2 text-xs text-base-content/50">
+                    "Words are counted in the text as written, including included files; numbering, math and the bibliography don't count. Section counts include subsections."
+                </p>
+            </div>
+        </aside>
+    }
+}
@@ -6,6 +6,7 @@ use std::time::Duration;
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use wasm_bindgen::JsCast;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use typst::syntax::package::PackageSpec;
 
 // Modules
@@ -14,15 +15,34 @@ mod compiler;
 mod utils;
 
 // Top-Level components
-use crate::components::{Editor, Preview, ImageGalleryDrawer};
+use crate::components::{event_chord, AssetDrawer, BibliographyModal, CitationPicker, CitationStylePicker, CommandPalette, CommandRegistry, Editor, KeymapDialog, OutlinePanel, Preview, ImageGalleryDrawer, ProblemsPanel, QrDialog, RenameSymbolDialog, SearchPanel, StatsPanel, scroll_to_offset};
 use crate::compiler::{
-    compile_to_pdf, compile_to_svg, install_package, packages, take_missing_packages,
+    compile_to_pdf, compile_to_svg, count_font_faces, install_fonts, install_package, packages,
+    page_svg, take_missing_packages,
+};
+use crate::utils::{download_bytes, download_svg_as_png};
+use crate::utils::assets::AssetStorage;
+use crate::utils::bibliography::{
+    compiler_inputs, load_bibliographies, save_bibliographies, BibFile, LEGACY_BIB_NAME,
+};
+use crate::utils::font_storage::FontStorage;
+use crate::utils::image_manager::ImageManager;
+use crate::utils::keymap::{load_keymap, save_keymap, Scope};
+use crate::utils::import::{
+    dropped_entries, file_name, image_mime, plan_import, read_entries, rewrite_paths, DroppedFile,
+    ImportPlan, ImportReport,
 };
-use crate::utils::download_bytes;
 use crate::utils::package_storage::PackageStorage;
 use crate::utils::project::{load_files, save_files, TypstFile};
-use crate::utils::share::{build_share_url, source_from_url, strip_url_fragment};
-use crate::utils::editing::{byte_to_utf16, insert_text, selection, set_selection, utf16_to_byte};
+use crate::utils::share::{
+    build_share_url, payload_from_url, strip_share_url, strip_url_fragment, view_mode_from_url,
+    SharePayload,
+};
+use crate::utils::bib_entries::delete_entry;
+use crate::utils::lint::FixAction;
+use crate::utils::rename::RenamePlan;
+use crate::utils::editing::{byte_to_utf16, insert_text, replace_changed, selection, set_selection, sync_value, utf16_to_byte};
+use crate::utils::format::format_source;
 
 /// Which file-management dialog (if any) is currently open.
 #[derive(Clone, Copy)]
@@ -30,6 +50,36 @@ enum FileDialog {
     New,
     Rename(usize),
     Delete(usize),
+    /// Keep the shared copy as the local project, replacing the stored one.
+    KeepCopy,
+    /// Replace the project with this many dropped `.typ` files.
+    Import(usize),
+}
+
+/// Which editor sidebar (if any) is open beside the editor.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Sidebar {
+    Search,
+    Outline,
+    Stats,
+    Problems,
+}
+
+/// A boolean setting stored in localStorage under `key`, written back on change.
+fn persisted_flag(key: &'static str) -> RwSignal<bool> {
+    let flag = RwSignal::new(
+        web_sys::window()
+            .and_then(|w| w.local_storage().ok().flatten())
+            .and_then(|s| s.get_item(key).ok().flatten())
+            .is_some_and(|v| v == "true"),
+    );
+    Effect::new(move |_| {
+        let on = flag.get();
+        if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+            let _ = storage.set_item(key, &on.to_string());
+        }
+    });
+    flag
 }
 
 /// Typst Studio main app component
@@ -81,17 +131,25 @@ pub fn App() -> impl IntoView {
     const TEMPLATE_IEEE: &str = include_str!("../templates/ieee.typ");
     const TEMPLATE_IEEE_BIB: &str = include_str!("../examples/refs.yml");
 
-    // Build the initial project. A shared URL fragment always yields a fresh
-    // single-file project (shared snapshots are single-file) and is then stripped
-    // so reloads use the persisted project instead of the stale snapshot.
-    // Otherwise restore the persisted multi-file project, falling back to a single
-    // `main.typ` from the legacy single-file key or the bundled default.
-    let initial_files: Vec<TypstFile> = if let Some(shared) = source_from_url() {
+    // Build the initial project. A shared URL fragment (compressed `#p=` payload
+    // or legacy single-file `#src=`) yields the shared project and is then
+    // stripped so reloads use the persisted project instead of the stale
+    // snapshot. Otherwise restore the persisted multi-file project, falling back
+    // to a single `main.typ` from the legacy single-file key or the bundled default.
+    //
+    // With `?view=1` a shared project opens in the read-only viewer instead: the
+    // fragment stays in the URL (reloads keep showing the shared document) and
+    // nothing is persisted until the user explicitly opens it in the editor.
+    let shared = payload_from_url();
+    let view_mode = RwSignal::new(shared.is_some() && view_mode_from_url());
+    // A viewed document opened in the editor is an unsaved copy: nothing is
+    // persisted, so the stored project survives, until the user keeps it.
+    let shared_copy = RwSignal::new(false);
+    if shared.is_some() && !view_mode.get_untracked() {
         strip_url_fragment();
-        vec![TypstFile {
-            name: "main.typ".to_string(),
-            content: shared,
-        }]
+    }
+    let initial_files: Vec<TypstFile> = if let Some(shared) = shared.as_ref() {
+        shared.files.clone()
     } else if let Some(files) = load_files() {
         files
     } else {
@@ -184,33 +242,41 @@ typst2023:
   url: "https://typst.app"
 "##;
 
-    let loaded_bib = if let Some(window) = web_sys::window() {
-        if let Ok(Some(storage)) = window.local_storage() {
-            storage
-                .get_item("typst_bibliography")
-                .ok()
-                .flatten()
-                .unwrap_or_else(|| initial_bib.to_string())
-        } else {
-            initial_bib.to_string()
-        }
-    } else {
-        initial_bib.to_string()
-    };
+    // A shared payload carries its own bibliography files; legacy links don't,
+    // so those keep the locally stored ones (migrated from the single
+    // `typst_bibliography` value on first load).
+    let loaded_bibs = shared
+        .as_ref()
+        .and_then(|p| p.bibliographies.clone())
+        .or_else(load_bibliographies)
+        .unwrap_or_else(|| {
+            vec![BibFile {
+                name: LEGACY_BIB_NAME.to_string(),
+                content: initial_bib.to_string(),
+            }]
+        });
 
-    let (bibliography, set_bibliography) = signal(loaded_bib);
+    let bibliographies = RwSignal::new(loaded_bibs);
     let (show_bib_modal, set_show_bib_modal) = signal(false);
 
     // Document settings preamble: hidden `#set` rules prepended at compile time
     // so they apply without appearing in the editor. Restored from localStorage.
     const DEFAULT_SETTINGS: &str =
         "#set math.equation(numbering: \"(1)\")\n#set page(numbering: \"1\")";
-    let loaded_settings = web_sys::window()
-        .and_then(|w| w.local_storage().ok().flatten())
-        .and_then(|s| s.get_item("typst_settings").ok().flatten())
+    let loaded_settings = shared
+        .as_ref()
+        .and_then(|p| p.settings.clone())
+        .or_else(|| {
+            web_sys::window()
+                .and_then(|w| w.local_storage().ok().flatten())
+                .and_then(|s| s.get_item("typst_settings").ok().flatten())
+        })
         .unwrap_or_else(|| DEFAULT_SETTINGS.to_string());
     let (settings, set_settings) = signal(loaded_settings);
     let (show_settings, set_show_settings) = signal(false);
+    let (show_cite_picker, set_show_cite_picker) = signal(false);
+    let sidebar = RwSignal::new(Option::<Sidebar>::None);
+    let close_sidebar = Callback::new(move |()| sidebar.set(None));
 
     // Append a preset `#set` rule to the settings preamble (on its own line).
     let add_setting = move |line: &str| {
@@ -225,25 +291,53 @@ typst2023:
     // Image gallery drawer state
     let (show_image_gallery, set_show_image_gallery) = signal(false);
 
+    // Project assets (data files) drawer state
+    let (show_assets, set_show_assets) = signal(false);
+
     // Template picker modal state
     let (show_templates, set_show_templates) = signal(false);
 
+    // Share-link QR code dialog state
+    let (show_qr, set_show_qr) = signal(false);
+
     // Inline file-management dialog (replaces native window.prompt/confirm).
     let file_dialog = RwSignal::new(Option::<FileDialog>::None);
     let dialog_input = RwSignal::new(String::new());
 
-    // Close any open overlay (modal/drawer/dialog) on the Escape key.
+    // Summary of the last project import (folder drop), shown as a dialog.
+    let import_report = RwSignal::new(Option::<ImportReport>::None);
+    // "Rename symbol" dialog target: `(file index, byte offset)`.
+    let rename_target = RwSignal::new(Option::<(usize, usize)>::None);
+    // The last multi-file change (rename, replace all), for its "Undo"
+    // toast: message, then the previous and the written content of every
+    // file it changed.
+    let bulk_undo = RwSignal::new(Option::<(String, Vec<(usize, String)>, Vec<(usize, String)>)>::None);
+
+    // Named commands (run from the palette or a shortcut) and their
+    // shortcuts, whose user overrides persist in localStorage.
+    let commands = CommandRegistry::default();
+    let keymap = RwSignal::new(load_keymap());
+    Effect::new(move |_| keymap.with(save_keymap));
+    let show_palette = RwSignal::new(false);
+    let show_keymap = RwSignal::new(false);
+
+    // Run the global command bound to a key press. Keys the editor (or a
+    // dialog) already handled are skipped; the viewer only closes dialogs.
     {
         use wasm_bindgen::closure::Closure;
         let on_keydown = Closure::<dyn FnMut(web_sys::KeyboardEvent)>::new(
             move |ev: web_sys::KeyboardEvent| {
-                if ev.key() == "Escape" {
-                    set_show_bib_modal.set(false);
-                    set_show_image_gallery.set(false);
-                    set_show_settings.set(false);
-                    set_show_templates.set(false);
-                    file_dialog.set(None);
+                if ev.default_prevented() {
+                    return;
                 }
+                let Some(spec) = event_chord(&ev).and_then(|c| keymap.with_untracked(|k| k.command_for(&c, Scope::Global))) else {
+                    return;
+                };
+                if view_mode.get_untracked() && spec.id != "view.closeDialogs" {
+                    return;
+                }
+                ev.prevent_default();
+                commands.run(spec.id);
             },
         );
         if let Some(window) = web_sys::window() {
@@ -280,6 +374,40 @@ typst2023:
         });
     }
 
+    // In-memory asset cache: virtual path -> bytes, served to `#read`/`#csv`/…
+    let (asset_cache, set_asset_cache) = signal(HashMap::<String, Vec<u8>>::new());
+
+    // Load assets from IndexedDB into cache on app start
+    spawn_local(async move {
+        match AssetStorage::new().list_all().await {
+            Ok(list) => {
+                log::info!("Asset cache initialized with {} assets", list.len());
+                set_asset_cache.set(list.into_iter().collect());
+            }
+            Err(e) => log::error!("Failed to load assets: {}", e),
+        }
+    });
+
+    // Folded editor regions per file name (see `utils::folding`); kept for
+    // the session only.
+    let folds = RwSignal::new(HashMap::<String, Vec<usize>>::new());
+    let active_name = Signal::derive(move || {
+        files.with(|f| f.get(active.get()).map(|f| f.name.clone()).unwrap_or_default())
+    });
+    // Unfold the open file, so the textarea holds the whole source again
+    // before it is edited or navigated by source offsets.
+    let unfold_active = move || {
+        let name = files.with_untracked(|f| f.get(active.get_untracked()).map(|f| f.name.clone()));
+        if let Some(name) = name.filter(|n| folds.with_untracked(|f| f.contains_key(n))) {
+            folds.update(|f| {
+                f.remove(&name);
+            });
+        }
+        if let Some(ta) = textarea_ref.get_untracked() {
+            sync_value(&ta, &source.get_untracked());
+        }
+    };
+
     // Insert text at the cursor (wrapped in Rc for sharing). Routed through
     // `insert_text` (execCommand) so the browser's native undo stack survives;
     // the dispatched `input` event keeps the `source` signal in sync.
@@ -287,8 +415,9 @@ typst2023:
         let Some(textarea) = textarea_ref.get() else { return };
 
         // Textarea selection offsets are UTF-16 code units (JS semantics).
+        // Offsets are into what the textarea shows, which may be folded.
         let (sel_start, sel_end) = selection(&textarea);
-        let current = source.get_untracked();
+        let current = textarea.value();
         let byte_start = utf16_to_byte(&current, sel_start);
         let byte_end = utf16_to_byte(&current, sel_end);
 
@@ -320,18 +449,61 @@ typst2023:
         }
     });
 
+    // Editor toolbar toggles.
+    let format_on_save = persisted_flag("typst_format_on_save");
+    let soft_wrap = persisted_flag("typst_soft_wrap");
+    let vim_mode = persisted_flag("typst_vim_mode");
+
+    // Write the whole project (files, bibliographies, settings) to localStorage.
+    let persist_project = move || {
+        save_files(&files.get_untracked());
+        save_bibliographies(&bibliographies.get_untracked());
+        if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+            let _ = storage.set_item("typst_settings", &settings.get_untracked());
+        }
+    };
+    // Keep the shared copy as the local project; that replaces the stored
+    // project, so ask first when there is one.
+    let keep_copy = move || {
+        if load_files().is_some() {
+            file_dialog.set(Some(FileDialog::KeepCopy));
+        } else {
+            shared_copy.set(false);
+            persist_project();
+        }
+    };
+
     // Ctrl+S: force-persist the whole project (files + bibliography + settings)
     // and flash a "Saved" toast. Autosave already runs on change; this gives the
-    // shortcut explicit, visible feedback.
+    // shortcut explicit, visible feedback. With format on save, the open file
+    // is formatted first (as an undoable edit); a file with syntax errors is
+    // saved unformatted.
+    // On a shared copy it offers to keep it instead.
     let save_project = Callback::new(move |_: ()| {
-        save_files(&files.get_untracked());
-        if let Some(window) = web_sys::window() {
-            if let Ok(Some(storage)) = window.local_storage() {
-                let _ = storage.set_item("typst_bibliography", &bibliography.get_untracked());
-                let _ = storage.set_item("typst_settings", &settings.get_untracked());
+        if shared_copy.get_untracked() {
+            keep_copy();
+            return;
+        }
+        let mut saved = "Saved".to_string();
+        if format_on_save.get_untracked() {
+            unfold_active();
+            let cur = source.get_untracked();
+            match (format_source(&cur), textarea_ref.get()) {
+                (Ok(new), Some(ta)) => {
+                    replace_changed(&ta, &cur, &new);
+                    let idx = active.get_untracked();
+                    files.update(|f| {
+                        if let Some(file) = f.get_mut(idx) {
+                            file.content = new;
+                        }
+                    });
+                }
+                (Err(_), _) => saved = "Saved (not formatted: syntax errors)".to_string(),
+                _ => {}
             }
         }
-        set_share_toast.set(Some("Saved".to_string()));
+        persist_project();
+        set_share_toast.set(Some(saved));
         spawn_local(async move {
             sleep(Duration::from_millis(1500)).await;
             set_share_toast.set(None);
@@ -341,16 +513,20 @@ typst2023:
     // Jump the editor caret to a byte offset (from a preview click), scrolling
     // the clicked source into view.
     let jump_to = Callback::new(move |byte: usize| {
+        // The viewer has no editor to jump into.
+        if view_mode.get_untracked() {
+            return;
+        }
         let Some(ta) = textarea_ref.get() else {
             return;
         };
+        unfold_active();
         let cur = source.get_untracked();
         let byte = byte.min(cur.len());
         let u = byte_to_utf16(&cur, byte);
         let _ = ta.focus();
         set_selection(&ta, u, u);
-        let line = cur[..byte].matches('\n').count() as f64;
-        ta.set_scroll_top(((line * 22.4) - 60.0).max(0.0) as i32);
+        scroll_to_offset(&ta, &cur, byte, soft_wrap.get_untracked());
     });
 
     // Mirror the editor buffer into the active file slot on every edit, so
@@ -372,13 +548,23 @@ typst2023:
     // Persist the whole project to localStorage on any change. The first (mount)
     // run is skipped so merely opening the app — or following a shared link —
     // never overwrites an existing stored project before the user edits it.
+    // The viewer never persists: a viewed document must not replace the local one.
     Effect::new(move |prev: Option<()>| {
         let snapshot = files.get();
-        if prev.is_some() {
+        if prev.is_some() && !view_mode.get_untracked() && !shared_copy.get_untracked() {
             save_files(&snapshot);
         }
     });
 
+    // Persist the bibliography files the same way (first run skipped, never
+    // while viewing).
+    Effect::new(move |prev: Option<()>| {
+        let snapshot = bibliographies.get();
+        if prev.is_some() && !view_mode.get_untracked() && !shared_copy.get_untracked() {
+            save_bibliographies(&snapshot);
+        }
+    });
+
     // Switch the editor to another tab (the outgoing file is already mirrored
     // into `files` by the effect above).
     let switch_to = move |idx: usize| {
@@ -388,8 +574,124 @@ typst2023:
         }
     };
 
+    // Open a project location (from the outline or problems sidebar): switch
+    // tabs if needed, then place the caret once the editor holds that file.
+    let open_at = Callback::new(move |(idx, byte): (usize, usize)| {
+        if idx == active.get_untracked() {
+            jump_to.run(byte);
+            return;
+        }
+        switch_to(idx);
+        spawn_local(async move {
+            sleep(Duration::from_millis(0)).await;
+            jump_to.run(byte);
+        });
+    });
+
+    // Apply a quick-fix from the problems sidebar. Edits to the open file go
+    // through the textarea so they can be undone; other files are patched
+    // directly. Fixes computed against stale text are dropped.
+    let apply_fix = Callback::new(move |action: FixAction| match action {
+        FixAction::Edit { file, range, text } => {
+            if file == active.get_untracked() {
+                let Some(ta) = textarea_ref.get() else { return };
+                let cur = source.get_untracked();
+                if cur.get(range.clone()).is_none() {
+                    return;
+                }
+                unfold_active();
+                set_selection(&ta, byte_to_utf16(&cur, range.start), byte_to_utf16(&cur, range.end));
+                insert_text(&ta, &text);
+            } else {
+                files.update(|f| {
+                    if let Some(f) = f.get_mut(file).filter(|f| f.content.get(range.clone()).is_some()) {
+                        f.content.replace_range(range, &text);
+                    }
+                });
+            }
+        }
+        FixAction::DeleteEntry { bib, key } => {
+            bibliographies.update(|bibs| {
+                if let Some(file) = bibs.iter_mut().find(|b| b.name == bib) {
+                    match delete_entry(file, &key) {
+                        Ok(content) => file.content = content,
+                        Err(e) => log::error!("Failed to delete {key} from {bib}: {e}"),
+                    }
+                }
+            });
+        }
+    });
+
+    // Replace the content of several files at once. The open file is replaced
+    // through the textarea, so the whole change is one native undo step there.
+    let replace_contents = move |contents: Vec<(usize, String)>| {
+        let current = active.get_untracked();
+        for (file, content) in contents {
+            if file == current {
+                if let Some(ta) = textarea_ref.get() {
+                    unfold_active();
+                    let len = source.with_untracked(|s| s.encode_utf16().count());
+                    set_selection(&ta, 0, len);
+                    insert_text(&ta, &content);
+                }
+            } else {
+                files.update(|f| {
+                    if let Some(f) = f.get_mut(file) {
+                        f.content = content;
+                    }
+                });
+            }
+        }
+    };
+
+    // Apply a change to several files at once (rename, replace all), keeping
+    // what it replaced so the toast can undo the whole change in one step.
+    let apply_bulk = Callback::new(move |(message, changed): (String, Vec<(usize, String)>)| {
+        let previous = files.with_untracked(|f| {
+            changed.iter().map(|(i, _)| (*i, f[*i].content.clone())).collect::<Vec<_>>()
+        });
+        replace_contents(changed.clone());
+        bulk_undo.set(Some((message.clone(), previous, changed)));
+        spawn_local(async move {
+            sleep(Duration::from_millis(8000)).await;
+            if bulk_undo.with_untracked(|u| u.as_ref().is_some_and(|(m, _, _)| *m == message)) {
+                bulk_undo.set(None);
+            }
+        });
+    });
+    let apply_rename = Callback::new(move |plan: RenamePlan| {
+        let changed = files.with_untracked(|f| plan.apply(f));
+        let message = format!("Renamed {} to {} in {} place(s)", plan.old, plan.new, plan.edits.len());
+        apply_bulk.run((message, changed));
+    });
+    // Undo only while every file still holds what the change wrote: typing
+    // since then would be overwritten by the snapshots.
+    let undo_bulk = move |_| {
+        let Some((_, previous, written)) = bulk_undo.get_untracked() else {
+            return;
+        };
+        bulk_undo.set(None);
+        let current = active.get_untracked();
+        let untouched = written.iter().all(|(file, content)| {
+            if *file == current {
+                source.with_untracked(|s| s == content)
+            } else {
+                files.with_untracked(|f| f.get(*file).is_some_and(|f| f.content == *content))
+            }
+        });
+        if untouched {
+            replace_contents(previous);
+            return;
+        }
+        set_share_toast.set(Some("Not undone: the files were edited since".to_string()));
+        spawn_local(async move {
+            sleep(Duration::from_millis(2500)).await;
+            set_share_toast.set(None);
+        });
+    };
+
     // Apply a bundled template: replace the whole project with a single
-    // `main.typ`, optionally swapping in a matching bibliography.
+    // `main.typ`, optionally swapping in a matching `refs.yml`.
     let apply_template = move |content: &str, bib: Option<&str>| {
         files.set(vec![TypstFile {
             name: "main.typ".to_string(),
@@ -398,13 +700,21 @@ typst2023:
         set_active.set(0);
         set_source.set(content.to_string());
         if let Some(b) = bib {
-            set_bibliography.set(b.to_string());
+            bibliographies.update(|bibs| {
+                match bibs.iter_mut().find(|f| f.name == LEGACY_BIB_NAME) {
+                    Some(file) => file.content = b.to_string(),
+                    None => bibs.push(BibFile {
+                        name: LEGACY_BIB_NAME.to_string(),
+                        content: b.to_string(),
+                    }),
+                }
+            });
         }
         set_show_templates.set(false);
     };
 
     // Open the inline dialogs (replacing native prompt/confirm).
-    let open_new_file = move |_| {
+    let open_new_file = move || {
         dialog_input.set(format!("file{}.typ", files.with_untracked(|f| f.len())));
         file_dialog.set(Some(FileDialog::New));
     };
@@ -420,60 +730,6 @@ typst2023:
         }
     };
 
-    // Apply the currently open dialog's action, then close it.
-    let apply_dialog = move || {
-        let Some(dialog) = file_dialog.get_untracked() else {
-            return;
-        };
-        match dialog {
-            FileDialog::New => {
-                let name = dialog_input.get_untracked().trim().to_string();
-                if name.is_empty() {
-                    return;
-                }
-                files.update(|f| {
-                    f.push(TypstFile {
-                        name,
-                        content: String::new(),
-                    })
-                });
-                let new_idx = files.with_untracked(|f| f.len() - 1);
-                set_active.set(new_idx);
-                set_source.set(String::new());
-            }
-            FileDialog::Rename(idx) => {
-                let name = dialog_input.get_untracked().trim().to_string();
-                if name.is_empty() {
-                    return;
-                }
-                files.update(|f| {
-                    if let Some(file) = f.get_mut(idx) {
-                        file.name = name;
-                    }
-                });
-            }
-            FileDialog::Delete(idx) => {
-                if files.with_untracked(|f| f.len()) <= 1 {
-                    file_dialog.set(None);
-                    return;
-                }
-                let old_active = active.get_untracked();
-                files.update(|f| {
-                    f.remove(idx);
-                });
-                let new_len = files.with_untracked(|f| f.len());
-                let new_active = if old_active > idx {
-                    old_active - 1
-                } else {
-                    old_active.min(new_len - 1)
-                };
-                set_active.set(new_active);
-                set_source.set(files.with_untracked(|f| f[new_active].content.clone()));
-            }
-        }
-        file_dialog.set(None);
-    };
-
     // ----- @preview package support -----
     // `package_epoch` is a reactive trigger: installing a package bumps it so the
     // compile Effect reruns. `in_flight` / `failed` are non-reactive bookkeeping
@@ -547,6 +803,195 @@ typst2023:
         }
     });
 
+    // ----- User fonts -----
+    // Bumped after fonts are installed (which rebuilds the engine) to recompile.
+    let font_epoch = RwSignal::new(0u32);
+
+    // On startup, re-install fonts imported with earlier projects.
+    spawn_local(async move {
+        match FontStorage::new().list_all().await {
+            Ok(list) if !list.is_empty() => {
+                install_fonts(list.into_iter().map(|(_, bytes)| bytes).collect());
+                font_epoch.update(|e| *e += 1);
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to load stored fonts: {e}"),
+        }
+    });
+
+    // ----- Project import (folder / files dropped onto the editor) -----
+    let (drag_over_editor, set_drag_over_editor) = signal(false);
+
+    // Route each dropped file to its store: `.typ` files replace the project,
+    // images go to the gallery, fonts to the engine and the font store, data
+    // files to the assets store, cited YAML / .bib files replace the
+    // bibliography files. Image paths in the sources are rewritten to gallery
+    // IDs.
+    let run_import = move |plan: ImportPlan| {
+        spawn_local(async move {
+            let mut report = ImportReport {
+                skipped: plan.skipped,
+                ..Default::default()
+            };
+            let mut replacements = HashMap::new();
+
+            if !plan.images.is_empty() {
+                let manager = ImageManager::new();
+                for image in plan.images {
+                    let data_url = format!(
+                        "data:{};base64,{}",
+                        image_mime(&image.path),
+                        STANDARD.encode(&image.bytes)
+                    );
+                    match manager.store_image(&data_url, file_name(&image.path)).await {
+                        Ok(id) => {
+                            replacements.insert(image.path, id);
+                            report.images += 1;
+                        }
+                        Err(e) => report.skipped.push((image.path, e)),
+                    }
+                }
+                if let Ok(imgs) = manager.list_all_images().await {
+                    set_image_cache.set(imgs.into_iter().map(|img| (img.id, img.data)).collect());
+                }
+            }
+
+            let mut new_fonts = Vec::new();
+            for font in plan.fonts {
+                if count_font_faces(&font.bytes) == 0 {
+                    report.skipped.push((font.path, "not a readable font file".to_string()));
+                    continue;
+                }
+                if let Err(e) = FontStorage::new().store(&font.path, &font.bytes).await {
+                    log::error!("Failed to store font {}: {e}", font.path);
+                }
+                new_fonts.push(font.bytes);
+                report.fonts += 1;
+            }
+            if !new_fonts.is_empty() {
+                install_fonts(new_fonts);
+                font_epoch.update(|e| *e += 1);
+            }
+
+            // Assets keep their project path, so the sources need no rewriting.
+            if !plan.assets.is_empty() {
+                let storage = AssetStorage::new();
+                for asset in plan.assets {
+                    match storage.store(&asset.path, &asset.bytes).await {
+                        Ok(()) => report.assets += 1,
+                        Err(e) => report.skipped.push((asset.path, e)),
+                    }
+                }
+                if let Ok(list) = storage.list_all().await {
+                    set_asset_cache.set(list.into_iter().collect());
+                }
+            }
+
+            if !plan.bibliographies.is_empty() {
+                report.bibliographies = plan.bibliographies.len();
+                bibliographies.set(
+                    plan.bibliographies
+                        .into_iter()
+                        .map(|(name, content)| BibFile { name, content })
+                        .collect(),
+                );
+            }
+
+            if !plan.typst.is_empty() {
+                let imported: Vec<TypstFile> = plan
+                    .typst
+                    .iter()
+                    .map(|(path, content)| TypstFile {
+                        name: path.clone(),
+                        content: rewrite_paths(content, path, &replacements),
+                    })
+                    .collect();
+                report.files = imported.len();
+                let main = imported[0].content.clone();
+                files.set(imported);
+                set_active.set(0);
+                set_source.set(main);
+            }
+
+            import_report.set(Some(report));
+        });
+    };
+    // A drop with `.typ` files replaces the project, so it waits in
+    // `pending_import` for the inline confirmation.
+    let pending_import = StoredValue::new(Option::<ImportPlan>::None);
+    let import_files = move |dropped: Vec<DroppedFile>| {
+        let plan = plan_import(dropped);
+        if plan.typst.is_empty() {
+            run_import(plan);
+        } else {
+            file_dialog.set(Some(FileDialog::Import(plan.typst.len())));
+            pending_import.set_value(Some(plan));
+        }
+    };
+
+    // Apply the currently open dialog's action, then close it.
+    let apply_dialog = move || {
+        let Some(dialog) = file_dialog.get_untracked() else {
+            return;
+        };
+        match dialog {
+            FileDialog::New => {
+                let name = dialog_input.get_untracked().trim().to_string();
+                if name.is_empty() {
+                    return;
+                }
+                files.update(|f| {
+                    f.push(TypstFile {
+                        name,
+                        content: String::new(),
+                    })
+                });
+                let new_idx = files.with_untracked(|f| f.len() - 1);
+                set_active.set(new_idx);
+                set_source.set(String::new());
+            }
+            FileDialog::Rename(idx) => {
+                let name = dialog_input.get_untracked().trim().to_string();
+                if name.is_empty() {
+                    return;
+                }
+                files.update(|f| {
+                    if let Some(file) = f.get_mut(idx) {
+                        file.name = name;
+                    }
+                });
+            }
+            FileDialog::KeepCopy => {
+                shared_copy.set(false);
+                persist_project();
+            }
+            FileDialog::Import(_) => {
+                if let Some(plan) = pending_import.try_update_value(Option::take).flatten() {
+                    run_import(plan);
+                }
+            }
+            FileDialog::Delete(idx) => {
+                if files.with_untracked(|f| f.len()) <= 1 {
+                    file_dialog.set(None);
+                    return;
+                }
+                let old_active = active.get_untracked();
+                files.update(|f| {
+                    f.remove(idx);
+                });
+                let new_len = files.with_untracked(|f| f.len());
+                let new_active = if old_active > idx {
+                    old_active - 1
+                } else {
+                    old_active.min(new_len - 1)
+                };
+                set_active.set(new_active);
+                set_source.set(files.with_untracked(|f| f[new_active].content.clone()));
+            }
+        }
+        file_dialog.set(None);
+    };
+
     // Debouncing: use a counter to identify the latest update
     let (debounce_id, set_debounce_id) = signal(0u32);
 
@@ -555,10 +1000,13 @@ typst2023:
     // to the compiler so the main file can `#include` / `#import` them.
     Effect::new(move |_| {
         let project = files.get();
-        let bib = bibliography.get();
+        let bibs = compiler_inputs(&bibliographies.get());
         let settings_val = settings.get();
         // Recompile when a package is installed (the download loop bumps this).
         let epoch = package_epoch.get();
+        // ...and when user fonts are installed.
+        font_epoch.track();
+        let assets = asset_cache.get();
 
         // Increment the ID to invalidate previous tasks
         let current_id = debounce_id.get_untracked() + 1;
@@ -574,13 +1022,6 @@ typst2023:
                 // Set compiling state
                 set_is_compiling.set(true);
 
-                // Pass the bibliography if it is not empty
-                let bib_option = if bib.trim().is_empty() {
-                    None
-                } else {
-                    Some(bib.as_str())
-                };
-
                 // Get current image cache
                 let images = image_cache.get_untracked();
 
@@ -591,7 +1032,7 @@ typst2023:
                     .map(|f| (f.name.clone(), f.content.clone()))
                     .collect();
 
-                match compile_to_svg(&main, &settings_val, bib_option, &images, &extra) {
+                match compile_to_svg(&main, &settings_val, &bibs, &images, &extra, &assets) {
                     Ok(svg) => {
                         // Count pages for the indicator (preserve scroll position
                         // — no scroll-to-top reset on recompile).
@@ -629,6 +1070,133 @@ typst2023:
         });
     });
 
+    // A URL carrying the whole project (files, bibliography, settings)
+    // compressed into the fragment; `view_only` links open in the viewer.
+    let share_url = Callback::new(move |view_only: bool| {
+        let payload = SharePayload::new(
+            files.get_untracked(),
+            bibliographies.get_untracked(),
+            settings.get_untracked(),
+        );
+        build_share_url(&payload, view_only)
+    });
+
+    // Copy a share link to the clipboard and confirm with a toast.
+    let copy_share_link = move |view_only: bool| {
+        let Some(url) = share_url.run(view_only) else { return };
+        let Some(window) = web_sys::window() else { return };
+        let promise = window.navigator().clipboard().write_text(&url);
+        spawn_local(async move {
+            let msg = match wasm_bindgen_futures::JsFuture::from(promise).await {
+                Ok(_) if view_only => "Viewer link copied to clipboard!",
+                Ok(_) => "Share link copied to clipboard!",
+                Err(_) => "Failed to copy share link",
+            };
+            set_share_toast.set(Some(msg.to_string()));
+            sleep(Duration::from_millis(2500)).await;
+            set_share_toast.set(None);
+        });
+    };
+
+    // Compile the whole project to PDF and download it (editor and viewer).
+    let download_pdf = move || {
+        let project = files.get();
+        let main = project.first().map(|f| f.content.clone()).unwrap_or_default();
+        if !main.is_empty() && error.get().is_none() {
+            let bibs = compiler_inputs(&bibliographies.get());
+            let images = image_cache.get();
+            let assets = asset_cache.get();
+            let settings_val = settings.get();
+            let extra: Vec<(String, String)> = project
+                .iter()
+                .skip(1)
+                .map(|f| (f.name.clone(), f.content.clone()))
+                .collect();
+            match compile_to_pdf(&main, &settings_val, &bibs, &images, &extra, &assets) {
+                Ok(pdf_bytes) => {
+                    download_bytes("document.pdf", "application/pdf", &pdf_bytes);
+                }
+                Err(e) => {
+                    log::error!("PDF compilation failed: {}", e);
+                    set_error.set(Some(e));
+                }
+            }
+        }
+    };
+
+    // Download the open file's source, and the rendered document as SVG.
+    let download_source = move || {
+        let content = source.get_untracked();
+        if !content.is_empty() {
+            let name = files
+                .with_untracked(|f| f.get(active.get_untracked()).map(|x| x.name.clone()))
+                .unwrap_or_else(|| "document.typ".to_string());
+            download_bytes(&name, "text/plain;charset=utf-8", content.as_bytes());
+        }
+    };
+    let download_svg = move || {
+        let svg_content = output.get_untracked();
+        if !svg_content.is_empty() && error.get_untracked().is_none() {
+            download_bytes("document.svg", "image/svg+xml", svg_content.as_bytes());
+        }
+    };
+
+    // ----- Global commands -----
+    // The editor registers the editor-scoped ones.
+    let toggle_sidebar = move |panel: Sidebar| {
+        sidebar.update(|s| *s = if *s == Some(panel) { None } else { Some(panel) })
+    };
+    let switch_by = move |step: isize| {
+        let len = files.with_untracked(|f| f.len()) as isize;
+        switch_to((active.get_untracked() as isize + step).rem_euclid(len) as usize);
+    };
+    commands.register("palette.open", move || show_palette.set(true));
+    commands.register("keymap.open", move || show_keymap.set(true));
+    // Close any open overlay (modal/drawer/dialog).
+    commands.register("view.closeDialogs", move || {
+        set_show_bib_modal.set(false);
+        set_show_image_gallery.set(false);
+        set_show_assets.set(false);
+        set_show_settings.set(false);
+        set_show_cite_picker.set(false);
+        set_show_templates.set(false);
+        set_show_qr.set(false);
+        show_palette.set(false);
+        show_keymap.set(false);
+        import_report.set(None);
+        rename_target.set(None);
+        file_dialog.set(None);
+    });
+    commands.register("file.save", move || save_project.run(()));
+    commands.register("file.new", open_new_file);
+    commands.register("file.rename", move || open_rename(active.get_untracked()));
+    commands.register("file.delete", move || open_delete(active.get_untracked()));
+    commands.register("file.next", move || switch_by(1));
+    commands.register("file.previous", move || switch_by(-1));
+    commands.register("templates.open", move || set_show_templates.set(true));
+    commands.register("export.pdf", download_pdf);
+    commands.register("export.svg", download_svg);
+    commands.register("export.source", download_source);
+    commands.register("share.editorLink", move || copy_share_link(false));
+    commands.register("share.viewerLink", move || copy_share_link(true));
+    commands.register("share.qr", move || set_show_qr.set(true));
+    commands.register("search.project", move || sidebar.set(Some(Sidebar::Search)));
+    commands.register("view.outline", move || toggle_sidebar(Sidebar::Outline));
+    commands.register("view.stats", move || toggle_sidebar(Sidebar::Stats));
+    commands.register("view.problems", move || toggle_sidebar(Sidebar::Problems));
+    commands.register("view.images", move || set_show_image_gallery.set(true));
+    commands.register("view.assets", move || set_show_assets.set(true));
+    commands.register("view.bibliography", move || set_show_bib_modal.set(true));
+    commands.register("view.theme", move || set_is_dark_theme.update(|v| *v = !*v));
+
+    // Viewer: leave read-only mode, editing the shared document as an unsaved
+    // copy. The stored project is untouched until the copy is kept.
+    let open_in_editor = move |_| {
+        strip_share_url();
+        shared_copy.set(true);
+        view_mode.set(false);
+    };
+
     view! {
         <Html attr:lang="en" attr:dir="ltr" attr:data-theme="dark" />
         <Title text="Typst Studio - Pure Rust WASM" />
@@ -642,6 +1210,25 @@ typst2023:
                     <img src="ty_bolt.svg" alt="Typst Studio" class="h-7 w-7" />
                     <h1 class="text-xl font-bold">"Typst Studio"</h1>
                     <span class="text-sm text-base-content/60">"(Pure Rust WASM)"</span>
+                    <Show when=move || view_mode.get()>
+                        <span class="badge badge-outline gap-1">
+                            <span class="icon-[lucide--eye] text-sm"></span>
+                            "Read-only"
+                        </span>
+                    </Show>
+                    <Show when=move || shared_copy.get()>
+                        <span class="badge badge-warning gap-1" title="Not saved in this browser">
+                            <span class="icon-[lucide--copy] text-sm"></span>
+                            "Unsaved copy"
+                        </span>
+                        <button
+                            class="btn btn-xs btn-primary"
+                            title="Keep this document as the project stored in this browser"
+                            on:click=move |_| keep_copy()
+                        >
+                            "Keep"
+                        </button>
+                    </Show>
 
                     // Compilation indicator
                     {move || {
@@ -674,155 +1261,182 @@ typst2023:
                 </div>
                 <div class="flex-none flex items-center gap-2">
 
-                    // New document (template picker)
-                    <button
-                        class="btn btn-sm btn-ghost gap-2"
-                        on:click=move |_| set_show_templates.set(true)
-                    >
-                        <span class="icon-[lucide--file-plus] text-lg"></span>
-                        "New"
-                    </button>
-
-                    // Image Gallery button
-                    <button
-                        class="btn btn-sm btn-ghost gap-2"
-                        on:click=move |_| set_show_image_gallery.set(true)
-                    >
-                        <span class="icon-[lucide--image] text-lg"></span>
-                        "Images"
-                    </button>
-
-                    // Bibliography button
-                    <button
-                        class="btn btn-sm btn-ghost gap-2"
-                        on:click=move |_| set_show_bib_modal.set(true)
-                    >
-                        <span class="icon-[lucide--book-open] text-lg"></span>
-                        "Bibliography"
-                    </button>
-
-                    // Upload .typ file button
-                    <label class="btn btn-sm btn-ghost gap-2 cursor-pointer">
-                        <input
-                            type="file"
-                            accept=".typ"
-                            class="hidden"
-                            on:change=move |ev| {
-                                let Some(input) = ev.target()
-                                    .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
-                                else { return };
-                                if let Some(files) = input.files() {
-                                    if let Some(file) = files.get(0) {
-                                        let Ok(reader) = web_sys::FileReader::new() else { return };
-                                        let reader_clone = reader.clone();
-                                        let onload = wasm_bindgen::closure::Closure::wrap(
-                                            Box::new(move |_: web_sys::Event| {
-                                                if let Ok(result) = reader_clone.result() {
-                                                    if let Some(text) = result.as_string() {
-                                                        set_source.set(text);
-                                                    }
-                                                }
-                                            }) as Box<dyn FnMut(_)>,
-                                        );
-                                        reader.set_onload(Some(onload.as_ref().unchecked_ref()));
-                                        let _ = reader.read_as_text(&file);
-                                        onload.forget();
-                                    }
+                    // Viewer actions: page export and the way back into the editor
+                    <Show when=move || view_mode.get()>
+                        <button class="btn btn-sm btn-ghost gap-2" on:click=move |_| download_pdf()>
+                            <span class="icon-[lucide--file-text] text-lg"></span>
+                            "PDF"
+                        </button>
+                        <button
+                            class="btn btn-sm btn-ghost gap-2"
+                            title="Download the current page as PNG"
+                            on:click=move |_| {
+                                let page = current_page.get_untracked().saturating_sub(1);
+                                if let Some(svg) = page_svg(page) {
+                                    download_svg_as_png(&format!("page-{}.png", page + 1), &svg, 2.0);
                                 }
                             }
-                        />
-                        <span class="icon-[lucide--upload] text-lg"></span>
-                        "Upload"
-                    </label>
+                        >
+                            <span class="icon-[lucide--image-down] text-lg"></span>
+                            "PNG"
+                        </button>
+                        <button class="btn btn-sm btn-primary gap-2" on:click=open_in_editor>
+                            <span class="icon-[lucide--pencil] text-lg"></span>
+                            "Open in editor"
+                        </button>
+                    </Show>
 
-                    // Share button: copy a URL with the source encoded in the fragment
-                    <button
-                        class="btn btn-sm btn-ghost gap-2"
-                        on:click=move |_| {
-                            let Some(url) = build_share_url(&source.get()) else { return };
-                            let Some(window) = web_sys::window() else { return };
-                            let promise = window.navigator().clipboard().write_text(&url);
-                            spawn_local(async move {
-                                let msg = match wasm_bindgen_futures::JsFuture::from(promise).await {
-                                    Ok(_) => "Share link copied to clipboard!",
-                                    Err(_) => "Failed to copy share link",
-                                };
-                                set_share_toast.set(Some(msg.to_string()));
-                                sleep(Duration::from_millis(2500)).await;
-                                set_share_toast.set(None);
-                            });
-                        }
-                    >
-                        <span class="icon-[lucide--share-2] text-lg"></span>
-                        "Share"
-                    </button>
-
-                    // Download .typ file button
-                    <button
-                        class="btn btn-sm btn-ghost gap-2"
-                        on:click=move |_| {
-                            let content = source.get();
-                            if !content.is_empty() {
-                                let name = files
-                                    .with_untracked(|f| f.get(active.get_untracked()).map(|x| x.name.clone()))
-                                    .unwrap_or_else(|| "document.typ".to_string());
-                                download_bytes(&name, "text/plain;charset=utf-8", content.as_bytes());
-                            }
-                        }
-                    >
-                        <span class="icon-[lucide--file-down] text-lg"></span>
-                        "Download"
-                    </button>
-
-                    // Download SVG button
-                    <button
-                        class="btn btn-sm btn-ghost gap-2"
-                        on:click=move |_| {
-                            let svg_content = output.get();
-                            if !svg_content.is_empty() && error.get().is_none() {
-                                download_bytes("document.svg", "image/svg+xml", svg_content.as_bytes());
-                            }
-                        }
-                    >
-                        <span class="icon-[lucide--download] text-lg"></span>
-                        "SVG"
-                    </button>
-
-                    // Download PDF button
-                    <button
-                        class="btn btn-sm btn-ghost gap-2"
-                        on:click=move |_| {
-                            let project = files.get();
-                            let main = project.first().map(|f| f.content.clone()).unwrap_or_default();
-                            if !main.is_empty() && error.get().is_none() {
-                                let bib = bibliography.get();
-                                let bib_option = if bib.is_empty() {
-                                    None
-                                } else {
-                                    Some(bib.as_str())
-                                };
-                                let images = image_cache.get();
-                                let settings_val = settings.get();
-                                let extra: Vec<(String, String)> = project
-                                    .iter()
-                                    .skip(1)
-                                    .map(|f| (f.name.clone(), f.content.clone()))
-                                    .collect();
-                                match compile_to_pdf(&main, &settings_val, bib_option, &images, &extra) {
-                                    Ok(pdf_bytes) => {
-                                        download_bytes("document.pdf", "application/pdf", &pdf_bytes);
-                                    }
-                                    Err(e) => {
-                                        log::error!("PDF compilation failed: {}", e);
-                                        set_error.set(Some(e));
+                    <Show when=move || !view_mode.get()>
+                        // New document (template picker)
+                        <button
+                            class="btn btn-sm btn-ghost gap-2"
+                            on:click=move |_| set_show_templates.set(true)
+                        >
+                            <span class="icon-[lucide--file-plus] text-lg"></span>
+                            "New"
+                        </button>
+
+                        // Image Gallery button
+                        <button
+                            class="btn btn-sm btn-ghost gap-2"
+                            on:click=move |_| set_show_image_gallery.set(true)
+                        >
+                            <span class="icon-[lucide--image] text-lg"></span>
+                            "Images"
+                        </button>
+
+                        // Project assets button
+                        <button
+                            class="btn btn-sm btn-ghost gap-2"
+                            on:click=move |_| set_show_assets.set(true)
+                        >
+                            <span class="icon-[lucide--database] text-lg"></span>
+                            "Assets"
+                        </button>
+
+                        // Sidebar toggles (search, outline, statistics, problems)
+                        {[
+                            (Sidebar::Search, "icon-[lucide--search] text-lg", "Search"),
+                            (Sidebar::Outline, "icon-[lucide--list-tree] text-lg", "Outline"),
+                            (Sidebar::Stats, "icon-[lucide--chart-column] text-lg", "Stats"),
+                            (Sidebar::Problems, "icon-[lucide--triangle-alert] text-lg", "Problems"),
+                        ]
+                            .into_iter()
+                            .map(|(panel, icon, label)| view! {
+                                <button
+                                    class="btn btn-sm btn-ghost gap-2"
+                                    class:btn-active=move || sidebar.get() == Some(panel)
+                                    aria-pressed=move || (sidebar.get() == Some(panel)).to_string()
+                                    on:click=move |_| toggle_sidebar(panel)
+                                >
+                                    <span class=icon></span>
+                                    {label}
+                                </button>
+                            })
+                            .collect::<Vec<_>>()}
+
+                        // Bibliography button
+                        <button
+                            class="btn btn-sm btn-ghost gap-2"
+                            on:click=move |_| set_show_bib_modal.set(true)
+                        >
+                            <span class="icon-[lucide--book-open] text-lg"></span>
+                            "Bibliography"
+                        </button>
+
+                        // Upload .typ file button
+                        <label class="btn btn-sm btn-ghost gap-2 cursor-pointer">
+                            <input
+                                type="file"
+                                accept=".typ"
+                                class="hidden"
+                                on:change=move |ev| {
+                                    let Some(input) = ev.target()
+                                        .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+                                    else { return };
+                                    if let Some(files) = input.files() {
+                                        if let Some(file) = files.get(0) {
+                                            let Ok(reader) = web_sys::FileReader::new() else { return };
+                                            let reader_clone = reader.clone();
+                                            let onload = wasm_bindgen::closure::Closure::wrap(
+                                                Box::new(move |_: web_sys::Event| {
+                                                    if let Ok(result) = reader_clone.result() {
+                                                        if let Some(text) = result.as_string() {
+                                                            set_source.set(text);
+                                                        }
+                                                    }
+                                                }) as Box<dyn FnMut(_)>,
+                                            );
+                                            reader.set_onload(Some(onload.as_ref().unchecked_ref()));
+                                            let _ = reader.read_as_text(&file);
+                                            onload.forget();
+                                        }
                                     }
                                 }
-                            }
-                        }
-                    >
-                        <span class="icon-[lucide--file-text] text-lg"></span>
-                        "PDF"
-                    </button>
+                            />
+                            <span class="icon-[lucide--upload] text-lg"></span>
+                            "Upload"
+                        </label>
+
+                        // Share menu: editor link, or read-only viewer link
+                        <div class="dropdown dropdown-end">
+                            <div tabindex="0" role="button" class="btn btn-sm btn-ghost gap-2">
+                                <span class="icon-[lucide--share-2] text-lg"></span>
+                                "Share"
+                            </div>
+                            <ul
+                                tabindex="0"
+                                class="dropdown-content menu bg-base-200 rounded-box z-50 w-56 p-2 shadow"
+                            >
+                                <li>
+                                    <button on:click=move |_| copy_share_link(false)>
+                                        <span class="icon-[lucide--pencil] text-base"></span>
+                                        "Copy editor link"
+                                    </button>
+                                </li>
+                                <li>
+                                    <button on:click=move |_| copy_share_link(true)>
+                                        <span class="icon-[lucide--eye] text-base"></span>
+                                        "Copy viewer link"
+                                    </button>
+                                </li>
+                                <li>
+                                    <button on:click=move |_| set_show_qr.set(true)>
+                                        <span class="icon-[lucide--qr-code] text-base"></span>
+                                        "QR code…"
+                                    </button>
+                                </li>
+                            </ul>
+                        </div>
+
+                        // Download .typ file button
+                        <button class="btn btn-sm btn-ghost gap-2" on:click=move |_| download_source()>
+                            <span class="icon-[lucide--file-down] text-lg"></span>
+                            "Download"
+                        </button>
+
+                        // Download SVG button
+                        <button class="btn btn-sm btn-ghost gap-2" on:click=move |_| download_svg()>
+                            <span class="icon-[lucide--download] text-lg"></span>
+                            "SVG"
+                        </button>
+
+                        // Download PDF button
+                        <button class="btn btn-sm btn-ghost gap-2" on:click=move |_| download_pdf()>
+                            <span class="icon-[lucide--file-text] text-lg"></span>
+                            "PDF"
+                        </button>
+
+                        // Command palette button
+                        <button
+                            class="btn btn-sm btn-ghost btn-square"
+                            title="Show all commands (Ctrl+Shift+P)"
+                            aria-label="Show all commands"
+                            on:click=move |_| show_palette.set(true)
+                        >
+                            <span class="icon-[lucide--command] text-lg"></span>
+                        </button>
+                    </Show>
 
                     // Theme toggle
                     <label class="swap swap-rotate">
@@ -876,9 +1490,44 @@ typst2023:
                     set_is_resizing.set(false);
                 }
             >
-                // Editor panel with dynamic width
+                // Editor panel with dynamic width (hidden, not unmounted, in the viewer).
+                // Dropping files or a project folder here imports them.
                 <div
-                    class="overflow-hidden flex flex-col"
+                    class="overflow-hidden flex flex-col relative"
+                    class:hidden=move || view_mode.get()
+                    on:dragover=move |ev: web_sys::DragEvent| {
+                        // Only file drops are ours; text drags keep the textarea default.
+                        let has_files = ev
+                            .data_transfer()
+                            .is_some_and(|dt| dt.types().includes(&"Files".into(), 0));
+                        if has_files {
+                            ev.prevent_default();
+                            set_drag_over_editor.set(true);
+                        }
+                    }
+                    on:dragleave=move |ev: web_sys::DragEvent| {
+                        // Ignore leave events fired when moving onto a child element.
+                        let inside = ev
+                            .related_target()
+                            .and_then(|t| t.dyn_into::<web_sys::Node>().ok())
+                            .zip(ev.current_target().and_then(|t| t.dyn_into::<web_sys::Node>().ok()))
+                            .is_some_and(|(related, panel)| panel.contains(Some(&related)));
+                        if !inside {
+                            set_drag_over_editor.set(false);
+                        }
+                    }
+                    on:drop=move |ev: web_sys::DragEvent| {
+                        set_drag_over_editor.set(false);
+                        let Some(dt) = ev.data_transfer() else { return };
+                        let entries = dropped_entries(&dt);
+                        if entries.is_empty() {
+                            return;
+                        }
+                        ev.prevent_default();
+                        spawn_local(async move {
+                            import_files(read_entries(entries).await);
+                        });
+                    }
                     style:flex=move || format!("0 0 {}%", editor_width.get())
                 >
                     // File tab bar (file 0 is the compiled entry point)
@@ -920,26 +1569,83 @@ typst2023:
                             class="px-2 py-1.5 text-base-content/60 hover:text-primary"
                             title="New file"
                             aria-label="New file"
-                            on:click=open_new_file
+                            on:click=move |_| open_new_file()
                         >
                             <span class="icon-[lucide--plus] text-sm"></span>
                         </button>
                     </div>
 
-                    <Editor
-                        source=source
-                        set_source=set_source
-                        settings=settings
-                        textarea_ref=textarea_ref
-                        insert_at_cursor=insert_at_cursor.clone()
-                        set_show_settings=set_show_settings
-                        on_save=save_project
-                    />
+                    <Show when=move || drag_over_editor.get()>
+                        <div class="absolute inset-0 z-20 flex flex-col items-center justify-center gap-2 bg-base-100/85 border-2 border-dashed border-primary pointer-events-none">
+                            <span class="icon-[lucide--folder-input] text-4xl text-primary"></span>
+                            <p class="font-semibold">"Drop a project folder or files to import"</p>
+                            <p class="text-sm text-base-content/60">
+                                ".typ, bibliographies (.yml/.bib), images, data and fonts"
+                            </p>
+                        </div>
+                    </Show>
+
+                    <div class="flex flex-1 min-h-0">
+                        <Show when=move || sidebar.get() == Some(Sidebar::Search)>
+                            <SearchPanel
+                                files=files
+                                on_close=close_sidebar
+                                on_open=open_at
+                                on_replace=apply_bulk
+                            />
+                        </Show>
+                        <Show when=move || sidebar.get() == Some(Sidebar::Outline)>
+                            <OutlinePanel
+                                files=files
+                                output=output
+                                error=error
+                                on_close=close_sidebar
+                                on_open=open_at
+                            />
+                        </Show>
+                        <Show when=move || sidebar.get() == Some(Sidebar::Stats)>
+                            <StatsPanel files=files output=output error=error on_close=close_sidebar />
+                        </Show>
+                        <Show when=move || sidebar.get() == Some(Sidebar::Problems)>
+                            <ProblemsPanel
+                                files=files
+                                bibliographies=bibliographies
+                                output=output
+                                error=error
+                                on_close=close_sidebar
+                                on_open=open_at
+                                on_fix=apply_fix
+                            />
+                        </Show>
+                        <div class="flex-1 min-w-0 flex flex-col">
+                            <Editor
+                                source=source
+                                set_source=set_source
+                                settings=settings
+                                textarea_ref=textarea_ref
+                                insert_at_cursor=insert_at_cursor.clone()
+                                set_show_settings=set_show_settings
+                                set_show_cite_picker=set_show_cite_picker
+                                on_save=save_project
+                                on_rename=Callback::new(move |offset: usize| {
+                                    rename_target.set(Some((active.get_untracked(), offset)));
+                                })
+                                format_on_save=format_on_save
+                                file_name=active_name
+                                folds=folds
+                                soft_wrap=soft_wrap
+                                vim_mode=vim_mode
+                                keymap=keymap.read_only()
+                                commands=commands
+                            />
+                        </div>
+                    </div>
                 </div>
 
                 // Resizer handle
                 <div
                     class="w-1 bg-base-300 hover:bg-primary cursor-col-resize transition-colors relative group"
+                    class:hidden=move || view_mode.get()
                     on:mousedown=move |ev| {
                         ev.prevent_default();
                         set_is_resizing.set(true);
@@ -966,81 +1672,27 @@ typst2023:
             </main>
 
             // Bibliography modal
-            {move || {
-                show_bib_modal
-                    .get()
-                    .then(|| {
-                        view! {
-                            <div class="modal modal-open" role="dialog" aria-modal="true">
-                                <div class="modal-box max-w-4xl">
-                                    <h3 class="font-bold text-lg flex items-center gap-2">
-                                        <span class="icon-[lucide--book-open] text-xl"></span>
-                                        "Bibliography Manager (YAML)"
-                                    </h3>
-                                    <p class="py-2 text-sm text-base-content/70">
-                                        "Edit your bibliography in Hayagriva YAML format. The file will be registered as 'refs.yml' for your documents."
-                                    </p>
-
-                                    <div class="form-control">
-                                        <label class="label">
-                                            <span class="label-text">"Bibliography YAML (refs.yml)"</span>
-                                        </label>
-                                        <textarea
-                                            class="textarea textarea-bordered h-96 font-mono text-sm"
-                                            aria-label="Bibliography YAML"
-                                            prop:value=move || bibliography.get()
-                                            on:input=move |ev| {
-                                                set_bibliography.set(event_target_value(&ev));
-                                            }
-                                            placeholder="key:\n  type: article\n  title: \"Title\"\n  author: [\"Author\"]\n  date: 2024"
-                                        />
-                                    </div>
+            <BibliographyModal
+                show=show_bib_modal
+                set_show=set_show_bib_modal
+                bibliographies=bibliographies
+                files=files
+            />
 
-                                    <div class="alert alert-info mt-4">
-                                        <span class="icon-[lucide--info] text-xl"></span>
-                                        <div class="text-sm">
-                                            <p class="font-bold">"Usage:"</p>
-                                            <ul class="list-disc list-inside mt-1">
-                                                <li>"Use @key to cite entries in your document"</li>
-                                                <li>"Add #bibliography(\"refs.yml\") at the end of your document"</li>
-                                                <li>"Changes are saved automatically to localStorage"</li>
-                                            </ul>
-                                        </div>
-                                    </div>
+            // Citation picker (toolbar "Citation" button)
+            <CitationPicker
+                show=show_cite_picker
+                set_show=set_show_cite_picker
+                bibliographies=bibliographies
+                insert_at_cursor=insert_at_cursor.clone()
+            />
 
-                                    <div class="modal-action">
-                                        <button
-                                            class="btn btn-primary gap-2"
-                                            on:click=move |_| {
-                                                // Save bibliography to localStorage
-                                                if let Some(window) = web_sys::window() {
-                                                    if let Ok(Some(storage)) = window.local_storage() {
-                                                        let _ = storage.set_item("typst_bibliography", &bibliography.get());
-                                                        log::info!("Bibliography saved to localStorage");
-                                                    }
-                                                }
-                                                set_show_bib_modal.set(false);
-                                            }
-                                        >
-                                            <span class="icon-[lucide--save] text-lg"></span>
-                                            "Save & Close"
-                                        </button>
-                                        <button
-                                            class="btn btn-ghost"
-                                            on:click=move |_| set_show_bib_modal.set(false)
-                                        >
-                                            "Cancel"
-                                        </button>
-                                    </div>
-                                </div>
-                                <div
-                                    class="modal-backdrop"
-                                    on:click=move |_| set_show_bib_modal.set(false)
-                                ></div>
-                            </div>
-                        }
-                    })
-            }}
+            // Rename symbol (F2 in the editor)
+            <RenameSymbolDialog
+                target=rename_target
+                files=files
+                on_apply=apply_rename
+            />
 
             // Document settings modal (hidden #set preamble, applied under the hood)
             {move || {
@@ -1069,6 +1721,12 @@ typst2023:
                                         <button class="btn btn-xs" on:click=move |_| add_setting("#set document(title: \"Title\", author: \"Author\")")>"Document"</button>
                                     </div>
 
+                                    <CitationStylePicker
+                                        settings=settings
+                                        set_settings=set_settings
+                                        asset_cache=asset_cache
+                                    />
+
                                     <textarea
                                         class="textarea textarea-bordered w-full h-64 font-mono text-sm"
                                         aria-label="Document settings (Typst #set rules)"
@@ -1081,10 +1739,11 @@ typst2023:
                                         <button
                                             class="btn btn-primary gap-2"
                                             on:click=move |_| {
-                                                if let Some(window) = web_sys::window() {
-                                                    if let Ok(Some(storage)) = window.local_storage() {
-                                                        let _ = storage.set_item("typst_settings", &settings.get());
-                                                    }
+                                                if let Some(storage) = web_sys::window()
+                                                    .and_then(|w| w.local_storage().ok().flatten())
+                                                    .filter(|_| !shared_copy.get_untracked())
+                                                {
+                                                    let _ = storage.set_item("typst_settings", &settings.get());
                                                 }
                                                 set_show_settings.set(false);
                                             }
@@ -1114,17 +1773,34 @@ typst2023:
                 file_dialog
                     .get()
                     .map(|dialog| {
-                        let is_delete = matches!(dialog, FileDialog::Delete(_));
-                        let (title, confirm_label, confirm_class) = match dialog {
-                            FileDialog::New => ("New file", "Create", "btn btn-primary"),
-                            FileDialog::Rename(_) => ("Rename file", "Rename", "btn btn-primary"),
-                            FileDialog::Delete(_) => ("Delete file", "Delete", "btn btn-error"),
+                        let (title, confirm_label, confirm_class, message) = match dialog {
+                            FileDialog::New => ("New file", "Create", "btn btn-primary", None),
+                            FileDialog::Rename(_) => ("Rename file", "Rename", "btn btn-primary", None),
+                            FileDialog::Delete(_) => (
+                                "Delete file",
+                                "Delete",
+                                "btn btn-error",
+                                Some("This file will be permanently removed.".to_string()),
+                            ),
+                            FileDialog::KeepCopy => (
+                                "Keep this document",
+                                "Replace",
+                                "btn btn-warning",
+                                Some("It replaces the project stored in this browser.".to_string()),
+                            ),
+                            FileDialog::Import(count) => (
+                                "Import project",
+                                "Replace",
+                                "btn btn-warning",
+                                Some(format!("The current project is replaced by the {count} imported .typ file(s).")),
+                            ),
                         };
+                        let is_confirm = message.is_some();
                         view! {
                             <div class="modal modal-open" role="dialog" aria-modal="true">
                                 <div class="modal-box">
                                     <h3 class="font-bold text-lg">{title}</h3>
-                                    {(!is_delete)
+                                    {(!is_confirm)
                                         .then(|| {
                                             view! {
                                                 <input
@@ -1143,14 +1819,7 @@ typst2023:
                                                 />
                                             }
                                         })}
-                                    {is_delete
-                                        .then(|| {
-                                            view! {
-                                                <p class="py-4">
-                                                    "This file will be permanently removed."
-                                                </p>
-                                            }
-                                        })}
+                                    {message.map(|message| view! { <p class="py-4">{message}</p> })}
                                     <div class="modal-action">
                                         <button class=confirm_class on:click=move |_| apply_dialog()>
                                             {confirm_label}
@@ -1245,6 +1914,94 @@ typst2023:
                 set_image_cache=set_image_cache
             />
 
+            // Project assets drawer
+            <AssetDrawer
+                show=show_assets
+                set_show=set_show_assets
+                asset_cache=asset_cache
+                set_asset_cache=set_asset_cache
+            />
+
+            // Project import summary
+            {move || {
+                import_report
+                    .get()
+                    .map(|report| {
+                        let mut imported = Vec::new();
+                        if report.files > 0 {
+                            imported.push(format!("{} Typst file(s)", report.files));
+                        }
+                        if report.images > 0 {
+                            imported.push(format!("{} image(s) added to the gallery", report.images));
+                        }
+                        if report.fonts > 0 {
+                            imported.push(format!("{} font file(s)", report.fonts));
+                        }
+                        if report.assets > 0 {
+                            imported.push(format!("{} data file(s) added to the assets", report.assets));
+                        }
+                        if report.bibliographies > 0 {
+                            imported.push(format!("{} bibliography file(s)", report.bibliographies));
+                        }
+                        let summary = if imported.is_empty() {
+                            "Nothing was imported.".to_string()
+                        } else {
+                            format!("Imported {}.", imported.join(", "))
+                        };
+                        let skipped = report.skipped;
+                        view! {
+                            <div class="modal modal-open" role="dialog" aria-modal="true">
+                                <div class="modal-box max-w-2xl">
+                                    <h3 class="font-bold text-lg flex items-center gap-2">
+                                        <span class="icon-[lucide--folder-input] text-xl"></span>
+                                        "Import finished"
+                                    </h3>
+                                    <p class="py-2">{summary}</p>
+                                    {(!skipped.is_empty())
+                                        .then(|| {
+                                            view! {
+                                                <p class="text-sm font-semibold mt-2">
+                                                    {format!("Skipped {} file(s):", skipped.len())}
+                                                </p>
+                                                <ul class="text-sm max-h-64 overflow-y-auto mt-1 space-y-1">
+                                                    {skipped
+                                                        .into_iter()
+                                                        .map(|(path, reason)| {
+                                                            view! {
+                                                                <li>
+                                                                    <code>{path}</code>
+                                                                    <span class="text-base-content/60">{format!(" — {reason}")}</span>
+                                                                </li>
+                                                            }
+                                                        })
+                                                        .collect::<Vec<_>>()}
+                                                </ul>
+                                            }
+                                        })}
+                                    <div class="modal-action">
+                                        <button class="btn btn-primary" on:click=move |_| import_report.set(None)>
+                                            "OK"
+                                        </button>
+                                    </div>
+                                </div>
+                                <div class="modal-backdrop" on:click=move |_| import_report.set(None)></div>
+                            </div>
+                        }
+                    })
+            }}
+
+            // Command palette and keyboard shortcut editor
+            <CommandPalette show=show_palette keymap=keymap.read_only() commands=commands />
+            <KeymapDialog show=show_keymap keymap=keymap />
+
+            // Share-link QR code dialog
+            <QrDialog
+                show=show_qr
+                set_show=set_show_qr
+                share_url=share_url
+                set_image_cache=set_image_cache
+            />
+
             // Share-link confirmation toast
             {move || {
                 share_toast
@@ -1260,6 +2017,23 @@ typst2023:
                         }
                     })
             }}
+
+            // Rename / replace-all confirmation with a project-wide undo
+            {move || {
+                bulk_undo
+                    .get()
+                    .map(|(msg, _, _)| {
+                        view! {
+                            <div class="toast toast-end toast-bottom z-50">
+                                <div class="alert alert-info">
+                                    <span class="icon-[lucide--undo-2] text-lg"></span>
+                                    <span>{msg}</span>
+                                    <button class="btn btn-sm btn-ghost" on:click=undo_bulk>"Undo"</button>
+                                </div>
+                            </div>
+                        }
+                    })
+            }}
         </div>
     }
 }
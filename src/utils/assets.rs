@@ -0,0 +1,189 @@
+//! Project assets: arbitrary data files (CSV, JSON, TOML, XML, YAML, plain
+//! text, CSL styles, …) served to the compiler under their virtual path, so
+//! `#read`, `#csv`, `#json`, `#yaml`, `#toml`, `#xml` and
+//! `bibliography(style: …)` can load them.
+//!
+//! Assets persist in IndexedDB (same key → bytes layout as
+//! [`crate::utils::package_storage`]), keyed by virtual path.
+
+use super::package_storage::PackageStorage;
+
+pub struct AssetStorage {
+    inner: PackageStorage,
+}
+
+impl AssetStorage {
+    pub fn new() -> Self {
+        Self {
+            inner: PackageStorage::with_database("typst_studio_assets", "assets"),
+        }
+    }
+
+    /// Store (or overwrite) the asset at `path`.
+    pub async fn store(&self, path: &str, bytes: &[u8]) -> Result<(), String> {
+        self.inner.store(path, bytes).await
+    }
+
+    /// Delete the asset at `path`.
+    pub async fn delete(&self, path: &str) -> Result<(), String> {
+        self.inner.delete(path).await
+    }
+
+    /// List every asset as `(path, bytes)`.
+    pub async fn list_all(&self) -> Result<Vec<(String, Vec<u8>)>, String> {
+        self.inner.list_all().await
+    }
+}
+
+impl Default for AssetStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Normalize a user-entered asset path into the project-relative form used as
+/// its key (`/data//sales.csv` → `data/sales.csv`).
+///
+/// Rejects empty paths, `.`/`..` segments, and `.typ` files (those belong in
+/// the project's file tabs).
+pub fn normalize_asset_path(input: &str) -> Result<String, String> {
+    let segments: Vec<&str> = input
+        .trim()
+        .split(['/', '\\'])
+        .filter(|s| !s.is_empty())
+        .collect();
+    if segments.is_empty() {
+        return Err("Asset path is empty".to_string());
+    }
+    if segments.iter().any(|s| *s == "." || *s == "..") {
+        return Err("Asset paths cannot contain '.' or '..' segments".to_string());
+    }
+    let path = segments.join("/");
+    if path.to_ascii_lowercase().ends_with(".typ") {
+        return Err("Add .typ files as project files, not assets".to_string());
+    }
+    Ok(path)
+}
+
+/// The Typst call that loads an asset, chosen by extension (falls back to
+/// `#read` for anything without a dedicated loader). CSL styles get the rule
+/// that makes them the bibliography style.
+pub fn load_snippet(path: &str) -> String {
+    let ext = path
+        .rsplit_once('.')
+        .map(|(_, e)| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    if ext == "csl" {
+        return format!("#set bibliography(style: \"{path}\")");
+    }
+    let func = match ext.as_str() {
+        "csv" => "csv",
+        "json" => "json",
+        "yml" | "yaml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        _ => "read",
+    };
+    format!("#let data = {func}(\"{path}\")")
+}
+
+/// Human-readable size (`512 B`, `1.5 KB`, `2.0 MB`).
+pub fn format_size(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{bytes} B")
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// First `max_rows` rows of a CSV document for the preview table. Handles
+/// quoted fields with embedded commas, doubled quotes and newlines.
+pub fn csv_preview(text: &str, max_rows: usize) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if rows.len() >= max_rows {
+            return rows;
+        }
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => row.push(std::mem::take(&mut field)),
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            '\r' if !in_quotes => {}
+            c => field.push(c),
+        }
+    }
+    if (!field.is_empty() || !row.is_empty()) && rows.len() < max_rows {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_asset_paths() {
+        assert_eq!(
+            normalize_asset_path(" /data//sales.csv ").as_deref(),
+            Ok("data/sales.csv")
+        );
+        assert_eq!(
+            normalize_asset_path("data\\q1.json").as_deref(),
+            Ok("data/q1.json")
+        );
+        assert!(normalize_asset_path("  ").is_err());
+        assert!(normalize_asset_path("../secret.txt").is_err());
+        assert!(normalize_asset_path("chapter.TYP").is_err());
+    }
+
+    #[test]
+    fn snippet_matches_loader() {
+        assert_eq!(
+            load_snippet("data/sales.csv"),
+            "#let data = csv(\"data/sales.csv\")"
+        );
+        assert_eq!(load_snippet("cfg.YAML"), "#let data = yaml(\"cfg.YAML\")");
+        assert_eq!(load_snippet("notes.txt"), "#let data = read(\"notes.txt\")");
+        assert_eq!(
+            load_snippet("styles/ieee-custom.csl"),
+            "#set bibliography(style: \"styles/ieee-custom.csl\")"
+        );
+    }
+
+    #[test]
+    fn sizes_are_human_readable() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(2 * 1024 * 1024), "2.0 MB");
+    }
+
+    #[test]
+    fn csv_preview_handles_quotes_and_limits_rows() {
+        let csv = "name,note\r\n\"Smith, J.\",\"said \"\"hi\"\"\"\nDoe,\"two\nlines\"\nlast,row\n";
+        let rows = csv_preview(csv, 3);
+        assert_eq!(
+            rows,
+            vec![
+                vec!["name", "note"],
+                vec!["Smith, J.", "said \"hi\""],
+                vec!["Doe", "two\nlines"],
+            ]
+        );
+        assert_eq!(csv_preview("a,b", 5), vec![vec!["a", "b"]]);
+    }
+}
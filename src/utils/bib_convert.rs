@@ -0,0 +1,405 @@
+//! Conversion between BibTeX/BibLaTeX and Hayagriva YAML, for co-authors
+//! who keep their references in the other format.
+//!
+//! `.bib` → YAML goes through Hayagriva's own BibLaTeX import (the one Typst
+//! uses for `.bib` files), so the result cites exactly like the original.
+//! YAML → `.bib` maps the Hayagriva fields BibLaTeX has a counterpart for.
+//! Both directions report the fields they had to drop.
+
+use biblatex::{Bibliography, Chunk, EntryType, Spanned};
+use serde_yaml::{Mapping, Value};
+
+/// The converted file plus every field that didn't survive, as
+/// `key: field` notes for the user.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Conversion {
+    pub content: String,
+    pub lossy: Vec<String>,
+}
+
+/// BibLaTeX fields (and BibTeX aliases) Hayagriva's import reads.
+const MAPPED_BIB_FIELDS: &[&str] = &[
+    "abstract", "addendum", "address", "afterword", "annotation", "annotator", "archiveprefix",
+    "author", "bookauthor", "booktitle", "chapter", "commentator", "date", "day", "doi",
+    "edition", "editor", "editora", "editorb", "editorc", "editortype", "editoratype",
+    "editorbtype", "editorctype", "eprint", "eprinttype", "eventdate", "eventtitle",
+    "foreword", "holder", "howpublished", "institution", "introduction", "isan", "isbn",
+    "ismn", "issn", "issue", "issuetitle", "iswc", "journal", "journaltitle", "location",
+    "maintitle", "month", "note", "number", "organization", "pages", "pagetotal", "part",
+    "publisher", "school", "series", "shorttitle", "title", "translator", "url", "urldate",
+    "venue", "version", "volume", "volumes", "year",
+];
+
+/// Convert a BibTeX/BibLaTeX file to Hayagriva YAML.
+pub fn bib_to_yaml(bib: &str) -> Result<Conversion, String> {
+    let parsed = Bibliography::parse(bib).map_err(|e| e.kind.to_string())?;
+    let library = hayagriva::io::from_biblatex(&parsed).map_err(|errors| {
+        errors
+            .iter()
+            .map(|e| e.kind.to_string())
+            .collect::<Vec<_>>()
+            .join("; ")
+    })?;
+
+    let mut lossy = Vec::new();
+    for entry in parsed.iter() {
+        for field in entry.fields.keys() {
+            if !MAPPED_BIB_FIELDS.contains(&field.as_str()) {
+                lossy.push(format!("{}: {field}", entry.key));
+            }
+        }
+    }
+
+    let content = if library.is_empty() {
+        String::new()
+    } else {
+        hayagriva::io::to_yaml_str(&library).map_err(|e| e.to_string())?
+    };
+    Ok(Conversion { content, lossy })
+}
+
+/// Convert a Hayagriva YAML file to BibLaTeX.
+pub fn yaml_to_bib(yaml: &str) -> Result<Conversion, String> {
+    if yaml.trim().is_empty() {
+        return Ok(Conversion::default());
+    }
+    // Validate with the parser Typst uses before walking the raw mapping.
+    hayagriva::io::from_yaml_str(yaml).map_err(|e| e.to_string())?;
+    let Value::Mapping(map) = serde_yaml::from_str::<Value>(yaml).map_err(|e| e.to_string())? else {
+        return Err("The file is not a YAML mapping of entries".to_string());
+    };
+
+    let mut out = Bibliography::new();
+    let mut lossy = Vec::new();
+    for (key, entry) in &map {
+        let (Some(key), Some(entry)) = (key.as_str(), entry.as_mapping()) else {
+            continue;
+        };
+        let mut converter = ToBib {
+            key,
+            entry: biblatex::Entry::new(key.to_string(), EntryType::Misc),
+            lossy: &mut lossy,
+        };
+        converter.convert(entry);
+        out.insert(converter.entry);
+    }
+    Ok(Conversion {
+        content: out.to_biblatex_string(),
+        lossy,
+    })
+}
+
+/// Builds one BibLaTeX entry from a Hayagriva entry mapping.
+struct ToBib<'a> {
+    key: &'a str,
+    entry: biblatex::Entry,
+    lossy: &'a mut Vec<String>,
+}
+
+impl ToBib<'_> {
+    fn convert(&mut self, entry: &Mapping) {
+        let kind = text(entry.get("type")).to_ascii_lowercase();
+        let parent = match entry.get("parent") {
+            Some(Value::Sequence(parents)) => {
+                for extra in parents.iter().skip(1) {
+                    let title = extra.as_mapping().map(|m| title(m.get("title"))).unwrap_or_default();
+                    self.drop(&format!("parent \"{title}\""));
+                }
+                parents.first().and_then(Value::as_mapping)
+            }
+            Some(Value::Mapping(parent)) => Some(parent),
+            _ => None,
+        };
+        let parent_kind = parent.map(|p| text(p.get("type")).to_ascii_lowercase()).unwrap_or_default();
+
+        let (bib_type, container) = match (kind.as_str(), parent_kind.as_str()) {
+            ("article", "proceedings" | "conference") => ("inproceedings", "booktitle"),
+            ("article", _) => ("article", "journaltitle"),
+            ("chapter", _) => ("inbook", "booktitle"),
+            ("anthos", _) => ("incollection", "booktitle"),
+            ("book", _) => ("book", "series"),
+            ("proceedings", _) => ("proceedings", "series"),
+            ("thesis", _) => ("thesis", "series"),
+            ("report", _) => ("report", "series"),
+            ("reference", _) => ("manual", "series"),
+            ("web", _) => ("online", "organization"),
+            ("misc", _) | ("", _) => ("misc", "howpublished"),
+            (other, _) => {
+                self.drop(&format!("type {other} (exported as @misc)"));
+                ("misc", "howpublished")
+            }
+        };
+        self.entry.entry_type = EntryType::new(bib_type);
+
+        for (field, value) in entry {
+            let Some(field) = field.as_str() else { continue };
+            match field {
+                "type" | "parent" => {}
+                "title" => self.title(value),
+                "author" => self.persons("author", value),
+                "editor" => self.persons("editor", value),
+                "date" => self.date(value),
+                "publisher" => self.publisher(value),
+                "location" => self.set("location", &text(Some(value))),
+                "organization" => {
+                    let name = if matches!(bib_type, "thesis" | "report") { "institution" } else { "organization" };
+                    self.set(name, &text(Some(value)));
+                }
+                "volume" => self.set("volume", &text(Some(value))),
+                "volume-total" => self.set("volumes", &text(Some(value))),
+                "issue" => self.set("number", &text(Some(value))),
+                "edition" => self.set("edition", &text(Some(value))),
+                "page-range" => self.set("pages", &text(Some(value))),
+                "page-total" => self.set("pagetotal", &text(Some(value))),
+                "url" => self.url(value),
+                "serial-number" => self.serials(value),
+                "language" => self.set("langid", &text(Some(value))),
+                "note" => self.set("note", &text(Some(value))),
+                "abstract" => self.set("abstract", &text(Some(value))),
+                other => self.drop(other),
+            }
+        }
+
+        let Some(parent) = parent else { return };
+        for (field, value) in parent {
+            let Some(field) = field.as_str() else { continue };
+            match field {
+                "type" => {}
+                "title" => self.set(container, &title(Some(value))),
+                // The child's own values win over the container's.
+                "editor" if self.entry.get("editor").is_none() => self.persons("editor", value),
+                "publisher" if self.entry.get("publisher").is_none() => self.publisher(value),
+                "location" if self.entry.get("location").is_none() => self.set("location", &text(Some(value))),
+                "date" if self.entry.get("date").is_none() && self.entry.get("year").is_none() => self.date(value),
+                "volume" if self.entry.get("volume").is_none() => self.set("volume", &text(Some(value))),
+                "issue" if self.entry.get("number").is_none() => self.set("number", &text(Some(value))),
+                "serial-number" => self.serials(value),
+                other => self.drop(&format!("parent.{other}")),
+            }
+        }
+    }
+
+    fn set(&mut self, field: &str, value: &str) {
+        let value = value.trim();
+        if !value.is_empty() {
+            self.entry.set(field, chunks(value));
+        }
+    }
+
+    fn drop(&mut self, field: &str) {
+        self.lossy.push(format!("{}: {field}", self.key));
+    }
+
+    fn title(&mut self, value: &Value) {
+        self.set("title", &title(Some(value)));
+        if let Some(short) = value.as_mapping().and_then(|m| m.get("short")) {
+            self.set("shorttitle", &text(Some(short)));
+        }
+    }
+
+    fn persons(&mut self, field: &str, value: &Value) {
+        let persons: Vec<String> = match value {
+            Value::Sequence(seq) => seq.iter().filter_map(person).collect(),
+            value => person(value).into_iter().collect(),
+        };
+        self.set(field, &persons.join(" and "));
+    }
+
+    /// Years go to `year` (what BibTeX styles read), fuller dates to `date`.
+    fn date(&mut self, value: &Value) {
+        let date = text(Some(value));
+        let field = if date.trim_start_matches('-').chars().all(|c| c.is_ascii_digit()) { "year" } else { "date" };
+        self.set(field, &date);
+    }
+
+    fn publisher(&mut self, value: &Value) {
+        match value.as_mapping() {
+            Some(map) => {
+                self.set("publisher", &text(map.get("name")));
+                if self.entry.get("location").is_none() {
+                    self.set("location", &text(map.get("location")));
+                }
+            }
+            None => self.set("publisher", &text(Some(value))),
+        }
+    }
+
+    fn url(&mut self, value: &Value) {
+        match value.as_mapping() {
+            Some(map) => {
+                self.set("url", &text(map.get("value")));
+                self.set("urldate", &text(map.get("date")));
+            }
+            None => self.set("url", &text(Some(value))),
+        }
+    }
+
+    /// A bare serial number is a DOI; keyed ones map where BibLaTeX has a field.
+    fn serials(&mut self, value: &Value) {
+        let Some(map) = value.as_mapping() else {
+            self.set("doi", &text(Some(value)));
+            return;
+        };
+        for (kind, number) in map {
+            let kind = kind.as_str().unwrap_or_default().to_ascii_lowercase();
+            let number = text(Some(number));
+            match kind.as_str() {
+                "doi" | "isbn" | "issn" => self.set(&kind, &number),
+                "arxiv" | "pmid" | "pmcid" => {
+                    self.set("eprint", &number);
+                    self.set("eprinttype", &kind);
+                }
+                other => self.drop(&format!("serial-number.{other}")),
+            }
+        }
+    }
+}
+
+fn chunks(text: &str) -> Vec<Spanned<Chunk>> {
+    vec![Spanned::detached(Chunk::Normal(text.to_string()))]
+}
+
+/// A scalar as text (numbers included), empty for anything else.
+fn text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        Some(Value::Bool(b)) => b.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// A Hayagriva title: a plain string or a `{ value: …, short: … }` map.
+fn title(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::Mapping(m)) => text(m.get("value")),
+        other => text(other),
+    }
+}
+
+/// A Hayagriva person in BibLaTeX's `Last, First` form.
+fn person(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Mapping(m) => {
+            let name = text(m.get("name"));
+            let given = text(m.get("given-name"));
+            let prefix = text(m.get("prefix"));
+            let name = if prefix.is_empty() { name } else { format!("{prefix} {name}") };
+            Some(if given.is_empty() { name } else { format!("{name}, {given}") })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIB: &str = r#"@article{knuth1984,
+  author = {Knuth, Donald E.},
+  title = {Literate Programming},
+  journal = {The Computer Journal},
+  volume = {27},
+  number = {2},
+  pages = {97--111},
+  year = {1984},
+  doi = {10.1093/comjnl/27.2.97},
+  keywords = {programming},
+}
+
+@book{lamport1994,
+  author = {Lamport, Leslie and Bibby, Duane},
+  title = {LaTeX: A Document Preparation System},
+  publisher = {Addison-Wesley},
+  edition = {2},
+  year = {1994},
+}
+"#;
+
+    const YAML: &str = r#"netwok2020:
+  type: article
+  title: The Challenges of Scientific Typesetting
+  author: ["Network, A.", "Smith, B."]
+  date: 2020-05
+  page-range: 1-10
+  parent:
+    type: periodical
+    title: Journal of Academic Publishing
+    volume: 4
+  archive: Local shelf
+conf2021:
+  type: article
+  title: Fast Layout
+  author: Doe, Jane
+  date: 2021
+  parent:
+    type: proceedings
+    title: Proceedings of the Layout Conference
+    publisher: ACM
+"#;
+
+    fn hay(yaml: &str) -> hayagriva::Library {
+        hayagriva::io::from_yaml_str(yaml).expect("valid yaml")
+    }
+
+    fn hay_bib(bib: &str) -> hayagriva::Library {
+        hayagriva::io::from_biblatex_str(bib).expect("valid bib")
+    }
+
+    /// Key, title, author names, year and container title.
+    type Essentials = (String, String, Vec<String>, Option<i32>, String);
+
+    /// The fields both formats carry, for comparing round trips.
+    fn essentials(library: &hayagriva::Library) -> Vec<Essentials> {
+        library
+            .iter()
+            .map(|e| {
+                let container = e
+                    .parents()
+                    .first()
+                    .and_then(|p| p.title())
+                    .map(|t| t.value.to_string())
+                    .unwrap_or_default();
+                (
+                    e.key().to_string(),
+                    e.title().map(|t| t.value.to_string()).unwrap_or_default(),
+                    e.authors().unwrap_or_default().iter().map(|p| p.name.clone()).collect(),
+                    e.date().map(|d| d.year),
+                    container,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bib_round_trips_through_yaml() {
+        let to_yaml = bib_to_yaml(BIB).expect("to yaml");
+        let back = yaml_to_bib(&to_yaml.content).expect("back to bib");
+        assert_eq!(essentials(&hay_bib(&back.content)), essentials(&hay_bib(BIB)));
+        assert_eq!(essentials(&hay(&to_yaml.content)), essentials(&hay_bib(BIB)));
+        assert!(back.content.contains("10.1093/comjnl/27.2.97"), "{}", back.content);
+    }
+
+    #[test]
+    fn yaml_round_trips_through_bib() {
+        let to_bib = yaml_to_bib(YAML).expect("to bib");
+        assert!(to_bib.content.contains("@inproceedings{conf2021"), "{}", to_bib.content);
+        assert!(to_bib.content.contains("booktitle = {Proceedings of the Layout Conference}"), "{}", to_bib.content);
+        let back = bib_to_yaml(&to_bib.content).expect("back to yaml");
+        assert_eq!(essentials(&hay(&back.content)), essentials(&hay(YAML)));
+    }
+
+    #[test]
+    fn lossy_fields_are_reported() {
+        assert_eq!(bib_to_yaml(BIB).unwrap().lossy, vec!["knuth1984: keywords"]);
+        assert_eq!(yaml_to_bib(YAML).unwrap().lossy, vec!["netwok2020: archive"]);
+    }
+
+    #[test]
+    fn invalid_input_is_an_error() {
+        assert!(bib_to_yaml("@article{x, title = {unclosed").is_err());
+        assert!(yaml_to_bib("x:\n  type: not-a-type\n").is_err());
+        assert_eq!(yaml_to_bib("  ").unwrap(), Conversion::default());
+    }
+}
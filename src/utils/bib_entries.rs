@@ -0,0 +1,887 @@
+//! Structured view of bibliography files for the bibliography manager:
+//! entry summaries, line-level validation with the parsers Typst uses
+//! (Hayagriva for YAML, `biblatex` + Hayagriva's conversion for `.bib`), and
+//! in-place add/edit/delete that keeps every field the form doesn't touch.
+//! Edits splice the one entry's source span, so comments, `@string` macros
+//! and the layout of the other entries survive byte-for-byte.
+//!
+//! Everything here is pure and host-tested.
+
+use super::bibliography::{BibFile, BibFormat};
+use biblatex::{Bibliography, Chunk, ChunksExt, RawBibliography, Spanned};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::ops::Range;
+use typst_syntax::{ast, parse, LinkedNode};
+
+/// One row of the entries table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntrySummary {
+    pub key: String,
+    /// The type as written in the file (`article`, `inproceedings`, …).
+    pub entry_type: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub year: String,
+    /// 1-based line where the entry starts.
+    pub line: usize,
+}
+
+/// A validation problem, with its 1-based line when known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BibDiagnostic {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl BibDiagnostic {
+    fn at(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+/// Result of checking one bibliography file. `entries` is empty when the file
+/// does not parse.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BibCheck {
+    pub entries: Vec<EntrySummary>,
+    pub diagnostics: Vec<BibDiagnostic>,
+}
+
+/// The fields the add/edit form works with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntryDraft {
+    pub key: String,
+    pub entry_type: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub year: String,
+}
+
+impl From<&EntrySummary> for EntryDraft {
+    fn from(entry: &EntrySummary) -> Self {
+        Self {
+            key: entry.key.clone(),
+            entry_type: entry.entry_type.clone(),
+            title: entry.title.clone(),
+            authors: entry.authors.clone(),
+            year: entry.year.clone(),
+        }
+    }
+}
+
+/// Common entry types offered by the form, per format.
+pub fn entry_types(format: BibFormat) -> &'static [&'static str] {
+    match format {
+        BibFormat::Hayagriva => &[
+            "article", "book", "chapter", "proceedings", "thesis", "report", "web", "misc",
+        ],
+        BibFormat::BibLatex => &[
+            "article", "book", "inbook", "incollection", "inproceedings", "online", "thesis",
+            "report", "misc",
+        ],
+    }
+}
+
+/// 1-based line of a byte offset.
+fn line_of(content: &str, byte: usize) -> usize {
+    content[..byte.min(content.len())].matches('\n').count() + 1
+}
+
+/// Parse and validate a bibliography file, in the format its name selects.
+pub fn check_bibliography(name: &str, content: &str) -> BibCheck {
+    if content.trim().is_empty() {
+        return BibCheck::default();
+    }
+    match BibFormat::from_name(name) {
+        Some(BibFormat::Hayagriva) => check_yaml(content),
+        Some(BibFormat::BibLatex) => check_biblatex(content),
+        None => BibCheck {
+            entries: Vec::new(),
+            diagnostics: vec![BibDiagnostic::at(None, "Unknown bibliography format")],
+        },
+    }
+}
+
+/// The entries that can be read from a bibliography file. A file that does
+/// not parse as a whole is read entry by entry, leaving out the broken ones.
+pub fn readable_entries(name: &str, content: &str) -> Vec<EntrySummary> {
+    let check = check_bibliography(name, content);
+    if !check.entries.is_empty() || check.diagnostics.is_empty() {
+        return check.entries;
+    }
+    let blocks: Vec<Range<usize>> = match BibFormat::from_name(name) {
+        Some(BibFormat::Hayagriva) => yaml_entry_spans(content).into_iter().map(|(_, span)| span).collect(),
+        Some(BibFormat::BibLatex) => biblatex_blocks(content),
+        None => return Vec::new(),
+    };
+    blocks
+        .into_iter()
+        .flat_map(|block| {
+            let first_line = line_of(content, block.start);
+            check_bibliography(name, &content[block]).entries.into_iter().map(move |mut entry| {
+                entry.line += first_line - 1;
+                entry
+            })
+        })
+        .collect()
+}
+
+/// Byte ranges of the `@` blocks of a BibLaTeX file, each running up to the
+/// next line that starts with `@`.
+fn biblatex_blocks(content: &str) -> Vec<Range<usize>> {
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if line.trim_start().starts_with('@') {
+            starts.push(offset + line.len() - line.trim_start().len());
+        }
+        offset += line.len();
+    }
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| start..starts.get(i + 1).copied().unwrap_or(content.len()))
+        .collect()
+}
+
+/// The key a top-level `key:` line of a YAML document defines.
+fn yaml_key_line(line: &str) -> Option<String> {
+    if line.starts_with([' ', '\t', '#', '-']) || line.trim().is_empty() {
+        return None;
+    }
+    let (key, _) = line.split_once(':')?;
+    let key = key.trim().trim_matches(['"', '\'']);
+    (!key.is_empty()).then(|| key.to_string())
+}
+
+/// Top-level keys of a YAML document with their 1-based line.
+fn yaml_top_level_keys(content: &str) -> Vec<(String, usize)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| Some((yaml_key_line(line)?, i + 1)))
+        .collect()
+}
+
+/// Top-level YAML entries with their byte range: the key line through the
+/// entry's last indented line. Blank lines and unindented comments after it
+/// are not part of it.
+fn yaml_entry_spans(content: &str) -> Vec<(String, Range<usize>)> {
+    let mut spans: Vec<(String, Range<usize>)> = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let end = offset + text.len();
+        if let Some(key) = yaml_key_line(text) {
+            spans.push((key, offset..end));
+        } else if text.starts_with([' ', '\t', '-']) && !text.trim().is_empty() {
+            if let Some((_, span)) = spans.last_mut() {
+                span.end = end;
+            }
+        }
+        offset += line.len();
+    }
+    spans
+}
+
+fn check_yaml(content: &str) -> BibCheck {
+    let mut check = BibCheck::default();
+
+    // Hayagriva keeps the last of two equal keys without complaint; Typst
+    // would silently cite the wrong entry, so flag them first.
+    let keys = yaml_top_level_keys(content);
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for (key, line) in &keys {
+        if let Some(first) = seen.insert(key, *line) {
+            check.diagnostics.push(BibDiagnostic::at(
+                Some(*line),
+                format!("Duplicate key `{key}` (first defined on line {first})"),
+            ));
+        }
+    }
+
+    if let Err(err) = hayagriva::io::from_yaml_str(content) {
+        let line = err.location().map(|loc| loc.line());
+        // serde_yaml appends " at line L column C" (shown separately) and lists
+        // every accepted entry type after an unknown one, which drowns the message.
+        let message = err.to_string();
+        let message = message
+            .split(" at line ")
+            .next()
+            .and_then(|m| m.split(", expected one of").next())
+            .unwrap_or(&message)
+            .to_string();
+        check.diagnostics.push(BibDiagnostic::at(line, message));
+        return check;
+    }
+
+    let Ok(Value::Mapping(map)) = serde_yaml::from_str::<Value>(content) else {
+        return check;
+    };
+    let lines: HashMap<&str, usize> = keys.iter().map(|(k, l)| (k.as_str(), *l)).collect();
+    for (key, entry) in &map {
+        let Some(key) = key.as_str() else { continue };
+        check.entries.push(EntrySummary {
+            key: key.to_string(),
+            entry_type: yaml_str(entry.get("type")),
+            title: yaml_title(entry.get("title")),
+            authors: yaml_authors(entry.get("author")),
+            year: leading_year(&yaml_str(entry.get("date"))),
+            line: lines.get(key).copied().unwrap_or(1),
+        });
+    }
+    check
+}
+
+/// A scalar as text (numbers included), empty for anything else.
+fn yaml_str(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        Some(Value::Bool(b)) => b.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// A Hayagriva title: a plain string or a `{ value: …, short: … }` map.
+fn yaml_title(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::Mapping(m)) => yaml_str(m.get("value")),
+        other => yaml_str(other),
+    }
+}
+
+/// A Hayagriva person: `"Last, First"` or a `{ name, given-name }` map.
+fn yaml_person(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Mapping(m) => {
+            let name = yaml_str(m.get("name"));
+            let given = yaml_str(m.get("given-name"));
+            Some(if given.is_empty() { name } else { format!("{name}, {given}") })
+        }
+        _ => None,
+    }
+}
+
+fn yaml_authors(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Sequence(seq)) => seq.iter().filter_map(yaml_person).collect(),
+        Some(value) => yaml_person(value).into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+/// The year at the start of a date (`2020-05-01` → `2020`).
+fn leading_year(date: &str) -> String {
+    let date = date.trim();
+    let digits = date.strip_prefix('-').unwrap_or(date);
+    let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+    date[..date.len() - digits.len() + end].to_string()
+}
+
+fn check_biblatex(content: &str) -> BibCheck {
+    let mut check = BibCheck::default();
+    let bib = match Bibliography::parse(content) {
+        Ok(bib) => bib,
+        Err(err) => {
+            check.diagnostics.push(BibDiagnostic::at(
+                Some(line_of(content, err.span.start)),
+                err.kind.to_string(),
+            ));
+            return check;
+        }
+    };
+    // Typst converts entries through Hayagriva; report what it would reject.
+    if let Err(errors) = hayagriva::io::from_biblatex(&bib) {
+        for err in errors {
+            check
+                .diagnostics
+                .push(BibDiagnostic::at(Some(line_of(content, err.span.start)), err.kind.to_string()));
+        }
+    }
+    for entry in bib.iter() {
+        let field = |name: &str| entry.get(name).map(|c| c.format_verbatim()).unwrap_or_default();
+        let year = match field("year") {
+            y if y.is_empty() => leading_year(&field("date")),
+            y => y,
+        };
+        let authors = entry
+            .author()
+            .map(|people| {
+                people
+                    .iter()
+                    .map(|p| {
+                        if p.given_name.is_empty() {
+                            p.name.clone()
+                        } else {
+                            format!("{}, {}", p.name, p.given_name)
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        check.entries.push(EntrySummary {
+            key: entry.key.clone(),
+            entry_type: entry.entry_type.to_string().to_lowercase(),
+            title: field("title"),
+            authors,
+            year,
+            line: entry_line(content, &entry.key),
+        });
+    }
+    check
+}
+
+/// Line of the `@type{key,` header of a BibLaTeX entry.
+fn entry_line(content: &str, key: &str) -> usize {
+    let mut from = 0;
+    while let Some(at) = content[from..].find('@').map(|i| from + i) {
+        let rest = &content[at..];
+        if let Some(open) = rest.find(['{', '(']) {
+            let head = rest[open + 1..].trim_start();
+            if head.starts_with(key) && head[key.len()..].trim_start().starts_with(',') {
+                return line_of(content, at);
+            }
+        }
+        from = at + 1;
+    }
+    1
+}
+
+/// Field-level problems with a form draft. `original_key` is the key of the
+/// entry being edited (`None` when adding).
+pub fn validate_draft(draft: &EntryDraft, existing: &[EntrySummary], original_key: Option<&str>) -> Vec<String> {
+    let mut errors = Vec::new();
+    let key = draft.key.trim();
+    if key.is_empty() {
+        errors.push("Key is required".to_string());
+    } else if !key.chars().all(|c| c.is_alphanumeric() || "_-:.".contains(c)) {
+        errors.push("Keys may only contain letters, digits and _ - : . (so @key works)".to_string());
+    } else if Some(key) != original_key && existing.iter().any(|e| e.key == key) {
+        errors.push(format!("Key `{key}` is already used"));
+    }
+    if draft.entry_type.trim().is_empty() {
+        errors.push("Type is required".to_string());
+    }
+    let year = draft.year.trim();
+    if !year.is_empty() && year.trim_start_matches('-').parse::<u32>().is_err() {
+        errors.push("Year must be a number".to_string());
+    }
+    errors
+}
+
+/// Add an entry (`original_key == None`) or update one in place, returning the
+/// new file content. Fields the form doesn't cover are kept; a renamed entry
+/// keeps its position.
+pub fn upsert_entry(file: &BibFile, original_key: Option<&str>, draft: &EntryDraft) -> Result<String, String> {
+    match BibFormat::from_name(&file.name) {
+        Some(BibFormat::Hayagriva) => upsert_yaml(&file.content, original_key, draft),
+        Some(BibFormat::BibLatex) => upsert_biblatex(&file.content, original_key, draft),
+        None => Err("Unknown bibliography format".to_string()),
+    }
+}
+
+/// Remove the entry `key`, returning the new file content.
+pub fn delete_entry(file: &BibFile, key: &str) -> Result<String, String> {
+    let span = match BibFormat::from_name(&file.name) {
+        Some(BibFormat::Hayagriva) => yaml_span(&file.content, key)?,
+        Some(BibFormat::BibLatex) => biblatex_span(&file.content, key)?,
+        None => return Err("Unknown bibliography format".to_string()),
+    };
+    Ok(remove_entry(&file.content, span))
+}
+
+/// Length of the whitespace-only lines at the start of `text`.
+fn leading_blank_len(text: &str) -> usize {
+    text.split_inclusive('\n').take_while(|l| l.trim().is_empty()).map(str::len).sum()
+}
+
+/// Cut an entry's lines out of `content`, with the blank lines after it (or
+/// before it, when nothing follows) so no gap is left behind.
+fn remove_entry(content: &str, span: Range<usize>) -> String {
+    let line_start = content[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    let mut start = if content[line_start..span.start].trim().is_empty() {
+        line_start
+    } else {
+        span.start
+    };
+    let line_end = content[span.end..].find('\n').map_or(content.len(), |i| span.end + i + 1);
+    let mut end = if content[span.end..line_end].trim().is_empty() {
+        line_end
+    } else {
+        span.end
+    };
+    end += leading_blank_len(&content[end..]);
+    if end == content.len() {
+        let before = &content[..start];
+        start -= before
+            .split_inclusive('\n')
+            .rev()
+            .take_while(|l| l.trim().is_empty())
+            .map(str::len)
+            .sum::<usize>();
+    }
+    let out = format!("{}{}", &content[..start], &content[end..]);
+    if out.trim().is_empty() {
+        String::new()
+    } else {
+        out
+    }
+}
+
+/// Add an entry after the last one, separated by a blank line.
+fn append_entry(content: &str, entry: &str) -> String {
+    let mut out = content.to_string();
+    if !out.trim().is_empty() {
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        if !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+    }
+    out.push_str(entry);
+    out.push('\n');
+    out
+}
+
+/// Write `entry` over the entry `original_key` (or append it when adding).
+fn splice_entry(content: &str, span: Option<Range<usize>>, entry: &str) -> String {
+    match span {
+        Some(span) => format!("{}{}{}", &content[..span.start], entry, &content[span.end..]),
+        None => append_entry(content, entry),
+    }
+}
+
+fn yaml_mapping(content: &str) -> Result<Mapping, String> {
+    if content.trim().is_empty() {
+        return Ok(Mapping::new());
+    }
+    match serde_yaml::from_str::<Value>(content).map_err(|e| e.to_string())? {
+        Value::Mapping(map) => Ok(map),
+        _ => Err("The file is not a YAML mapping of entries".to_string()),
+    }
+}
+
+/// Source range of the YAML entry `key`.
+fn yaml_span(content: &str, key: &str) -> Result<Range<usize>, String> {
+    yaml_mapping(content)?;
+    yaml_entry_spans(content)
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, span)| span)
+        .ok_or_else(|| format!("Entry `{key}` is not a top-level `{key}:` line; edit the file as text"))
+}
+
+fn upsert_yaml(content: &str, original_key: Option<&str>, draft: &EntryDraft) -> Result<String, String> {
+    let map = yaml_mapping(content)?;
+    let span = original_key.map(|k| yaml_span(content, k)).transpose()?;
+    let key = draft.key.trim();
+    let mut entry = original_key
+        .and_then(|k| map.get(k))
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default();
+
+    entry.insert("type".into(), draft.entry_type.trim().into());
+    let title = draft.title.trim();
+    match entry.get_mut("title") {
+        _ if title.is_empty() => {
+            entry.remove("title");
+        }
+        // Keep `short:` & co. of a structured title.
+        Some(Value::Mapping(t)) => {
+            t.insert("value".into(), title.into());
+        }
+        _ => {
+            entry.insert("title".into(), title.into());
+        }
+    }
+    let authors: Vec<Value> = draft
+        .authors
+        .iter()
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .map(Value::from)
+        .collect();
+    match authors.len() {
+        0 => entry.remove("author"),
+        1 => entry.insert("author".into(), authors[0].clone()),
+        _ => entry.insert("author".into(), Value::Sequence(authors)),
+    };
+    let year = draft.year.trim();
+    let current = leading_year(&yaml_str(entry.get("date")));
+    if year.is_empty() {
+        entry.remove("date");
+    } else if current != year {
+        // A full date is kept while its year is unchanged.
+        let value = year.parse::<i64>().map(Value::from).unwrap_or_else(|_| year.into());
+        entry.insert("date".into(), value);
+    }
+
+    let mut single = Mapping::new();
+    single.insert(key.into(), Value::Mapping(entry));
+    let text = serde_yaml::to_string(&single).map_err(|e| e.to_string())?;
+    Ok(splice_entry(content, span, text.trim_end()))
+}
+
+fn chunks(text: &str) -> Vec<Spanned<Chunk>> {
+    vec![Spanned::detached(Chunk::Normal(text.to_string()))]
+}
+
+/// Source range of the BibLaTeX entry `key`, from `@` to its closing brace.
+fn biblatex_span(content: &str, key: &str) -> Result<Range<usize>, String> {
+    Bibliography::parse(content).map_err(|e| e.kind.to_string())?;
+    let raw = RawBibliography::parse(content).map_err(|e| e.kind.to_string())?;
+    let entry = raw
+        .entries
+        .iter()
+        .find(|e| e.v.key.v == key)
+        .ok_or_else(|| format!("No entry `{key}`"))?;
+    // The raw span stops before the closing delimiter.
+    let close = entry.span.end + leading_whitespace(&content[entry.span.end..]);
+    let end = content[close..].chars().next().map_or(close, |c| close + c.len_utf8());
+    Ok(entry.span.start..end)
+}
+
+fn leading_whitespace(text: &str) -> usize {
+    text.len() - text.trim_start().len()
+}
+
+fn upsert_biblatex(content: &str, original_key: Option<&str>, draft: &EntryDraft) -> Result<String, String> {
+    let bib = Bibliography::parse(content).map_err(|e| e.kind.to_string())?;
+    let span = original_key.map(|k| biblatex_span(content, k)).transpose()?;
+    let raw = RawBibliography::parse(content).map_err(|e| e.kind.to_string())?;
+    let original = original_key.and_then(|k| raw.entries.iter().find(|e| e.v.key.v == k));
+    let key = draft.key.trim();
+
+    // Untouched fields are copied as written, macros included.
+    let mut fields: Vec<String> = Vec::new();
+    fn replaced(name: &str, value: Option<String>, fields: &mut Vec<String>) {
+        if let Some(value) = value {
+            fields.push(format!("{name} = {value}"));
+        }
+    }
+    let title = draft.title.trim();
+    let mut title = (!title.is_empty()).then(|| chunks(title).to_biblatex_string(false));
+    let authors: Vec<&str> = draft.authors.iter().map(|a| a.trim()).filter(|a| !a.is_empty()).collect();
+    let mut authors = (!authors.is_empty()).then(|| chunks(&authors.join(" and ")).to_biblatex_string(false));
+    let year = draft.year.trim();
+    // Entries dated with `date` keep it (full date kept while the year matches).
+    let resolved = original_key.and_then(|k| bib.get(k));
+    let date = resolved.and_then(|e| e.get("date")).map(|c| c.format_verbatim());
+    let mut year_field = match (&date, year.is_empty()) {
+        (_, true) => None,
+        (Some(date), false) if leading_year(date) == year => Some(("date", None)),
+        (Some(_), false) => Some(("date", Some(chunks(year).to_biblatex_string(false)))),
+        (None, false) => Some(("year", Some(chunks(year).to_biblatex_string(false)))),
+    };
+    for pair in original.map(|e| e.v.fields.as_slice()).unwrap_or_default() {
+        let name = pair.key.v.to_ascii_lowercase();
+        let written = content[pair.key.span.start..pair.value.span.end].trim_end().to_string();
+        match name.as_str() {
+            "title" => replaced("title", title.take(), &mut fields),
+            "author" => replaced("author", authors.take(), &mut fields),
+            "year" | "date" => match year_field {
+                Some((field, value)) if field == name => {
+                    fields.push(value.map_or(written, |v| format!("{field} = {v}")));
+                    year_field = None;
+                }
+                _ => {}
+            },
+            _ => fields.push(written),
+        }
+    }
+    replaced("title", title, &mut fields);
+    replaced("author", authors, &mut fields);
+    if let Some((field, Some(value))) = year_field {
+        fields.push(format!("{field} = {value}"));
+    }
+
+    let mut text = format!("@{}{{{key},\n", draft.entry_type.trim());
+    for field in fields {
+        text.push_str(&format!("  {field},\n"));
+    }
+    text.push('}');
+    Ok(splice_entry(content, span, &text))
+}
+
+/// How often each bibliography key is cited across the project sources:
+/// `@key` references and `cite(<key>)` calls.
+pub fn citation_counts<'a>(sources: impl IntoIterator<Item = &'a str>) -> HashMap<String, usize> {
+    fn visit(node: &LinkedNode, counts: &mut HashMap<String, usize>) {
+        if let Some(reference) = node.cast::<ast::Ref>() {
+            *counts.entry(reference.target().to_string()).or_default() += 1;
+        } else if let Some(call) = node.cast::<ast::FuncCall>() {
+            if matches!(call.callee(), ast::Expr::Ident(ident) if ident.as_str() == "cite") {
+                for arg in call.args().items() {
+                    if let ast::Arg::Pos(ast::Expr::Label(label)) = arg {
+                        *counts.entry(label.get().to_string()).or_default() += 1;
+                    }
+                }
+            }
+        }
+        for child in node.children() {
+            visit(&child, counts);
+        }
+    }
+
+    let mut counts = HashMap::new();
+    for source in sources {
+        let root = parse(source);
+        visit(&LinkedNode::new(&root), &mut counts);
+    }
+    counts
+}
+
+/// Keys defined in more than one bibliography file, with the files defining
+/// them. Typst rejects such duplicates when the files are loaded together.
+pub fn cross_file_duplicates(checks: &[(String, BibCheck)]) -> Vec<(String, Vec<String>)> {
+    let mut owners: Vec<(String, Vec<String>)> = Vec::new();
+    for (name, check) in checks {
+        for entry in &check.entries {
+            match owners.iter_mut().find(|(k, _)| *k == entry.key) {
+                Some((_, files)) if !files.contains(name) => files.push(name.clone()),
+                Some(_) => {}
+                None => owners.push((entry.key.clone(), vec![name.clone()])),
+            }
+        }
+    }
+    owners.retain(|(_, files)| files.len() > 1);
+    owners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = "knuth84:\n  type: article\n  title: Literate Programming\n  author: Knuth, Donald E.\n  date: 1984-05\n  journal: The Computer Journal\n\nlamport:\n  type: book\n  title:\n    value: LaTeX\n    short: LaTeX\n  author: [\"Lamport, Leslie\", \"Other, A.\"]\n  date: 1994\n";
+
+    const BIB: &str = "% export\n@article{knuth84,\n  title = {Literate Programming},\n  author = {Knuth, Donald E.},\n  date = {1984-05},\n  journal = {The Computer Journal},\n}\n\n@book{lamport, title = {LaTeX}, author = {Lamport, Leslie and Other, A.}, year = {1994}}\n";
+
+    fn file(name: &str, content: &str) -> BibFile {
+        BibFile {
+            name: name.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    fn draft(key: &str, ty: &str, title: &str, authors: &[&str], year: &str) -> EntryDraft {
+        EntryDraft {
+            key: key.to_string(),
+            entry_type: ty.to_string(),
+            title: title.to_string(),
+            authors: authors.iter().map(|a| a.to_string()).collect(),
+            year: year.to_string(),
+        }
+    }
+
+    #[test]
+    fn summarizes_yaml_entries() {
+        let check = check_bibliography("refs.yml", YAML);
+        assert!(check.diagnostics.is_empty(), "{:?}", check.diagnostics);
+        assert_eq!(check.entries.len(), 2);
+        let knuth = &check.entries[0];
+        assert_eq!(
+            (knuth.key.as_str(), knuth.entry_type.as_str(), knuth.year.as_str(), knuth.line),
+            ("knuth84", "article", "1984", 1)
+        );
+        assert_eq!(knuth.authors, ["Knuth, Donald E."]);
+        let lamport = &check.entries[1];
+        assert_eq!(lamport.title, "LaTeX");
+        assert_eq!(lamport.authors.len(), 2);
+        assert_eq!(lamport.line, 8);
+    }
+
+    #[test]
+    fn summarizes_biblatex_entries() {
+        let check = check_bibliography("refs.bib", BIB);
+        assert!(check.diagnostics.is_empty(), "{:?}", check.diagnostics);
+        let keys: Vec<_> = check.entries.iter().map(|e| (e.key.as_str(), e.line, e.year.as_str())).collect();
+        assert_eq!(keys, [("knuth84", 2, "1984"), ("lamport", 9, "1994")]);
+        assert_eq!(check.entries[1].authors, ["Lamport, Leslie", "Other, A."]);
+        assert_eq!(check.entries[1].entry_type, "book");
+    }
+
+    #[test]
+    fn yaml_errors_carry_their_line() {
+        // `title` is indented one space too far, so YAML folds it into the
+        // type on line 2: a classic hand-editing slip.
+        let broken = "a:\n  type: book\n   title: Oops\n";
+        let check = check_bibliography("refs.yml", broken);
+        assert!(check.entries.is_empty());
+        assert_eq!(check.diagnostics.len(), 1);
+        let diag = &check.diagnostics[0];
+        assert_eq!(diag.line, Some(2), "{diag:?}");
+        assert!(diag.message.contains("unknown variant `book title`"), "{diag:?}");
+        assert!(!diag.message.contains(" at line ") && !diag.message.contains("expected one of"));
+
+        let unclosed = "a:\n  type: book\n  author: [\"A\", \"B\"\n  date: 2020\n";
+        let check = check_bibliography("refs.yml", unclosed);
+        assert!(check.diagnostics[0].line.is_some_and(|l| l >= 3), "{:?}", check.diagnostics);
+    }
+
+    #[test]
+    fn duplicate_keys_are_reported() {
+        let yaml = "a:\n  type: book\nb:\n  type: book\na:\n  type: web\n";
+        let check = check_bibliography("refs.yml", yaml);
+        assert_eq!(check.diagnostics[0].line, Some(5));
+        assert!(check.diagnostics[0].message.contains("line 1"));
+
+        let bib = "@book{a, title={A}}\n@book{a, title={B}}\n";
+        let check = check_bibliography("refs.bib", bib);
+        assert_eq!(check.diagnostics.len(), 1);
+        assert!(check.diagnostics[0].message.contains("duplicate"), "{:?}", check.diagnostics);
+    }
+
+    #[test]
+    fn biblatex_parse_errors_carry_their_line() {
+        let check = check_bibliography("refs.bib", "@book{ok, title={A}}\n\n@book{broken, title={B}\n");
+        assert_eq!(check.diagnostics.len(), 1);
+        assert!(check.diagnostics[0].line.is_some_and(|l| l >= 3), "{:?}", check.diagnostics);
+    }
+
+    #[test]
+    fn draft_validation() {
+        let existing = check_bibliography("refs.yml", YAML).entries;
+        assert!(validate_draft(&draft("new", "book", "T", &[], "2020"), &existing, None).is_empty());
+        assert_eq!(validate_draft(&draft("knuth84", "book", "", &[], ""), &existing, None).len(), 1);
+        // Editing an entry may keep its own key.
+        assert!(validate_draft(&draft("knuth84", "book", "", &[], ""), &existing, Some("knuth84")).is_empty());
+        let errors = validate_draft(&draft("bad key", "", "", &[], "19x4"), &existing, None);
+        assert_eq!(errors.len(), 3, "{errors:?}");
+    }
+
+    #[test]
+    fn yaml_edit_keeps_other_fields_and_position() {
+        let refs = file("refs.yml", YAML);
+        let edited = upsert_entry(
+            &refs,
+            Some("knuth84"),
+            &draft("knuth1984", "article", "Literate Programming (2nd)", &["Knuth, D."], "1984"),
+        )
+        .unwrap();
+        let check = check_bibliography("refs.yml", &edited);
+        assert!(check.diagnostics.is_empty(), "{:?}", check.diagnostics);
+        assert_eq!(check.entries[0].key, "knuth1984");
+        assert_eq!(check.entries[0].title, "Literate Programming (2nd)");
+        // Untouched fields survive, and the full date stays since the year is unchanged.
+        assert!(edited.contains("journal: The Computer Journal"), "{edited}");
+        assert!(edited.contains("1984-05"), "{edited}");
+
+        let added = upsert_entry(&file("refs.yml", &edited), None, &draft("new", "web", "Site", &[], "")).unwrap();
+        let check = check_bibliography("refs.yml", &added);
+        assert_eq!(check.entries.last().map(|e| e.key.as_str()), Some("new"));
+
+        let removed = delete_entry(&file("refs.yml", &added), "lamport").unwrap();
+        let keys: Vec<_> = check_bibliography("refs.yml", &removed).entries.into_iter().map(|e| e.key).collect();
+        assert_eq!(keys, ["knuth1984", "new"]);
+    }
+
+    #[test]
+    fn structured_yaml_title_keeps_short_form() {
+        let edited = upsert_entry(&file("refs.yml", YAML), Some("lamport"), &draft("lamport", "book", "LaTeX 2e", &[], "1994"))
+            .unwrap();
+        assert!(edited.contains("short: LaTeX"), "{edited}");
+        assert_eq!(check_bibliography("refs.yml", &edited).entries[1].title, "LaTeX 2e");
+    }
+
+    #[test]
+    fn biblatex_edit_add_delete() {
+        let refs = file("refs.bib", BIB);
+        let edited = upsert_entry(
+            &refs,
+            Some("knuth84"),
+            &draft("knuth1984", "article", "Literate Programming", &["Knuth, Donald E."], "1985"),
+        )
+        .unwrap();
+        let check = check_bibliography("refs.bib", &edited);
+        assert!(check.diagnostics.is_empty(), "{:?}", check.diagnostics);
+        assert_eq!(check.entries[0].key, "knuth1984");
+        assert_eq!(check.entries[0].year, "1985");
+        assert!(edited.contains("The Computer Journal"), "{edited}");
+        assert!(edited.starts_with("% export\n@article{knuth1984,\n"), "{edited}");
+
+        let added = upsert_entry(&file("refs.bib", &edited), None, &draft("web1", "online", "Site", &["Doe, J."], "2024"))
+            .unwrap();
+        let check = check_bibliography("refs.bib", &added);
+        assert_eq!(check.entries.len(), 3);
+        assert_eq!(check.entries[2].entry_type, "online");
+
+        let removed = delete_entry(&file("refs.bib", &added), "lamport").unwrap();
+        assert_eq!(check_bibliography("refs.bib", &removed).entries.len(), 2);
+    }
+
+    #[test]
+    fn yaml_edits_leave_the_rest_of_the_file_alone() {
+        let yaml = "# Exported from Zotero\nknuth84:\n  type: article\n  # the 1984 paper\n  title: Literate Programming\n  date: 1984-05\n\n# Books\nlamport:\n    type: book\n    title: LaTeX\n    date: 1994\n";
+        let edited = upsert_entry(&file("refs.yml", yaml), Some("knuth84"), &draft("knuth84", "article", "LP", &[], "1984")).unwrap();
+        let (head, tail) = yaml.split_at(yaml.find("\n\n# Books").unwrap());
+        assert!(edited.starts_with("# Exported from Zotero\nknuth84:\n"), "{edited}");
+        assert!(edited.ends_with(tail), "{edited}");
+        assert_ne!(&edited[..edited.len() - tail.len()], head);
+        assert!(edited.contains("title: LP"), "{edited}");
+
+        let added = upsert_entry(&file("refs.yml", yaml), None, &draft("new", "web", "Site", &[], "")).unwrap();
+        assert!(added.starts_with(yaml), "{added}");
+
+        let removed = delete_entry(&file("refs.yml", yaml), "knuth84").unwrap();
+        assert_eq!(removed, "# Exported from Zotero\n# Books\nlamport:\n    type: book\n    title: LaTeX\n    date: 1994\n");
+        let removed = delete_entry(&file("refs.yml", yaml), "lamport").unwrap();
+        // Comments outside the entry stay, even the one above it.
+        assert_eq!(removed, &yaml[..yaml.find("lamport:").unwrap()]);
+    }
+
+    #[test]
+    fn biblatex_edits_keep_comments_and_macros() {
+        let bib = "% export\n@string{cj = {The Computer Journal}}\n\n@article{knuth84,\n  title = {Literate Programming},\n  author = {Knuth, Donald E.},\n  journal = cj,\n  date = {1984-05},\n}\n\n% hand-written\n@book{lamport, title = {LaTeX}, author = {Lamport, Leslie}, year = {1994}}\n\n@comment{keep me}\n";
+        let edited = upsert_entry(
+            &file("refs.bib", bib),
+            Some("knuth84"),
+            &draft("knuth84", "article", "Literate Programming (2nd)", &["Knuth, Donald E."], "1984"),
+        )
+        .unwrap();
+        assert_eq!(
+            edited,
+            bib.replace(
+                "  title = {Literate Programming},\n  author = {Knuth, Donald E.},\n  journal = cj,",
+                "  title = {Literate Programming (2nd)},\n  author = {Knuth, Donald E.},\n  journal = cj,",
+            )
+        );
+        let check = check_bibliography("refs.bib", &edited);
+        assert!(check.diagnostics.is_empty(), "{:?}", check.diagnostics);
+        assert_eq!(check.entries[0].year, "1984");
+
+        let removed = delete_entry(&file("refs.bib", bib), "lamport").unwrap();
+        assert_eq!(
+            removed,
+            bib.replace("@book{lamport, title = {LaTeX}, author = {Lamport, Leslie}, year = {1994}}\n\n", "")
+        );
+        let removed = delete_entry(&file("refs.bib", &removed), "knuth84").unwrap();
+        assert_eq!(removed, "% export\n@string{cj = {The Computer Journal}}\n\n% hand-written\n@comment{keep me}\n");
+    }
+
+    #[test]
+    fn counts_citations_across_files() {
+        let counts = citation_counts(["See @knuth84 and @lamport[p. 4].", "#cite(<knuth84>, supplement: [ch. 2])\n@knuth84"]);
+        assert_eq!(counts.get("knuth84"), Some(&3));
+        assert_eq!(counts.get("lamport"), Some(&1));
+        assert_eq!(counts.get("missing"), None);
+    }
+
+    #[test]
+    fn finds_keys_shared_between_files() {
+        let checks = vec![
+            ("refs.yml".to_string(), check_bibliography("refs.yml", YAML)),
+            ("refs.bib".to_string(), check_bibliography("refs.bib", BIB)),
+        ];
+        let dups = cross_file_duplicates(&checks);
+        assert_eq!(dups.len(), 2);
+        assert_eq!(dups[0], ("knuth84".to_string(), vec!["refs.yml".to_string(), "refs.bib".to_string()]));
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+
+/// One bibliography file of the project, served to the compiler under `name`
+/// (e.g. `refs.yml`, `zotero.bib`) so `#bibliography("zotero.bib")` resolves.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BibFile {
+    pub name: String,
+    pub content: String,
+}
+
+/// Name of the single bibliography file before projects could hold several.
+pub const LEGACY_BIB_NAME: &str = "refs.yml";
+
+/// localStorage key holding every bibliography file as JSON.
+const BIBS_KEY: &str = "typst_bibliographies";
+
+/// Legacy key holding the one Hayagriva bibliography (served as `refs.yml`).
+const LEGACY_BIB_KEY: &str = "typst_bibliography";
+
+/// Bibliography file formats Typst reads. Typst picks the parser from the file
+/// extension, so the name decides how the content is interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BibFormat {
+    /// Hayagriva YAML (`.yml` / `.yaml`).
+    Hayagriva,
+    /// BibLaTeX / BibTeX (`.bib`), e.g. a Zotero or JabRef export.
+    BibLatex,
+}
+
+impl BibFormat {
+    /// The format Typst will parse a file with, from its extension.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.rsplit_once('.')?.1.to_ascii_lowercase().as_str() {
+            "yml" | "yaml" => Some(Self::Hayagriva),
+            "bib" => Some(Self::BibLatex),
+            _ => None,
+        }
+    }
+
+    /// Guess the format of `content`: BibLaTeX entries start with `@type{`,
+    /// anything else is taken as YAML. `%` comment lines are skipped. Returns
+    /// `None` for blank content.
+    pub fn sniff(content: &str) -> Option<Self> {
+        let first = content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('%') && !line.starts_with('#'))?;
+        Some(if first.starts_with('@') {
+            Self::BibLatex
+        } else {
+            Self::Hayagriva
+        })
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Hayagriva => "Hayagriva YAML",
+            Self::BibLatex => "BibLaTeX",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Hayagriva => "yml",
+            Self::BibLatex => "bib",
+        }
+    }
+}
+
+/// A warning when `content` looks like a different format than `name` makes
+/// Typst parse it as (e.g. a BibTeX export pasted into `refs.yml`).
+pub fn format_mismatch(name: &str, content: &str) -> Option<String> {
+    let declared = BibFormat::from_name(name)?;
+    let sniffed = BibFormat::sniff(content)?;
+    (declared != sniffed).then(|| {
+        format!(
+            "This looks like {} but {} is parsed as {}. Rename it to .{}.",
+            sniffed.label(),
+            name,
+            declared.label(),
+            sniffed.extension()
+        )
+    })
+}
+
+/// Validate a new bibliography file name against the existing ones.
+pub fn validate_bib_name(name: &str, existing: &[BibFile]) -> Result<String, String> {
+    let name = name.trim().trim_start_matches('/').to_string();
+    if name.is_empty() {
+        return Err("File name is empty".to_string());
+    }
+    if BibFormat::from_name(&name).is_none() {
+        return Err("Bibliography files must end in .yml, .yaml or .bib".to_string());
+    }
+    if existing.iter().any(|b| b.name == name) {
+        return Err(format!("{name} already exists"));
+    }
+    Ok(name)
+}
+
+/// `(name, content)` pairs for the compiler, skipping blank files.
+pub fn compiler_inputs(bibs: &[BibFile]) -> Vec<(String, String)> {
+    bibs.iter()
+        .filter(|b| !b.content.trim().is_empty())
+        .map(|b| (b.name.clone(), b.content.clone()))
+        .collect()
+}
+
+/// Load the persisted bibliography files, migrating the legacy single
+/// `typst_bibliography` value to a `refs.yml` file. `None` when nothing is stored.
+pub fn load_bibliographies() -> Option<Vec<BibFile>> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    if let Some(json) = storage.get_item(BIBS_KEY).ok().flatten() {
+        if let Ok(bibs) = serde_json::from_str::<Vec<BibFile>>(&json) {
+            return Some(bibs);
+        }
+    }
+    let legacy = storage.get_item(LEGACY_BIB_KEY).ok()??;
+    let bibs = vec![BibFile {
+        name: LEGACY_BIB_NAME.to_string(),
+        content: legacy,
+    }];
+    save_bibliographies(&bibs);
+    let _ = storage.remove_item(LEGACY_BIB_KEY);
+    Some(bibs)
+}
+
+/// Persist every bibliography file to localStorage (best-effort, fails silently).
+pub fn save_bibliographies(bibs: &[BibFile]) {
+    let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) else {
+        return;
+    };
+    if let Ok(json) = serde_json::to_string(bibs) {
+        let _ = storage.set_item(BIBS_KEY, &json);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bib(name: &str) -> BibFile {
+        BibFile {
+            name: name.to_string(),
+            content: String::new(),
+        }
+    }
+
+    #[test]
+    fn format_comes_from_extension() {
+        assert_eq!(BibFormat::from_name("refs.yml"), Some(BibFormat::Hayagriva));
+        assert_eq!(BibFormat::from_name("lit/Refs.YAML"), Some(BibFormat::Hayagriva));
+        assert_eq!(BibFormat::from_name("zotero.bib"), Some(BibFormat::BibLatex));
+        assert_eq!(BibFormat::from_name("notes.txt"), None);
+    }
+
+    #[test]
+    fn sniffs_biblatex_and_yaml() {
+        let bib = "% JabRef export\n\n@article{knuth1984,\n  title = {Literate Programming},\n}";
+        assert_eq!(BibFormat::sniff(bib), Some(BibFormat::BibLatex));
+        let yaml = "# refs\nknuth1984:\n  type: article\n";
+        assert_eq!(BibFormat::sniff(yaml), Some(BibFormat::Hayagriva));
+        assert_eq!(BibFormat::sniff("  \n"), None);
+    }
+
+    #[test]
+    fn mismatch_suggests_the_right_extension() {
+        let warning = format_mismatch("refs.yml", "@book{x, title={T}}").expect("mismatch");
+        assert!(warning.contains(".bib"), "got: {warning}");
+        assert!(format_mismatch("refs.bib", "@book{x, title={T}}").is_none());
+        assert!(format_mismatch("refs.yml", "").is_none());
+    }
+
+    #[test]
+    fn validates_new_names() {
+        let existing = [bib("refs.yml")];
+        assert_eq!(validate_bib_name(" zotero.bib ", &existing).as_deref(), Ok("zotero.bib"));
+        assert!(validate_bib_name("refs.yml", &existing).is_err());
+        assert!(validate_bib_name("refs.txt", &existing).is_err());
+        assert!(validate_bib_name("", &existing).is_err());
+    }
+
+    #[test]
+    fn blank_files_are_not_served() {
+        let bibs = [
+            bib("empty.bib"),
+            BibFile {
+                name: "refs.yml".to_string(),
+                content: "a:\n  type: book\n".to_string(),
+            },
+        ];
+        let inputs = compiler_inputs(&bibs);
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].0, "refs.yml");
+    }
+}
@@ -0,0 +1,428 @@
+//! Multiple carets for the editor.
+//!
+//! The textarea has a single native selection, so additional carets are kept
+//! here as byte offsets into its text. A multi-caret edit is computed on the
+//! whole text and written back as one change (`editing::replace_changed`),
+//! which keeps it a single native undo step.
+
+use super::editing::{indent_block, outdent_block};
+use std::ops::Range;
+
+/// A selection: `anchor` stays put while `head` moves; a plain caret when
+/// they are equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Caret {
+    pub anchor: usize,
+    pub head: usize,
+}
+
+impl Caret {
+    pub fn at(offset: usize) -> Self {
+        Self { anchor: offset, head: offset }
+    }
+
+    pub fn range(self) -> Range<usize> {
+        self.anchor.min(self.head)..self.anchor.max(self.head)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.anchor == self.head
+    }
+}
+
+/// Caret movements, as the arrow, Home and End keys do them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+}
+
+/// Every caret, sorted and non-overlapping. `primary` is the one mirrored
+/// by the native selection (the last one added).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Carets {
+    pub list: Vec<Caret>,
+    pub primary: usize,
+}
+
+/// One caret's share of an edit: `range` is replaced by `text`, and the
+/// caret then selects `select` (offsets into `text`).
+struct Edit {
+    range: Range<usize>,
+    text: String,
+    select: (usize, usize),
+}
+
+impl Carets {
+    pub fn new(caret: Caret) -> Self {
+        Self { list: vec![caret], primary: 0 }
+    }
+
+    pub fn primary(&self) -> Caret {
+        self.list[self.primary]
+    }
+
+    /// Add a caret (or selection) and make it the primary one.
+    pub fn add(&mut self, caret: Caret) {
+        self.list.push(caret);
+        self.primary = self.list.len() - 1;
+        self.normalize();
+    }
+
+    /// Alt+click: add a caret at `offset`, or remove the caret already
+    /// there unless it is the only one.
+    pub fn toggle(&mut self, offset: usize) {
+        match self.list.iter().position(|c| c.is_empty() && c.head == offset) {
+            Some(i) if self.list.len() > 1 => {
+                self.list.remove(i);
+                self.primary = self.list.len() - 1;
+            }
+            Some(_) => {}
+            None => self.add(Caret::at(offset)),
+        }
+    }
+
+    /// Sort the carets and merge the ones that overlap or coincide.
+    fn normalize(&mut self) {
+        let primary = self.list[self.primary];
+        let mut tagged: Vec<(Caret, bool)> = self.list.iter().map(|&c| (c, c == primary)).collect();
+        tagged.sort_by_key(|(c, _)| (c.range().start, c.range().end));
+        let mut merged: Vec<(Caret, bool)> = Vec::with_capacity(tagged.len());
+        for (caret, is_primary) in tagged {
+            match merged.last_mut() {
+                Some((last, last_primary))
+                    if caret.range().start < last.range().end || caret.range() == last.range() =>
+                {
+                    let end = last.range().end.max(caret.range().end);
+                    *last = Caret { anchor: last.range().start, head: end };
+                    *last_primary |= is_primary;
+                }
+                _ => merged.push((caret, is_primary)),
+            }
+        }
+        self.primary = merged.iter().position(|(_, p)| *p).unwrap_or(merged.len() - 1);
+        self.list = merged.into_iter().map(|(c, _)| c).collect();
+    }
+
+    /// Apply one edit per caret (left to right) to `text`.
+    fn apply(&self, text: &str, edit: impl Fn(Caret) -> Edit) -> (String, Carets) {
+        let mut out = String::with_capacity(text.len());
+        let mut list = Vec::with_capacity(self.list.len());
+        let mut last = 0;
+        for &caret in &self.list {
+            let Edit { range, text: insert, select } = edit(caret);
+            // Neighbouring carets never touch the same text twice.
+            let start = range.start.max(last);
+            let end = range.end.max(start);
+            out.push_str(&text[last..start]);
+            let at = out.len();
+            out.push_str(&insert);
+            list.push(Caret { anchor: at + select.0, head: at + select.1 });
+            last = end;
+        }
+        out.push_str(&text[last..]);
+        let mut carets = Carets { list, primary: self.primary };
+        carets.normalize();
+        (out, carets)
+    }
+
+    /// Replace every selection with `insert`, leaving the carets after it.
+    pub fn insert(&self, text: &str, insert: &str) -> (String, Carets) {
+        self.apply(text, |caret| Edit {
+            range: caret.range(),
+            text: insert.to_string(),
+            select: (insert.len(), insert.len()),
+        })
+    }
+
+    /// Type an auto-paired character: wrap each selection in `open`/`close`,
+    /// or insert the pair with the caret between them.
+    pub fn pair(&self, text: &str, open: char, close: char) -> (String, Carets) {
+        self.apply(text, |caret| {
+            let inner = &text[caret.range()];
+            let at = open.len_utf8();
+            Edit {
+                range: caret.range(),
+                text: format!("{open}{inner}{close}"),
+                select: (at, at + inner.len()),
+            }
+        })
+    }
+
+    /// Move every caret over `ch` when each one sits right before it (the
+    /// user re-types a closer that was auto-inserted); `None` otherwise.
+    pub fn skip(&self, text: &str, ch: char) -> Option<Carets> {
+        self.list
+            .iter()
+            .all(|c| c.is_empty() && text[c.head..].starts_with(ch))
+            .then(|| self.map(|c| Caret::at(c.head + ch.len_utf8())))
+    }
+
+    /// Backspace: delete each selection, or the character before each caret.
+    pub fn backspace(&self, text: &str) -> (String, Carets) {
+        self.apply(text, |caret| {
+            let range = if caret.is_empty() {
+                prev_char(text, caret.head)..caret.head
+            } else {
+                caret.range()
+            };
+            Edit { range, text: String::new(), select: (0, 0) }
+        })
+    }
+
+    /// Delete: delete each selection, or the character after each caret.
+    pub fn delete(&self, text: &str) -> (String, Carets) {
+        self.apply(text, |caret| {
+            let range = if caret.is_empty() {
+                caret.head..next_char(text, caret.head)
+            } else {
+                caret.range()
+            };
+            Edit { range, text: String::new(), select: (0, 0) }
+        })
+    }
+
+    /// Indent (or outdent) every line a caret touches once, through
+    /// `indent_block` / `outdent_block`; carets keep their place in the line.
+    pub fn indent(&self, text: &str, outdent: bool) -> (String, Carets) {
+        let starts = line_starts(text);
+        let line_of = |offset: usize| starts.partition_point(|&s| s <= offset) - 1;
+        // Inclusive line spans, merged where carets share lines.
+        let mut blocks: Vec<(usize, usize)> = Vec::new();
+        for caret in &self.list {
+            let (first, last) = (line_of(caret.range().start), line_of(caret.range().end));
+            match blocks.last_mut() {
+                Some(block) if first <= block.1 => block.1 = block.1.max(last),
+                _ => blocks.push((first, last)),
+            }
+        }
+        let mut new = text.to_string();
+        for &(first, last) in blocks.iter().rev() {
+            new = if outdent {
+                outdent_block(&new, starts[first], starts[last]).0
+            } else {
+                indent_block(&new, starts[first], starts[last]).0
+            };
+        }
+        let new_starts = line_starts(&new);
+        let line_len = |starts: &[usize], text: &str, line: usize| {
+            starts.get(line + 1).map_or(text.len(), |&next| next - 1) - starts[line]
+        };
+        let map = |offset: usize| {
+            let line = line_of(offset);
+            let delta = line_len(&new_starts, &new, line) as isize - line_len(&starts, text, line) as isize;
+            let col = ((offset - starts[line]) as isize + delta).max(0) as usize;
+            new_starts[line] + col
+        };
+        let carets = self.map(|c| Caret { anchor: map(c.anchor), head: map(c.head) });
+        (new, carets)
+    }
+
+    /// Move every caret; with `extend`, heads move and anchors stay.
+    pub fn moved(&self, text: &str, motion: Motion, extend: bool) -> Carets {
+        self.map(|caret| {
+            // An arrow collapses a selection to the side it points at.
+            if !extend && !caret.is_empty() {
+                match motion {
+                    Motion::Left => return Caret::at(caret.range().start),
+                    Motion::Right => return Caret::at(caret.range().end),
+                    _ => {}
+                }
+            }
+            let head = step(text, caret.head, motion);
+            if extend {
+                Caret { anchor: caret.anchor, head }
+            } else {
+                Caret::at(head)
+            }
+        })
+    }
+
+    /// Ctrl+D: select the word at the primary caret if it is empty, else add
+    /// a selection of the next occurrence of the primary selection's text.
+    /// Returns whether anything changed.
+    pub fn add_next_occurrence(&mut self, text: &str) -> bool {
+        let primary = self.primary();
+        if primary.is_empty() {
+            let word = word_at(text, primary.head);
+            if word.is_empty() {
+                return false;
+            }
+            self.list[self.primary] = Caret { anchor: word.start, head: word.end };
+            self.normalize();
+            return true;
+        }
+        let needle = &text[primary.range()];
+        let from = primary.range().end;
+        let found = text[from..]
+            .match_indices(needle)
+            .map(|(i, _)| from + i)
+            .chain(text[..from].match_indices(needle).map(|(i, _)| i))
+            .find(|&start| !self.list.iter().any(|c| c.range() == (start..start + needle.len())));
+        match found {
+            Some(start) => {
+                self.add(Caret { anchor: start, head: start + needle.len() });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Column selection from `from` to `to` (`(line, column)`, columns in
+    /// characters): one selection per line, clipped to the line's end.
+    pub fn column(text: &str, from: (usize, usize), to: (usize, usize)) -> Carets {
+        let list = (from.0.min(to.0)..=from.0.max(to.0))
+            .map(|line| Caret {
+                anchor: offset_at(text, line, from.1),
+                head: offset_at(text, line, to.1),
+            })
+            .collect::<Vec<_>>();
+        let primary = if to.0 >= from.0 { list.len() - 1 } else { 0 };
+        let mut carets = Carets { list, primary };
+        carets.normalize();
+        carets
+    }
+
+    fn map(&self, f: impl Fn(Caret) -> Caret) -> Carets {
+        let mut carets = Carets {
+            list: self.list.iter().map(|&c| f(c)).collect(),
+            primary: self.primary,
+        };
+        carets.normalize();
+        carets
+    }
+}
+
+/// Byte offset of each line start.
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0).chain(text.match_indices('\n').map(|(i, _)| i + 1)).collect()
+}
+
+/// `(line, column)` of a byte offset, the column in characters.
+pub fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count(), before[start..].chars().count())
+}
+
+/// Byte offset of `(line, column)`, clamped to the text and the line's end.
+pub fn offset_at(text: &str, line: usize, col: usize) -> usize {
+    let Some(start) = line_starts(text).get(line).copied() else {
+        return text.len();
+    };
+    let rest = &text[start..];
+    let line_text = &rest[..rest.find('\n').unwrap_or(rest.len())];
+    start + line_text.char_indices().nth(col).map_or(line_text.len(), |(i, _)| i)
+}
+
+fn prev_char(text: &str, offset: usize) -> usize {
+    text[..offset].char_indices().next_back().map_or(0, |(i, _)| i)
+}
+
+fn next_char(text: &str, offset: usize) -> usize {
+    text[offset..].chars().next().map_or(offset, |c| offset + c.len_utf8())
+}
+
+fn step(text: &str, offset: usize, motion: Motion) -> usize {
+    let (line, col) = line_col(text, offset);
+    match motion {
+        Motion::Left => prev_char(text, offset),
+        Motion::Right => next_char(text, offset),
+        Motion::Up if line == 0 => 0,
+        Motion::Up => offset_at(text, line - 1, col),
+        Motion::Down => offset_at(text, line + 1, col),
+        Motion::Home => offset_at(text, line, 0),
+        Motion::End => offset_at(text, line, usize::MAX),
+    }
+}
+
+/// The identifier-like word around `offset` (Typst identifiers may contain
+/// `-`), empty if there is none.
+fn word_at(text: &str, offset: usize) -> Range<usize> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    let start = text[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_word(c))
+        .last()
+        .map_or(offset, |(i, _)| i);
+    let end = text[offset..].find(|c| !is_word(c)).map_or(text.len(), |i| offset + i);
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn carets(offsets: &[usize]) -> Carets {
+        let mut carets = Carets::new(Caret::at(offsets[0]));
+        for &offset in &offsets[1..] {
+            carets.add(Caret::at(offset));
+        }
+        carets
+    }
+
+    fn heads(carets: &Carets) -> Vec<usize> {
+        carets.list.iter().map(|c| c.head).collect()
+    }
+
+    #[test]
+    fn edits_apply_at_every_caret() {
+        let text = "[a], [b]\n[c], [d]\n";
+        let row = carets(&[1, 6, 10]);
+        let (typed, after) = row.insert(text, "x");
+        assert_eq!(typed, "[xa], [xb]\n[xc], [d]\n");
+        assert_eq!(heads(&after), vec![2, 8, 13]);
+        assert_eq!(after.primary, 2);
+
+        let (deleted, after) = after.backspace(&typed);
+        assert_eq!(deleted, text);
+        assert_eq!(heads(&after), vec![1, 6, 10]);
+        assert_eq!(after.delete(text).0, "[], []\n[], [d]\n");
+
+        let (paired, after) = row.pair(text, '(', ')');
+        assert_eq!(paired, "[()a], [()b]\n[()c], [d]\n");
+        assert_eq!(after.skip(&paired, ')').map(|c| heads(&c)), Some(vec![3, 10, 16]));
+        assert_eq!(after.skip(&paired, ']'), None);
+
+        // Carets that run into each other merge.
+        let (_, merged) = carets(&[1, 2]).backspace("abc");
+        assert_eq!(merged.list, vec![Caret::at(0)]);
+    }
+
+    #[test]
+    fn lines_indent_once_per_caret_block() {
+        let (indented, after) = carets(&[0, 1, 5]).indent("a\nb\nc\n", false);
+        assert_eq!(indented, "  a\nb\n  c\n");
+        assert_eq!(heads(&after), vec![2, 3, 9]);
+        let (outdented, after) = carets(&[2, 4]).indent("a\n  b\nc\n", true);
+        assert_eq!(outdented, "a\nb\nc\n");
+        assert_eq!(heads(&after), vec![2]);
+    }
+
+    #[test]
+    fn selections_grow_by_occurrence_and_column() {
+        let text = "#let width = 1\n#f(width: width)\n";
+        let mut carets = Carets::new(Caret::at(7));
+        assert!(carets.add_next_occurrence(text));
+        assert_eq!(&text[carets.primary().range()], "width");
+        assert!(carets.add_next_occurrence(text));
+        assert!(carets.add_next_occurrence(text));
+        assert_eq!(carets.list.len(), 3);
+        assert!(!carets.add_next_occurrence(text));
+        let (renamed, _) = carets.insert(text, "w");
+        assert_eq!(renamed, "#let w = 1\n#f(w: w)\n");
+
+        let table = "ab\nabcd\n\nabc\n";
+        let block = Carets::column(table, (0, 1), (3, 3));
+        let selected: Vec<&str> = block.list.iter().map(|c| &table[c.range()]).collect();
+        assert_eq!(selected, vec!["b", "bc", "", "bc"]);
+        let moved = block.moved(table, Motion::Right, false);
+        assert_eq!(heads(&moved), vec![2, 6, 9, 12]);
+        assert_eq!(heads(&moved.moved(table, Motion::Up, false)), vec![0, 2, 8]);
+    }
+}
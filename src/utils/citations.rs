@@ -0,0 +1,225 @@
+//! Citation picker support: every entry of the project's bibliography files
+//! (parsed directly, so it works before the first compile), fuzzy search over
+//! key/title/authors/year, and the markup inserted for a selection.
+
+use super::bib_entries::{readable_entries, EntrySummary};
+use super::bibliography::BibFile;
+use std::collections::HashSet;
+
+/// An entry offered by the picker, with the file it comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CiteCandidate {
+    pub entry: EntrySummary,
+    pub file: String,
+}
+
+/// How the selection is inserted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CiteForm {
+    /// `@key` references.
+    At,
+    /// `#cite(<key>)` calls, which also take a supplement.
+    Cite,
+}
+
+/// Entries of every bibliography file in order; a key defined twice is
+/// offered once (from its first file). Files that don't parse contribute
+/// the entries that parse on their own.
+pub fn candidates(bibs: &[BibFile]) -> Vec<CiteCandidate> {
+    let mut seen = HashSet::new();
+    bibs.iter()
+        .flat_map(|file| {
+            readable_entries(&file.name, &file.content)
+                .into_iter()
+                .map(|entry| CiteCandidate {
+                    entry,
+                    file: file.name.clone(),
+                })
+        })
+        .filter(|c| seen.insert(c.entry.key.clone()))
+        .collect()
+}
+
+/// Score `needle` as an in-order subsequence of `haystack` (both lowercase):
+/// consecutive matches and matches at word starts score higher, a plain
+/// substring highest. `None` when the characters don't all appear in order.
+fn fuzzy_score(needle: &str, haystack: &str) -> Option<i32> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    if let Some(pos) = haystack.find(needle) {
+        let at_word = pos == 0 || !haystack[..pos].ends_with(char::is_alphanumeric);
+        return Some(needle.chars().count() as i32 * 4 + if at_word { 10 } else { 5 });
+    }
+    let mut score = 0;
+    let mut prev_matched = false;
+    let mut prev_char = ' ';
+    let mut wanted = needle.chars().peekable();
+    for c in haystack.chars() {
+        match wanted.peek() {
+            Some(&w) if w == c => {
+                score += 1;
+                if prev_matched {
+                    score += 2;
+                }
+                if !prev_char.is_alphanumeric() {
+                    score += 2;
+                }
+                wanted.next();
+                prev_matched = true;
+            }
+            Some(_) => prev_matched = false,
+            None => break,
+        }
+        prev_char = c;
+    }
+    wanted.peek().is_none().then_some(score)
+}
+
+/// Candidates matching every whitespace-separated word of `query` in any of
+/// key, title, authors or year, best first (key matches weigh double). An
+/// empty query keeps the file order.
+pub fn search<'a>(candidates: &'a [CiteCandidate], query: &str) -> Vec<&'a CiteCandidate> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if words.is_empty() {
+        return candidates.iter().collect();
+    }
+    let mut scored: Vec<(i32, &CiteCandidate)> = candidates
+        .iter()
+        .filter_map(|c| {
+            let key = c.entry.key.to_lowercase();
+            let fields = [
+                c.entry.title.to_lowercase(),
+                c.entry.authors.join(" ").to_lowercase(),
+                c.entry.year.to_lowercase(),
+            ];
+            let mut total = 0;
+            for word in &words {
+                let best = std::iter::once(fuzzy_score(word, &key).map(|s| s * 2))
+                    .chain(fields.iter().map(|f| fuzzy_score(word, f)))
+                    .flatten()
+                    .max()?;
+                total += best;
+            }
+            Some((total, c))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.entry.key.cmp(&b.1.entry.key)));
+    scored.into_iter().map(|(_, c)| c).collect()
+}
+
+/// Whether `key` can be written as `@key` / `<key>`: Typst label characters,
+/// not ending in `.` or `:` (which would be read as punctuation).
+fn is_plain_label(key: &str) -> bool {
+    !key.is_empty()
+        && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+        && !key.ends_with(['.', ':'])
+}
+
+fn label_expr(key: &str) -> String {
+    if is_plain_label(key) {
+        format!("<{key}>")
+    } else {
+        format!("label({key:?})")
+    }
+}
+
+/// Markup citing `keys`, separated by spaces so Typst groups them. A
+/// non-empty supplement goes on the last citation (`#cite` form only).
+/// Keys that aren't valid label syntax always use `#cite(label("…"))`.
+pub fn citation_markup(keys: &[&str], form: CiteForm, supplement: &str) -> String {
+    let supplement = supplement.trim();
+    keys.iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let last = i + 1 == keys.len();
+            match form {
+                CiteForm::At if is_plain_label(key) => format!("@{key}"),
+                _ if last && !supplement.is_empty() && form == CiteForm::Cite => {
+                    format!("#cite({}, supplement: [{supplement}])", label_expr(key))
+                }
+                _ => format!("#cite({})", label_expr(key)),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bib(name: &str, content: &str) -> BibFile {
+        BibFile {
+            name: name.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    fn fixture() -> Vec<CiteCandidate> {
+        candidates(&[
+            bib(
+                "refs.yml",
+                "knuth1984:\n  type: article\n  title: Literate Programming\n  author: Knuth, Donald E.\n  date: 1984\n\
+                 lamport1994:\n  type: book\n  title: A Document Preparation System\n  author: Lamport, Leslie\n  date: 1994\n",
+            ),
+            bib(
+                "zotero.bib",
+                "@book{knuth1984, title = {Duplicate}}\n@article{shannon1948, author = {Shannon, Claude}, title = {A Mathematical Theory of Communication}, year = {1948}}",
+            ),
+        ])
+    }
+
+    #[test]
+    fn candidates_span_files_without_duplicates() {
+        let keys: Vec<_> = fixture().iter().map(|c| (c.entry.key.clone(), c.file.clone())).collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("knuth1984".to_string(), "refs.yml".to_string()),
+                ("lamport1994".to_string(), "refs.yml".to_string()),
+                ("shannon1948".to_string(), "zotero.bib".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn broken_files_still_offer_their_readable_entries() {
+        let found = candidates(&[
+            bib(
+                "refs.yml",
+                "good:\n  type: book\n  title: Fine\nbroken:\n  type: book\n   title: Oops\nlater:\n  type: web\n",
+            ),
+            bib("zotero.bib", "@book{ok, title = {A}}\n@book{unclosed, title = {B}\n\n@misc{after, title = {C}}\n"),
+        ]);
+        let keys: Vec<_> = found.iter().map(|c| (c.entry.key.as_str(), c.entry.line)).collect();
+        assert_eq!(keys, [("good", 1), ("later", 7), ("ok", 1), ("after", 4)]);
+    }
+
+    #[test]
+    fn fuzzy_search_matches_any_field() {
+        let all = fixture();
+        let keys = |q: &str| search(&all, q).iter().map(|c| c.entry.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys(""), vec!["knuth1984", "lamport1994", "shannon1948"]);
+        assert_eq!(keys("shanon"), vec!["shannon1948"]);
+        assert_eq!(keys("mathematical theory"), vec!["shannon1948"]);
+        assert_eq!(keys("lit prog"), vec!["knuth1984"]);
+        assert_eq!(keys("1994"), vec!["lamport1994"]);
+        assert_eq!(keys("leslie"), vec!["lamport1994"]);
+        assert!(keys("zzz").is_empty());
+    }
+
+    #[test]
+    fn markup_for_single_and_multiple_entries() {
+        assert_eq!(citation_markup(&["knuth1984"], CiteForm::At, ""), "@knuth1984");
+        assert_eq!(citation_markup(&["a", "b"], CiteForm::At, "ignored"), "@a @b");
+        assert_eq!(
+            citation_markup(&["a", "b"], CiteForm::Cite, "p. 7"),
+            "#cite(<a>) #cite(<b>, supplement: [p. 7])"
+        );
+        assert_eq!(citation_markup(&["a"], CiteForm::Cite, " "), "#cite(<a>)");
+        // Keys that can't be written as labels.
+        assert_eq!(citation_markup(&["doe 2020"], CiteForm::At, ""), "#cite(label(\"doe 2020\"))");
+        assert_eq!(citation_markup(&["etal."], CiteForm::At, ""), "#cite(label(\"etal.\"))");
+    }
+}
@@ -0,0 +1,130 @@
+//! Citation styles: Typst's built-in CSL archive plus `.csl` files uploaded as
+//! project assets (resolved by path, e.g. `style: "styles/my-journal.csl"`).
+//!
+//! The chosen style lives in the settings preamble as a single
+//! `#set bibliography(style: "…")` rule, so it applies to every
+//! `#bibliography(...)` call that doesn't pass its own `style:`.
+
+use hayagriva::archive::ArchivedStyle;
+use hayagriva::citationberg::Style;
+
+/// Start of the settings rule this module manages.
+const STYLE_RULE: &str = "#set bibliography(style:";
+
+/// Built-in styles as `(name, display name)`, in archive order. Styles with
+/// aliases use the shortest one (`ieee`, `apa`, `mla`).
+pub fn builtin_styles() -> Vec<(&'static str, &'static str)> {
+    ArchivedStyle::all()
+        .iter()
+        .filter_map(|s| Some((*s.names().iter().min_by_key(|n| n.len())?, s.display_name())))
+        .collect()
+}
+
+pub fn is_csl_path(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".csl")
+}
+
+/// Check that `bytes` is a CSL style Typst can load and return its title.
+/// Dependent styles (aliases of another style) are rejected with a pointer
+/// to the parent style they need.
+pub fn inspect_csl(bytes: &[u8]) -> Result<String, String> {
+    let text = std::str::from_utf8(bytes).map_err(|_| "The style is not UTF-8 text".to_string())?;
+    match Style::from_xml(text).map_err(|e| format!("Not a valid CSL style ({e})"))? {
+        Style::Independent(style) => Ok(style.info.title.value),
+        Style::Dependent(style) => Err(format!(
+            "\"{}\" is a dependent style; upload its parent instead ({})",
+            style.info.title.value, style.parent_link.href
+        )),
+    }
+}
+
+/// The style selected by the settings preamble, if any.
+pub fn style_from_settings(settings: &str) -> Option<String> {
+    settings
+        .lines()
+        .rev()
+        .filter_map(|line| line.trim().strip_prefix(STYLE_RULE))
+        .find_map(|rest| {
+            let rest = rest.trim_start().strip_prefix('"')?;
+            Some(rest[..rest.find('"')?].to_string())
+        })
+}
+
+/// `settings` with the style rule set to `style` (replacing an existing
+/// one), or removed for `None`.
+pub fn with_style(settings: &str, style: Option<&str>) -> String {
+    let mut lines: Vec<String> = settings
+        .lines()
+        .filter(|line| !line.trim().starts_with(STYLE_RULE))
+        .map(str::to_string)
+        .collect();
+    if let Some(style) = style {
+        lines.push(format!("{STYLE_RULE} {style:?})"));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+pub(crate) const TEST_CSL: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<style xmlns="http://purl.org/net/xbiblio/csl" class="in-text" version="1.0">
+  <info>
+    <title>Test Numeric</title>
+    <id>test-numeric</id>
+    <updated>2024-01-01T00:00:00+00:00</updated>
+  </info>
+  <citation>
+    <layout prefix="&lt;" suffix="&gt;" delimiter="; ">
+      <text variable="citation-number"/>
+    </layout>
+  </citation>
+  <bibliography>
+    <layout>
+      <text variable="title"/>
+    </layout>
+  </bibliography>
+</style>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtins_come_from_the_archive() {
+        let styles = builtin_styles();
+        assert!(styles.iter().any(|(name, _)| *name == "ieee"));
+        assert!(styles.iter().any(|(name, _)| *name == "chicago-author-date"));
+    }
+
+    #[test]
+    fn inspects_independent_and_dependent_styles() {
+        assert_eq!(inspect_csl(TEST_CSL.as_bytes()).as_deref(), Ok("Test Numeric"));
+        let dependent = r#"<style xmlns="http://purl.org/net/xbiblio/csl" version="1.0">
+  <info>
+    <title>Some Journal</title>
+    <id>some-journal</id>
+    <link href="http://www.zotero.org/styles/nature" rel="independent-parent"/>
+    <updated>2024-01-01T00:00:00+00:00</updated>
+  </info>
+</style>"#;
+        let err = inspect_csl(dependent.as_bytes()).unwrap_err();
+        assert!(err.contains("styles/nature"), "{err}");
+        assert!(inspect_csl(b"<html></html>").is_err());
+    }
+
+    #[test]
+    fn style_rule_round_trips_through_settings() {
+        let settings = "#set page(paper: \"a4\")\n#set bibliography(style: \"apa\")";
+        assert_eq!(style_from_settings(settings).as_deref(), Some("apa"));
+
+        let updated = with_style(settings, Some("styles/journal.csl"));
+        assert_eq!(
+            updated,
+            "#set page(paper: \"a4\")\n#set bibliography(style: \"styles/journal.csl\")"
+        );
+        assert_eq!(style_from_settings(&updated).as_deref(), Some("styles/journal.csl"));
+
+        assert_eq!(with_style(&updated, None), "#set page(paper: \"a4\")");
+        assert_eq!(style_from_settings("#set text(size: 11pt)"), None);
+    }
+}
@@ -1,3 +1,5 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
 
 /// Trigger a browser download of `bytes` saved as `filename` with the given MIME type.
@@ -33,3 +35,78 @@ pub fn download_bytes(filename: &str, mime: &str, bytes: &[u8]) {
 
     let _ = web_sys::Url::revoke_object_url(&url);
 }
+
+/// Rasterize a standalone SVG document to PNG and download it as `filename`.
+///
+/// `scale` multiplies the SVG's intrinsic CSS-pixel size (2.0 gives a sharp
+/// image on typical displays). The browser does the rasterization through an
+/// `<img>` drawn onto a white `<canvas>`, so the download starts once the image
+/// has loaded; like [`download_bytes`], any failing step is silently skipped.
+pub fn download_svg_as_png(filename: &str, svg: &str, scale: f64) {
+    let Some(document) = web_sys::window().and_then(|w| w.document()) else {
+        return;
+    };
+
+    let parts = js_sys::Array::new();
+    parts.push(&wasm_bindgen::JsValue::from_str(svg));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type("image/svg+xml");
+    let Ok(blob) = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options) else {
+        return;
+    };
+    let Ok(url) = web_sys::Url::create_object_url_with_blob(&blob) else {
+        return;
+    };
+    let Ok(img) = web_sys::HtmlImageElement::new() else {
+        let _ = web_sys::Url::revoke_object_url(&url);
+        return;
+    };
+
+    let filename = filename.to_string();
+    let img_clone = img.clone();
+    let svg_url = url.clone();
+    let onload = Closure::once(move |_: web_sys::Event| {
+        let _ = web_sys::Url::revoke_object_url(&svg_url);
+        let width = (img_clone.natural_width() as f64 * scale).round().max(1.0);
+        let height = (img_clone.natural_height() as f64 * scale).round().max(1.0);
+        let Some(canvas) = document
+            .create_element("canvas")
+            .ok()
+            .and_then(|el| el.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+        else {
+            return;
+        };
+        canvas.set_width(width as u32);
+        canvas.set_height(height as u32);
+        let Some(ctx) = canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .and_then(|c| c.dyn_into::<web_sys::CanvasRenderingContext2d>().ok())
+        else {
+            return;
+        };
+        // Typst pages have a transparent background; flatten onto white.
+        ctx.set_fill_style_str("#ffffff");
+        ctx.fill_rect(0.0, 0.0, width, height);
+        if ctx
+            .draw_image_with_html_image_element_and_dw_and_dh(&img_clone, 0.0, 0.0, width, height)
+            .is_err()
+        {
+            return;
+        }
+        let Ok(data_url) = canvas.to_data_url() else {
+            return;
+        };
+        let Some(bytes) = data_url
+            .split_once(',')
+            .and_then(|(_, b64)| STANDARD.decode(b64).ok())
+        else {
+            return;
+        };
+        download_bytes(&filename, "image/png", &bytes);
+    });
+    img.set_onload(Some(onload.as_ref().unchecked_ref()));
+    img.set_src(&url);
+    onload.forget();
+}
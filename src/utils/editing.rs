@@ -4,9 +4,12 @@
 //! Leptos `prop:value`) wipe the browser's native undo stack. Routing edits
 //! through `document.execCommand("insertText", …)` instead keeps Ctrl+Z working
 //! because the browser records them as user edits. The pure helpers
-//! (`indent_block` / `outdent_block` / `find_matches`) carry the editing logic
-//! that is unit-tested on the host.
+//! (`indent_block` / `outdent_block` / `find_matches` / `minimal_edit` /
+//! `list_enter` / `renest_item`) carry the editing logic that is unit-tested
+//! on the host.
 
+use std::ops::Range;
+use typst_syntax::{parse, LinkedNode, Side, SyntaxKind};
 use wasm_bindgen::JsCast;
 use web_sys::{HtmlDocument, HtmlTextAreaElement};
 
@@ -25,6 +28,18 @@ pub fn insert_text(textarea: &HtmlTextAreaElement, text: &str) {
     }
 }
 
+/// Step the textarea's native undo history back (or forward with `redo`),
+/// as Ctrl+Z / Ctrl+Y would.
+pub fn undo(textarea: &HtmlTextAreaElement, redo: bool) {
+    let _ = textarea.focus();
+    if let Some(doc) = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.dyn_into::<HtmlDocument>().ok())
+    {
+        let _ = doc.exec_command(if redo { "redo" } else { "undo" });
+    }
+}
+
 /// Convert a UTF-16 code-unit offset (as used by DOM textarea selections) into a
 /// byte offset valid for slicing a Rust `str`. Returns `s.len()` if past the end.
 pub fn utf16_to_byte(s: &str, utf16_offset: usize) -> usize {
@@ -64,6 +79,15 @@ pub fn set_selection(textarea: &HtmlTextAreaElement, start: usize, end: usize) {
     let _ = textarea.set_selection_end(Some(end as u32));
 }
 
+/// Write `text` into the textarea only if it differs: setting `.value` wipes
+/// the native undo stack, so edits that already went through `insert_text`
+/// must not be written back.
+pub fn sync_value(textarea: &HtmlTextAreaElement, text: &str) {
+    if textarea.value() != text {
+        textarea.set_value(text);
+    }
+}
+
 /// Number of leading spaces a tab inserts.
 pub const INDENT: &str = "  ";
 
@@ -138,6 +162,68 @@ pub fn auto_pair_close(open: char) -> Option<char> {
     }
 }
 
+/// What Enter does inside a list, enum or term item.
+#[derive(Debug, PartialEq)]
+pub enum ListEnter {
+    /// Insert this: a line break, the item's indentation and the next marker.
+    Continue(String),
+    /// The item is empty: clear this line (its byte range) to end the list.
+    End(Range<usize>),
+}
+
+/// The item whose marker (`-`, `+`, `1.` or `/`) starts the line around
+/// `offset`, as `(line range, indentation, marker)`. The marker is looked up
+/// in the syntax tree, so a `-` in code or a raw block does not count.
+fn item_at(text: &str, offset: usize) -> Option<(Range<usize>, &str, String)> {
+    let start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+    let line = &text[start..end];
+    let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    let root = parse(text);
+    let leaf = LinkedNode::new(&root).leaf_at(start + indent.len(), Side::After)?;
+    let is_marker = leaf.offset() == start + indent.len()
+        && matches!(leaf.kind(), SyntaxKind::ListMarker | SyntaxKind::EnumMarker | SyntaxKind::TermMarker);
+    is_marker.then(|| (start..end, indent, leaf.text().to_string()))
+}
+
+/// Enter at byte offset `caret` of `text`: continue the list, enum or term
+/// list the caret's line belongs to with the same indentation (numbered
+/// items count up), or end it on an empty item. `None` when the caret is not
+/// inside an item's text.
+pub fn list_enter(text: &str, caret: usize) -> Option<ListEnter> {
+    let (line, indent, marker) = item_at(text, caret)?;
+    let body = line.start + indent.len() + marker.len();
+    if caret < body {
+        return None;
+    }
+    if text[body..line.end].trim().is_empty() {
+        return Some(ListEnter::End(line));
+    }
+    // A number with no successor keeps its marker unchanged.
+    let next = match marker.strip_suffix('.').and_then(|n| n.parse::<usize>().ok()?.checked_add(1)) {
+        Some(number) => format!("{number}."),
+        None => marker,
+    };
+    Some(ListEnter::Continue(format!("\n{indent}{next} ")))
+}
+
+/// Tab / Shift+Tab on an item line: nest it one level deeper (or shallower)
+/// through [`indent_block`] / [`outdent_block`]. Returns the line's byte
+/// range, its new text and the new caret offset, which stays on the same
+/// character; `None` if the caret's line is not an item.
+pub fn renest_item(text: &str, caret: usize, outdent: bool) -> Option<(Range<usize>, String, usize)> {
+    let (line, _, _) = item_at(text, caret)?;
+    let (new, _) = if outdent {
+        outdent_block(text, caret, caret)
+    } else {
+        indent_block(text, caret, caret)
+    };
+    let new_line = new[line.start..new.len() - (text.len() - line.end)].to_string();
+    let delta = new_line.len() as isize - line.len() as isize;
+    let caret = line.start + ((caret - line.start) as isize + delta).max(0) as usize;
+    Some((line, new_line, caret))
+}
+
 /// Byte ranges of every (case-sensitive) occurrence of `needle` in `haystack`.
 /// Empty `needle` yields no matches.
 pub fn find_matches(haystack: &str, needle: &str) -> Vec<(usize, usize)> {
@@ -155,6 +241,36 @@ pub fn find_matches(haystack: &str, needle: &str) -> Vec<(usize, usize)> {
     out
 }
 
+/// The smallest single edit turning `old` into `new`: the byte range of `old`
+/// to replace (everything between the common prefix and suffix) and its
+/// replacement. Both ends fall on char boundaries.
+pub fn minimal_edit(old: &str, new: &str) -> (Range<usize>, String) {
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(old.len().min(new.len()), |((i, _), _)| i);
+    let suffix = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+    (prefix..old.len() - suffix, new[prefix..new.len() - suffix].to_string())
+}
+
+/// Rewrite the textarea from `old` to `new` as one undoable edit covering
+/// only the part that changed, so the caret and scroll stay put elsewhere.
+pub fn replace_changed(textarea: &HtmlTextAreaElement, old: &str, new: &str) {
+    if old == new {
+        return;
+    }
+    let (range, text) = minimal_edit(old, new);
+    set_selection(textarea, byte_to_utf16(old, range.start), byte_to_utf16(old, range.end));
+    insert_text(textarea, &text);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,4 +328,56 @@ mod tests {
         assert_eq!(find_matches("abc", "x"), vec![]);
         assert_eq!(find_matches("abc", ""), vec![]);
     }
+
+    #[test]
+    fn enter_continues_items() {
+        let text = "- one\n  + two\n/ Term: text\n9. nine\n";
+        let at = |needle: &str| text.find(needle).unwrap() + needle.len();
+        assert_eq!(list_enter(text, at("one")), Some(ListEnter::Continue("\n- ".into())));
+        assert_eq!(list_enter(text, at("two")), Some(ListEnter::Continue("\n  + ".into())));
+        assert_eq!(list_enter(text, at("text")), Some(ListEnter::Continue("\n/ ".into())));
+        assert_eq!(list_enter(text, at("nine")), Some(ListEnter::Continue("\n10. ".into())));
+        // Splitting an item mid-text continues too; before the marker it does not.
+        assert_eq!(list_enter(text, at("on")), Some(ListEnter::Continue("\n- ".into())));
+        assert_eq!(list_enter(text, at("  ")), None);
+    }
+
+    #[test]
+    fn enter_after_the_largest_number_repeats_it() {
+        let text = format!("{}. last", usize::MAX);
+        let marker = format!("\n{}. ", usize::MAX);
+        assert_eq!(list_enter(&text, text.len()), Some(ListEnter::Continue(marker)));
+    }
+
+    #[test]
+    fn enter_on_empty_item_ends_the_list() {
+        let text = "- one\n- \nafter";
+        assert_eq!(list_enter(text, 8), Some(ListEnter::End(6..8)));
+        assert_eq!(list_enter("- one\n  -", 9), Some(ListEnter::End(6..9)));
+    }
+
+    #[test]
+    fn markers_outside_markup_are_not_items() {
+        assert_eq!(list_enter("#let a = 1\n- x", 10), None);
+        assert_eq!(list_enter("```\n- x\n```", 7), None);
+        assert_eq!(list_enter("#{\n  - x\n}", 9), None);
+        assert_eq!(list_enter("Plain text", 5), None);
+    }
+
+    #[test]
+    fn tab_renests_items() {
+        let text = "- a\n- b\n";
+        assert_eq!(renest_item(text, 7, false), Some((4..7, "  - b".to_string(), 9)));
+        assert_eq!(renest_item("- a\n  - b", 8, true), Some((4..9, "- b".to_string(), 6)));
+        assert_eq!(renest_item("text", 2, false), None);
+    }
+
+    #[test]
+    fn minimal_edit_keeps_common_ends() {
+        assert_eq!(minimal_edit("let a=1", "let a = 1"), (5..6, " = ".to_string()));
+        assert_eq!(minimal_edit("abc", "abc"), (3..3, String::new()));
+        assert_eq!(minimal_edit("aa", "aaa"), (2..2, "a".to_string()));
+        assert_eq!(minimal_edit("é", "è"), (0..2, "è".to_string()));
+        assert_eq!(minimal_edit("xé", "x"), (1..3, String::new()));
+    }
 }
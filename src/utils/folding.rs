@@ -0,0 +1,318 @@
+//! Code folding for the editor, from the `typst_syntax` tree.
+//!
+//! A textarea cannot hide text, so folding works on a projection: the
+//! textarea shows the source with folded lines cut out ([`Folded`]), and
+//! every edit made there is mapped back onto the full source with
+//! [`Folded::apply`]. Folds are remembered by the line they start on.
+
+use super::editing::minimal_edit;
+use std::ops::Range;
+use typst_syntax::{ast, is_newline, LinkedNode, Source, SyntaxKind};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoldKind {
+    /// A heading and everything up to the next heading of the same or a
+    /// higher level.
+    Heading,
+    CodeBlock,
+    ContentBlock,
+    /// The parenthesized arguments of a function call.
+    Args,
+    Math,
+}
+
+/// A foldable region: folding it hides lines `line + 1 ..= end` (0-based).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FoldRange {
+    pub line: usize,
+    pub end: usize,
+    pub kind: FoldKind,
+}
+
+/// Every foldable region, by start line; where several start on one line,
+/// only the outermost is kept.
+pub fn fold_ranges(source: &Source) -> Vec<FoldRange> {
+    let mut ranges = Vec::new();
+    collect(source, &LinkedNode::new(source.root()), &mut ranges);
+    ranges.sort_by_key(|r| (r.line, std::cmp::Reverse(r.end)));
+    ranges.dedup_by_key(|r| r.line);
+    ranges
+}
+
+fn line(source: &Source, byte: usize) -> usize {
+    source.byte_to_line(byte).unwrap_or(0)
+}
+
+fn collect(source: &Source, node: &LinkedNode, out: &mut Vec<FoldRange>) {
+    let range = node.range();
+    // A single-line node holds nothing that spans lines.
+    if line(source, range.start) == line(source, range.end) {
+        return;
+    }
+    let delimited = match node.kind() {
+        SyntaxKind::CodeBlock => Some((FoldKind::CodeBlock, range.clone())),
+        SyntaxKind::ContentBlock => Some((FoldKind::ContentBlock, range.clone())),
+        SyntaxKind::Equation => Some((FoldKind::Math, range.clone())),
+        SyntaxKind::Args => {
+            let open = node.children().find(|c| c.kind() == SyntaxKind::LeftParen);
+            let close = node.children().find(|c| c.kind() == SyntaxKind::RightParen);
+            open.zip(close).map(|(open, close)| (FoldKind::Args, open.offset()..close.range().end))
+        }
+        SyntaxKind::Markup => {
+            headings(source, node, out);
+            None
+        }
+        _ => None,
+    };
+    if let Some((kind, range)) = delimited {
+        let start = line(source, range.start);
+        let close = range.end.saturating_sub(1);
+        let close_line = line(source, close);
+        // Keep the closing delimiter visible when it opens its line.
+        let head = &source.text()[source.line_to_byte(close_line).unwrap_or(0)..close];
+        let end = if head.trim().is_empty() { close_line - 1 } else { close_line };
+        if end > start {
+            out.push(FoldRange { line: start, end, kind });
+        }
+    }
+    for child in node.children() {
+        collect(source, &child, out);
+    }
+}
+
+/// Heading regions among the children of a markup node.
+fn headings(source: &Source, markup: &LinkedNode, out: &mut Vec<FoldRange>) {
+    let headings: Vec<(usize, usize)> = markup
+        .children()
+        .filter_map(|child| {
+            let heading = child.cast::<ast::Heading>()?;
+            Some((line(source, child.offset()), heading.depth().get()))
+        })
+        .collect();
+    let Some(last) = markup.children().rev().find(|c| !matches!(c.kind(), SyntaxKind::Space | SyntaxKind::Parbreak))
+    else {
+        return;
+    };
+    let last_line = line(source, last.range().end.saturating_sub(1));
+    for (i, &(start, depth)) in headings.iter().enumerate() {
+        let mut end = headings[i + 1..]
+            .iter()
+            .find(|&&(_, d)| d <= depth)
+            .map_or(last_line, |&(next, _)| next.saturating_sub(1));
+        // Leave the blank lines before the next section visible.
+        while end > start && line_is_blank(source, end) {
+            end -= 1;
+        }
+        if end > start {
+            out.push(FoldRange { line: start, end, kind: FoldKind::Heading });
+        }
+    }
+}
+
+fn line_is_blank(source: &Source, line: usize) -> bool {
+    let start = source.line_to_byte(line).unwrap_or(0);
+    let end = source.line_to_byte(line + 1).unwrap_or(source.len_bytes());
+    source.text()[start..end].trim().is_empty()
+}
+
+/// The editor text with folded regions cut out, and where they went.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Folded {
+    /// What the textarea shows.
+    pub visible: String,
+    /// `(offset in visible, hidden source range)` of each cut, in order.
+    cuts: Vec<(usize, Range<usize>)>,
+    /// The hidden source lines of each applied fold, `line + 1 .. end + 1`.
+    pub hidden_lines: Vec<Range<usize>>,
+}
+
+/// Project `source` with the folds starting on the `folded` lines applied.
+/// Lines that start no fold, or sit inside another applied fold, are
+/// ignored.
+pub fn fold(source: &Source, ranges: &[FoldRange], folded: &[usize]) -> Folded {
+    let text = source.text();
+    let mut out = Folded::default();
+    let mut last = 0;
+    for range in ranges.iter().filter(|r| folded.contains(&r.line)) {
+        if out.hidden_lines.last().is_some_and(|h| h.end > range.line) {
+            continue;
+        }
+        let Some(start) = source.line_to_byte(range.line + 1) else {
+            continue;
+        };
+        let end = source.line_to_byte(range.end + 1).unwrap_or(text.len());
+        out.visible.push_str(&text[last..start]);
+        out.cuts.push((out.visible.len(), start..end));
+        out.hidden_lines.push(range.line + 1..range.end + 1);
+        last = end;
+    }
+    out.visible.push_str(&text[last..]);
+    out
+}
+
+impl Folded {
+    /// The full text when nothing is folded.
+    pub fn unfolded(text: &str) -> Self {
+        Self {
+            visible: text.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_folded(&self) -> bool {
+        !self.cuts.is_empty()
+    }
+
+    /// The source offset of a visible offset. A caret at a cut is at the
+    /// start of the line after the hidden text, so it maps past it.
+    pub fn to_source(&self, visible: usize) -> usize {
+        self.map(visible, true)
+    }
+
+    fn map(&self, visible: usize, past_cut: bool) -> usize {
+        self.cuts
+            .iter()
+            .take_while(|(at, _)| *at < visible || (past_cut && *at == visible))
+            .fold(visible, |offset, (_, hidden)| offset + hidden.len())
+    }
+
+    /// The visible offset of a source offset, `None` if it is hidden.
+    pub fn to_visible(&self, source: usize) -> Option<usize> {
+        let mut shift = 0;
+        for (_, hidden) in &self.cuts {
+            if source < hidden.start {
+                break;
+            }
+            if source < hidden.end {
+                return None;
+            }
+            shift += hidden.len();
+        }
+        Some(source - shift)
+    }
+
+    /// Like [`Folded::to_visible`], but a hidden offset maps to the end of
+    /// the line its fold starts on.
+    pub fn nearest_visible(&self, source: usize) -> usize {
+        self.to_visible(source).unwrap_or_else(|| {
+            self.cuts
+                .iter()
+                .find(|(_, hidden)| hidden.contains(&source))
+                .map_or(self.visible.len(), |(at, _)| at.saturating_sub(1))
+        })
+    }
+
+    /// Map the textarea's new content back onto `source`, returning the new
+    /// source and the edit that was made to it (`(replaced range, inserted
+    /// text)`). Hidden text is only removed if a deletion spans the cut.
+    pub fn apply(&self, source: &str, visible: &str) -> (String, (Range<usize>, String)) {
+        let (range, text) = minimal_edit(&self.visible, visible);
+        let start = self.map(range.start, true);
+        let end = self.map(range.end, false).max(start);
+        let mut new = String::with_capacity(source.len() + text.len());
+        new.push_str(&source[..start]);
+        new.push_str(&text);
+        new.push_str(&source[end..]);
+        (new, (start..end, text))
+    }
+}
+
+/// Move folded line numbers past an edit of `old` (`range` replaced by
+/// `text`). Folds on lines the edit rewrote are dropped, except the line it
+/// starts on.
+pub fn shift_folds(folded: &[usize], old: &str, range: Range<usize>, text: &str) -> Vec<usize> {
+    let first = line_breaks(&old[..range.start]);
+    let last = first + line_breaks(&old[range.clone()]);
+    let added = line_breaks(text);
+    folded
+        .iter()
+        .filter_map(|&line| match line {
+            l if l <= first => Some(l),
+            l if l > last => Some(l + added - (last - first)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Line breaks in `text`, counted as `Source` does (`\r\n` is one).
+fn line_breaks(text: &str) -> usize {
+    let mut count = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_newline(c) {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "= Intro\nText.\n\n== Detail\n#let f(x) = {\n  x\n}\n\n= Next\n#figure(\n  [a],\n  caption: [\n    b\n  ],\n)\n$ a\n  = b $\n";
+
+    fn ranges() -> Vec<(usize, usize, FoldKind)> {
+        fold_ranges(&Source::detached(DOC)).into_iter().map(|r| (r.line, r.end, r.kind)).collect()
+    }
+
+    #[test]
+    fn regions_come_from_the_tree() {
+        assert_eq!(
+            ranges(),
+            vec![
+                (0, 6, FoldKind::Heading),
+                (3, 6, FoldKind::Heading),
+                (4, 5, FoldKind::CodeBlock),
+                (8, 16, FoldKind::Heading),
+                (9, 13, FoldKind::Args),
+                (11, 12, FoldKind::ContentBlock),
+                (15, 16, FoldKind::Math),
+            ]
+        );
+    }
+
+    #[test]
+    fn edits_in_a_folded_view_map_back() {
+        let source = Source::detached(DOC);
+        let ranges = fold_ranges(&source);
+        let folded = fold(&source, &ranges, &[4, 5, 9, 11]);
+        assert_eq!(folded.hidden_lines, vec![5..6, 10..14]);
+        assert_eq!(
+            folded.visible,
+            "= Intro\nText.\n\n== Detail\n#let f(x) = {\n}\n\n= Next\n#figure(\n)\n$ a\n  = b $\n"
+        );
+        let code = DOC.find("  x").unwrap();
+        assert_eq!(folded.to_visible(code), None);
+        assert_eq!(folded.nearest_visible(code), folded.visible.find("{\n").unwrap() + 1);
+        let close = folded.visible.find("}\n").unwrap();
+        assert_eq!(folded.to_source(close), DOC.find("}\n").unwrap());
+
+        // Typing at the end of the fold line stays before the hidden text;
+        // at the start of the line after the fold, it lands after it.
+        let visible = folded.visible.replacen("{\n}", "{ // f\n}", 1);
+        let (new, (range, text)) = folded.apply(DOC, &visible);
+        assert!(new.contains("#let f(x) = { // f\n  x\n}\n"));
+        assert_eq!((range.start, text.as_str()), (DOC.find("{\n").unwrap() + 1, " // f"));
+        let visible = folded.visible.replacen("{\n}", "{\n!}", 1);
+        let (new, _) = folded.apply(DOC, &visible);
+        assert!(new.contains("#let f(x) = {\n  x\n!}\n"));
+
+        // Deleting across a cut removes what it hid.
+        let visible = folded.visible.replacen("#figure(\n)\n", "", 1);
+        let (new, _) = folded.apply(DOC, &visible);
+        assert_eq!(&new[new.find("= Next").unwrap()..], "= Next\n$ a\n  = b $\n");
+    }
+
+    #[test]
+    fn folds_follow_line_edits() {
+        let at = DOC.find("== Detail").unwrap();
+        assert_eq!(shift_folds(&[0, 4, 8], DOC, at..at, "New\n\n"), vec![0, 6, 10]);
+        let text = DOC.find("Text.").unwrap();
+        assert_eq!(shift_folds(&[0, 1, 2, 4], DOC, text..at, ""), vec![0, 1, 2]);
+        assert_eq!(shift_folds(&[3, 4], DOC, at..at + 2, "="), vec![3, 4]);
+    }
+}
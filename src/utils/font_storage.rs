@@ -0,0 +1,35 @@
+//! IndexedDB store for user font files (TTF/OTF/TTC) imported with a project.
+//!
+//! Same key → raw-bytes layout as [`crate::utils::package_storage`], in its own
+//! database and keyed by the font's project path. Fonts are re-installed into
+//! the compiler on startup.
+
+use super::package_storage::PackageStorage;
+
+pub struct FontStorage {
+    inner: PackageStorage,
+}
+
+impl FontStorage {
+    pub fn new() -> Self {
+        Self {
+            inner: PackageStorage::with_database("typst_studio_fonts", "fonts"),
+        }
+    }
+
+    /// Store a font file under its path.
+    pub async fn store(&self, path: &str, bytes: &[u8]) -> Result<(), String> {
+        self.inner.store(path, bytes).await
+    }
+
+    /// List every stored font as `(path, bytes)`.
+    pub async fn list_all(&self) -> Result<Vec<(String, Vec<u8>)>, String> {
+        self.inner.list_all().await
+    }
+}
+
+impl Default for FontStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,455 @@
+//! "Format document" / "Format selection": reprints Typst source from its
+//! syntax tree. Only whitespace in code changes — indentation of blocks and
+//! bracketed lists, one space around binary operators, `=`, `=>` and after
+//! commas, colons and keywords, and argument lists wrapped one per line when
+//! they run past [`MAX_WIDTH`]. Markup is copied as written, except for the
+//! space after heading and list markers and enumerations numbered `1.`, `2.`,
+//! … which become `+`; lines of markup inside a re-indented code block move
+//! with it. Line breaks in code are never removed.
+//!
+//! The result is checked against the input: if anything other than
+//! whitespace, commas and enum markers differs, the source is left alone.
+
+use super::editing::INDENT;
+use std::collections::HashSet;
+use std::ops::Range;
+use typst_syntax::{parse, LinkedNode, SyntaxKind, SyntaxNode};
+
+/// Lines longer than this get their argument lists wrapped.
+pub const MAX_WIDTH: usize = 100;
+
+/// Format the whole of `text`.
+pub fn format_source(text: &str) -> Result<String, String> {
+    format_range(text, 0..text.len())
+}
+
+/// Format the top-level elements of `text` that `range` touches; the rest is
+/// kept as is. Returns the whole new text.
+pub fn format_range(text: &str, range: Range<usize>) -> Result<String, String> {
+    let root = parse(text);
+    if root.erroneous() {
+        return Err("Fix the syntax errors first — only valid source is formatted.".to_string());
+    }
+    let mut printer = Printer {
+        src: text,
+        out: String::with_capacity(text.len()),
+    };
+    printer.markup(&LinkedNode::new(&root), 0, Some(&range));
+    let out = printer.out;
+    let formatted = parse(&out);
+    if formatted.erroneous() || tokens(&root) != tokens(&formatted) {
+        return Err("The formatter couldn't keep this source intact, so it was left unchanged.".to_string());
+    }
+    Ok(out)
+}
+
+/// The non-whitespace tokens of a tree, for the integrity check.
+fn tokens(root: &SyntaxNode) -> Vec<String> {
+    fn walk(node: &SyntaxNode, out: &mut Vec<String>) {
+        match node.kind() {
+            SyntaxKind::Space | SyntaxKind::Parbreak | SyntaxKind::Comma => {}
+            SyntaxKind::EnumMarker => out.push("+".to_string()),
+            _ if node.children().len() == 0 => out.push(node.text().to_string()),
+            _ => node.children().for_each(|child| walk(child, out)),
+        }
+    }
+    let mut out = Vec::new();
+    walk(root, &mut out);
+    out
+}
+
+struct Printer<'a> {
+    src: &'a str,
+    out: String,
+}
+
+impl Printer<'_> {
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    /// Leading whitespace of the line being written.
+    fn line_indent(&self) -> String {
+        let line = &self.out[self.out.rfind('\n').map_or(0, |i| i + 1)..];
+        line.chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+    }
+
+    /// How far the line being written moved relative to the source line
+    /// holding `offset`, in spaces; 0 when tabs are involved.
+    fn shift_at(&self, offset: usize) -> isize {
+        let start = self.src[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let old: String = self.src[start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+        let new = self.line_indent();
+        if old.contains('\t') || new.contains('\t') {
+            0
+        } else {
+            new.len() as isize - old.len() as isize
+        }
+    }
+
+    /// End the line (dropping trailing spaces) and add up to one blank line,
+    /// as many as `whitespace` had.
+    fn newlines(&mut self, whitespace: &str) {
+        let trimmed = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed);
+        let count = whitespace.matches('\n').count().min(2);
+        self.push(&"\n".repeat(count));
+    }
+
+    /// Markup whitespace, with the indentation after its last line break
+    /// moved by `shift`.
+    fn markup_space(&mut self, text: &str, shift: isize) {
+        match text.rfind('\n') {
+            Some(i) if shift != 0 && text[i + 1..].chars().all(|c| c == ' ') => {
+                let width = (text.len() - i - 1).saturating_add_signed(shift);
+                self.push(&text[..=i]);
+                self.push(&" ".repeat(width));
+            }
+            _ => self.push(text),
+        }
+    }
+
+    /// Markup or math: copied, except for embedded code, item markers and
+    /// the indentation `shift`. At the root, `only` limits formatting to
+    /// the elements touching that range.
+    fn markup(&mut self, node: &LinkedNode, shift: isize, only: Option<&Range<usize>>) {
+        let children: Vec<LinkedNode> = node.children().collect();
+        let plus = if node.kind() == SyntaxKind::Markup {
+            numbered_runs(&children)
+        } else {
+            HashSet::new()
+        };
+        let mut embedded = false;
+        for (i, child) in children.iter().enumerate() {
+            let selected = only.is_none_or(|range| {
+                let own = child.range();
+                own.start < range.end && range.start < own.end || range.is_empty() && own.contains(&range.start)
+            });
+            if !selected {
+                self.push(&child.get().clone().into_text());
+                embedded = child.kind() == SyntaxKind::Hash;
+                continue;
+            }
+            if std::mem::take(&mut embedded) {
+                self.code(child);
+                continue;
+            }
+            match child.kind() {
+                SyntaxKind::Hash => {
+                    self.push("#");
+                    embedded = true;
+                }
+                SyntaxKind::Raw => self.push(&child.get().clone().into_text()),
+                SyntaxKind::Space | SyntaxKind::Parbreak => self.markup_space(child.text(), shift),
+                SyntaxKind::ListItem | SyntaxKind::EnumItem | SyntaxKind::TermItem | SyntaxKind::Heading => {
+                    self.item(child, shift, plus.contains(&i))
+                }
+                _ if child.get().children().len() == 0 => self.push(child.text()),
+                _ => self.markup(child, shift, None),
+            }
+        }
+    }
+
+    /// A heading or list item: one space after the marker, `+` for a
+    /// renumbered enum item.
+    fn item(&mut self, node: &LinkedNode, shift: isize, plus: bool) {
+        let mut after_marker = false;
+        for child in node.children() {
+            match child.kind() {
+                SyntaxKind::HeadingMarker | SyntaxKind::ListMarker | SyntaxKind::TermMarker => {
+                    self.push(child.text());
+                    after_marker = true;
+                    continue;
+                }
+                SyntaxKind::EnumMarker => {
+                    self.push(if plus { "+" } else { child.text() });
+                    after_marker = true;
+                    continue;
+                }
+                SyntaxKind::Space if after_marker && !child.text().contains('\n') => self.push(" "),
+                SyntaxKind::Space => self.markup_space(child.text(), shift),
+                _ if child.get().children().len() == 0 => self.push(child.text()),
+                _ => self.markup(&child, shift, None),
+            }
+            after_marker = false;
+        }
+    }
+
+    fn code(&mut self, node: &LinkedNode) {
+        match node.kind() {
+            SyntaxKind::Raw | SyntaxKind::Str => self.push(&node.get().clone().into_text()),
+            _ if node.get().children().len() == 0 => self.push(node.text()),
+            SyntaxKind::ContentBlock | SyntaxKind::Equation => {
+                let shift = self.shift_at(node.offset());
+                self.markup(node, shift, None);
+            }
+            SyntaxKind::CodeBlock => self.block(node),
+            SyntaxKind::Args | SyntaxKind::Array | SyntaxKind::Dict | SyntaxKind::Params => self.list(node),
+            _ => {
+                let children: Vec<LinkedNode> = node.children().collect();
+                let here = self.line_indent();
+                let continued = format!("{here}{INDENT}");
+                self.sequence(node.kind(), &children, &|next| {
+                    if next.kind() == SyntaxKind::Else {
+                        here.clone()
+                    } else {
+                        continued.clone()
+                    }
+                });
+            }
+        }
+    }
+
+    /// `{ … }`: statements one level in, the closing brace level with the
+    /// line that opened it.
+    fn block(&mut self, node: &LinkedNode) {
+        let base = self.line_indent();
+        let inner = format!("{base}{INDENT}");
+        let children: Vec<LinkedNode> = node
+            .children()
+            .flat_map(|child| match child.kind() {
+                SyntaxKind::Code => child.children().collect(),
+                _ => vec![child],
+            })
+            .collect();
+        self.sequence(SyntaxKind::Code, &children, &|next| {
+            if next.kind() == SyntaxKind::RightBrace {
+                base.clone()
+            } else {
+                inner.clone()
+            }
+        });
+    }
+
+    /// A parenthesized list of arguments, items or parameters. Kept on one
+    /// line if it fits; otherwise (or if already broken over lines) items go
+    /// one level in and the closing paren back level with the opening line.
+    fn list(&mut self, node: &LinkedNode) {
+        let children: Vec<LinkedNode> = node.children().collect();
+        let open = children.iter().position(|c| c.kind() == SyntaxKind::LeftParen);
+        let close = children.iter().rposition(|c| c.kind() == SyntaxKind::RightParen);
+        let (Some(open), Some(close)) = (open, close) else {
+            // `x => …` parameters, `f[…]` arguments.
+            let here = self.line_indent();
+            self.sequence(node.kind(), &children, &|_| here.clone());
+            return;
+        };
+        let base = self.line_indent();
+        let inner = format!("{base}{INDENT}");
+        let between = &children[open + 1..close];
+        let broken = between.iter().any(|c| c.kind() == SyntaxKind::Space && c.text().contains('\n'));
+        let commented = between
+            .iter()
+            .any(|c| matches!(c.kind(), SyntaxKind::LineComment | SyntaxKind::BlockComment));
+        let items: Vec<&LinkedNode> = between
+            .iter()
+            .filter(|c| !matches!(c.kind(), SyntaxKind::Space | SyntaxKind::Comma))
+            .collect();
+
+        let nl = |next: &LinkedNode| {
+            if next.kind() == SyntaxKind::RightParen {
+                base.clone()
+            } else {
+                inner.clone()
+            }
+        };
+        let saved = self.out.len();
+        // A list broken over lines ends with a comma when its closing paren
+        // has a line of its own.
+        let last = between.iter().rev().find(|c| c.kind() != SyntaxKind::Space);
+        match children.get(close - 1) {
+            Some(space)
+                if broken
+                    && !commented
+                    && space.kind() == SyntaxKind::Space
+                    && space.text().contains('\n')
+                    && last.is_some_and(|c| c.kind() != SyntaxKind::Comma) =>
+            {
+                self.sequence(node.kind(), &children[..close - 1], &nl);
+                self.push(",");
+                self.newlines(space.text());
+                self.push(&base);
+                self.push(")");
+            }
+            _ => self.sequence(node.kind(), &children[..=close], &nl),
+        }
+        let wrap = !broken && !commented && items.len() >= 2 && !items.iter().any(|c| c.kind() == SyntaxKind::Colon);
+        if wrap && self.width_from(saved) > MAX_WIDTH {
+            self.out.truncate(saved);
+            self.push("(");
+            for item in items {
+                self.push("\n");
+                self.push(&inner);
+                self.code(item);
+                self.push(",");
+            }
+            self.push("\n");
+            self.push(&base);
+            self.push(")");
+        }
+        // Trailing content blocks of a call.
+        for child in &children[close + 1..] {
+            self.code(child);
+        }
+    }
+
+    /// Width of the first output line touched since byte `from`.
+    fn width_from(&self, from: usize) -> usize {
+        let start = self.out[..from].rfind('\n').map_or(0, |i| i + 1);
+        self.out[start..].split('\n').next().unwrap_or_default().chars().count()
+    }
+
+    /// Print sibling nodes, replacing the whitespace between them: line
+    /// breaks are kept (the next line indented by `indent`), other gaps
+    /// follow [`gap`].
+    fn sequence(&mut self, parent: SyntaxKind, children: &[LinkedNode], indent: &dyn Fn(&LinkedNode) -> String) {
+        let mut prev: Option<SyntaxKind> = None;
+        let mut space: Option<String> = None;
+        for child in children {
+            if child.kind() == SyntaxKind::Space {
+                space = Some(child.text().to_string());
+                continue;
+            }
+            match space.take() {
+                Some(space) if space.contains('\n') => {
+                    self.newlines(&space);
+                    self.push(&indent(child));
+                }
+                space => {
+                    if let Some(prev) = prev {
+                        self.push(gap(parent, prev, child.kind(), space.is_some()));
+                    }
+                }
+            }
+            self.code(child);
+            prev = Some(child.kind());
+        }
+        if let Some(space) = space.filter(|s| s.contains('\n')) {
+            self.newlines(&space);
+        }
+    }
+}
+
+/// The space between two tokens on one line of code.
+fn gap(parent: SyntaxKind, prev: SyntaxKind, next: SyntaxKind, had_space: bool) -> &'static str {
+    use SyntaxKind::*;
+    if matches!(next, Comma | Semicolon | RightParen | Colon | Dot | Dots) || matches!(prev, LeftParen | Dot | Dots) {
+        return "";
+    }
+    if parent == Unary && matches!(prev, Minus | Plus) {
+        return "";
+    }
+    if matches!(prev, Comma | Colon | Eq | Arrow) || matches!(next, Eq | Arrow) || parent == Binary {
+        return " ";
+    }
+    // `{ x }` on one line; `{}` when empty.
+    if parent == Code && (prev == LeftBrace) != (next == RightBrace) {
+        return " ";
+    }
+    if prev.is_keyword() || next.is_keyword() || had_space {
+        " "
+    } else {
+        ""
+    }
+}
+
+/// Indices of enum items numbered `1.`, `2.`, … in order, which can be
+/// written as `+`. Items separated only by whitespace form one list.
+fn numbered_runs(children: &[LinkedNode]) -> HashSet<usize> {
+    let mut out = HashSet::new();
+    let mut run: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut flush = |run: &mut Vec<(usize, Option<usize>)>| {
+        if run.iter().enumerate().all(|(n, (_, number))| *number == Some(n + 1)) {
+            out.extend(run.iter().map(|(i, _)| *i));
+        }
+        run.clear();
+    };
+    for (i, child) in children.iter().enumerate() {
+        match child.kind() {
+            SyntaxKind::EnumItem => {
+                let number = child
+                    .children()
+                    .find(|c| c.kind() == SyntaxKind::EnumMarker)
+                    .and_then(|marker| marker.text().strip_suffix('.')?.parse().ok());
+                run.push((i, number));
+            }
+            SyntaxKind::Space | SyntaxKind::Parbreak => {}
+            _ => flush(&mut run),
+        }
+    }
+    flush(&mut run);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_code_and_keeps_markup() {
+        let source = "\
+=   Intro  text
+1.  one
+2. two
+   + nested
+#let f(x,y)={
+let z=x+y*2   // sum
+    if z>1 [
+        Big *text*   here
+    ] else { none }
+}
+#let xs = (1,2 , 3)
+#set text(font: \"Libertinus Serif\", size: 11pt, fill: rgb(\"#333333\"), lang: \"en\", region: \"gb\", hyphenate: false)
+3. kept 3.
+Some   markup  stays.   #f(1,2)\n";
+        let expected = "\
+= Intro  text
++ one
++ two
+   + nested
+#let f(x, y) = {
+  let z = x + y * 2 // sum
+  if z > 1 [
+      Big *text*   here
+  ] else { none }
+}
+#let xs = (1, 2, 3)
+#set text(
+  font: \"Libertinus Serif\",
+  size: 11pt,
+  fill: rgb(\"#333333\"),
+  lang: \"en\",
+  region: \"gb\",
+  hyphenate: false,
+)
+3. kept 3.
+Some   markup  stays.   #f(1, 2)\n";
+        assert_eq!(format_source(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let samples = [
+            include_str!("../../templates/blank.typ"),
+            include_str!("../../templates/article.typ"),
+            include_str!("../../templates/ieee.typ"),
+            include_str!("../../examples/example.typ"),
+            "#let f(a, b: 1, ..rest) = {\n  for (k, v) in rest.named() {\n    (k: v)\n  }\n}\n#show heading: it => block(it.body)\n",
+            "#show heading.where(level:1): it=>{\nset text(size:14pt , weight:\"bold\")\n}\n\
+             #let conf(title:none,doc)={\n    set page(paper:\"a4\",margin:(x:2cm,y:2.5cm),header: context {if title!=none [#title]})\n\
+             \x20   align(center)[\n        #text(17pt,title)\n    ]\n  doc\n}\n#figure(\n  table(columns:3,[a],[b]),\n    caption:[A table]\n)\n",
+        ];
+        for sample in samples {
+            let once = format_source(sample).unwrap();
+            assert_eq!(format_source(&once).unwrap(), once);
+        }
+    }
+
+    #[test]
+    fn selection_and_errors() {
+        let source = "#let a=1\n\n#let b=2\n";
+        let at_b = source.find("b=").unwrap();
+        assert_eq!(format_range(source, at_b..at_b).unwrap(), "#let a=1\n\n#let b = 2\n");
+        assert!(format_source("#let a = (1, 2\n").is_err());
+    }
+}
@@ -1,87 +1,463 @@
-use typst_syntax::{parse, SyntaxKind, SyntaxNode};
+//! Editor syntax highlighting, driven by `typst_syntax::highlight`: every
+//! node that the Typst highlighter tags becomes a `<span class="typ-…">`, so
+//! the overlay colours exactly what the Typst tooling does (set/show targets
+//! as functions, `#` interpolations, escapes, links, math operators, …). The
+//! colours per tag live in `tailwind.css`, one palette per theme.
+//!
+//! Code in a raw block with a language tag (```` ```rust ````) is coloured
+//! by syntect with the syntaxes Typst renders raw blocks with, mapped onto
+//! the same `typ-*` classes.
+//!
+//! Output is one HTML string per source line (spans that cross a line break
+//! are closed and reopened), which lets [`LineHighlighter`] keep the overlay
+//! up to date by re-rendering only the lines an edit reparsed.
 
-/// Convert Typst code into HTML with syntax highlighting.
-pub fn highlight_typst(source: &str) -> String {
-    let root = parse(source);
-    let mut html = String::new();
+use std::ops::Range;
+use std::sync::LazyLock;
+use syntect::parsing::{ParseState, Scope, ScopeStack};
+use typst::text::RAW_SYNTAXES;
+use typst_syntax::{highlight, is_newline, LinkedNode, Source, SyntaxKind, Tag};
 
-    html.push_str("<pre class=\"typst-highlighted\"><code>");
-    highlight_node(&root, &mut html);
-    html.push_str("</code></pre>");
+/// Lines re-rendered around a reparsed region, for tags that depend on a
+/// neighbouring token (an identifier before `(`, after `#` or a show colon).
+const CONTEXT: usize = 1;
 
-    html
+/// Highlighted HTML per source line, kept in step with the editor text by
+/// incremental reparsing: an edit re-renders only the lines it touches.
+pub struct LineHighlighter {
+    source: Source,
+    lines: Vec<String>,
 }
 
-/// Recursively highlight a node and its children.
-fn highlight_node(node: &SyntaxNode, html: &mut String) {
-    for child in node.children() {
-        let kind = child.kind();
-        let text = child.text();
-
-        // Pick the CSS class based on the token kind
-        let class = match kind {
-            // Comments
-            SyntaxKind::LineComment | SyntaxKind::BlockComment => "comment",
+/// Replace `removed` lines starting at line `start` with `inserted`.
+#[derive(Debug, PartialEq)]
+pub struct LinePatch {
+    pub start: usize,
+    pub removed: usize,
+    pub inserted: Vec<String>,
+}
 
-            // Keywords and markup
-            SyntaxKind::Hash => "keyword",
-            SyntaxKind::Star | SyntaxKind::Underscore => "markup",
+impl LineHighlighter {
+    pub fn new(text: &str) -> Self {
+        let source = Source::detached(text);
+        let lines = render_lines(&source, 0..source.len_lines());
+        Self { source, lines }
+    }
 
-            // Strings
-            SyntaxKind::Str => "string",
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
 
-            // Numbers
-            SyntaxKind::Int | SyntaxKind::Float => "number",
+    /// The parsed text, for other passes over the same tree.
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
 
-            // Heading
-            SyntaxKind::Heading => "heading",
+    /// Move to `text`, returning the lines that changed (`None` if the text
+    /// is the same).
+    pub fn update(&mut self, text: &str) -> Option<LinePatch> {
+        if self.source.text() == text {
+            return None;
+        }
+        let old_len = self.source.len_lines();
+        let reparsed = self.source.replace(text);
+        let new_len = self.source.len_lines();
+        let line = |byte| self.source.byte_to_line(byte).unwrap_or(new_len - 1);
+        let start = line(reparsed.start).saturating_sub(CONTEXT);
+        let end = (line(reparsed.end) + 1 + CONTEXT).min(new_len);
+        // Everything from line `end` on is untouched, only shifted.
+        let removed = old_len + end - new_len - start;
+        let inserted = render_lines(&self.source, start..end);
+        self.lines.splice(start..start + removed, inserted.iter().cloned());
+        Some(LinePatch { start, removed, inserted })
+    }
+}
 
-            // Code block
-            SyntaxKind::Raw => "code",
+/// The HTML of `lines` (line indices) of `source`.
+fn render_lines(source: &Source, lines: Range<usize>) -> Vec<String> {
+    let from = source.line_to_byte(lines.start).unwrap_or(source.len_bytes());
+    let to = source.line_to_byte(lines.end).unwrap_or(source.len_bytes());
+    let mut writer = LineWriter {
+        window: from..to,
+        ..LineWriter::default()
+    };
+    writer.node(&LinkedNode::new(source.root()));
+    if lines.end == source.len_lines() {
+        writer.lines.push(writer.line);
+    }
+    writer.lines
+}
 
-            // Math
-            SyntaxKind::Math | SyntaxKind::MathIdent | SyntaxKind::Equation => "math",
+/// TextMate scope prefixes and the class they get in embedded code; the
+/// first prefix of the innermost matching scope wins, so specific scopes
+/// come before their parents.
+static SCOPE_CLASSES: LazyLock<Vec<(Scope, &'static str)>> = LazyLock::new(|| {
+    [
+        ("comment", "typ-comment"),
+        ("constant.character.escape", "typ-escape"),
+        ("constant.numeric", "typ-num"),
+        ("constant.language", "typ-key"),
+        ("string", "typ-str"),
+        ("keyword.operator", "typ-op"),
+        ("keyword", "typ-key"),
+        ("storage", "typ-key"),
+        ("entity.name.function", "typ-func"),
+        ("entity.name.macro", "typ-func"),
+        ("support.macro", "typ-func"),
+        ("support.function", "typ-func"),
+        ("variable.function", "typ-func"),
+        ("entity.name.type", "typ-type"),
+        ("entity.name.class", "typ-type"),
+        ("support.type", "typ-type"),
+        ("support.class", "typ-type"),
+        ("entity.name.tag", "typ-key"),
+        ("entity.other.attribute-name", "typ-pol"),
+        ("markup.heading", "typ-heading"),
+        ("markup.bold", "typ-strong"),
+        ("markup.italic", "typ-emph"),
+        ("markup.underline.link", "typ-link"),
+        ("markup.raw", "typ-raw"),
+        ("punctuation.definition.string", "typ-str"),
+        ("punctuation.definition.comment", "typ-comment"),
+        ("punctuation", "typ-punct"),
+        ("invalid", "typ-error"),
+    ]
+    .into_iter()
+    .filter_map(|(prefix, class)| Some((Scope::new(prefix).ok()?, class)))
+    .collect()
+});
 
-            // Functions and identifiers
-            SyntaxKind::FuncCall | SyntaxKind::Ident => "function",
+/// The class of the innermost scope on `stack` that has one.
+fn scope_class(stack: &ScopeStack) -> Option<&'static str> {
+    stack.as_slice().iter().rev().find_map(|&scope| {
+        SCOPE_CLASSES
+            .iter()
+            .find(|(prefix, _)| prefix.is_prefix_of(scope))
+            .map(|&(_, class)| class)
+    })
+}
 
-            // Labels and references
-            SyntaxKind::Label | SyntaxKind::Ref => "label",
+/// Coloured spans `(source byte range, class)` of the code in a raw block,
+/// or `None` without a language tag Typst knows.
+fn embedded_tokens(raw: &LinkedNode) -> Option<Vec<(Range<usize>, &'static str)>> {
+    let lang = raw.children().find(|c| c.kind() == SyntaxKind::RawLang)?;
+    let close = raw.children().rev().find(|c| c.kind() == SyntaxKind::RawDelim)?;
+    let syntax = RAW_SYNTAXES.find_syntax_by_token(lang.text())?;
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut tokens = Vec::new();
+    let body = lang.range().end..close.offset();
+    let text = raw.get().clone().into_text();
+    let mut line_start = body.start;
+    for line in text[body.start - raw.offset()..body.end - raw.offset()].split_inclusive('\n') {
+        let code = line.trim_end_matches(['\n', '\r']);
+        let mut at = 0;
+        for (pos, op) in state.parse_line(code, &RAW_SYNTAXES).ok()? {
+            if pos > at {
+                if let Some(class) = scope_class(&stack) {
+                    push_token(&mut tokens, line_start + at..line_start + pos, class);
+                }
+                at = pos;
+            }
+            stack.apply(&op).ok()?;
+        }
+        if code.len() > at {
+            if let Some(class) = scope_class(&stack) {
+                push_token(&mut tokens, line_start + at..line_start + code.len(), class);
+            }
+        }
+        line_start += line.len();
+    }
+    Some(tokens)
+}
 
-            // Operators (Star is already covered by markup)
-            SyntaxKind::Plus | SyntaxKind::Minus | SyntaxKind::Slash |
-            SyntaxKind::Eq | SyntaxKind::EqEq => "operator",
+/// Append a token, merging it into the previous one if they touch and share
+/// a class (a string's quotes and contents become one span).
+fn push_token(tokens: &mut Vec<(Range<usize>, &'static str)>, range: Range<usize>, class: &'static str) {
+    match tokens.last_mut() {
+        Some((last, last_class)) if last.end == range.start && *last_class == class => last.end = range.end,
+        _ => tokens.push((range, class)),
+    }
+}
 
-            // Default: no class
-            _ => "",
-        };
+/// Renders the tagged tree within a byte window, one string per line.
+#[derive(Default)]
+struct LineWriter {
+    window: Range<usize>,
+    /// Classes of the spans open at the current position, outermost first.
+    open: Vec<&'static str>,
+    line: String,
+    lines: Vec<String>,
+    /// The last character written was `\r` (so a `\n` after it is one break).
+    after_cr: bool,
+    /// Coloured spans of the raw block being written, if it has a language.
+    embedded: Vec<(Range<usize>, &'static str)>,
+}
 
-        if child.children().next().is_some() {
-            // Node with children: recurse
-            if !class.is_empty() {
-                html.push_str(&format!("<span class=\"{}\">", class));
+impl LineWriter {
+    /// Tagged inner nodes (headings, strong, emph, …) wrap their children,
+    /// so tags nest.
+    fn node(&mut self, node: &LinkedNode) {
+        let range = node.range();
+        if range.end <= self.window.start || range.start >= self.window.end {
+            return;
+        }
+        let mut class = highlight(node).map(Tag::css_class);
+        if node.kind() == SyntaxKind::Raw {
+            if let Some(tokens) = embedded_tokens(node) {
+                self.embedded = tokens;
+                class = Some("typ-raw typ-embedded");
             }
-            highlight_node(child, html);
-            if !class.is_empty() {
-                html.push_str("</span>");
+        }
+        if let Some(class) = class {
+            self.open_span(class);
+            self.open.push(class);
+        }
+        if node.get().children().len() == 0 {
+            let from = range.start.max(self.window.start);
+            let to = range.end.min(self.window.end);
+            match node.kind() {
+                SyntaxKind::Text | SyntaxKind::RawTrimmed if !self.embedded.is_empty() => {
+                    self.embedded_text(node.text(), range.start, from..to)
+                }
+                _ => self.text(&node.text()[from - range.start..to - range.start]),
             }
         } else {
-            // Leaf node: write the text
-            let escaped = html_escape(text.as_str());
-            if !class.is_empty() {
-                html.push_str(&format!("<span class=\"{}\">{}</span>", class, escaped));
-            } else {
-                html.push_str(&escaped);
+            for child in node.children() {
+                self.node(&child);
+            }
+        }
+        if class.is_some() {
+            self.open.pop();
+            self.line.push_str("</span>");
+        }
+        if node.kind() == SyntaxKind::Raw {
+            self.embedded.clear();
+        }
+    }
+
+    /// Write the `window` part of a raw-block leaf that starts at `offset`,
+    /// coloured by the block's embedded tokens.
+    fn embedded_text(&mut self, text: &str, offset: usize, window: Range<usize>) {
+        let mut at = window.start;
+        let tokens = std::mem::take(&mut self.embedded);
+        for (range, class) in &tokens {
+            if range.end <= at || range.start >= window.end {
+                continue;
             }
+            if range.start > at {
+                self.text(&text[at - offset..range.start - offset]);
+                at = range.start;
+            }
+            let end = range.end.min(window.end);
+            self.open_span(class);
+            self.open.push(class);
+            self.text(&text[at - offset..end - offset]);
+            self.open.pop();
+            self.line.push_str("</span>");
+            at = end;
+        }
+        if window.end > at {
+            self.text(&text[at - offset..window.end - offset]);
         }
+        self.embedded = tokens;
+    }
+
+    fn open_span(&mut self, class: &str) {
+        self.line.push_str("<span class=\"");
+        self.line.push_str(class);
+        self.line.push_str("\">");
+    }
+
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            let after_cr = std::mem::replace(&mut self.after_cr, c == '\r');
+            if c == '\n' && after_cr {
+                continue;
+            }
+            if !is_newline(c) {
+                html_escape(c, &mut self.line);
+                continue;
+            }
+            for _ in &self.open {
+                self.line.push_str("</span>");
+            }
+            self.lines.push(std::mem::take(&mut self.line));
+            for class in self.open.clone() {
+                self.open_span(class);
+            }
+        }
+    }
+}
+
+/// Convert Typst code into HTML with syntax highlighting, one source line
+/// per output line.
+#[cfg(test)]
+fn highlight_typst(source: &str) -> String {
+    let lines = LineHighlighter::new(source).lines;
+    format!("<pre class=\"typst-highlighted\"><code>{}</code></pre>", lines.join("\n"))
+}
+
+/// Append `c` with HTML entities escaped.
+fn html_escape(c: char, html: &mut String) {
+    match c {
+        '&' => html.push_str("&amp;"),
+        '<' => html.push_str("&lt;"),
+        '>' => html.push_str("&gt;"),
+        '"' => html.push_str("&quot;"),
+        '\'' => html.push_str("&#39;"),
+        _ => html.push(c),
     }
 }
 
-/// Escape HTML entities
-fn html_escape(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#39;")
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// The `(class, text)` of every tagged leaf, innermost tag only.
+    fn tagged(source: &str) -> Vec<(&'static str, String)> {
+        fn walk(node: &LinkedNode, out: &mut Vec<(&'static str, String)>) {
+            if node.get().children().len() == 0 {
+                let mut at = Some(node.clone());
+                while let Some(n) = at {
+                    if let Some(tag) = highlight(&n) {
+                        out.push((tag.css_class(), node.text().to_string()));
+                        break;
+                    }
+                    at = n.parent().cloned();
+                }
+            }
+            for child in node.children() {
+                walk(&child, out);
+            }
+        }
+        let root = typst_syntax::parse(source);
+        let mut out = Vec::new();
+        walk(&LinkedNode::new(&root), &mut out);
+        out
+    }
+
+    fn class_of(source: &str, text: &str) -> &'static str {
+        tagged(source)
+            .into_iter()
+            .find(|(_, t)| t == text)
+            .map_or("", |(class, _)| class)
+    }
+
+    #[test]
+    fn tags_cover_code_markup_and_math() {
+        let source = "#set text(size: 11pt)\n#show heading: it => emph(it)\n\
+                      #let f(x) = if x > 1 { x } else { none }\n#import \"a.typ\": b\n\
+                      = Title \\# https://typst.app <intro> @intro\n\
+                      - *bold* _it_ `raw` #f(2) #b.c\n$ x^2 + alpha & = 1 $\n";
+        assert_eq!(class_of(source, "set"), "typ-key");
+        assert_eq!(class_of(source, "text"), "typ-func");
+        assert_eq!(class_of(source, "heading"), "typ-func");
+        assert_eq!(class_of(source, "emph"), "typ-func");
+        assert_eq!(class_of(source, "import"), "typ-key");
+        assert_eq!(class_of(source, "none"), "typ-key");
+        assert_eq!(class_of(source, ">"), "typ-op");
+        assert_eq!(class_of(source, "11pt"), "typ-num");
+        assert_eq!(class_of(source, "\"a.typ\""), "typ-str");
+        assert_eq!(class_of(source, "\\#"), "typ-escape");
+        assert_eq!(class_of(source, "https://typst.app"), "typ-link");
+        assert_eq!(class_of(source, "<intro>"), "typ-label");
+        assert_eq!(class_of(source, "@intro"), "typ-ref");
+        assert_eq!(class_of(source, "-"), "typ-marker");
+        assert_eq!(class_of(source, "bold"), "typ-strong");
+        assert_eq!(class_of(source, "it"), "typ-emph");
+        assert_eq!(class_of(source, "raw"), "typ-raw");
+        assert_eq!(class_of(source, "b"), "typ-pol");
+        assert_eq!(class_of(source, "^"), "typ-math-op");
+        assert_eq!(class_of(source, "&"), "typ-math-op");
+        assert_eq!(class_of(source, "$"), "typ-math-delim");
+        assert_eq!(class_of(source, "alpha"), "typ-pol");
+        assert_eq!(class_of(source, "Title"), "typ-heading");
+
+        let html = highlight_typst("= A <b> & \"c\"");
+        assert!(html.contains("<span class=\"typ-heading\">= A</span>"));
+        assert!(html.contains("&lt;b&gt;"));
+        assert!(html.contains("&amp; &quot;c&quot;"));
+    }
+
+    #[test]
+    fn raw_blocks_highlight_embedded_code() {
+        let html = highlight_typst("```rust\nlet s = \"hi\"; // note\n```\n`plain` ```nolang x```");
+        let lines: Vec<&str> = html.lines().collect();
+        assert!(lines[0].ends_with("<span class=\"typ-raw typ-embedded\">```rust</span>"));
+        assert!(lines[1].contains("<span class=\"typ-key\">let</span> s <span class=\"typ-op\">=</span>"));
+        assert!(lines[1].contains("<span class=\"typ-str\">&quot;hi&quot;</span>"));
+        assert!(lines[1].contains("<span class=\"typ-comment\">// note</span>"));
+        // Unknown or missing languages stay one raw span.
+        assert!(lines[3].contains("<span class=\"typ-raw\">```nolang x```</span>"));
+        assert!(lines[3].contains("<span class=\"typ-raw\">`plain`</span>"));
+    }
+
+    #[test]
+    fn incremental_updates_match_a_full_render() {
+        let mut text =
+            std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/example2.typ")).unwrap();
+        let mut highlighter = LineHighlighter::new(&text);
+        let mut mirror = highlighter.lines().to_vec();
+        fn at(text: &str, needle: &str) -> usize {
+            text.find(needle).unwrap()
+        }
+        // (byte range to replace, replacement) applied in turn; each opens or
+        // closes constructs that change the tags of later lines.
+        type Edit = fn(&str) -> (Range<usize>, String);
+        let edits: [Edit; 10] = [
+            |t| (at(t, "= ")..at(t, "= "), "x".to_string()),
+            |t| (at(t, "#")..at(t, "#") + 1, "/* #".to_string()),
+            |t| (at(t, "/* ")..at(t, "/* ") + 3, String::new()),
+            |t| (t.len() / 2..t.len() / 2, "\n$ x^2\n".to_string()),
+            |t| (at(t, "$ x^2")..at(t, "$ x^2") + 6, String::new()),
+            |t| (t.len()..t.len(), "\r\n#let f(x) = x\r\n#f".to_string()),
+            |t| (t.len()..t.len(), "(1)".to_string()),
+            |t| (10..t.len() - 10, "*strong\n\nno*".to_string()),
+            |t| (0..t.len(), String::new()),
+            |_| (0..0, "= Title\n\nText".to_string()),
+        ];
+        for edit in edits {
+            let (range, with) = edit(&text);
+            text.replace_range(range, &with);
+            let patch = highlighter.update(&text).unwrap();
+            mirror.splice(patch.start..patch.start + patch.removed, patch.inserted);
+            let fresh = LineHighlighter::new(&text);
+            assert_eq!(highlighter.lines(), fresh.lines(), "after inserting {with:?}");
+            assert_eq!(mirror, fresh.lines());
+        }
+        assert_eq!(highlighter.update(&text), None);
+    }
+
+    /// Compare the highlighted HTML of each bundled document with its
+    /// snapshot in `src/utils/snapshots/`. Run with `UPDATE_SNAPSHOTS=1` to
+    /// (re)write them after an intended change, then review the diff.
+    #[test]
+    fn example_documents_match_snapshots() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let documents = [
+            "examples/example.typ",
+            "examples/example2.typ",
+            "templates/article.typ",
+            "templates/blank.typ",
+            "templates/ieee.typ",
+        ];
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        for document in documents {
+            let source = std::fs::read_to_string(root.join(document)).unwrap();
+            // One line of HTML per source line keeps snapshot diffs readable.
+            let html = highlight_typst(&source) + "\n";
+            let name = document.replace('/', "_").replace(".typ", ".html");
+            let path = root.join("src/utils/snapshots").join(format!("highlight_{name}"));
+            if update {
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, &html).unwrap();
+                continue;
+            }
+            let expected = std::fs::read_to_string(&path)
+                .unwrap_or_else(|_| panic!("missing {}; run with UPDATE_SNAPSHOTS=1", path.display()));
+            assert!(html == expected, "{document} no longer matches {}", path.display());
+        }
+    }
 }
@@ -0,0 +1,409 @@
+//! Import a local Typst project dropped onto the editor (a folder, or loose
+//! files): classify each file, decide where it goes, and rewrite the image
+//! paths the sources use to the gallery's IDs.
+//!
+//! The planning and rewriting are pure and host-tested; only
+//! [`dropped_entries`] / [`read_entries`] touch the DOM.
+
+use super::project::resolve_path;
+use std::collections::HashMap;
+use typst_syntax::{ast, parse, LinkedNode, SyntaxKind};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+/// One file read from the drop, with its project-relative path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DroppedFile {
+    pub path: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Where a dropped file belongs, decided by its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    Typst,
+    /// `.yml` / `.yaml`: a Hayagriva bibliography when a source passes it to
+    /// `bibliography(...)`, a data asset otherwise.
+    Yaml,
+    /// `.bib`: a BibLaTeX bibliography when cited, a data asset otherwise.
+    BibLatex,
+    Image,
+    Font,
+    Data,
+    Other,
+}
+
+/// Classify a path by its (case-insensitive) extension.
+pub fn classify(path: &str) -> FileKind {
+    let ext = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "typ" => FileKind::Typst,
+        "yml" | "yaml" => FileKind::Yaml,
+        "bib" => FileKind::BibLatex,
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" => FileKind::Image,
+        "ttf" | "otf" | "ttc" => FileKind::Font,
+        "csv" | "json" | "toml" | "txt" | "xml" | "csl" => FileKind::Data,
+        _ => FileKind::Other,
+    }
+}
+
+/// MIME type for an image path, used to build the gallery's data URL.
+pub fn image_mime(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).as_deref() {
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        _ => "image/png",
+    }
+}
+
+/// Last path segment (`figures/plot.png` → `plot.png`).
+pub fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Functions whose first positional string argument (or, for `bibliography`,
+/// array of strings) is a project path we may need to rewrite.
+const PATH_FUNCS: [&str; 2] = ["image", "bibliography"];
+
+/// A path string passed to one of [`PATH_FUNCS`].
+struct PathLiteral {
+    func: String,
+    /// Byte range of the string literal, quotes included.
+    range: std::ops::Range<usize>,
+    value: String,
+}
+
+/// Collect the path arguments of every `image(...)` / `bibliography(...)`
+/// call, in source order.
+fn path_literals(source: &str) -> Vec<PathLiteral> {
+    fn visit(node: &LinkedNode, out: &mut Vec<PathLiteral>) {
+        if let Some(call) = node.cast::<ast::FuncCall>() {
+            if let ast::Expr::Ident(ident) = call.callee() {
+                if PATH_FUNCS.contains(&ident.as_str()) {
+                    let args = node.children().find(|c| c.kind() == SyntaxKind::Args);
+                    let mut literals: Vec<LinkedNode> = args
+                        .iter()
+                        .flat_map(|args| args.children().find(|c| c.kind() == SyntaxKind::Str))
+                        .collect();
+                    // `bibliography(("a.yml", "b.bib"))` takes an array of paths.
+                    if literals.is_empty() && ident.as_str() == "bibliography" {
+                        if let Some(array) = args.and_then(|a| a.children().find(|c| c.kind() == SyntaxKind::Array)) {
+                            literals = array.children().filter(|c| c.kind() == SyntaxKind::Str).collect();
+                        }
+                    }
+                    for lit in literals {
+                        if let Some(s) = lit.cast::<ast::Str>() {
+                            out.push(PathLiteral {
+                                func: ident.as_str().to_string(),
+                                range: lit.range(),
+                                value: s.get().to_string(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        for child in node.children() {
+            visit(&child, out);
+        }
+    }
+
+    let root = parse(source);
+    let mut out = Vec::new();
+    visit(&LinkedNode::new(&root), &mut out);
+    out
+}
+
+/// Rewrite the `image(...)` / `bibliography(...)` paths in `source` (the
+/// content of project file `file_path`) whose resolved project path has an
+/// entry in `replacements` — e.g. `figures/plot.png` → gallery id `004`.
+///
+/// The targets are served at the project root, so they are written
+/// root-absolute (`"/004"`): a bare id would resolve against the folder of a
+/// nested file such as `ch/one.typ`.
+pub fn rewrite_paths(source: &str, file_path: &str, replacements: &HashMap<String, String>) -> String {
+    let mut out = source.to_string();
+    // Splice from the end so earlier ranges stay valid.
+    for lit in path_literals(source).into_iter().rev() {
+        let Some(target) = resolve_path(file_path, &lit.value).and_then(|p| replacements.get(&p)) else {
+            continue;
+        };
+        let target = format!("/{}", target.trim_start_matches('/'));
+        out.replace_range(lit.range, &format!("{target:?}"));
+    }
+    out
+}
+
+/// Drop a shared leading directory (the dropped folder's own name), so a
+/// folder `thesis/` yields `main.typ` rather than `thesis/main.typ`.
+fn strip_common_root(files: &mut [DroppedFile]) {
+    let Some(root) = files
+        .first()
+        .and_then(|f| f.path.split_once('/'))
+        .map(|(root, _)| format!("{root}/"))
+    else {
+        return;
+    };
+    if files.iter().all(|f| f.path.starts_with(&root)) {
+        for f in files.iter_mut() {
+            f.path = f.path[root.len()..].to_string();
+        }
+    }
+}
+
+/// Where every dropped file goes.
+#[derive(Debug, Default)]
+pub struct ImportPlan {
+    /// Project `.typ` files as `(path, content)`, entry point first.
+    pub typst: Vec<(String, String)>,
+    /// The YAML / BibLaTeX files a source passes to `bibliography(...)`, as
+    /// `(path, content)`. They are served under the same path, so the sources
+    /// need no rewriting.
+    pub bibliographies: Vec<(String, String)>,
+    pub images: Vec<DroppedFile>,
+    pub fonts: Vec<DroppedFile>,
+    /// Data files (CSV, JSON, uncited YAML, …), kept at their project path.
+    pub assets: Vec<DroppedFile>,
+    /// `(path, reason)` for everything that is not imported.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Sort dropped files into the studio's stores.
+///
+/// The entry point is a root-level `main.typ` if there is one, otherwise the
+/// first root-level `.typ` file (then the first `.typ` anywhere). It compiles
+/// from the project root, so nested entry points keep root-relative paths.
+pub fn plan_import(mut files: Vec<DroppedFile>) -> ImportPlan {
+    strip_common_root(&mut files);
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut plan = ImportPlan::default();
+    let mut bib_candidates = Vec::new();
+    for file in files {
+        match classify(&file.path) {
+            FileKind::Typst => match String::from_utf8(file.bytes) {
+                Ok(content) => plan.typst.push((file.path, content)),
+                Err(_) => plan.skipped.push((file.path, "not valid UTF-8 text".to_string())),
+            },
+            FileKind::Yaml | FileKind::BibLatex => bib_candidates.push(file),
+            FileKind::Image => plan.images.push(file),
+            FileKind::Font => plan.fonts.push(file),
+            FileKind::Data => plan.assets.push(file),
+            FileKind::Other => plan.skipped.push((file.path, "unsupported file type".to_string())),
+        }
+    }
+
+    // A YAML / .bib file is a bibliography only if a source actually cites it.
+    let cited: Vec<String> = plan
+        .typst
+        .iter()
+        .flat_map(|(path, content)| {
+            path_literals(content)
+                .into_iter()
+                .filter(|lit| lit.func == "bibliography")
+                .filter_map(|lit| resolve_path(path, &lit.value))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    for file in bib_candidates {
+        if !cited.contains(&file.path) {
+            plan.assets.push(file);
+        } else {
+            match String::from_utf8(file.bytes) {
+                Ok(content) => plan.bibliographies.push((file.path, content)),
+                Err(_) => plan.skipped.push((file.path, "not valid UTF-8 text".to_string())),
+            }
+        }
+    }
+
+    let is_root = |p: &str| !p.contains('/');
+    let main = plan
+        .typst
+        .iter()
+        .position(|(p, _)| p == "main.typ")
+        .or_else(|| plan.typst.iter().position(|(p, _)| is_root(p)));
+    if let Some(idx) = main {
+        let entry = plan.typst.remove(idx);
+        plan.typst.insert(0, entry);
+    }
+    plan
+}
+
+/// What an import did, for the summary dialog.
+#[derive(Clone, Debug, Default)]
+pub struct ImportReport {
+    pub files: usize,
+    pub images: usize,
+    pub fonts: usize,
+    pub assets: usize,
+    pub bibliographies: usize,
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Grab the file-system entries of a drop. Must run synchronously inside the
+/// `drop` handler: the `DataTransfer` is emptied once the event returns.
+pub fn dropped_entries(dt: &web_sys::DataTransfer) -> Vec<web_sys::FileSystemEntry> {
+    let items = dt.items();
+    (0..items.length())
+        .filter_map(|i| items.get(i))
+        .filter_map(|item| item.webkit_get_as_entry().ok().flatten())
+        .collect()
+}
+
+/// Walk the dropped entries (descending into folders, skipping hidden files
+/// and directories such as `.git`) and read every file's bytes.
+pub async fn read_entries(entries: Vec<web_sys::FileSystemEntry>) -> Vec<DroppedFile> {
+    let mut stack = entries;
+    let mut out = Vec::new();
+    while let Some(entry) = stack.pop() {
+        if entry.name().starts_with('.') {
+            continue;
+        }
+        if entry.is_directory() {
+            let reader = entry
+                .unchecked_into::<web_sys::FileSystemDirectoryEntry>()
+                .create_reader();
+            // `readEntries` returns directory contents in batches until empty.
+            loop {
+                let promise = js_sys::Promise::new(&mut |resolve, reject| {
+                    let _ = reader.read_entries_with_callback_and_callback(&resolve, &reject);
+                });
+                let Ok(batch) = JsFuture::from(promise).await else { break };
+                let batch: js_sys::Array = batch.unchecked_into();
+                if batch.length() == 0 {
+                    break;
+                }
+                stack.extend(batch.iter().map(|e| e.unchecked_into::<web_sys::FileSystemEntry>()));
+            }
+        } else if entry.is_file() {
+            let path = entry.full_path().trim_start_matches('/').to_string();
+            let file_entry = entry.unchecked_into::<web_sys::FileSystemFileEntry>();
+            let promise = js_sys::Promise::new(&mut |resolve, reject| {
+                file_entry.file_with_callback_and_callback(&resolve, &reject);
+            });
+            let Ok(file) = JsFuture::from(promise).await else { continue };
+            let file: web_sys::File = file.unchecked_into();
+            let Ok(buffer) = JsFuture::from(file.array_buffer()).await else { continue };
+            out.push(DroppedFile {
+                path,
+                bytes: js_sys::Uint8Array::new(&buffer).to_vec(),
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, content: &str) -> DroppedFile {
+        DroppedFile {
+            path: path.to_string(),
+            bytes: content.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn classifies_by_extension() {
+        assert_eq!(classify("main.typ"), FileKind::Typst);
+        assert_eq!(classify("refs.YAML"), FileKind::Yaml);
+        assert_eq!(classify("refs.bib"), FileKind::BibLatex);
+        assert_eq!(classify("fig/plot.JPG"), FileKind::Image);
+        assert_eq!(classify("fonts/Inter.otf"), FileKind::Font);
+        assert_eq!(classify("data.csv"), FileKind::Data);
+        assert_eq!(classify("styles/journal.csl"), FileKind::Data);
+        assert_eq!(classify("README"), FileKind::Other);
+    }
+
+    #[test]
+    fn strips_dropped_folder_name() {
+        let plan = plan_import(vec![
+            file("thesis/main.typ", "= Main"),
+            file("thesis/ch/one.typ", "= One"),
+        ]);
+        let paths: Vec<&str> = plan.typst.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(paths, ["main.typ", "ch/one.typ"]);
+    }
+
+    #[test]
+    fn entry_point_prefers_main_then_root_files() {
+        let plan = plan_import(vec![file("a.typ", ""), file("main.typ", ""), file("b/c.typ", "")]);
+        assert_eq!(plan.typst[0].0, "main.typ");
+
+        let plan = plan_import(vec![file("b/c.typ", ""), file("report.typ", "")]);
+        assert_eq!(plan.typst[0].0, "report.typ");
+    }
+
+    #[test]
+    fn cited_yaml_and_bib_are_bibliographies_other_data_are_assets() {
+        let plan = plan_import(vec![
+            file("main.typ", "#bibliography((\"lit/refs.yaml\", \"/lit/zotero.bib\"))"),
+            file("lit/refs.yaml", "key:\n  type: book\n"),
+            file("lit/zotero.bib", "@book{x,}"),
+            file("config.yml", "a: 1"),
+            file("old.bib", "@book{y,}"),
+            file("table.csv", "a,b"),
+            file("notes.docx", ""),
+        ]);
+        let bibs: Vec<&str> = plan.bibliographies.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(bibs, ["lit/refs.yaml", "lit/zotero.bib"]);
+        let assets: Vec<&str> = plan.assets.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(assets, ["table.csv", "config.yml", "old.bib"]);
+        let skipped: Vec<&str> = plan.skipped.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(skipped, ["notes.docx"]);
+    }
+
+    #[test]
+    fn routes_images_and_fonts() {
+        let plan = plan_import(vec![
+            file("main.typ", ""),
+            file("fig/a.png", "png"),
+            file("fonts/x.ttf", "ttf"),
+        ]);
+        assert_eq!(plan.images.len(), 1);
+        assert_eq!(plan.fonts.len(), 1);
+        assert!(plan.skipped.is_empty());
+    }
+
+    #[test]
+    fn rewrites_image_and_bibliography_paths() {
+        let replacements = HashMap::from([
+            ("fig/a.png".to_string(), "004".to_string()),
+            ("lit/refs.yaml".to_string(), "refs.yml".to_string()),
+        ]);
+        let src = "#image(\"../fig/a.png\", width: 50%)\n#figure(image(\"../fig/b.png\"), caption: \"../fig/a.png\")\n#bibliography(\"/lit/refs.yaml\")";
+        let out = rewrite_paths(src, "ch/one.typ", &replacements);
+        assert_eq!(
+            out,
+            "#image(\"/004\", width: 50%)\n#figure(image(\"../fig/b.png\"), caption: \"../fig/a.png\")\n#bibliography(\"/refs.yml\")"
+        );
+    }
+
+    #[test]
+    fn rewritten_paths_compile_from_a_nested_file() {
+        use crate::compiler::compile_to_svg;
+        use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10"/></svg>"#;
+        let images = HashMap::from([("004".to_string(), format!("data:image/svg+xml;base64,{}", STANDARD.encode(svg)))]);
+        let bibs = [("refs.yml".to_string(), "knuth:\n  type: book\n  title: TAOCP\n  author: Knuth, Donald\n".to_string())];
+        let replacements = HashMap::from([
+            ("fig/a.png".to_string(), "004".to_string()),
+            ("lit/refs.yaml".to_string(), "refs.yml".to_string()),
+        ]);
+        let chapter = rewrite_paths(
+            "#image(\"../fig/a.png\")\n@knuth\n#bibliography(\"../lit/refs.yaml\")",
+            "ch/one.typ",
+            &replacements,
+        );
+        let extra = [("ch/one.typ".to_string(), chapter)];
+        compile_to_svg("#include \"ch/one.typ\"", "", &bibs, &images, &extra, &HashMap::new())
+            .expect("nested chapter compiles with rewritten paths");
+    }
+}
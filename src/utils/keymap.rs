@@ -0,0 +1,392 @@
+//! Named commands and the user-editable keymap.
+//!
+//! [`COMMANDS`] lists every command the command palette offers, with its
+//! default shortcuts. A [`Keymap`] stores only the user's changes to those
+//! defaults and is persisted in localStorage. Shortcuts are written as
+//! chords like `Ctrl+Shift+P`: modifiers in the order `Ctrl`, `Shift`,
+//! `Alt` (Cmd counts as Ctrl), then the key.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Where a command's shortcut works: anywhere in the app, or only while the
+/// source editor has focus (so `Tab` or `Ctrl+B` keep their usual meaning
+/// in dialogs).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    Editor,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub id: &'static str,
+    /// Palette label, prefixed with its category.
+    pub title: &'static str,
+    pub scope: Scope,
+    pub keys: &'static [&'static str],
+}
+
+const fn global(id: &'static str, title: &'static str, keys: &'static [&'static str]) -> CommandSpec {
+    CommandSpec { id, title, scope: Scope::Global, keys }
+}
+
+const fn editor(id: &'static str, title: &'static str, keys: &'static [&'static str]) -> CommandSpec {
+    CommandSpec { id, title, scope: Scope::Editor, keys }
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    global("palette.open", "Show all commands", &["Ctrl+Shift+P"]),
+    global("keymap.open", "Preferences: Keyboard shortcuts", &[]),
+    global("view.closeDialogs", "View: Close dialogs", &["Escape"]),
+    global("file.save", "File: Save project", &["Ctrl+S"]),
+    global("file.new", "File: New file", &[]),
+    global("file.rename", "File: Rename file", &[]),
+    global("file.delete", "File: Delete file", &[]),
+    global("file.next", "File: Next file", &["Ctrl+Alt+PageDown"]),
+    global("file.previous", "File: Previous file", &["Ctrl+Alt+PageUp"]),
+    global("templates.open", "File: New from template", &[]),
+    global("export.pdf", "Export: PDF", &[]),
+    global("export.svg", "Export: SVG", &[]),
+    global("export.source", "Export: Download .typ file", &[]),
+    global("share.editorLink", "Share: Copy editor link", &[]),
+    global("share.viewerLink", "Share: Copy viewer link", &[]),
+    global("share.qr", "Share: QR code", &[]),
+    global("search.project", "Search: Find in files", &["Ctrl+Shift+F"]),
+    global("view.outline", "View: Toggle outline", &[]),
+    global("view.stats", "View: Toggle statistics", &[]),
+    global("view.problems", "View: Toggle problems", &[]),
+    global("view.images", "View: Images", &[]),
+    global("view.assets", "View: Assets", &[]),
+    global("view.bibliography", "View: Bibliography", &[]),
+    global("view.theme", "View: Toggle dark theme", &[]),
+    editor("editor.find", "Edit: Find and replace", &["Ctrl+F"]),
+    editor("editor.complete", "Edit: Trigger completion", &["Ctrl+Space"]),
+    editor("editor.addNextOccurrence", "Edit: Add next occurrence", &["Ctrl+D"]),
+    editor("editor.bold", "Edit: Bold", &["Ctrl+B"]),
+    editor("editor.italic", "Edit: Italic", &["Ctrl+I"]),
+    editor("editor.indent", "Edit: Indent", &["Tab"]),
+    editor("editor.outdent", "Edit: Outdent", &["Shift+Tab"]),
+    editor("editor.format", "Edit: Format document", &["Shift+Alt+F"]),
+    editor("editor.rename", "Edit: Rename symbol", &["F2"]),
+    editor("editor.fold", "Edit: Fold region", &["Ctrl+Shift+["]),
+    editor("editor.unfold", "Edit: Unfold region", &["Ctrl+Shift+]"]),
+    editor("editor.citation", "Insert: Citation", &[]),
+    editor("editor.settings", "Preferences: Document settings", &[]),
+    editor("editor.toggleSoftWrap", "Preferences: Toggle soft wrap", &[]),
+    editor("editor.toggleVim", "Preferences: Toggle Vim mode", &[]),
+    editor("editor.toggleFormatOnSave", "Preferences: Toggle format on save", &[]),
+];
+
+pub fn command(id: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|c| c.id == id)
+}
+
+/// localStorage key holding the keymap overrides as JSON.
+const KEYMAP_KEY: &str = "typst_keymap";
+
+/// The user's shortcuts: for each changed command, its full list of chords
+/// (empty to unbind it). Unchanged commands use their defaults.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Keymap {
+    overrides: BTreeMap<String, Vec<String>>,
+}
+
+impl Keymap {
+    /// Parse stored overrides, dropping unknown commands and chords that do
+    /// not parse (the rest are normalized).
+    pub fn from_json(json: &str) -> Self {
+        let stored: BTreeMap<String, Vec<String>> = serde_json::from_str(json).unwrap_or_default();
+        let mut keymap = Self::default();
+        for (id, keys) in stored {
+            if command(&id).is_some() {
+                let keys = keys.iter().filter_map(|k| parse_chord(k).ok()).collect();
+                keymap.set(&id, keys);
+            }
+        }
+        keymap
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// The chords bound to `id`.
+    pub fn keys(&self, id: &str) -> Vec<String> {
+        match self.overrides.get(id) {
+            Some(keys) => keys.clone(),
+            None => command(id).map_or_else(Vec::new, |c| c.keys.iter().map(|k| k.to_string()).collect()),
+        }
+    }
+
+    pub fn is_default(&self, id: &str) -> bool {
+        !self.overrides.contains_key(id)
+    }
+
+    /// Bind `id` to exactly `keys`; the defaults again drop the override.
+    pub fn set(&mut self, id: &str, mut keys: Vec<String>) {
+        let mut seen = Vec::new();
+        keys.retain(|k| !seen.contains(k) && {
+            seen.push(k.clone());
+            true
+        });
+        let defaults = command(id).map(|c| c.keys).unwrap_or_default();
+        if keys.iter().map(String::as_str).eq(defaults.iter().copied()) {
+            self.overrides.remove(id);
+        } else {
+            self.overrides.insert(id.to_string(), keys);
+        }
+    }
+
+    pub fn add(&mut self, id: &str, chord: &str) {
+        let mut keys = self.keys(id);
+        keys.push(chord.to_string());
+        self.set(id, keys);
+    }
+
+    pub fn remove(&mut self, id: &str, chord: &str) {
+        let keys = self.keys(id).into_iter().filter(|k| k != chord).collect();
+        self.set(id, keys);
+    }
+
+    pub fn reset(&mut self, id: &str) {
+        self.overrides.remove(id);
+    }
+
+    /// The command of `scope` bound to `chord`; with a conflict, the first
+    /// in [`COMMANDS`] order wins.
+    pub fn command_for(&self, chord: &str, scope: Scope) -> Option<&'static CommandSpec> {
+        COMMANDS
+            .iter()
+            .filter(|c| c.scope == scope)
+            .find(|c| self.keys(c.id).iter().any(|k| k == chord))
+    }
+
+    /// Chords bound to more than one command, with those commands' ids.
+    pub fn conflicts(&self) -> Vec<(String, Vec<&'static str>)> {
+        let mut by_chord: BTreeMap<String, Vec<&'static str>> = BTreeMap::new();
+        for c in COMMANDS {
+            for key in self.keys(c.id) {
+                by_chord.entry(key).or_default().push(c.id);
+            }
+        }
+        by_chord.into_iter().filter(|(_, ids)| ids.len() > 1).collect()
+    }
+}
+
+/// Load the persisted keymap (the defaults if there is none).
+pub fn load_keymap() -> Keymap {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|s| s.get_item(KEYMAP_KEY).ok().flatten())
+        .map(|json| Keymap::from_json(&json))
+        .unwrap_or_default()
+}
+
+/// Persist the keymap to localStorage (best-effort, fails silently).
+pub fn save_keymap(keymap: &Keymap) {
+    if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        let _ = storage.set_item(KEYMAP_KEY, &keymap.to_json());
+    }
+}
+
+const NAMED_KEYS: &[&str] = &[
+    "Tab", "Enter", "Escape", "Space", "Backspace", "Delete", "Insert", "Home", "End", "PageUp", "PageDown",
+    "ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight",
+];
+
+/// The chord of a key press (`KeyboardEvent` `key` and `code`), or `None`
+/// for a modifier on its own. Letters and digits come from `key`, so other
+/// layouts keep their labels; other printable keys come from `code`, since
+/// Shift and Alt change what `key` reports (`Shift+[` gives `{`).
+pub fn chord(key: &str, code: &str, ctrl: bool, shift: bool, alt: bool) -> Option<String> {
+    let mut chars = key.chars();
+    let single = chars.next().filter(|_| chars.next().is_none());
+    let name = match single {
+        Some(c) if c.is_ascii_alphanumeric() => c.to_ascii_uppercase().to_string(),
+        _ => match code {
+            "Space" => "Space".to_string(),
+            _ if code.starts_with("Key") && code.len() == 4 => code[3..].to_string(),
+            _ if code.starts_with("Digit") && code.len() == 6 => code[5..].to_string(),
+            "BracketLeft" => "[".to_string(),
+            "BracketRight" => "]".to_string(),
+            "Comma" => ",".to_string(),
+            "Period" => ".".to_string(),
+            "Slash" => "/".to_string(),
+            "Backslash" => "\\".to_string(),
+            "Semicolon" => ";".to_string(),
+            "Quote" => "'".to_string(),
+            "Backquote" => "`".to_string(),
+            "Minus" => "-".to_string(),
+            "Equal" => "=".to_string(),
+            _ => match single {
+                Some(c) => c.to_uppercase().to_string(),
+                None if matches!(key, "Shift" | "Control" | "Alt" | "Meta" | "AltGraph" | "CapsLock" | "Dead")
+                    || key == "Unidentified"
+                    || key.is_empty() =>
+                {
+                    return None
+                }
+                None => key.to_string(),
+            },
+        },
+    };
+    Some(join(ctrl, shift, alt, &name))
+}
+
+fn join(ctrl: bool, shift: bool, alt: bool, key: &str) -> String {
+    let mut chord = String::new();
+    for (on, name) in [(ctrl, "Ctrl+"), (shift, "Shift+"), (alt, "Alt+")] {
+        if on {
+            chord.push_str(name);
+        }
+    }
+    chord.push_str(key);
+    chord
+}
+
+/// Normalize a hand-written chord such as `cmd+shift+p`.
+pub fn parse_chord(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    // The key itself may be `+`.
+    let (mods, key) = match text.strip_suffix("++") {
+        Some(mods) => (mods, "+"),
+        None => text.rsplit_once('+').unwrap_or(("", text)),
+    };
+    let (mut ctrl, mut shift, mut alt) = (false, false, false);
+    for part in mods.split('+').filter(|p| !p.is_empty()) {
+        match part.trim().to_ascii_lowercase().as_str() {
+            "ctrl" | "control" | "cmd" | "meta" => ctrl = true,
+            "shift" => shift = true,
+            "alt" | "option" => alt = true,
+            other => return Err(format!("Unknown modifier: {other}")),
+        }
+    }
+    let key = key.trim();
+    let mut chars = key.chars();
+    let name = match (chars.next(), chars.next()) {
+        (None, _) => return Err("Missing key".to_string()),
+        (Some(c), None) => c.to_uppercase().to_string(),
+        _ if key.eq_ignore_ascii_case("esc") => "Escape".to_string(),
+        _ => match NAMED_KEYS.iter().find(|k| k.eq_ignore_ascii_case(key)) {
+            Some(named) => named.to_string(),
+            None if matches!(key.to_ascii_uppercase().strip_prefix('F').map(str::parse::<u8>), Some(Ok(1..=24))) => {
+                key.to_ascii_uppercase()
+            }
+            None => return Err(format!("Unknown key: {key}")),
+        },
+    };
+    Ok(join(ctrl, shift, alt, &name))
+}
+
+/// How well `query` matches `text`, case-insensitively: `None` unless its
+/// characters appear in order. Matches at word starts and runs of adjacent
+/// matches score higher, gaps between matches lower.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query: Vec<char> = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let Some(&first) = query.first() else {
+        return Some(0);
+    };
+    // Match greedily from each place the query's first character occurs,
+    // keeping the best.
+    (0..text.len())
+        .filter(|&start| text[start] == first)
+        .filter_map(|start| {
+            let mut score = 0;
+            let mut matched = 0;
+            let mut last: Option<usize> = None;
+            for (i, &c) in text.iter().enumerate().skip(start) {
+                if matched < query.len() && c == query[matched] {
+                    score += 1;
+                    if i == 0 || !text[i - 1].is_alphanumeric() {
+                        score += 8;
+                    }
+                    match last {
+                        Some(l) if l + 1 == i => score += 4,
+                        Some(l) => score -= (i - l - 1).min(3) as i32,
+                        None => {}
+                    }
+                    last = Some(i);
+                    matched += 1;
+                }
+            }
+            (matched == query.len()).then_some(score)
+        })
+        .max()
+}
+
+/// Commands whose title matches `query`, best first.
+pub fn palette_matches(query: &str) -> Vec<&'static CommandSpec> {
+    let mut found: Vec<(i32, &'static CommandSpec)> =
+        COMMANDS.iter().filter_map(|c| Some((fuzzy_score(query, c.title)?, c))).collect();
+    found.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    found.into_iter().map(|(_, c)| c).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chords_are_normalized() {
+        assert_eq!(chord("P", "KeyP", true, true, false).as_deref(), Some("Ctrl+Shift+P"));
+        assert_eq!(chord("{", "BracketLeft", true, true, false).as_deref(), Some("Ctrl+Shift+["));
+        assert_eq!(chord("ƒ", "KeyF", false, true, true).as_deref(), Some("Shift+Alt+F"));
+        assert_eq!(chord("q", "KeyA", true, false, false).as_deref(), Some("Ctrl+Q"));
+        assert_eq!(chord(" ", "Space", true, false, false).as_deref(), Some("Ctrl+Space"));
+        assert_eq!(chord("Tab", "Tab", false, true, false).as_deref(), Some("Shift+Tab"));
+        assert_eq!(chord("Shift", "ShiftLeft", false, true, false), None);
+        assert_eq!(parse_chord("cmd+shift+p").as_deref(), Ok("Ctrl+Shift+P"));
+        assert_eq!(parse_chord("alt + pagedown").as_deref(), Ok("Alt+PageDown"));
+        assert_eq!(parse_chord("Ctrl++").as_deref(), Ok("Ctrl++"));
+        assert_eq!(parse_chord("esc").as_deref(), Ok("Escape"));
+        assert_eq!(parse_chord("f12").as_deref(), Ok("F12"));
+        assert_eq!(parse_chord("hyper+x"), Err("Unknown modifier: hyper".to_string()));
+        assert_eq!(parse_chord("ctrl+nope"), Err("Unknown key: nope".to_string()));
+        for spec in COMMANDS {
+            for key in spec.keys {
+                assert_eq!(parse_chord(key).as_deref(), Ok(*key));
+            }
+        }
+    }
+
+    #[test]
+    fn overrides_rebind_and_report_conflicts() {
+        let mut keymap = Keymap::default();
+        assert!(keymap.conflicts().is_empty());
+        assert_eq!(keymap.command_for("Ctrl+B", Scope::Editor).map(|c| c.id), Some("editor.bold"));
+        assert_eq!(keymap.command_for("Ctrl+B", Scope::Global), None);
+
+        keymap.add("view.theme", "Ctrl+B");
+        keymap.remove("file.save", "Ctrl+S");
+        assert_eq!(keymap.command_for("Ctrl+B", Scope::Global).map(|c| c.id), Some("view.theme"));
+        assert_eq!(keymap.command_for("Ctrl+S", Scope::Global), None);
+        assert_eq!(keymap.conflicts(), vec![("Ctrl+B".to_string(), vec!["view.theme", "editor.bold"])]);
+
+        let restored = Keymap::from_json(&keymap.to_json());
+        assert_eq!(restored, keymap);
+        keymap.add("file.save", "Ctrl+S");
+        assert!(keymap.is_default("file.save"));
+        keymap.reset("view.theme");
+        assert!(keymap.conflicts().is_empty());
+
+        let stored = Keymap::from_json(r#"{"export.pdf":["ctrl+alt+p","bogus+x"],"gone":["Ctrl+G"]}"#);
+        assert_eq!(stored.keys("export.pdf"), vec!["Ctrl+Alt+P"]);
+        assert_eq!(stored.keys("gone"), Vec::<String>::new());
+        assert_eq!(Keymap::from_json("not json"), Keymap::default());
+    }
+
+    #[test]
+    fn palette_ranks_fuzzy_matches() {
+        let ids = |q: &str| palette_matches(q).iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(ids("").len(), COMMANDS.len());
+        assert_eq!(ids("pdf")[0], "export.pdf");
+        assert_eq!(ids("togvim"), vec!["editor.toggleVim"]);
+        assert_eq!(ids("tdt")[0], "view.theme");
+        assert_eq!(ids("fnd rep")[0], "editor.find");
+        assert!(ids("zzz").is_empty());
+        assert!(fuzzy_score("ff", "Find in files") > fuzzy_score("ff", "Toggle soft wrap off"));
+    }
+}
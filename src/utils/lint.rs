@@ -0,0 +1,541 @@
+//! Label and reference integrity checks over every project file, the loaded
+//! bibliographies and — when there is one — the compiled document. Typst
+//! only errors on some of these (a missing label), and one at a time; the
+//! rest (unused labels, uncited entries) never surface at all.
+//!
+//! Labels and references are collected from the syntax trees; the compiled
+//! document adds labels and citations produced by code (loops, `label(…)`,
+//! templates) so they aren't reported as missing or unused.
+
+use super::bib_entries::check_bibliography;
+use super::bibliography::BibFile;
+use super::project::{resolve_path, TypstFile};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use typst_syntax::{ast, parse, LinkedNode, SyntaxKind};
+
+/// Where a compiled element is written: the project file (`None` for the
+/// main file) and byte offset.
+pub type ElementSource = (Option<String>, usize);
+
+/// Labels and citations of the compiled document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocLabels {
+    pub labels: Vec<(String, Option<ElementSource>)>,
+    pub cited: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    DuplicateLabel,
+    /// `@name` matching neither a label nor a bibliography entry.
+    MissingTarget,
+    UnusedLabel,
+    /// `cite(<key>)` with a key absent from the loaded bibliographies.
+    MissingCitation,
+    UncitedEntry,
+}
+
+/// What a quick-fix does when applied.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FixAction {
+    /// Replace `range` of project file `file` with `text`.
+    Edit { file: usize, range: Range<usize>, text: String },
+    /// Remove the entry `key` from the bibliography file `bib`.
+    DeleteEntry { bib: String, key: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuickFix {
+    pub title: String,
+    pub action: FixAction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub message: String,
+    /// Project file index and byte offset the warning points at.
+    pub target: Option<(usize, usize)>,
+    /// Bibliography file, for entry warnings.
+    pub bib: Option<String>,
+    pub fix: Option<QuickFix>,
+}
+
+/// A label attached in markup (`<name>` after an element).
+struct LabelDef {
+    name: String,
+    file: usize,
+    /// The `<name>` itself.
+    range: Range<usize>,
+    /// What the "remove" fix deletes: the label and the spaces before it.
+    removal: Range<usize>,
+}
+
+/// A reference to a label or bibliography key.
+struct Reference {
+    target: String,
+    file: usize,
+    /// The span holding the name, replaced by the "did you mean" fix.
+    range: Range<usize>,
+    /// Replacement text for a corrected target `name`.
+    rewrite: fn(&str) -> String,
+    /// `cite(…)` only accepts bibliography keys.
+    citation: bool,
+}
+
+#[derive(Default)]
+struct Collected {
+    defs: Vec<LabelDef>,
+    refs: Vec<Reference>,
+    /// Labels used by code: show rules, `query`, `ref`, …
+    used: HashSet<String>,
+    /// Bibliography paths loaded by `bibliography(…)` calls.
+    loaded: HashSet<String>,
+    /// Whether a bibliography is printed with `full: true`.
+    full_bibliography: bool,
+}
+
+/// Lint the project. `doc` is the compiled document's labels and citations,
+/// if the last compile succeeded.
+pub fn lint_project(files: &[TypstFile], bibs: &[BibFile], doc: Option<&DocLabels>) -> Vec<Lint> {
+    let mut found = Collected::default();
+    for (i, file) in files.iter().enumerate() {
+        let root = parse(&file.content);
+        let ctx = FileCtx {
+            index: i,
+            name: &file.name,
+            text: &file.content,
+        };
+        collect(&LinkedNode::new(&root), &ctx, &mut found);
+    }
+
+    // Keys of the bibliography files the document loads, with their file.
+    let mut bib_keys: HashMap<String, String> = HashMap::new();
+    let loaded: Vec<&BibFile> = bibs.iter().filter(|b| found.loaded.contains(&b.name)).collect();
+    for bib in &loaded {
+        for entry in check_bibliography(&bib.name, &bib.content).entries {
+            bib_keys.entry(entry.key).or_insert_with(|| bib.name.clone());
+        }
+    }
+
+    let file_index = |name: &Option<String>| match name {
+        None => Some(0),
+        Some(name) => files.iter().position(|f| f.name == *name),
+    };
+    // How many compiled elements carry each label, and where the first is.
+    type LabelCount = (usize, Option<(usize, usize)>);
+    let mut doc_counts: HashMap<&str, LabelCount> = HashMap::new();
+    for (name, source) in doc.map(|d| d.labels.as_slice()).unwrap_or_default() {
+        let at = source.as_ref().and_then(|(file, offset)| Some((file_index(file)?, *offset)));
+        let slot = doc_counts.entry(name).or_insert((0, at));
+        slot.0 += 1;
+    }
+
+    let defined: HashSet<&str> = found.defs.iter().map(|d| d.name.as_str()).chain(doc_counts.keys().copied()).collect();
+    let mut lints = Vec::new();
+
+    // Duplicate labels: every markup definition after the first, or a single
+    // definition that code attaches to several elements.
+    let mut by_name: HashMap<&str, Vec<&LabelDef>> = HashMap::new();
+    for def in &found.defs {
+        by_name.entry(&def.name).or_default().push(def);
+    }
+    let mut taken: HashSet<String> = defined.iter().map(|s| s.to_string()).collect();
+    for def in &found.defs {
+        let defs = &by_name[def.name.as_str()];
+        if defs.len() > 1 {
+            if std::ptr::eq(defs[0], def) {
+                continue;
+            }
+            let fresh = fresh_label(&def.name, &mut taken);
+            lints.push(Lint {
+                kind: LintKind::DuplicateLabel,
+                message: format!("Label <{}> is defined {} times", def.name, defs.len()),
+                target: Some((def.file, def.range.start)),
+                bib: None,
+                fix: Some(QuickFix {
+                    title: format!("Rename to <{fresh}>"),
+                    action: FixAction::Edit {
+                        file: def.file,
+                        range: def.range.clone(),
+                        text: format!("<{fresh}>"),
+                    },
+                }),
+            });
+        } else if let Some(&(count @ 2.., _)) = doc_counts.get(def.name.as_str()) {
+            lints.push(Lint {
+                kind: LintKind::DuplicateLabel,
+                message: format!("Label <{}> ends up on {count} elements", def.name),
+                target: Some((def.file, def.range.start)),
+                bib: None,
+                fix: None,
+            });
+        }
+    }
+    let mut generated: Vec<_> = doc_counts
+        .iter()
+        .filter(|(name, (count, _))| *count > 1 && !by_name.contains_key(*name))
+        .collect();
+    generated.sort_by_key(|(name, _)| *name);
+    for (name, (count, at)) in generated {
+        lints.push(Lint {
+            kind: LintKind::DuplicateLabel,
+            message: format!("Label <{name}> ends up on {count} elements"),
+            target: *at,
+            bib: None,
+            fix: None,
+        });
+    }
+
+    // References to nothing, with the closest existing name as a fix.
+    for reference in &found.refs {
+        let name = reference.target.as_str();
+        let (ok, kind, message, candidates): (bool, _, _, Vec<&str>) = if reference.citation {
+            (
+                bib_keys.contains_key(name),
+                LintKind::MissingCitation,
+                format!("Citation key \"{name}\" is not in the bibliography"),
+                bib_keys.keys().map(String::as_str).collect(),
+            )
+        } else {
+            (
+                defined.contains(name) || bib_keys.contains_key(name),
+                LintKind::MissingTarget,
+                format!("@{name} matches no label or bibliography entry"),
+                defined.iter().copied().chain(bib_keys.keys().map(String::as_str)).collect(),
+            )
+        };
+        if ok {
+            continue;
+        }
+        let fix = closest(name, &candidates).map(|best| QuickFix {
+            title: format!("Change to {}", (reference.rewrite)(best)),
+            action: FixAction::Edit {
+                file: reference.file,
+                range: reference.range.clone(),
+                text: (reference.rewrite)(best),
+            },
+        });
+        lints.push(Lint {
+            kind,
+            message,
+            target: Some((reference.file, reference.range.start)),
+            bib: None,
+            fix,
+        });
+    }
+
+    // Labels nothing refers to.
+    let referenced: HashSet<&str> = found
+        .refs
+        .iter()
+        .map(|r| r.target.as_str())
+        .chain(found.used.iter().map(String::as_str))
+        .collect();
+    for def in &found.defs {
+        if referenced.contains(def.name.as_str()) || by_name[def.name.as_str()].len() > 1 {
+            continue;
+        }
+        lints.push(Lint {
+            kind: LintKind::UnusedLabel,
+            message: format!("Label <{}> is never referenced", def.name),
+            target: Some((def.file, def.range.start)),
+            bib: None,
+            fix: Some(QuickFix {
+                title: "Remove label".to_string(),
+                action: FixAction::Edit {
+                    file: def.file,
+                    range: def.removal.clone(),
+                    text: String::new(),
+                },
+            }),
+        });
+    }
+
+    // Entries of loaded bibliographies nobody cites (pointless when the
+    // whole bibliography is printed anyway).
+    if !found.full_bibliography {
+        let cited: HashSet<&str> = referenced
+            .iter()
+            .copied()
+            .chain(doc.iter().flat_map(|d| d.cited.iter().map(String::as_str)))
+            .collect();
+        for bib in &loaded {
+            for entry in check_bibliography(&bib.name, &bib.content).entries {
+                if cited.contains(entry.key.as_str()) || bib_keys.get(&entry.key) != Some(&bib.name) {
+                    continue;
+                }
+                lints.push(Lint {
+                    kind: LintKind::UncitedEntry,
+                    message: format!("Entry \"{}\" is never cited", entry.key),
+                    target: None,
+                    bib: Some(bib.name.clone()),
+                    fix: Some(QuickFix {
+                        title: "Delete entry".to_string(),
+                        action: FixAction::DeleteEntry {
+                            bib: bib.name.clone(),
+                            key: entry.key,
+                        },
+                    }),
+                });
+            }
+        }
+    }
+
+    lints
+}
+
+/// The project file being collected from.
+struct FileCtx<'a> {
+    index: usize,
+    name: &'a str,
+    text: &'a str,
+}
+
+fn collect(node: &LinkedNode, ctx: &FileCtx, out: &mut Collected) {
+    match node.kind() {
+        SyntaxKind::Label => {
+            let label = node.cast::<ast::Label>().map(|l| l.get().to_string()).unwrap_or_default();
+            if node.parent_kind() == Some(SyntaxKind::Markup) {
+                let range = node.range();
+                // Also drop the spaces separating the label from its element.
+                let start = ctx.text[..range.start].trim_end_matches([' ', '\t']).len();
+                out.defs.push(LabelDef {
+                    name: label,
+                    file: ctx.index,
+                    removal: start..range.end,
+                    range,
+                });
+            } else {
+                out.used.insert(label);
+            }
+        }
+        SyntaxKind::Ref => {
+            if let Some(reference) = node.cast::<ast::Ref>() {
+                let marker = node.children().next().map(|m| m.range()).unwrap_or(node.range());
+                out.refs.push(Reference {
+                    target: reference.target().to_string(),
+                    file: ctx.index,
+                    range: marker,
+                    rewrite: |name| format!("@{name}"),
+                    citation: false,
+                });
+            }
+        }
+        SyntaxKind::FuncCall => {
+            if let Some(call) = node.cast::<ast::FuncCall>() {
+                collect_call(node, call, ctx, out);
+            }
+        }
+        _ => {}
+    }
+    for child in node.children() {
+        collect(&child, ctx, out);
+    }
+}
+
+/// `cite(<key>)`, `ref(<name>)` and `bibliography(…)` calls.
+fn collect_call(node: &LinkedNode, call: ast::FuncCall, ctx: &FileCtx, out: &mut Collected) {
+    let ast::Expr::Ident(callee) = call.callee() else {
+        return;
+    };
+    let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) else {
+        return;
+    };
+    let first = args.children().find(|c| c.cast::<ast::Expr>().is_some());
+    match callee.as_str() {
+        "cite" | "ref" => {
+            let Some(first) = first else { return };
+            let (target, rewrite): (String, fn(&str) -> String) = match first.cast::<ast::Expr>() {
+                Some(ast::Expr::Label(label)) => (label.get().to_string(), |name| format!("<{name}>")),
+                // `label("…")` for names that aren't valid label syntax.
+                Some(ast::Expr::FuncCall(inner))
+                    if matches!(inner.callee(), ast::Expr::Ident(id) if id.as_str() == "label") =>
+                {
+                    let Some(ast::Arg::Pos(ast::Expr::Str(s))) = inner.args().items().next() else {
+                        return;
+                    };
+                    (s.get().to_string(), |name| format!("label({name:?})"))
+                }
+                _ => return,
+            };
+            out.refs.push(Reference {
+                target,
+                file: ctx.index,
+                range: first.range(),
+                rewrite,
+                citation: callee.as_str() == "cite",
+            });
+        }
+        "bibliography" => {
+            for arg in call.args().items() {
+                match arg {
+                    ast::Arg::Pos(ast::Expr::Str(path)) => {
+                        out.loaded.extend(resolve_path(ctx.name, &path.get()));
+                    }
+                    ast::Arg::Pos(ast::Expr::Array(paths)) => {
+                        for item in paths.items() {
+                            if let ast::ArrayItem::Pos(ast::Expr::Str(path)) = item {
+                                out.loaded.extend(resolve_path(ctx.name, &path.get()));
+                            }
+                        }
+                    }
+                    ast::Arg::Named(named) if named.name().as_str() == "full" => {
+                        out.full_bibliography |= matches!(named.expr(), ast::Expr::Bool(b) if b.get());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `name-2`, `name-3`, … — the first that isn't taken (and now is).
+fn fresh_label(name: &str, taken: &mut HashSet<String>) -> String {
+    let fresh = (2..)
+        .map(|n| format!("{name}-{n}"))
+        .find(|candidate| !taken.contains(candidate))
+        .expect("unbounded range");
+    taken.insert(fresh.clone());
+    fresh
+}
+
+/// The candidate closest to `name` by edit distance, if close enough to be a
+/// plausible typo.
+fn closest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(2);
+    candidates
+        .iter()
+        .map(|c| (edit_distance(name, c), *c))
+        .filter(|(d, _)| *d <= limit)
+        .min()
+        .map(|(_, c)| c)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (diag + usize::from(ca != *cb)).min(row[j] + 1).min(row[j + 1] + 1);
+            diag = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFS: &str = "knuth:\n  type: book\n  title: TAOCP\n  author: Knuth, Donald\n  date: 1968\n\
+                        lamport:\n  type: book\n  title: LaTeX\n  author: Lamport, Leslie\n  date: 1986\n\
+                        shannon:\n  type: article\n  title: Communication\n  author: Shannon, Claude\n  date: 1948\n";
+
+    fn project(main: &str) -> (Vec<TypstFile>, Vec<BibFile>) {
+        let files = vec![TypstFile {
+            name: "main.typ".to_string(),
+            content: main.to_string(),
+        }];
+        let bibs = vec![
+            BibFile {
+                name: "refs.yml".to_string(),
+                content: REFS.to_string(),
+            },
+            // Not loaded by the document, so never linted.
+            BibFile {
+                name: "old.yml".to_string(),
+                content: "stale:\n  type: book\n  title: Old\n".to_string(),
+            },
+        ];
+        (files, bibs)
+    }
+
+    /// The text of `files[0]` after applying `fix`.
+    fn fixed(files: &[TypstFile], fix: &QuickFix) -> String {
+        let FixAction::Edit { file, range, text } = &fix.action else {
+            panic!("not an edit: {fix:?}");
+        };
+        let mut content = files[*file].content.clone();
+        content.replace_range(range.clone(), text);
+        content
+    }
+
+    #[test]
+    fn reports_every_kind_with_fixes() {
+        let (files, bibs) = project(
+            "#bibliography(\"refs.yml\")\n= Intro <intro>\nSee @intro, @fig:plot and @knuht. @shannon\n\
+             #figure(rect(), caption: [A]) <fig:plt>\n= Again <intro>\n#cite(<lamprt>)\n\
+             #show <styled>: set text(red)\nText <styled>\n= Unused <unused>\n",
+        );
+        let lints = lint_project(&files, &bibs, None);
+        let summary: Vec<_> = lints
+            .iter()
+            .map(|l| (l.kind, l.message.as_str(), l.fix.as_ref().map(|f| f.title.as_str())))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (LintKind::DuplicateLabel, "Label <intro> is defined 2 times", Some("Rename to <intro-2>")),
+                (LintKind::MissingTarget, "@fig:plot matches no label or bibliography entry", Some("Change to @fig:plt")),
+                (LintKind::MissingTarget, "@knuht matches no label or bibliography entry", Some("Change to @knuth")),
+                (LintKind::MissingCitation, "Citation key \"lamprt\" is not in the bibliography", Some("Change to <lamport>")),
+                (LintKind::UnusedLabel, "Label <fig:plt> is never referenced", Some("Remove label")),
+                (LintKind::UnusedLabel, "Label <unused> is never referenced", Some("Remove label")),
+                (LintKind::UncitedEntry, "Entry \"knuth\" is never cited", Some("Delete entry")),
+                (LintKind::UncitedEntry, "Entry \"lamport\" is never cited", Some("Delete entry")),
+            ]
+        );
+
+        let main = &files[0].content;
+        assert_eq!(lints[0].target, Some((0, main.rfind("<intro>").unwrap())));
+        assert!(fixed(&files, lints[0].fix.as_ref().unwrap()).contains("= Again <intro-2>\n"));
+        assert!(fixed(&files, lints[2].fix.as_ref().unwrap()).contains("and @knuth. @shannon"));
+        assert!(fixed(&files, lints[3].fix.as_ref().unwrap()).contains("#cite(<lamport>)"));
+        assert!(fixed(&files, lints[5].fix.as_ref().unwrap()).contains("\n= Unused\n"));
+        assert_eq!(
+            lints[6].fix.as_ref().unwrap().action,
+            FixAction::DeleteEntry {
+                bib: "refs.yml".to_string(),
+                key: "knuth".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn compiled_labels_and_citations_count() {
+        let (files, bibs) = project(
+            "#bibliography(\"refs.yml\", full: true)\n#for i in range(2) [#figure(rect()) <loop>]\n\
+             See @loop, @gen and #cite(label(\"knuth\")).\n",
+        );
+        // Without the document, the generated label looks missing.
+        let lints = lint_project(&files, &bibs, None);
+        assert_eq!(
+            lints.iter().map(|l| l.message.as_str()).collect::<Vec<_>>(),
+            vec!["@gen matches no label or bibliography entry"]
+        );
+
+        let doc = DocLabels {
+            labels: vec![
+                ("loop".to_string(), Some((None, 60))),
+                ("loop".to_string(), Some((None, 60))),
+                ("gen".to_string(), None),
+                ("gen".to_string(), None),
+            ],
+            cited: vec!["knuth".to_string()],
+        };
+        let lints = lint_project(&files, &bibs, Some(&doc));
+        assert_eq!(
+            lints.iter().map(|l| (l.message.as_str(), l.fix.is_some())).collect::<Vec<_>>(),
+            vec![
+                ("Label <loop> ends up on 2 elements", false),
+                ("Label <gen> ends up on 2 elements", false),
+            ]
+        );
+    }
+}
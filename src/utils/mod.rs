@@ -1,11 +1,30 @@
+pub mod assets;
+pub mod bib_convert;
+pub mod bib_entries;
+pub mod bibliography;
+pub mod carets;
+pub mod citations;
+pub mod csl;
 pub mod download;
 pub mod editing;
+pub mod folding;
+pub mod format;
+pub mod font_storage;
 pub mod highlight;
 pub mod image_storage;
 pub mod image_manager;
+pub mod keymap;
+pub mod import;
+pub mod lint;
+pub mod outline;
 pub mod package_storage;
 pub mod project;
+pub mod qr;
+pub mod rename;
+pub mod search;
 pub mod share;
+pub mod stats;
+pub mod vim;
+pub mod wrap;
 
-pub use download::download_bytes;
-pub use highlight::highlight_typst;
+pub use download::{download_bytes, download_svg_as_png};
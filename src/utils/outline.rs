@@ -0,0 +1,93 @@
+//! Document outline entries for the outline panel, plus the syntax-only
+//! fallback used before the first successful compile (or while the project
+//! doesn't compile): `= Heading` markup across every project file.
+
+use super::project::TypstFile;
+use typst_syntax::ast::AstNode;
+use typst_syntax::{ast, parse, LinkedNode, SyntaxKind};
+
+/// One heading in the outline panel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineEntry {
+    pub level: usize,
+    pub text: String,
+    /// 1-based page, known once the document has compiled.
+    pub page: Option<usize>,
+    /// Project file index and byte offset to jump the editor to.
+    pub target: Option<(usize, usize)>,
+}
+
+/// Headings written as `=` markup in `files`, main file first, then the
+/// others in tab order.
+pub fn syntax_outline(files: &[TypstFile]) -> Vec<OutlineEntry> {
+    fn visit(node: &LinkedNode, file: usize, out: &mut Vec<OutlineEntry>) {
+        if let Some(heading) = node.cast::<ast::Heading>() {
+            let mut text = String::new();
+            plain_text(heading.body().to_untyped(), &mut text);
+            out.push(OutlineEntry {
+                level: heading.depth().get(),
+                text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+                page: None,
+                target: Some((file, node.offset())),
+            });
+            return;
+        }
+        for child in node.children() {
+            visit(&child, file, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        let root = parse(&file.content);
+        visit(&LinkedNode::new(&root), i, &mut out);
+    }
+    out
+}
+
+/// The visible text of heading markup: emphasis/strong markers, labels and
+/// code are dropped.
+pub(crate) fn plain_text(node: &typst_syntax::SyntaxNode, out: &mut String) {
+    match node.kind() {
+        SyntaxKind::Text | SyntaxKind::SmartQuote | SyntaxKind::Shorthand => out.push_str(node.text()),
+        SyntaxKind::Space => out.push(' '),
+        SyntaxKind::Escape => out.push_str(node.text().trim_start_matches('\\')),
+        SyntaxKind::Label | SyntaxKind::FuncCall | SyntaxKind::Hash => {}
+        _ => {
+            for child in node.children() {
+                plain_text(child, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, content: &str) -> TypstFile {
+        TypstFile {
+            name: name.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn headings_across_files_with_plain_text() {
+        let files = [
+            file("main.typ", "= Intro <intro>\nText\n== Why *Typst*?\n```\n= not a heading\n```\n"),
+            file("chapter.typ", "#let x = 1\n=== Deep \\#1\n"),
+        ];
+        let outline = syntax_outline(&files);
+        let summary: Vec<_> = outline.iter().map(|e| (e.level, e.text.as_str(), e.target)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "Intro", Some((0, 0))),
+                (2, "Why Typst?", Some((0, 21))),
+                (3, "Deep #1", Some((1, 11))),
+            ]
+        );
+        assert!(outline.iter().all(|e| e.page.is_none()));
+    }
+}
@@ -16,9 +16,15 @@ pub struct PackageStorage {
 
 impl PackageStorage {
     pub fn new() -> Self {
+        Self::with_database("typst_studio_packages", "packages")
+    }
+
+    /// The same key → raw-bytes store in another database, for other binary
+    /// caches (see [`crate::utils::font_storage`]).
+    pub fn with_database(db_name: &str, store_name: &str) -> Self {
         Self {
-            db_name: "typst_studio_packages".to_string(),
-            store_name: "packages".to_string(),
+            db_name: db_name.to_string(),
+            store_name: store_name.to_string(),
         }
     }
 
@@ -98,6 +104,25 @@ impl PackageStorage {
         Ok(())
     }
 
+    /// Remove the entry stored under `key` (a no-op if there is none).
+    pub async fn delete(&self, key: &str) -> Result<(), String> {
+        let db = self.init().await?;
+        let transaction = db
+            .transaction_with_str_and_mode(&self.store_name, IdbTransactionMode::Readwrite)
+            .map_err(|e| format!("Failed to create transaction: {e:?}"))?;
+        let store = transaction
+            .object_store(&self.store_name)
+            .map_err(|e| format!("Failed to get object store: {e:?}"))?;
+
+        let request = store
+            .delete(&JsValue::from_str(key))
+            .map_err(|e| format!("Failed to delete entry: {e:?}"))?;
+        JsFuture::from(Self::request_to_promise(&request))
+            .await
+            .map_err(|e| format!("Failed to delete entry: {e:?}"))?;
+        Ok(())
+    }
+
     /// List every cached package as `(spec_key, tarball_bytes)`.
     pub async fn list_all(&self) -> Result<Vec<(String, Vec<u8>)>, String> {
         let db = self.init().await?;
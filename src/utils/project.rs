@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 /// A single editable project file. The file at index 0 is the compilation entry
 /// point ("main"); the others are served to the compiler as `extra_files` so the
 /// main file can reach them via `#include` / `#import`.
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TypstFile {
     pub name: String,
     pub content: String,
@@ -29,3 +29,37 @@ pub fn save_files(files: &[TypstFile]) {
         let _ = storage.set_item(FILES_KEY, &json);
     }
 }
+
+/// Project path of `path` as written in the file `from`: relative to that
+/// file's directory, or to the project root when it starts with `/`. `None`
+/// if the path climbs out of the project, which Typst refuses too.
+pub fn resolve_path(from: &str, path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = match path.strip_prefix('/') {
+        Some(_) => Vec::new(),
+        None => from.rsplit_once('/').map(|(dir, _)| dir.split('/').collect()).unwrap_or_default(),
+    };
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            _ => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_relative_and_rooted_paths() {
+        assert_eq!(resolve_path("main.typ", "fig/a.png").as_deref(), Some("fig/a.png"));
+        assert_eq!(resolve_path("ch/one.typ", "../fig/./a.png").as_deref(), Some("fig/a.png"));
+        assert_eq!(resolve_path("ch/one.typ", "/fig/a.png").as_deref(), Some("fig/a.png"));
+        assert_eq!(resolve_path("main.typ", "../outside.png"), None);
+        assert_eq!(resolve_path("ch/one.typ", "../../outside.png"), None);
+    }
+}
@@ -0,0 +1,699 @@
+//! Minimal QR Code encoder (ISO/IEC 18004, model 2) for share links.
+//!
+//! Only byte mode is implemented — share URLs are arbitrary UTF-8 — which keeps
+//! the encoder small: version selection, Reed–Solomon error correction over
+//! GF(256), module placement, and mask selection by the standard penalty rules.
+//! Pure Rust with no DOM access, so it is unit-tested on the host.
+
+/// Error-correction level: the share of codewords that can be restored.
+/// Higher levels survive more damage (smudged print) but hold less data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EcLevel {
+    Low,
+    #[default]
+    Medium,
+    Quartile,
+    High,
+}
+
+impl EcLevel {
+    pub const ALL: [EcLevel; 4] = [EcLevel::Low, EcLevel::Medium, EcLevel::Quartile, EcLevel::High];
+
+    /// Short UI label with the nominal recovery capacity.
+    pub fn label(self) -> &'static str {
+        match self {
+            EcLevel::Low => "L · 7%",
+            EcLevel::Medium => "M · 15%",
+            EcLevel::Quartile => "Q · 25%",
+            EcLevel::High => "H · 30%",
+        }
+    }
+
+    /// Row index into the block tables.
+    fn ordinal(self) -> usize {
+        self as usize
+    }
+
+    /// The 2-bit value written into the format information.
+    fn format_bits(self) -> u32 {
+        match self {
+            EcLevel::Low => 1,
+            EcLevel::Medium => 0,
+            EcLevel::Quartile => 3,
+            EcLevel::High => 2,
+        }
+    }
+}
+
+const MIN_VERSION: usize = 1;
+const MAX_VERSION: usize = 40;
+
+// Error-correction codewords per block, indexed by [level][version] (index 0 unused).
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28],
+    [0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30, 30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+];
+
+// Number of error-correction blocks, indexed by [level][version] (index 0 unused).
+const NUM_ERROR_CORRECTION_BLOCKS: [[u8; 41]; 4] = [
+    [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25],
+    [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49],
+    [0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29, 34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68],
+    [0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32, 35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81],
+];
+
+// Penalty weights used when choosing the mask pattern.
+const PENALTY_N1: i32 = 3;
+const PENALTY_N2: i32 = 3;
+const PENALTY_N3: i32 = 40;
+const PENALTY_N4: i32 = 10;
+
+/// An encoded QR symbol: a square grid of dark (`true`) and light modules.
+#[derive(Clone, Debug)]
+pub struct QrCode {
+    version: usize,
+    size: usize,
+    modules: Vec<bool>,
+    /// Modules belonging to function patterns (finders, timing, format…),
+    /// which masking and data placement must skip.
+    is_function: Vec<bool>,
+}
+
+/// Largest byte payload a QR code can hold at `level` (version 40).
+pub fn max_bytes(level: EcLevel) -> usize {
+    byte_capacity(MAX_VERSION, level)
+}
+
+/// Encode `data` in byte mode at the smallest version that fits.
+///
+/// Fails with a readable message when the data exceeds the version-40
+/// capacity for the chosen level.
+pub fn encode(data: &[u8], level: EcLevel) -> Result<QrCode, String> {
+    let version = (MIN_VERSION..=MAX_VERSION)
+        .find(|&v| data.len() <= byte_capacity(v, level))
+        .ok_or_else(|| {
+            format!(
+                "Too long for a QR code: {} bytes, the maximum at level {} is {}",
+                data.len(),
+                level.label(),
+                max_bytes(level)
+            )
+        })?;
+
+    // Segment: mode indicator, character count, then the raw bytes.
+    let mut bits = BitBuffer::default();
+    bits.push(0b0100, 4);
+    bits.push(data.len() as u32, count_bits(version));
+    for &b in data {
+        bits.push(u32::from(b), 8);
+    }
+
+    // Terminator, byte alignment, then alternating pad codewords.
+    let capacity_bits = num_data_codewords(version, level) * 8;
+    let terminator = (capacity_bits - bits.len()).min(4);
+    bits.push(0, terminator as u8);
+    let align = (8 - bits.len() % 8) % 8;
+    bits.push(0, align as u8);
+    let mut codewords = bits.into_bytes();
+    for &pad in [0xEC, 0x11].iter().cycle() {
+        if codewords.len() * 8 >= capacity_bits {
+            break;
+        }
+        codewords.push(pad);
+    }
+
+    let all_codewords = add_ecc_and_interleave(&codewords, version, level);
+    Ok(QrCode::from_codewords(version, level, &all_codewords))
+}
+
+impl QrCode {
+    /// Symbol version (1–40); the side length is `4 * version + 17`.
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// Side length in modules.
+    #[cfg(test)]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module at column `x`, row `y` is dark. Out of range is light.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+
+    /// Render as a standalone SVG (black on white) with a quiet zone of
+    /// `border` modules. Each module is drawn 8 px wide so the intrinsic size
+    /// rasterizes crisply.
+    pub fn to_svg(&self, border: usize) -> String {
+        let dim = self.size + 2 * border;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.get(x, y) {
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + border, y + border));
+                }
+            }
+        }
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" viewBox=\"0 0 {dim} {dim}\" width=\"{px}\" height=\"{px}\" shape-rendering=\"crispEdges\"><rect width=\"{dim}\" height=\"{dim}\" fill=\"#FFFFFF\"/><path d=\"{path}\" fill=\"#000000\"/></svg>",
+            px = dim * 8
+        )
+    }
+
+    fn from_codewords(version: usize, level: EcLevel, codewords: &[u8]) -> Self {
+        let size = version * 4 + 17;
+        let mut qr = QrCode {
+            version,
+            size,
+            modules: vec![false; size * size],
+            is_function: vec![false; size * size],
+        };
+        qr.draw_function_patterns(level);
+        qr.draw_codewords(codewords);
+
+        // Try every mask and keep the one with the lowest penalty. Masking is
+        // an XOR, so applying the same mask again undoes it.
+        let mut best = (0u8, i32::MAX);
+        for mask in 0..8u8 {
+            qr.apply_mask(mask);
+            qr.draw_format_bits(level, mask);
+            let penalty = qr.penalty_score();
+            if penalty < best.1 {
+                best = (mask, penalty);
+            }
+            qr.apply_mask(mask);
+        }
+        qr.apply_mask(best.0);
+        qr.draw_format_bits(level, best.0);
+        qr
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        let idx = y * self.size + x;
+        self.modules[idx] = dark;
+        self.is_function[idx] = true;
+    }
+
+    fn draw_function_patterns(&mut self, level: EcLevel) {
+        let size = self.size;
+        // Timing patterns.
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        // Finder patterns (with separators) in three corners.
+        self.draw_finder(3, 3);
+        self.draw_finder(size - 4, 3);
+        self.draw_finder(3, size - 4);
+        // Alignment patterns, except where they would overlap the finders.
+        let positions = self.alignment_positions();
+        let n = positions.len();
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                let corner = (i == 0 && j == 0) || (i == 0 && j == n - 1) || (i == n - 1 && j == 0);
+                if !corner {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+        // Reserve the format areas (overwritten once the mask is chosen).
+        self.draw_format_bits(level, 0);
+        self.draw_version();
+    }
+
+    fn draw_finder(&mut self, cx: usize, cy: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let dist = dx.abs().max(dy.abs());
+                let x = cx as i32 + dx;
+                let y = cy as i32 + dy;
+                if (0..self.size as i32).contains(&x) && (0..self.size as i32).contains(&y) {
+                    self.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, cx: usize, cy: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function((cx as i32 + dx) as usize, (cy as i32 + dy) as usize, dark);
+            }
+        }
+    }
+
+    /// Centre coordinates of the alignment patterns along each axis.
+    fn alignment_positions(&self) -> Vec<usize> {
+        if self.version == 1 {
+            return Vec::new();
+        }
+        let count = self.version / 7 + 2;
+        let step = if self.version == 32 {
+            26
+        } else {
+            (self.version * 4 + count * 2 + 1) / (count * 2 - 2) * 2
+        };
+        let mut positions: Vec<usize> = (0..count - 1).map(|i| self.size - 7 - i * step).collect();
+        positions.push(6);
+        positions.reverse();
+        positions
+    }
+
+    fn draw_format_bits(&mut self, level: EcLevel, mask: u8) {
+        let bits = format_bits(level, mask);
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+        // First copy, around the top-left finder.
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        // Second copy, split between the other two finders.
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // The always-dark module.
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let bits = version_bits(self.version);
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Place the codeword bits in the zig-zag column-pair order, skipping
+    /// function modules and the vertical timing column.
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let total_bits = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = size as i32 - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vert } else { vert };
+                    let idx = y * size + x;
+                    if !self.is_function[idx] && i < total_bits {
+                        self.modules[idx] = (codewords[i >> 3] >> (7 - (i & 7))) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let idx = y * self.size + x;
+                if invert && !self.is_function[idx] {
+                    self.modules[idx] = !self.modules[idx];
+                }
+            }
+        }
+    }
+
+    /// Standard mask penalty: long runs, 2×2 blocks, finder-like patterns and
+    /// dark/light imbalance.
+    fn penalty_score(&self) -> i32 {
+        let size = self.size;
+        let mut result = 0;
+        for transpose in [false, true] {
+            for a in 0..size {
+                let mut run_color = false;
+                let mut run_len = 0i32;
+                let mut history = [0i32; 7];
+                for b in 0..size {
+                    let dark = if transpose { self.get(a, b) } else { self.get(b, a) };
+                    if dark == run_color {
+                        run_len += 1;
+                        if run_len == 5 {
+                            result += PENALTY_N1;
+                        } else if run_len > 5 {
+                            result += 1;
+                        }
+                    } else {
+                        self.add_run_history(run_len, &mut history);
+                        if !run_color {
+                            result += self.count_finder_patterns(&history) * PENALTY_N3;
+                        }
+                        run_color = dark;
+                        run_len = 1;
+                    }
+                }
+                result += self.terminate_runs(run_color, run_len, &mut history) * PENALTY_N3;
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let c = self.get(x, y);
+                if c == self.get(x + 1, y) && c == self.get(x, y + 1) && c == self.get(x + 1, y + 1) {
+                    result += PENALTY_N2;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|&&m| m).count() as i32;
+        let total = (size * size) as i32;
+        let k = ((dark * 20 - total * 10).abs() + total - 1) / total - 1;
+        result + k * PENALTY_N4
+    }
+
+    fn add_run_history(&self, mut run_len: i32, history: &mut [i32; 7]) {
+        if history[0] == 0 {
+            // The light border outside the symbol counts as part of the first run.
+            run_len += self.size as i32;
+        }
+        history.rotate_right(1);
+        history[0] = run_len;
+    }
+
+    /// Count 1:1:3:1:1 finder-like patterns with 4 light modules on either side.
+    fn count_finder_patterns(&self, history: &[i32; 7]) -> i32 {
+        let n = history[1];
+        let core = n > 0
+            && history[2] == n
+            && history[3] == n * 3
+            && history[4] == n
+            && history[5] == n;
+        i32::from(core && history[0] >= n * 4 && history[6] >= n)
+            + i32::from(core && history[6] >= n * 4 && history[0] >= n)
+    }
+
+    fn terminate_runs(&self, run_color: bool, mut run_len: i32, history: &mut [i32; 7]) -> i32 {
+        if run_color {
+            self.add_run_history(run_len, history);
+            run_len = 0;
+        }
+        run_len += self.size as i32;
+        self.add_run_history(run_len, history);
+        self.count_finder_patterns(history)
+    }
+}
+
+/// Append-only big-endian bit sequence.
+#[derive(Default)]
+struct BitBuffer {
+    bits: Vec<bool>,
+}
+
+impl BitBuffer {
+    fn push(&mut self, value: u32, len: u8) {
+        for i in (0..len).rev() {
+            self.bits.push((value >> i) & 1 != 0);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.bits
+            .chunks(8)
+            .map(|chunk| chunk.iter().fold(0u8, |acc, &b| (acc << 1) | u8::from(b)))
+            .collect()
+    }
+}
+
+/// Width of the byte-mode character count field.
+fn count_bits(version: usize) -> u8 {
+    if version <= 9 {
+        8
+    } else {
+        16
+    }
+}
+
+/// Modules available for data + ECC codewords (everything but function patterns).
+fn num_raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let num_align = version / 7 + 2;
+        result -= (25 * num_align - 10) * num_align - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn num_data_codewords(version: usize, level: EcLevel) -> usize {
+    let l = level.ordinal();
+    num_raw_data_modules(version) / 8
+        - usize::from(ECC_CODEWORDS_PER_BLOCK[l][version])
+            * usize::from(NUM_ERROR_CORRECTION_BLOCKS[l][version])
+}
+
+/// Bytes that fit in one byte-mode segment at `version` / `level`.
+fn byte_capacity(version: usize, level: EcLevel) -> usize {
+    let header = 4 + usize::from(count_bits(version));
+    (num_data_codewords(version, level) * 8).saturating_sub(header) / 8
+}
+
+/// 15-bit format information: level + mask, BCH(15,5) protected and masked.
+fn format_bits(level: EcLevel, mask: u8) -> u32 {
+    let data = level.format_bits() << 3 | u32::from(mask);
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    ((data << 10) | rem) ^ 0x5412
+}
+
+/// 18-bit version information: version, BCH(18,6) protected.
+fn version_bits(version: usize) -> u32 {
+    let mut rem = version as u32;
+    for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+    }
+    (version as u32) << 12 | rem
+}
+
+/// Split the data codewords into blocks, append each block's ECC and
+/// interleave them column-wise as the standard prescribes.
+fn add_ecc_and_interleave(data: &[u8], version: usize, level: EcLevel) -> Vec<u8> {
+    let l = level.ordinal();
+    let num_blocks = usize::from(NUM_ERROR_CORRECTION_BLOCKS[l][version]);
+    let ecc_len = usize::from(ECC_CODEWORDS_PER_BLOCK[l][version]);
+    let raw_codewords = num_raw_data_modules(version) / 8;
+    let num_short_blocks = num_blocks - raw_codewords % num_blocks;
+    let short_block_len = raw_codewords / num_blocks;
+
+    let divisor = reed_solomon_divisor(ecc_len);
+    let mut blocks = Vec::with_capacity(num_blocks);
+    let mut k = 0;
+    for i in 0..num_blocks {
+        let data_len = short_block_len - ecc_len + usize::from(i >= num_short_blocks);
+        let mut block = data[k..k + data_len].to_vec();
+        k += data_len;
+        let ecc = reed_solomon_remainder(&block, &divisor);
+        if i < num_short_blocks {
+            // Placeholder so all blocks have the same length; skipped below.
+            block.push(0);
+        }
+        block.extend(ecc);
+        blocks.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..=short_block_len {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_block_len - ecc_len || j >= num_short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+/// Coefficients of the RS generator polynomial of the given degree (the
+/// leading 1 is implicit), highest power first.
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &b in data {
+        let factor = b ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    result
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z = 0u8;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x1D);
+        z ^= ((y >> i) & 1) * x;
+    }
+    z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity_matches_standard_tables() {
+        // Byte-mode capacities from ISO/IEC 18004 Table 7.
+        assert_eq!(byte_capacity(1, EcLevel::Low), 17);
+        assert_eq!(byte_capacity(1, EcLevel::High), 7);
+        assert_eq!(byte_capacity(10, EcLevel::Medium), 213);
+        assert_eq!(max_bytes(EcLevel::Low), 2953);
+        assert_eq!(max_bytes(EcLevel::Medium), 2331);
+        assert_eq!(max_bytes(EcLevel::Quartile), 1663);
+        assert_eq!(max_bytes(EcLevel::High), 1273);
+    }
+
+    #[test]
+    fn reed_solomon_matches_reference_block() {
+        // "HELLO WORLD" at 1-M (alphanumeric), the worked example from the spec annex.
+        let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+        let ecc = reed_solomon_remainder(&data, &reed_solomon_divisor(10));
+        assert_eq!(ecc, [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+    }
+
+    #[test]
+    fn format_and_version_bits_match_reference() {
+        assert_eq!(format_bits(EcLevel::Medium, 0), 0b101010000010010);
+        assert_eq!(format_bits(EcLevel::Low, 4), 0b110011000101111);
+        assert_eq!(format_bits(EcLevel::Quartile, 0), 0b011010101011111);
+        assert_eq!(format_bits(EcLevel::High, 0), 0b001011010001001);
+        assert_eq!(version_bits(7), 0b000111110010010100);
+    }
+
+    #[test]
+    fn picks_smallest_version_and_draws_finders() {
+        let qr = encode(b"https://example.com", EcLevel::Medium).unwrap();
+        assert_eq!(qr.version(), 2);
+        assert_eq!(qr.size(), 25);
+        // Finder pattern: dark outer ring, light ring, dark 3×3 core.
+        for (cx, cy) in [(3, 3), (qr.size() - 4, 3), (3, qr.size() - 4)] {
+            assert!(qr.get(cx, cy));
+            assert!(qr.get(cx - 3, cy - 3));
+            assert!(!qr.get(cx - 2, cy));
+            assert!(!qr.get(cx + 4, cy) || cx + 4 >= qr.size());
+        }
+        // Dark module next to the bottom-left finder.
+        assert!(qr.get(8, qr.size() - 8));
+    }
+
+    #[test]
+    fn format_info_copies_agree_and_decode() {
+        let qr = encode(b"format check", EcLevel::Quartile).unwrap();
+        let size = qr.size();
+        let mut first = 0u32;
+        let mut second = 0u32;
+        let first_pos: Vec<(usize, usize)> = (0..=5)
+            .map(|i| (8, i))
+            .chain([(8, 7), (8, 8), (7, 8)])
+            .chain((9..15).map(|i| (14 - i, 8)))
+            .collect();
+        let second_pos: Vec<(usize, usize)> = (0..8)
+            .map(|i| (size - 1 - i, 8))
+            .chain((8..15).map(|i| (8, size - 15 + i)))
+            .collect();
+        for (i, (&(x1, y1), &(x2, y2))) in first_pos.iter().zip(&second_pos).enumerate() {
+            first |= u32::from(qr.get(x1, y1)) << i;
+            second |= u32::from(qr.get(x2, y2)) << i;
+        }
+        assert_eq!(first, second);
+        let mask = (0..8u8)
+            .find(|&m| format_bits(EcLevel::Quartile, m) == first)
+            .expect("format bits encode level Q with some mask");
+        assert!(mask < 8);
+    }
+
+    #[test]
+    fn ecc_makes_blocks_divisible_by_generator() {
+        let data = vec![0x5A; 15];
+        let divisor = reed_solomon_divisor(30);
+        let mut codeword = data.clone();
+        codeword.extend(reed_solomon_remainder(&data, &divisor));
+        assert!(reed_solomon_remainder(&codeword, &divisor).iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn interleaving_fills_every_codeword() {
+        for level in EcLevel::ALL {
+            for version in [1, 5, 10, 27, 40] {
+                let data = vec![0xA5; num_data_codewords(version, level)];
+                let all = add_ecc_and_interleave(&data, version, level);
+                assert_eq!(all.len(), num_raw_data_modules(version) / 8);
+            }
+        }
+    }
+
+    #[test]
+    fn too_long_input_reports_capacity() {
+        let data = vec![b'a'; max_bytes(EcLevel::High) + 1];
+        let err = encode(&data, EcLevel::High).unwrap_err();
+        assert!(err.contains("1273"), "{err}");
+        assert!(encode(&data, EcLevel::Low).is_ok());
+    }
+
+    #[test]
+    fn svg_is_standalone_and_square() {
+        let qr = encode(b"svg", EcLevel::Low).unwrap();
+        let svg = qr.to_svg(4);
+        let dim = qr.size() + 8;
+        assert!(svg.contains("xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains(&format!("viewBox=\"0 0 {dim} {dim}\"")));
+        assert!(svg.contains("M4,4h1v1h-1z"), "top-left finder corner is dark");
+    }
+}
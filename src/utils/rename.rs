@@ -0,0 +1,770 @@
+//! Project-wide "Rename symbol" for `#let` bindings, function parameters and
+//! labels. Bindings are resolved through the syntax trees with Typst's
+//! scoping rules — blocks, closures, loops and imports between project
+//! files — so a shadowing re-binding, a string or a comment that happens to
+//! spell the same name is left alone. Labels are project-global: every
+//! `<name>` and `@name` changes together.
+
+use super::project::{resolve_path, TypstFile};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use typst_syntax::ast::{self, AstNode};
+use typst_syntax::{is_ident, is_valid_label_literal_id, LinkedNode, Side, Source, SyntaxKind, SyntaxNode};
+
+/// Words that look like identifiers but can't name a binding.
+const KEYWORDS: &[&str] = &[
+    "none", "auto", "true", "false", "not", "and", "or", "let", "set", "show", "context", "if", "else", "for",
+    "in", "while", "break", "continue", "return", "import", "include", "as",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// A variable, function or parameter.
+    Binding,
+    Label,
+}
+
+/// One replacement in project file `file`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenameEdit {
+    pub file: usize,
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenamePlan {
+    pub kind: SymbolKind,
+    pub old: String,
+    pub new: String,
+    /// Sorted by file, then offset; never overlapping.
+    pub edits: Vec<RenameEdit>,
+}
+
+impl RenamePlan {
+    /// The new content of every file the rename touches, as
+    /// `(file index, content)`.
+    pub fn apply(&self, files: &[TypstFile]) -> Vec<(usize, String)> {
+        let mut out: Vec<(usize, String)> = Vec::new();
+        // Back to front, so earlier ranges stay valid.
+        for edit in self.edits.iter().rev() {
+            if out.last().is_none_or(|(file, _)| *file != edit.file) {
+                out.push((edit.file, files[edit.file].content.clone()));
+            }
+            if let Some((_, content)) = out.last_mut() {
+                content.replace_range(edit.range.clone(), &edit.text);
+            }
+        }
+        out.reverse();
+        out
+    }
+}
+
+/// A changed line for the preview: 1-based `line` of project file `file`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreviewLine {
+    pub file: usize,
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// The lines `plan` changes, before and after, in edit order.
+pub fn preview(files: &[TypstFile], plan: &RenamePlan) -> Vec<PreviewLine> {
+    let mut lines: Vec<PreviewLine> = Vec::new();
+    let mut edits = plan.edits.iter().peekable();
+    while let Some(first) = edits.next() {
+        let text = &files[first.file].content;
+        let start = text[..first.range.start].rfind('\n').map_or(0, |i| i + 1);
+        let end = text[first.range.end..].find('\n').map_or(text.len(), |i| first.range.end + i);
+        // Every edit on the same line goes into one entry.
+        let mut on_line = vec![first];
+        while let Some(next) = edits.next_if(|e| e.file == first.file && e.range.start < end) {
+            on_line.push(next);
+        }
+        let mut after = text[start..end].to_string();
+        for edit in on_line.iter().rev() {
+            after.replace_range(edit.range.start - start..edit.range.end - start, &edit.text);
+        }
+        lines.push(PreviewLine {
+            file: first.file,
+            line: text[..start].matches('\n').count() + 1,
+            before: text[start..end].to_string(),
+            after,
+        });
+    }
+    lines
+}
+
+/// The symbol at byte `offset` of project file `file`, if it's one that can
+/// be renamed, with its current name.
+pub fn symbol_at(files: &[TypstFile], file: usize, offset: usize) -> Option<(SymbolKind, String)> {
+    let source = Source::detached(files.get(file)?.content.clone());
+    let leaf = leaf_at(&source, offset)?;
+    Some(match leaf.kind() {
+        SyntaxKind::Label | SyntaxKind::RefMarker => (SymbolKind::Label, label_name(leaf.text()).to_string()),
+        _ => (SymbolKind::Binding, leaf.text().to_string()),
+    })
+}
+
+/// Work out every edit renaming the symbol at byte `offset` of project file
+/// `file` to `new_name`, or why it can't be done.
+pub fn plan_rename(files: &[TypstFile], file: usize, offset: usize, new_name: &str) -> Result<RenamePlan, String> {
+    let sources: Vec<Source> = files.iter().map(|f| Source::detached(f.content.clone())).collect();
+    let leaf = sources
+        .get(file)
+        .and_then(|source| leaf_at(source, offset))
+        .ok_or("Place the cursor on a variable, function, parameter or label to rename it.")?;
+    let new = new_name.trim();
+    match leaf.kind() {
+        SyntaxKind::Label | SyntaxKind::RefMarker => plan_label(&sources, label_name(leaf.text()), new),
+        _ => plan_binding(files, &sources, file, leaf.range(), leaf.text(), new),
+    }
+}
+
+/// The renameable leaf touching `offset`, preferring the one after it.
+fn leaf_at(source: &Source, offset: usize) -> Option<LinkedNode<'_>> {
+    let root = LinkedNode::new(source.root());
+    [Side::After, Side::Before].into_iter().find_map(|side| {
+        root.leaf_at(offset, side).filter(|leaf| {
+            matches!(
+                leaf.kind(),
+                SyntaxKind::Ident | SyntaxKind::MathIdent | SyntaxKind::Label | SyntaxKind::RefMarker
+            )
+        })
+    })
+}
+
+/// `name` from `<name>` or `@name`.
+fn label_name(text: &str) -> &str {
+    text.trim_start_matches(['<', '@']).trim_end_matches('>')
+}
+
+fn plan_label(sources: &[Source], old: &str, new: &str) -> Result<RenamePlan, String> {
+    if !is_valid_label_literal_id(new) {
+        return Err(format!("`{new}` isn't a valid label name."));
+    }
+    let mut edits = Vec::new();
+    let mut defined = false;
+    for (file, source) in sources.iter().enumerate() {
+        let mut leaves = Vec::new();
+        label_leaves(&LinkedNode::new(source.root()), &mut leaves);
+        for (kind, name, range) in leaves {
+            if name == new && kind == SyntaxKind::Label {
+                return Err(format!("A label `<{new}>` already exists."));
+            }
+            if name != old {
+                continue;
+            }
+            let text = match kind {
+                SyntaxKind::Label => {
+                    defined = true;
+                    format!("<{new}>")
+                }
+                _ => format!("@{new}"),
+            };
+            edits.push(RenameEdit { file, range, text });
+        }
+    }
+    if !defined {
+        return Err(format!("`@{old}` doesn't refer to a label in this project (it may be a bibliography key)."));
+    }
+    Ok(RenamePlan {
+        kind: SymbolKind::Label,
+        old: old.to_string(),
+        new: new.to_string(),
+        edits,
+    })
+}
+
+/// Every `<label>` and `@reference` marker, as `(kind, name, range)`.
+fn label_leaves(node: &LinkedNode, out: &mut Vec<(SyntaxKind, String, Range<usize>)>) {
+    match node.kind() {
+        SyntaxKind::Label | SyntaxKind::RefMarker => {
+            out.push((node.kind(), label_name(node.text()).to_string(), node.range()));
+        }
+        _ => {
+            for child in node.children() {
+                label_leaves(&child, out);
+            }
+        }
+    }
+}
+
+fn plan_binding(
+    files: &[TypstFile],
+    sources: &[Source],
+    file: usize,
+    range: Range<usize>,
+    old: &str,
+    new: &str,
+) -> Result<RenamePlan, String> {
+    let analysis = analyze(files, sources);
+    let site = analysis
+        .site_at(file, &range)
+        .ok_or_else(|| format!("`{old}` isn't defined in this project — it's built in or comes from a package."))?;
+    if analysis.fixed.contains(&site) {
+        return Err(format!(
+            "`{old}` is named by its import; rename it where it's defined, or import it with `as`."
+        ));
+    }
+    if new == old {
+        return Err("Enter a new name.".to_string());
+    }
+    if !is_ident(new) || KEYWORDS.contains(&new) {
+        return Err(format!("`{new}` isn't a valid identifier."));
+    }
+    let mut edits: Vec<RenameEdit> = analysis
+        .references(site)
+        .into_iter()
+        .map(|(file, range)| RenameEdit { file, range, text: new.to_string() })
+        .collect();
+    edits.sort_by_key(|e| (e.file, e.range.start));
+    edits.dedup();
+    let plan = RenamePlan {
+        kind: SymbolKind::Binding,
+        old: old.to_string(),
+        new: new.to_string(),
+        edits,
+    };
+
+    // Re-resolve the renamed project: the renamed places must still refer
+    // to the one binding, and nothing else may now refer to it.
+    let mut renamed = files.to_vec();
+    for (file, content) in plan.apply(files) {
+        renamed[file].content = content;
+    }
+    let sources: Vec<Source> = renamed.iter().map(|f| Source::detached(f.content.clone())).collect();
+    let after = analyze(&renamed, &sources);
+    let moved = |file: usize, offset: usize| {
+        let shift: isize = plan
+            .edits
+            .iter()
+            .filter(|e| e.file == file && e.range.end <= offset)
+            .map(|e| e.text.len() as isize - e.range.len() as isize)
+            .sum();
+        offset.saturating_add_signed(shift)
+    };
+    let expected: HashSet<(usize, usize)> = plan.edits.iter().map(|e| (e.file, moved(e.file, e.range.start))).collect();
+    let found: HashSet<(usize, usize)> = after
+        .references((site.0, moved(site.0, site.1)))
+        .into_iter()
+        .map(|(file, range)| (file, range.start))
+        .collect();
+    if found != expected {
+        return Err(format!("Renaming to `{new}` would clash with another `{new}` in scope."));
+    }
+    Ok(plan)
+}
+
+/// A binding's definition: project file and byte offset of its name.
+type Site = (usize, usize);
+
+#[derive(Clone, Copy)]
+struct Binding {
+    site: Site,
+    /// The project file, for a module imported as a whole.
+    module: Option<usize>,
+}
+
+/// A name written in project file `file` at `range`, referring to `site`.
+struct Occurrence {
+    file: usize,
+    range: Range<usize>,
+    site: Site,
+}
+
+/// `name: …` passed to the function defined at `callee`.
+struct NamedArg {
+    file: usize,
+    range: Range<usize>,
+    callee: Site,
+    name: String,
+}
+
+#[derive(Default)]
+struct Analysis {
+    /// Definitions and uses; a definition refers to itself.
+    occurrences: Vec<Occurrence>,
+    named_args: Vec<NamedArg>,
+    /// Named parameters of functions bound by `let`, by function.
+    params: HashMap<Site, Vec<(String, Site)>>,
+    /// Bindings whose name comes from elsewhere: a bare import's file name,
+    /// an item imported from outside the project.
+    fixed: HashSet<Site>,
+}
+
+impl Analysis {
+    /// The binding the name at `range` of `file` refers to.
+    fn site_at(&self, file: usize, range: &Range<usize>) -> Option<Site> {
+        let here = |f: usize, r: &Range<usize>| f == file && r == range;
+        self.occurrences
+            .iter()
+            .find(|o| here(o.file, &o.range))
+            .map(|o| o.site)
+            .or_else(|| {
+                let arg = self.named_args.iter().find(|a| here(a.file, &a.range))?;
+                self.param(arg)
+            })
+    }
+
+    fn param(&self, arg: &NamedArg) -> Option<Site> {
+        self.params
+            .get(&arg.callee)?
+            .iter()
+            .find(|(name, _)| *name == arg.name)
+            .map(|(_, site)| *site)
+    }
+
+    /// Every place naming the binding at `site`, itself included.
+    fn references(&self, site: Site) -> Vec<(usize, Range<usize>)> {
+        let uses = self.occurrences.iter().filter(|o| o.site == site).map(|o| (o.file, o.range.clone()));
+        let args = self
+            .named_args
+            .iter()
+            .filter(|a| self.param(a) == Some(site))
+            .map(|a| (a.file, a.range.clone()));
+        uses.chain(args).collect()
+    }
+}
+
+/// Resolve every name in the project.
+fn analyze(files: &[TypstFile], sources: &[Source]) -> Analysis {
+    let exports: Vec<HashMap<String, Site>> = sources
+        .iter()
+        .enumerate()
+        .map(|(file, source)| {
+            source
+                .root()
+                .children()
+                .filter_map(|node| node.cast::<ast::LetBinding>())
+                .flat_map(|binding| binding.kind().bindings())
+                .filter_map(|ident| Some((ident.get().to_string(), (file, source.range(ident.span())?.start))))
+                .collect()
+        })
+        .collect();
+    let mut out = Analysis::default();
+    for (file, source) in sources.iter().enumerate() {
+        let mut resolver = Resolver {
+            files,
+            source,
+            file,
+            exports: &exports,
+            frames: vec![Frame::default()],
+            out: &mut out,
+        };
+        resolver.walk(source.root());
+    }
+    out
+}
+
+/// The names bound in one scope.
+#[derive(Default)]
+struct Frame {
+    names: HashMap<String, Binding>,
+    /// Project files imported with `*`.
+    stars: Vec<usize>,
+}
+
+struct Resolver<'a> {
+    files: &'a [TypstFile],
+    source: &'a Source,
+    file: usize,
+    /// Top-level bindings of each file, for imports.
+    exports: &'a [HashMap<String, Site>],
+    frames: Vec<Frame>,
+    out: &'a mut Analysis,
+}
+
+impl Resolver<'_> {
+    fn range(&self, node: &SyntaxNode) -> Range<usize> {
+        self.source.range(node.span()).unwrap_or(0..0)
+    }
+
+    fn lookup(&self, name: &str) -> Option<Binding> {
+        self.frames.iter().rev().find_map(|frame| {
+            frame.names.get(name).copied().or_else(|| {
+                frame.stars.iter().find_map(|&module| {
+                    let site = *self.exports[module].get(name)?;
+                    Some(Binding { site, module: None })
+                })
+            })
+        })
+    }
+
+    fn refer(&mut self, node: &SyntaxNode, site: Site) {
+        let range = self.range(node);
+        self.out.occurrences.push(Occurrence { file: self.file, range, site });
+    }
+
+    /// Bind `ident` in the innermost scope.
+    fn define(&mut self, ident: ast::Ident, module: Option<usize>) -> Site {
+        let site = (self.file, self.range(ident.to_untyped()).start);
+        self.refer(ident.to_untyped(), site);
+        self.bind_as(ident.get(), Binding { site, module });
+        site
+    }
+
+    fn bind_as(&mut self, name: &str, binding: Binding) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.names.insert(name.to_string(), binding);
+        }
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.frames.push(Frame::default());
+        f(self);
+        self.frames.pop();
+    }
+
+    fn walk(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            SyntaxKind::Ident | SyntaxKind::MathIdent => {
+                if let Some(binding) = self.lookup(node.text()) {
+                    self.refer(node, binding.site);
+                }
+            }
+            SyntaxKind::CodeBlock | SyntaxKind::ContentBlock => self.scoped(|r| {
+                for child in node.children() {
+                    r.walk(child);
+                }
+            }),
+            SyntaxKind::LetBinding => {
+                if let Some(binding) = node.cast() {
+                    self.let_binding(binding);
+                }
+            }
+            SyntaxKind::Closure => {
+                if let Some(closure) = node.cast() {
+                    self.closure(closure);
+                }
+            }
+            SyntaxKind::ForLoop => {
+                if let Some(for_loop) = node.cast::<ast::ForLoop>() {
+                    self.walk(for_loop.iterable().to_untyped());
+                    self.scoped(|r| {
+                        r.pattern(for_loop.pattern());
+                        r.walk(for_loop.body().to_untyped());
+                    });
+                }
+            }
+            SyntaxKind::ModuleImport => {
+                if let Some(import) = node.cast() {
+                    self.import(import);
+                }
+            }
+            SyntaxKind::FieldAccess => {
+                if let Some(access) = node.cast::<ast::FieldAccess>() {
+                    self.walk(access.target().to_untyped());
+                    if let Some(site) = self.module_field(access) {
+                        self.refer(access.field().to_untyped(), site);
+                    }
+                }
+            }
+            SyntaxKind::FuncCall => {
+                if let Some(call) = node.cast() {
+                    self.call(call);
+                }
+            }
+            // A dictionary key is not a variable.
+            SyntaxKind::Named => {
+                if let Some(named) = node.cast::<ast::Named>() {
+                    self.walk(named.expr().to_untyped());
+                }
+            }
+            _ => {
+                for child in node.children() {
+                    self.walk(child);
+                }
+            }
+        }
+    }
+
+    fn let_binding(&mut self, binding: ast::LetBinding) {
+        match binding.kind() {
+            // Named functions can call themselves, so the name comes first.
+            ast::LetBindingKind::Closure(name) => {
+                let site = self.define(name, None);
+                if let Some(ast::Expr::Closure(closure)) = binding.init() {
+                    let params = self.closure(closure);
+                    self.out.params.insert(site, params);
+                }
+            }
+            ast::LetBindingKind::Normal(pattern) => {
+                let params = match binding.init() {
+                    Some(ast::Expr::Closure(closure)) => Some(self.closure(closure)),
+                    Some(init) => {
+                        self.walk(init.to_untyped());
+                        None
+                    }
+                    None => None,
+                };
+                match (pattern, params) {
+                    (ast::Pattern::Normal(ast::Expr::Ident(name)), Some(params)) => {
+                        let site = self.define(name, None);
+                        self.out.params.insert(site, params);
+                    }
+                    (pattern, _) => self.pattern(pattern),
+                }
+            }
+        }
+    }
+
+    /// Walk a closure; returns its named parameters.
+    fn closure(&mut self, closure: ast::Closure) -> Vec<(String, Site)> {
+        // Defaults are evaluated where the closure is defined.
+        for param in closure.params().children() {
+            if let ast::Param::Named(named) = param {
+                self.walk(named.expr().to_untyped());
+            }
+        }
+        let mut named = Vec::new();
+        self.scoped(|r| {
+            for param in closure.params().children() {
+                match param {
+                    ast::Param::Pos(pattern) => r.pattern(pattern),
+                    ast::Param::Named(param) => {
+                        let site = r.define(param.name(), None);
+                        named.push((param.name().get().to_string(), site));
+                    }
+                    ast::Param::Spread(spread) => {
+                        if let Some(sink) = spread.sink_ident() {
+                            r.define(sink, None);
+                        }
+                    }
+                }
+            }
+            r.walk(closure.body().to_untyped());
+        });
+        named
+    }
+
+    /// Bind the names of a `let`, `for` or parameter pattern.
+    fn pattern(&mut self, pattern: ast::Pattern) {
+        match pattern {
+            ast::Pattern::Normal(ast::Expr::Ident(ident)) => {
+                self.define(ident, None);
+            }
+            ast::Pattern::Normal(expr) => self.walk(expr.to_untyped()),
+            ast::Pattern::Placeholder(_) => {}
+            ast::Pattern::Parenthesized(inner) => self.pattern(inner.pattern()),
+            ast::Pattern::Destructuring(destructuring) => {
+                for item in destructuring.items() {
+                    match item {
+                        ast::DestructuringItem::Pattern(pattern) => self.pattern(pattern),
+                        ast::DestructuringItem::Named(named) => self.pattern(named.pattern()),
+                        ast::DestructuringItem::Spread(spread) => {
+                            if let Some(sink) = spread.sink_ident() {
+                                self.define(sink, None);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn import(&mut self, import: ast::ModuleImport) {
+        let module = match import.source() {
+            ast::Expr::Str(path) => {
+                let path = resolve_path(&self.files[self.file].name, &path.get());
+                self.files.iter().position(|f| Some(&f.name) == path.as_ref())
+            }
+            source => {
+                self.walk(source.to_untyped());
+                None
+            }
+        };
+        if let Some(name) = import.new_name() {
+            self.define(name, module);
+        } else if import.imports().is_none() {
+            if let Ok(name) = import.bare_name() {
+                let site = (self.file, self.range(import.source().to_untyped()).start);
+                self.out.fixed.insert(site);
+                self.bind_as(&name, Binding { site, module });
+            }
+        }
+        match import.imports() {
+            Some(ast::Imports::Wildcard) => {
+                if let (Some(module), Some(frame)) = (module, self.frames.last_mut()) {
+                    frame.stars.push(module);
+                }
+            }
+            Some(ast::Imports::Items(items)) => {
+                for item in items.iter() {
+                    let path: Vec<ast::Ident> = item.path().iter().collect();
+                    let exported = match (module, path.as_slice()) {
+                        (Some(module), [name]) => self.exports[module].get(name.as_str()).copied(),
+                        _ => None,
+                    };
+                    if let Some(site) = exported {
+                        self.refer(item.original_name().to_untyped(), site);
+                    }
+                    match (item, exported) {
+                        (ast::ImportItem::Renamed(renamed), _) => {
+                            self.define(renamed.new_name(), None);
+                        }
+                        (ast::ImportItem::Simple(path), Some(site)) => {
+                            self.bind_as(path.name().get(), Binding { site, module: None });
+                        }
+                        (ast::ImportItem::Simple(path), None) => {
+                            let site = self.define(path.name(), None);
+                            self.out.fixed.insert(site);
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// `module.name` for a project file imported as a whole.
+    fn module_field(&self, access: ast::FieldAccess) -> Option<Site> {
+        let ast::Expr::Ident(target) = access.target() else {
+            return None;
+        };
+        let module = self.lookup(target.as_str())?.module?;
+        self.exports[module].get(access.field().as_str()).copied()
+    }
+
+    fn call(&mut self, call: ast::FuncCall) {
+        self.walk(call.callee().to_untyped());
+        let callee = self.callee(call.callee());
+        for arg in call.args().items() {
+            match arg {
+                ast::Arg::Named(named) => {
+                    if let Some(callee) = callee {
+                        self.out.named_args.push(NamedArg {
+                            file: self.file,
+                            range: self.range(named.name().to_untyped()),
+                            callee,
+                            name: named.name().get().to_string(),
+                        });
+                    }
+                    self.walk(named.expr().to_untyped());
+                }
+                arg => self.walk(arg.to_untyped()),
+            }
+        }
+    }
+
+    /// The function a call goes to: `f(…)`, `module.f(…)` or `f.with(…)`.
+    fn callee(&self, expr: ast::Expr) -> Option<Site> {
+        match expr {
+            ast::Expr::Ident(ident) => self.lookup(ident.as_str()).map(|b| b.site),
+            ast::Expr::FieldAccess(access) if access.field().as_str() == "with" => self.callee(access.target()),
+            ast::Expr::FieldAccess(access) => self.module_field(access),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(main: &str, lib: &str) -> Vec<TypstFile> {
+        vec![
+            TypstFile {
+                name: "main.typ".to_string(),
+                content: main.to_string(),
+            },
+            TypstFile {
+                name: "lib/util.typ".to_string(),
+                content: lib.to_string(),
+            },
+        ]
+    }
+
+    fn renamed(files: &[TypstFile], file: usize, at: &str, new: &str) -> Result<Vec<String>, String> {
+        let offset = files[file].content.find(at).expect("marker");
+        let plan = plan_rename(files, file, offset, new)?;
+        let mut out: Vec<String> = files.iter().map(|f| f.content.clone()).collect();
+        for (file, content) in plan.apply(files) {
+            out[file] = content;
+        }
+        Ok(out)
+    }
+
+    #[test]
+    fn renames_bindings_across_files_respecting_scope() {
+        let files = project(
+            "#import \"lib/util.typ\": box-it, gap\n#import \"lib/util.typ\" as u\n\
+             #let gap = 1\n#box-it(width: gap)[\"box-it\"] // box-it\n#u.box-it(width: 2)\n\
+             #let draw = box-it.with(width: 3)\n",
+            "#let gap = 4pt\n#let box-it(width: 1, body) = {\n  let gap = width * gap\n  block(inset: gap, body)\n}\n\
+             #for gap in (1, 2) { box-it(width: gap)[] }\n",
+        );
+
+        // The function, from a use in another file: definition, import item,
+        // module field; not the string or the comment.
+        let out = renamed(&files, 0, "box-it(width: gap)", "frame").unwrap();
+        assert_eq!(
+            out[0],
+            "#import \"lib/util.typ\": frame, gap\n#import \"lib/util.typ\" as u\n\
+             #let gap = 1\n#frame(width: gap)[\"box-it\"] // box-it\n#u.frame(width: 2)\n\
+             #let draw = frame.with(width: 3)\n"
+        );
+        assert!(out[1].starts_with("#let gap = 4pt\n#let frame(width: 1, body)"));
+        assert!(out[1].contains("{ frame(width: gap)[] }"));
+
+        // A parameter: its uses in the body and named arguments at call sites.
+        let out = renamed(&files, 1, "width: 1", "size").unwrap();
+        assert!(out[1].contains("box-it(size: 1, body) = {\n  let gap = size * gap"));
+        assert!(out[1].contains("box-it(size: gap)[]"));
+        assert!(out[0].contains("#box-it(size: gap)") && out[0].contains("#u.box-it(size: 2)"));
+        assert!(out[0].contains("box-it.with(size: 3)"));
+
+        // The top-level `gap` of the library: shadowed by the inner `let` and
+        // the loop variable; main.typ imports it but re-binds its own.
+        let out = renamed(&files, 1, "gap = 4pt", "spacing").unwrap();
+        assert_eq!(
+            out[1],
+            "#let spacing = 4pt\n#let box-it(width: 1, body) = {\n  let gap = width * spacing\n  block(inset: gap, body)\n}\n\
+             #for gap in (1, 2) { box-it(width: gap)[] }\n"
+        );
+        assert!(out[0].starts_with("#import \"lib/util.typ\": box-it, spacing\n"));
+        assert!(out[0].contains("#let gap = 1\n#box-it(width: gap)"));
+    }
+
+    #[test]
+    fn rejects_clashes_and_non_symbols() {
+        let files = project("#let a = 1\n#let b = 2\n#{ let c = 1; a + b + c }\n", "");
+        let at = |s: &str| files[0].content.find(s).unwrap();
+        assert!(plan_rename(&files, 0, at("b = 2"), "a").is_err());
+        assert!(plan_rename(&files, 0, at("c ="), "a").is_err());
+        assert!(plan_rename(&files, 0, at("a ="), "let").is_err());
+        assert!(plan_rename(&files, 0, at("1"), "x").is_err());
+        // Fine in a scope where the new name isn't used.
+        assert_eq!(plan_rename(&files, 0, at("c ="), "d").unwrap().edits.len(), 2);
+        // Builtins aren't project symbols.
+        let files = project("#text(red)[x]\n", "");
+        assert!(plan_rename(&files, 0, 1, "txt").is_err());
+    }
+
+    #[test]
+    fn renames_labels_and_previews_lines() {
+        let files = project(
+            "= Intro <intro>\nSee @intro and @intro[here].\n#ref(<intro>, supplement: \"<intro>\")\n",
+            "As in @intro, @knuth.\n",
+        );
+        let offset = files[0].content.find("@intro").unwrap() + 2;
+        assert_eq!(symbol_at(&files, 0, offset), Some((SymbolKind::Label, "intro".to_string())));
+        let plan = plan_rename(&files, 0, offset, "sec:intro").unwrap();
+        assert_eq!(plan.edits.len(), 5);
+        let lines = preview(&files, &plan);
+        let lines: Vec<_> = lines.iter().map(|l| (l.file, l.line, l.after.as_str())).collect();
+        assert_eq!(
+            lines,
+            vec![
+                (0, 1, "= Intro <sec:intro>"),
+                (0, 2, "See @sec:intro and @sec:intro[here]."),
+                (0, 3, "#ref(<sec:intro>, supplement: \"<intro>\")"),
+                (1, 1, "As in @sec:intro, @knuth."),
+            ]
+        );
+        let knuth = files[1].content.find("@knuth").unwrap();
+        assert!(plan_rename(&files, 1, knuth, "k").is_err());
+        assert!(plan_rename(&files, 0, offset, "intro").is_err());
+    }
+}
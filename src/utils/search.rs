@@ -0,0 +1,264 @@
+//! Project-wide search for the search sidebar: literal or regex patterns,
+//! optionally case-insensitive or whole-word, over every project file, with
+//! a filter on the syntactic mode a match starts in (markup, code or math).
+//! The editor's find bar stays a plain search of the open file
+//! (`editing::find_matches`).
+
+use super::project::TypstFile;
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+use typst_syntax::{parse, LinkedNode, Side, SyntaxKind};
+
+/// Which parts of the source a match may start in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchScope {
+    #[default]
+    Anywhere,
+    Markup,
+    /// Code blocks and `#` expressions, including their strings.
+    Code,
+    Math,
+}
+
+impl SearchScope {
+    pub const ALL: [SearchScope; 4] = [Self::Anywhere, Self::Markup, Self::Code, Self::Math];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Anywhere => "Anywhere",
+            Self::Markup => "Markup only",
+            Self::Code => "Code only",
+            Self::Math => "Math only",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchOptions {
+    pub query: String,
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub scope: SearchScope,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchMatch {
+    pub file: usize,
+    /// Byte range in the file.
+    pub range: Range<usize>,
+    /// 1-based line number.
+    pub line: usize,
+    pub line_text: String,
+    /// The match within `line_text` (clipped to the line).
+    pub in_line: Range<usize>,
+}
+
+/// Compile the options into a regex; `Ok(None)` for an empty query.
+fn matcher(options: &SearchOptions) -> Result<Option<Regex>, String> {
+    if options.query.is_empty() {
+        return Ok(None);
+    }
+    let pattern = if options.regex {
+        options.query.clone()
+    } else {
+        regex::escape(&options.query)
+    };
+    let pattern = if options.whole_word {
+        format!(r"\b(?:{pattern})\b")
+    } else {
+        pattern
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .multi_line(true)
+        .build()
+        .map(Some)
+        .map_err(|e| match e {
+            regex::Error::Syntax(message) => message.lines().last().unwrap_or_default().trim().to_string(),
+            other => other.to_string(),
+        })
+}
+
+/// The syntactic mode at byte `offset` of a parsed file.
+fn scope_at(root: &LinkedNode, offset: usize) -> SearchScope {
+    let Some(mut node) = root.leaf_at(offset, Side::After) else {
+        return SearchScope::Markup;
+    };
+    if node.kind() == SyntaxKind::Hash {
+        return SearchScope::Code;
+    }
+    while let Some(parent) = node.parent() {
+        match parent.kind() {
+            SyntaxKind::Code | SyntaxKind::CodeBlock => return SearchScope::Code,
+            // An embedded `#expr` is code inside markup or math.
+            SyntaxKind::Markup | SyntaxKind::Math => {
+                if node.prev_sibling().is_some_and(|s| s.kind() == SyntaxKind::Hash) {
+                    return SearchScope::Code;
+                }
+                return if parent.kind() == SyntaxKind::Math {
+                    SearchScope::Math
+                } else {
+                    SearchScope::Markup
+                };
+            }
+            _ => node = parent.clone(),
+        }
+    }
+    SearchScope::Markup
+}
+
+/// Byte ranges of the matches in `text` that pass the scope filter.
+fn file_matches(regex: &Regex, text: &str, scope: SearchScope) -> Vec<Range<usize>> {
+    let ranges = regex.find_iter(text).filter(|m| !m.is_empty()).map(|m| m.range());
+    if scope == SearchScope::Anywhere {
+        return ranges.collect();
+    }
+    let root = parse(text);
+    let root = LinkedNode::new(&root);
+    ranges.filter(|r| scope_at(&root, r.start) == scope).collect()
+}
+
+/// Every match in the project, by file and then position.
+pub fn search_project(files: &[TypstFile], options: &SearchOptions) -> Result<Vec<SearchMatch>, String> {
+    let Some(regex) = matcher(options)? else {
+        return Ok(Vec::new());
+    };
+    let mut out = Vec::new();
+    for (file, f) in files.iter().enumerate() {
+        let text = &f.content;
+        for range in file_matches(&regex, text, options.scope) {
+            let start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+            let end = text[range.start..].find('\n').map_or(text.len(), |i| range.start + i);
+            out.push(SearchMatch {
+                file,
+                line: text[..start].matches('\n').count() + 1,
+                line_text: text[start..end].to_string(),
+                in_line: range.start - start..range.end.min(end) - start,
+                range,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Replace every match, returning the new content of each changed file as
+/// `(file index, content)`. With regex on, `$1` / `${name}` in
+/// `replacement` expand to capture groups.
+pub fn replace_all(
+    files: &[TypstFile],
+    options: &SearchOptions,
+    replacement: &str,
+) -> Result<Vec<(usize, String)>, String> {
+    let Some(regex) = matcher(options)? else {
+        return Ok(Vec::new());
+    };
+    let mut out = Vec::new();
+    for (file, f) in files.iter().enumerate() {
+        let text = &f.content;
+        let ranges = file_matches(&regex, text, options.scope);
+        if ranges.is_empty() {
+            continue;
+        }
+        let mut content = String::with_capacity(text.len());
+        let mut last = 0;
+        for range in ranges {
+            content.push_str(&text[last..range.start]);
+            match regex.captures_at(text, range.start).filter(|_| options.regex) {
+                Some(caps) => caps.expand(replacement, &mut content),
+                None => content.push_str(replacement),
+            }
+            last = range.end;
+        }
+        content.push_str(&text[last..]);
+        out.push((file, content));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<TypstFile> {
+        vec![
+            TypstFile {
+                name: "main.typ".to_string(),
+                content: "= Alpha\nalpha and Alphabet $alpha + beta$\n#let alpha = \"alpha\"\n".to_string(),
+            },
+            TypstFile {
+                name: "ch.typ".to_string(),
+                content: "No match here.\n#text(fill: red)[ALPHA]\n".to_string(),
+            },
+        ]
+    }
+
+    fn found(options: &SearchOptions) -> Vec<(usize, usize, String)> {
+        let files = files();
+        search_project(&files, options)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.file, m.line, m.line_text[m.in_line].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn options_and_scopes_filter_matches() {
+        let mut options = SearchOptions {
+            query: "alpha".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(found(&options).len(), 7);
+        options.case_sensitive = true;
+        assert_eq!(found(&options).len(), 4);
+        options.case_sensitive = false;
+        options.whole_word = true;
+        assert_eq!(found(&options).len(), 6);
+
+        options.scope = SearchScope::Math;
+        assert_eq!(found(&options), vec![(0, 2, "alpha".to_string())]);
+        options.scope = SearchScope::Code;
+        assert_eq!(found(&options), vec![(0, 3, "alpha".to_string()), (0, 3, "alpha".to_string())]);
+        options.scope = SearchScope::Markup;
+        let markup = found(&options);
+        assert_eq!(markup.len(), 3);
+        assert_eq!(markup[2], (1, 2, "ALPHA".to_string()));
+
+        let options = SearchOptions {
+            query: r"Alpha\w+".to_string(),
+            regex: true,
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(found(&options), vec![(0, 2, "Alphabet".to_string())]);
+        let broken = SearchOptions {
+            query: "(".to_string(),
+            regex: true,
+            ..Default::default()
+        };
+        assert!(search_project(&files(), &broken).is_err());
+    }
+
+    #[test]
+    fn replaces_across_files_with_captures() {
+        let options = SearchOptions {
+            query: r"(\w+)(bet)".to_string(),
+            regex: true,
+            ..Default::default()
+        };
+        let changed = replace_all(&files(), &options, "$2-$1").unwrap();
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0].1.contains("alpha and bet-Alpha $alpha"));
+
+        let options = SearchOptions {
+            query: "alpha".to_string(),
+            whole_word: true,
+            scope: SearchScope::Markup,
+            ..Default::default()
+        };
+        let changed = replace_all(&files(), &options, "$x").unwrap();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(changed[0].1, "= $x\n$x and Alphabet $alpha + beta$\n#let alpha = \"alpha\"\n");
+        assert_eq!(changed[1].1, "No match here.\n#text(fill: red)[$x]\n");
+    }
+}
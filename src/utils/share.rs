@@ -1,12 +1,96 @@
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use super::bibliography::{BibFile, LEGACY_BIB_NAME};
+use super::project::TypstFile;
 
 /// Fragment key under which the encoded source lives: `#src=<base64>`.
+///
+/// Legacy single-file format; still decoded so links shared before the
+/// compressed payload existed keep opening.
 const HASH_PREFIX: &str = "src=";
 
+/// Fragment key for the compressed project payload: `#p=<base64(deflate(json))>`.
+const PAYLOAD_PREFIX: &str = "p=";
+
+/// Version written into every new payload. Decoding rejects newer versions
+/// (a link produced by a future build) instead of misreading them.
+///
+/// v2 replaced the single `bibliography` string (served as `refs.yml`) with a
+/// list of named bibliography files; v1 payloads are upgraded on decode.
+pub const PAYLOAD_VERSION: u32 = 2;
+
+/// Query parameter that opens a shared payload in the read-only viewer.
+const VIEW_PARAM: &str = "view=1";
+
+/// Upper bound on the inflated payload size, so a hand-crafted fragment can't
+/// balloon into an arbitrarily large allocation.
+const MAX_INFLATED_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Everything a shared link carries: the whole multi-file project plus its
+/// bibliography files, the hidden settings preamble and `sys.inputs`.
+///
+/// `bibliographies` / `settings` are `None` only for payloads recovered from a
+/// legacy `#src=` link, which never carried them; the app then keeps its local
+/// values instead of blanking them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SharePayload {
+    pub version: u32,
+    pub files: Vec<TypstFile>,
+    #[serde(default)]
+    pub bibliographies: Option<Vec<BibFile>>,
+    /// v1 only: the single Hayagriva bibliography. Read, never written;
+    /// [`decode_payload`] moves it into `bibliographies` as `refs.yml`.
+    #[serde(default, skip_serializing)]
+    bibliography: Option<String>,
+    #[serde(default)]
+    pub settings: Option<String>,
+    /// `sys.inputs` key/value pairs. The studio does not set any yet, so new
+    /// links carry an empty map; the field keeps the format stable once it does.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, String>,
+}
+
+impl SharePayload {
+    /// A current-version payload for the given project state.
+    pub fn new(files: Vec<TypstFile>, bibliographies: Vec<BibFile>, settings: String) -> Self {
+        Self {
+            version: PAYLOAD_VERSION,
+            files,
+            bibliographies: Some(bibliographies),
+            bibliography: None,
+            settings: Some(settings),
+            inputs: BTreeMap::new(),
+        }
+    }
+
+    /// Wrap a legacy single-file snapshot as a one-file project.
+    fn from_legacy_source(source: String) -> Self {
+        Self {
+            version: PAYLOAD_VERSION,
+            files: vec![TypstFile {
+                name: "main.typ".to_string(),
+                content: source,
+            }],
+            bibliographies: None,
+            bibliography: None,
+            settings: None,
+            inputs: BTreeMap::new(),
+        }
+    }
+}
+
 /// Encode source text into a URL-safe, unpadded base64 string suitable for a URL fragment.
 ///
 /// URL-safe alphabet (`-`/`_`) and no padding (`=`) mean the result needs no further
-/// percent-escaping inside a fragment.
+/// percent-escaping inside a fragment. New links use [`encode_payload`]; this
+/// legacy encoder only remains to exercise `#src=` decoding in tests.
+#[cfg(test)]
 pub fn encode_source(src: &str) -> String {
     URL_SAFE_NO_PAD.encode(src.as_bytes())
 }
@@ -20,6 +104,44 @@ pub fn decode_source(encoded: &str) -> Option<String> {
     String::from_utf8(bytes).ok()
 }
 
+/// Serialize a payload to JSON, raw-deflate it and base64 (URL-safe, unpadded)
+/// the result. Typst sources are highly repetitive, so this is typically a
+/// fraction of the plain `#src=` encoding of the main file alone.
+pub fn encode_payload(payload: &SharePayload) -> String {
+    let json = serde_json::to_vec(payload).unwrap_or_default();
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    // Writing into a `Vec` cannot fail.
+    let _ = encoder.write_all(&json);
+    let compressed = encoder.finish().unwrap_or_default();
+    URL_SAFE_NO_PAD.encode(compressed)
+}
+
+/// Inverse of [`encode_payload`]. Returns `None` for malformed base64, corrupt
+/// deflate data, invalid JSON, a payload from a newer format version, or one
+/// without files. Older payloads are upgraded to the current layout.
+pub fn decode_payload(encoded: &str) -> Option<SharePayload> {
+    let compressed = URL_SAFE_NO_PAD.decode(encoded).ok()?;
+    let mut json = Vec::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_INFLATED_BYTES)
+        .read_to_end(&mut json)
+        .ok()?;
+    let mut payload: SharePayload = serde_json::from_slice(&json).ok()?;
+    if payload.version > PAYLOAD_VERSION || payload.files.is_empty() {
+        return None;
+    }
+    if let Some(content) = payload.bibliography.take() {
+        payload.bibliographies.get_or_insert_with(|| {
+            vec![BibFile {
+                name: LEGACY_BIB_NAME.to_string(),
+                content,
+            }]
+        });
+    }
+    payload.version = PAYLOAD_VERSION;
+    Some(payload)
+}
+
 /// Read shared source from the current URL fragment (`#src=...`), if present and valid.
 pub fn source_from_url() -> Option<String> {
     let hash = web_sys::window()?.location().hash().ok()?;
@@ -28,6 +150,48 @@ pub fn source_from_url() -> Option<String> {
     decode_source(payload)
 }
 
+/// Read a shared project from the current URL fragment: a compressed `#p=`
+/// payload, or a legacy `#src=` link (via [`source_from_url`]) wrapped as a
+/// single-file project.
+pub fn payload_from_url() -> Option<SharePayload> {
+    let hash = web_sys::window()?.location().hash().ok()?;
+    let hash = hash.strip_prefix('#').unwrap_or(&hash);
+    if let Some(encoded) = hash.strip_prefix(PAYLOAD_PREFIX) {
+        return decode_payload(encoded);
+    }
+    source_from_url().map(SharePayload::from_legacy_source)
+}
+
+/// Whether a `location.search` string (with or without its leading `?`)
+/// requests the read-only viewer (`?view=1`, possibly among other parameters).
+pub fn has_view_flag(search: &str) -> bool {
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .any(|param| param == VIEW_PARAM)
+}
+
+/// Whether the current URL asks for the read-only viewer layout.
+pub fn view_mode_from_url() -> bool {
+    web_sys::window()
+        .and_then(|w| w.location().search().ok())
+        .is_some_and(|search| has_view_flag(&search))
+}
+
+/// Drop both the query string and the fragment from the URL bar, without
+/// adding a history entry. Used when leaving the viewer for the editor, so a
+/// reload opens the (now persisted) project in the editor rather than the
+/// shared snapshot in the viewer.
+pub fn strip_share_url() {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let path = window.location().pathname().unwrap_or_default();
+    if let Ok(history) = window.history() {
+        let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&path));
+    }
+}
+
 /// Remove the fragment from the URL bar without adding a history entry.
 ///
 /// Called right after consuming a shared link so subsequent reloads restore the
@@ -45,20 +209,52 @@ pub fn strip_url_fragment() {
     }
 }
 
-/// Build a shareable absolute URL embedding `src` in the fragment.
-pub fn build_share_url(src: &str) -> Option<String> {
+/// Build a shareable absolute URL embedding the compressed `payload` in the
+/// fragment. With `view_only`, the link opens in the read-only viewer.
+pub fn build_share_url(payload: &SharePayload, view_only: bool) -> Option<String> {
     let location = web_sys::window()?.location();
     let origin = location.origin().ok()?;
     let pathname = location.pathname().ok()?;
+    let query = if view_only {
+        format!("?{VIEW_PARAM}")
+    } else {
+        String::new()
+    };
     Some(format!(
-        "{origin}{pathname}#{HASH_PREFIX}{}",
-        encode_source(src)
+        "{origin}{pathname}{query}#{PAYLOAD_PREFIX}{}",
+        encode_payload(payload)
     ))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{decode_source, encode_source};
+    use super::*;
+
+    fn project() -> SharePayload {
+        SharePayload::new(
+            vec![
+                TypstFile {
+                    name: "main.typ".to_string(),
+                    content: "= Title\n#include \"chapter.typ\"\n@key".to_string(),
+                },
+                TypstFile {
+                    name: "chapter.typ".to_string(),
+                    content: "== Chapter 😀\n$x^2$".to_string(),
+                },
+            ],
+            vec![
+                BibFile {
+                    name: "refs.yml".to_string(),
+                    content: "key:\n  type: book\n  title: T\n".to_string(),
+                },
+                BibFile {
+                    name: "zotero.bib".to_string(),
+                    content: "@book{other, title = {O}}".to_string(),
+                },
+            ],
+            "#set page(numbering: \"1\")".to_string(),
+        )
+    }
 
     #[test]
     fn ascii_roundtrips() {
@@ -86,9 +282,87 @@ mod tests {
         assert_eq!(decode_source(&encoded), None);
     }
 
+    #[test]
+    fn payload_roundtrips_whole_project() {
+        let mut payload = project();
+        payload.inputs.insert("draft".to_string(), "true".to_string());
+        assert_eq!(decode_payload(&encode_payload(&payload)), Some(payload));
+    }
+
+    #[test]
+    fn payload_is_smaller_than_plain_source() {
+        let body = "= Section\n\nSome repeated paragraph text with #emph[markup].\n\n".repeat(50);
+        let payload = SharePayload::new(
+            vec![TypstFile {
+                name: "main.typ".to_string(),
+                content: body.clone(),
+            }],
+            Vec::new(),
+            String::new(),
+        );
+        assert!(encode_payload(&payload).len() * 4 < encode_source(&body).len());
+    }
+
+    #[test]
+    fn legacy_source_becomes_single_file_project() {
+        let source = decode_source(&encode_source("= Old")).unwrap();
+        let legacy = SharePayload::from_legacy_source(source);
+        assert_eq!(legacy.files.len(), 1);
+        assert_eq!(legacy.files[0].name, "main.typ");
+        assert_eq!(legacy.files[0].content, "= Old");
+        // Legacy links never carried these, so the local values are kept.
+        assert_eq!(legacy.bibliographies, None);
+        assert_eq!(legacy.settings, None);
+    }
+
+    #[test]
+    fn v1_payload_bibliography_becomes_refs_yml() {
+        let v1 = r#"{"version":1,"files":[{"name":"main.typ","content":"@key"}],"bibliography":"key:\n  type: book\n","settings":""}"#;
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(v1.as_bytes()).unwrap();
+        let encoded = URL_SAFE_NO_PAD.encode(encoder.finish().unwrap());
+
+        let payload = decode_payload(&encoded).expect("v1 payload");
+        assert_eq!(payload.version, PAYLOAD_VERSION);
+        assert_eq!(
+            payload.bibliographies,
+            Some(vec![BibFile {
+                name: "refs.yml".to_string(),
+                content: "key:\n  type: book\n".to_string(),
+            }])
+        );
+        // Re-encoding writes the v2 layout only.
+        let json = serde_json::to_string(&payload).unwrap();
+        assert!(!json.contains("\"bibliography\""), "got: {json}");
+    }
+
+    #[test]
+    fn corrupt_or_unsupported_payload_returns_none() {
+        // Valid base64, but not deflate data.
+        assert_eq!(decode_payload(&encode_source("not deflate")), None);
+        assert_eq!(decode_payload("###"), None);
+
+        let mut future = project();
+        future.version = PAYLOAD_VERSION + 1;
+        assert_eq!(decode_payload(&encode_payload(&future)), None);
+
+        let mut empty = project();
+        empty.files.clear();
+        assert_eq!(decode_payload(&encode_payload(&empty)), None);
+    }
+
+    #[test]
+    fn view_flag_is_detected_among_params() {
+        assert!(has_view_flag("?view=1"));
+        assert!(has_view_flag("view=1"));
+        assert!(has_view_flag("?lang=en&view=1"));
+        assert!(!has_view_flag(""));
+        assert!(!has_view_flag("?view=0"));
+        assert!(!has_view_flag("?preview=1"));
+    }
+
     // Helper mirroring encode_source but for raw bytes, to craft invalid UTF-8.
     fn encode_source_bytes(bytes: &[u8]) -> String {
-        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
         URL_SAFE_NO_PAD.encode(bytes)
     }
 }
@@ -0,0 +1,353 @@
+//! Document statistics for the stats panel. Words, characters and the words
+//! of each heading section are counted in the source as written — starting at
+//! the main file and following `#include`s between project files — so
+//! generated text (numbering, page headers, the bibliography) doesn't count
+//! towards word limits. Pages and element tallies come from the compiled
+//! document (`compiler::document_counts`) when there is one, with estimates
+//! from the syntax trees otherwise.
+
+use super::outline::plain_text;
+use super::project::{resolve_path, TypstFile};
+use std::collections::HashSet;
+use typst_syntax::ast::AstNode;
+use typst_syntax::{ast, parse, SyntaxKind, SyntaxNode};
+
+/// Reading speed behind [`DocStats::reading_minutes`].
+pub const WORDS_PER_MINUTE: usize = 230;
+
+/// Words under one heading, including its subsections.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SectionStats {
+    pub level: usize,
+    pub title: String,
+    pub words: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ElementCounts {
+    pub figures: usize,
+    pub tables: usize,
+    /// Display (block) equations.
+    pub equations: usize,
+    pub citations: usize,
+    /// Distinct cited bibliography keys.
+    pub references: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocStats {
+    pub words: usize,
+    /// Characters other than whitespace.
+    pub characters: usize,
+    pub characters_with_spaces: usize,
+    /// Words before the first heading (title, abstract, …).
+    pub front_words: usize,
+    pub sections: Vec<SectionStats>,
+    /// Known only for a compiled document.
+    pub pages: Option<usize>,
+    pub elements: ElementCounts,
+}
+
+impl DocStats {
+    /// Estimated reading time, rounded up to whole minutes.
+    pub fn reading_minutes(&self) -> usize {
+        self.words.div_ceil(WORDS_PER_MINUTE)
+    }
+}
+
+/// Accumulates runs of text into word and character counts, attributing
+/// words to the current heading section. A word is a whitespace-delimited
+/// token with at least one alphanumeric character, so stray punctuation such
+/// as a lone `–` isn't counted.
+#[derive(Default)]
+struct TextTally {
+    stats: DocStats,
+    /// Words directly under each section, before nesting is applied.
+    own: Vec<usize>,
+    in_token: bool,
+    token_counted: bool,
+}
+
+impl TextTally {
+    /// Add a run of text. `joined` continues the previous run (e.g. a styled
+    /// part of the same word); otherwise the run starts a new word.
+    fn text(&mut self, text: &str, joined: bool) {
+        if !joined {
+            self.in_token = false;
+        }
+        for c in text.chars() {
+            self.stats.characters_with_spaces += 1;
+            if c.is_whitespace() {
+                self.in_token = false;
+                continue;
+            }
+            self.stats.characters += 1;
+            if !self.in_token {
+                self.in_token = true;
+                self.token_counted = false;
+            }
+            if c.is_alphanumeric() && !self.token_counted {
+                self.token_counted = true;
+                self.stats.words += 1;
+                match self.own.last_mut() {
+                    Some(own) => *own += 1,
+                    None => self.stats.front_words += 1,
+                }
+            }
+        }
+    }
+
+    /// Start a new section; the heading's own text is counted in it.
+    fn heading(&mut self, level: usize, title: &str) {
+        self.in_token = false;
+        self.stats.sections.push(SectionStats {
+            level,
+            title: title.split_whitespace().collect::<Vec<_>>().join(" "),
+            words: 0,
+        });
+        self.own.push(0);
+    }
+
+    /// The counts so far, with each section's words including those of its
+    /// subsections.
+    fn finish(self) -> DocStats {
+        let mut stats = self.stats;
+        for i in 0..stats.sections.len() {
+            let level = stats.sections[i].level;
+            let nested = stats.sections[i + 1..].iter().take_while(|s| s.level > level).count();
+            stats.sections[i].words = self.own[i..=i + nested].iter().sum();
+        }
+        stats
+    }
+}
+
+/// Statistics from the syntax trees, starting at the main file (index 0) and
+/// inlining `#include`d project files. Element tallies are estimates from
+/// the markup (`@key` counts as a citation unless a label of that name is
+/// defined); pages are left unknown.
+pub fn syntax_stats(files: &[TypstFile]) -> DocStats {
+    let Some(main) = files.first() else {
+        return DocStats::default();
+    };
+    let mut walk = SyntaxWalk {
+        files,
+        tally: TextTally::default(),
+        including: vec![main.name.as_str()],
+        elements: ElementCounts::default(),
+        cited: Vec::new(),
+        labels: HashSet::new(),
+    };
+    walk.node(&parse(&main.content));
+    let SyntaxWalk {
+        tally,
+        mut elements,
+        cited,
+        labels,
+        ..
+    } = walk;
+    let cited: Vec<String> = cited.into_iter().filter(|key| !labels.contains(key)).collect();
+    elements.citations = cited.len();
+    elements.references = cited.iter().collect::<HashSet<_>>().len();
+    DocStats {
+        elements,
+        ..tally.finish()
+    }
+}
+
+struct SyntaxWalk<'a> {
+    files: &'a [TypstFile],
+    tally: TextTally,
+    /// Files currently being walked, to stop include cycles.
+    including: Vec<&'a str>,
+    elements: ElementCounts,
+    /// Targets of `@key` references and `cite` calls, in order.
+    cited: Vec<String>,
+    labels: HashSet<String>,
+}
+
+impl<'a> SyntaxWalk<'a> {
+    fn node(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            SyntaxKind::Heading => {
+                if let Some(heading) = node.cast::<ast::Heading>() {
+                    let mut title = String::new();
+                    plain_text(heading.body().to_untyped(), &mut title);
+                    self.tally.heading(heading.depth().get(), &title);
+                }
+            }
+            SyntaxKind::Text | SyntaxKind::SmartQuote | SyntaxKind::Shorthand => {
+                self.tally.text(node.text(), true);
+                return;
+            }
+            SyntaxKind::Escape => {
+                self.tally.text(node.text().trim_start_matches('\\'), true);
+                return;
+            }
+            SyntaxKind::Space | SyntaxKind::Parbreak | SyntaxKind::Linebreak => {
+                self.tally.text(" ", false);
+                return;
+            }
+            SyntaxKind::Equation => {
+                if node.cast::<ast::Equation>().is_some_and(|eq| eq.block()) {
+                    self.elements.equations += 1;
+                }
+                return;
+            }
+            SyntaxKind::Label => {
+                if let Some(label) = node.cast::<ast::Label>() {
+                    self.labels.insert(label.get().to_string());
+                }
+                return;
+            }
+            SyntaxKind::Ref => {
+                if let Some(reference) = node.cast::<ast::Ref>() {
+                    self.cited.push(reference.target().to_string());
+                }
+                return;
+            }
+            // Table cells and other adjacent content blocks don't run together.
+            SyntaxKind::ContentBlock => {
+                self.tally.text("", false);
+                for child in node.children() {
+                    self.node(child);
+                }
+                self.tally.text("", false);
+                return;
+            }
+            SyntaxKind::ModuleInclude => {
+                if let Some(ast::Expr::Str(path)) = node.cast::<ast::ModuleInclude>().map(|i| i.source()) {
+                    self.include(&path.get());
+                }
+                return;
+            }
+            SyntaxKind::FuncCall => {
+                if let Some(call) = node.cast::<ast::FuncCall>() {
+                    if self.call(call) {
+                        return;
+                    }
+                }
+            }
+            _ => {}
+        }
+        for child in node.children() {
+            self.node(child);
+        }
+    }
+
+    /// Walk the project file `path` refers to from the file being walked,
+    /// unless it's already being walked.
+    fn include(&mut self, path: &str) {
+        let current = self.including.last().copied().unwrap_or_default();
+        let Some(path) = resolve_path(current, path) else {
+            return;
+        };
+        let Some(file) = self.files.iter().find(|f| f.name == path) else {
+            return;
+        };
+        if !self.including.contains(&file.name.as_str()) {
+            self.including.push(&file.name);
+            self.node(&parse(&file.content));
+            self.including.pop();
+        }
+    }
+
+    /// Handle the calls that matter for the counts; `true` when the call's
+    /// arguments need no further walking.
+    fn call(&mut self, call: ast::FuncCall) -> bool {
+        let ast::Expr::Ident(callee) = call.callee() else {
+            return false;
+        };
+        let mut positional = call.args().items().filter_map(|arg| match arg {
+            ast::Arg::Pos(expr) => Some(expr),
+            _ => None,
+        });
+        match callee.as_str() {
+            "figure" => {
+                let is_table = matches!(
+                    positional.next(),
+                    Some(ast::Expr::FuncCall(body))
+                        if matches!(body.callee(), ast::Expr::Ident(id) if id.as_str() == "table")
+                );
+                if is_table {
+                    self.elements.tables += 1;
+                } else {
+                    self.elements.figures += 1;
+                }
+                false
+            }
+            "cite" => {
+                if let Some(ast::Expr::Label(label)) = positional.next() {
+                    self.cited.push(label.get().to_string());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tally_counts_words_across_runs_and_sections() {
+        let mut tally = TextTally::default();
+        tally.text("A title", false);
+        tally.heading(1, "Intro");
+        tally.text("Intro", false);
+        tally.text("Some ", false);
+        tally.text("bold", true);
+        tally.text("face – here.", true);
+        tally.heading(2, "Detail");
+        tally.text("Detail", false);
+        tally.text("two words", false);
+        tally.heading(1, "End");
+        tally.text("End", false);
+        let stats = tally.finish();
+
+        assert_eq!(stats.words, 10);
+        assert_eq!(stats.front_words, 2);
+        let sections: Vec<_> = stats.sections.iter().map(|s| (s.level, s.title.as_str(), s.words)).collect();
+        assert_eq!(sections, vec![(1, "Intro", 7), (2, "Detail", 3), (1, "End", 1)]);
+        assert_eq!(stats.characters, 46);
+        assert_eq!(stats.reading_minutes(), 1);
+    }
+
+    #[test]
+    fn syntax_stats_follow_includes() {
+        let files = [
+            TypstFile {
+                name: "main.typ".to_string(),
+                content: "= Intro\nHello *big* world. @knuth @fig and @knuth\n\
+                          #figure(rect(), caption: [A box]) <fig>\n\
+                          #figure(table(columns: 2)[a][b])\n$ x = 1 $ and $y$\n\
+                          #include \"chapters/ch.typ\"\n"
+                    .to_string(),
+            },
+            TypstFile {
+                name: "chapters/ch.typ".to_string(),
+                content: "== Chapter\nMore text #cite(<lamport>)\n#include \"../main.typ\"\n".to_string(),
+            },
+        ];
+        let stats = syntax_stats(&files);
+        let sections: Vec<_> = stats.sections.iter().map(|s| (s.level, s.title.as_str(), s.words)).collect();
+        // Intro's own 10 words (heading, text, caption, table cells; no math)
+        // plus the included chapter; the cyclic include is not followed again.
+        assert_eq!(sections, vec![(1, "Intro", 13), (2, "Chapter", 3)]);
+        assert_eq!(stats.words, 13);
+        assert_eq!(stats.pages, None);
+        assert_eq!(
+            stats.elements,
+            ElementCounts {
+                figures: 1,
+                tables: 1,
+                equations: 1,
+                citations: 3,
+                references: 2,
+            }
+        );
+        assert_eq!(resolve_path("chapters/a.typ", "/main.typ").as_deref(), Some("main.typ"));
+        assert_eq!(resolve_path("main.typ", "./parts/b.typ").as_deref(), Some("parts/b.typ"));
+    }
+}
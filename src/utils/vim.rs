@@ -0,0 +1,1167 @@
+//! Vim-style modal editing for the editor.
+//!
+//! [`Vim`] is a pure state machine: the editor feeds it every key with the
+//! textarea's text and selection, and gets back the new text (written as one
+//! undoable change), the selection to show and any editor command (`:w`
+//! saves). Insert mode leaves typing to the textarea, so auto-pairing and
+//! list continuation keep working there.
+//!
+//! Covered: normal, insert, visual and visual-line modes; the motions
+//! `h j k l w b e 0 ^ $ gg G % f t F T ; , n N`; the operators `d c y > <`
+//! (doubled for whole lines) and `x X D C Y s p P`; counts; registers
+//! (`"a`–`"z`, upper case appends, `"0` holds the last yank); `.` repeat;
+//! `/` search and the `:w` and `:<line>` commands.
+
+use super::carets::{line_col, offset_at};
+use super::editing::{find_matches, indent_block, minimal_edit, outdent_block};
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+}
+
+/// Editor actions a key asks for besides editing the text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Save,
+    Undo,
+    Redo,
+}
+
+/// What a handled key did.
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    /// The new text, if the key changed it.
+    pub text: Option<String>,
+    /// The byte range to select: a one-character block for the normal-mode
+    /// cursor, the selection in visual modes, a caret in insert mode.
+    pub selection: Range<usize>,
+    pub command: Option<Command>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Register {
+    text: String,
+    linewise: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Outdent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordNext,
+    WordPrev,
+    WordEnd,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FileStart,
+    FileEnd,
+    MatchBracket,
+    /// `f`, `t`, `F` or `T` and the character to look for.
+    Find { kind: char, ch: char },
+    RepeatFind { reverse: bool },
+    SearchNext { reverse: bool },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InsertAt {
+    Cursor,
+    After,
+    LineStart,
+    LineEnd,
+    Below,
+    Above,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cmd {
+    Move(Motion),
+    /// `None` applies to `count` whole lines (`dd`, `yy`, `>>`), or in a
+    /// visual mode to the selection.
+    Operate(Operator, Option<Motion>),
+    Insert(InsertAt),
+    Visual { linewise: bool },
+    SwapEnds,
+    Paste { before: bool },
+    Undo,
+    Redo,
+    Repeat,
+    CommandLine(char),
+    Escape,
+}
+
+struct Parsed {
+    register: Option<char>,
+    count: Option<usize>,
+    cmd: Cmd,
+}
+
+enum Parse<T> {
+    Incomplete,
+    Invalid,
+    Done(T),
+}
+
+/// How an operator takes the text between the cursor and a motion's target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Span {
+    Exclusive,
+    Inclusive,
+    Linewise,
+}
+
+/// The last normal-mode change, for `.`: its keys and what was typed in
+/// the insert mode it started, if any.
+#[derive(Clone, Debug, PartialEq)]
+struct Change {
+    keys: Vec<String>,
+    inserted: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Vim {
+    pub mode: Mode,
+    /// Keys of the command being typed.
+    pending: Vec<String>,
+    registers: HashMap<char, Register>,
+    /// The cursor; in visual modes the moving end of the selection.
+    head: usize,
+    /// The fixed end of a visual selection.
+    anchor: usize,
+    /// The column `j` and `k` aim for across shorter lines.
+    goal: Option<usize>,
+    last_find: Option<(char, char)>,
+    last_search: Option<String>,
+    last_change: Option<Change>,
+    /// The text when a recorded change entered insert mode, to tell what
+    /// was typed once it is left.
+    insert_from: Option<String>,
+    replaying: bool,
+    /// The `/` or `:` line being typed.
+    cmdline: Option<String>,
+    message: Option<String>,
+}
+
+impl Vim {
+    /// Handle `key` (a `KeyboardEvent.key` value, or `<C-r>` for Ctrl+R).
+    /// `None` means the key is not Vim's: the textarea should handle it.
+    pub fn key(&mut self, text: &str, selection: Range<usize>, key: &str) -> Option<Response> {
+        self.message = None;
+        if let Some(line) = self.cmdline.take() {
+            return Some(self.command_line_key(text, line, key));
+        }
+        match self.mode {
+            Mode::Insert if key == "Escape" => Some(self.leave_insert(text, selection.start.min(text.len()))),
+            Mode::Insert => None,
+            _ if !is_vim_key(key) => None,
+            Mode::Normal => {
+                let head = clamp_cursor(text, selection.start.min(text.len()));
+                if head != self.head {
+                    self.goal = None;
+                }
+                self.head = head;
+                Some(self.command_key(text, key))
+            }
+            Mode::Visual | Mode::VisualLine => {
+                // The text may have changed under the selection (another file,
+                // an undo or a format), so both ends are put back on a character.
+                self.head = clamp_cursor(text, self.head);
+                self.anchor = clamp_cursor(text, self.anchor);
+                Some(self.command_key(text, key))
+            }
+        }
+    }
+
+    /// The status line: the mode, a pending command, the command line or a
+    /// message.
+    pub fn status(&self) -> String {
+        if let Some(line) = &self.cmdline {
+            return line.clone();
+        }
+        if let Some(message) = &self.message {
+            return message.clone();
+        }
+        let mode = match self.mode {
+            Mode::Normal => "-- NORMAL --",
+            Mode::Insert => "-- INSERT --",
+            Mode::Visual => "-- VISUAL --",
+            Mode::VisualLine => "-- VISUAL LINE --",
+        };
+        format!("{mode} {}", self.pending.concat()).trim_end().to_string()
+    }
+
+    fn command_key(&mut self, text: &str, key: &str) -> Response {
+        self.pending.push(key.to_string());
+        match parse(&self.pending, self.mode != Mode::Normal) {
+            Parse::Incomplete => self.respond(text, None, None),
+            Parse::Invalid => {
+                self.pending.clear();
+                self.respond(text, None, None)
+            }
+            Parse::Done(parsed) => {
+                let keys = std::mem::take(&mut self.pending);
+                self.execute(text, parsed, keys)
+            }
+        }
+    }
+
+    fn respond(&self, text: &str, new: Option<String>, command: Option<Command>) -> Response {
+        let t = new.as_deref().unwrap_or(text);
+        let (a, b) = (self.anchor.min(self.head), self.anchor.max(self.head));
+        let selection = match self.mode {
+            Mode::Insert => self.head..self.head,
+            Mode::Normal if self.head < t.len() && !t[self.head..].starts_with('\n') => {
+                self.head..next_char(t, self.head)
+            }
+            Mode::Normal => self.head..self.head,
+            Mode::Visual => a..next_char(t, b),
+            Mode::VisualLine => line_start(t, a)..line_end(t, b),
+        };
+        Response { text: new, selection, command }
+    }
+
+    fn execute(&mut self, text: &str, parsed: Parsed, keys: Vec<String>) -> Response {
+        let Parsed { register, count, cmd } = parsed;
+        let visual = self.mode != Mode::Normal;
+        let pos = self.head;
+        if !matches!(cmd, Cmd::Move(Motion::Up | Motion::Down)) {
+            self.goal = None;
+        }
+        let repeatable = !visual
+            && !self.replaying
+            && matches!(
+                cmd,
+                Cmd::Operate(Operator::Delete | Operator::Change | Operator::Indent | Operator::Outdent, _)
+                    | Cmd::Insert(_)
+                    | Cmd::Paste { .. }
+            );
+        if repeatable {
+            self.last_change = Some(Change { keys, inserted: None });
+        }
+        let new = match cmd {
+            Cmd::Move(motion) => {
+                if let Some((target, _)) = self.motion(text, pos, motion, count, None) {
+                    self.head = clamp_cursor(text, target);
+                }
+                None
+            }
+            Cmd::Operate(op, motion) => self.operate(text, op, motion, count, register),
+            Cmd::Insert(at) => self.insert(text, at),
+            Cmd::Visual { linewise } => {
+                let mode = if linewise { Mode::VisualLine } else { Mode::Visual };
+                if self.mode == mode {
+                    self.mode = Mode::Normal;
+                } else {
+                    if !visual {
+                        self.anchor = pos;
+                    }
+                    self.mode = mode;
+                }
+                None
+            }
+            Cmd::SwapEnds => {
+                std::mem::swap(&mut self.anchor, &mut self.head);
+                None
+            }
+            Cmd::Paste { before } => self.paste(text, before, count.unwrap_or(1), register),
+            Cmd::Undo => return self.respond(text, None, Some(Command::Undo)),
+            Cmd::Redo => return self.respond(text, None, Some(Command::Redo)),
+            Cmd::Repeat => return self.repeat(text),
+            Cmd::CommandLine(prefix) => {
+                self.cmdline = Some(prefix.to_string());
+                None
+            }
+            Cmd::Escape => {
+                self.mode = Mode::Normal;
+                self.head = clamp_cursor(text, pos);
+                None
+            }
+        };
+        if repeatable && self.mode == Mode::Insert {
+            self.insert_from = Some(new.clone().unwrap_or_else(|| text.to_string()));
+        }
+        self.respond(text, new, None)
+    }
+
+    fn leave_insert(&mut self, text: &str, pos: usize) -> Response {
+        if let Some(before) = self.insert_from.take() {
+            let (_, typed) = minimal_edit(&before, text);
+            if let Some(change) = self.last_change.as_mut() {
+                change.inserted = Some(typed);
+            }
+        }
+        self.mode = Mode::Normal;
+        self.head = if pos > line_start(text, pos) { prev_char(text, pos) } else { pos };
+        self.respond(text, None, None)
+    }
+
+    /// `.`: run the last change again, retyping its inserted text.
+    fn repeat(&mut self, text: &str) -> Response {
+        let Some(change) = self.last_change.clone() else {
+            return self.respond(text, None, None);
+        };
+        let Parse::Done(parsed) = parse(&change.keys, false) else {
+            return self.respond(text, None, None);
+        };
+        self.replaying = true;
+        let mut response = self.execute(text, parsed, change.keys.clone());
+        if self.mode == Mode::Insert {
+            let mut new = response.text.take().unwrap_or_else(|| text.to_string());
+            let typed = change.inserted.unwrap_or_default();
+            new.insert_str(self.head, &typed);
+            response = self.leave_insert(&new, self.head + typed.len());
+            response.text = Some(new);
+        }
+        self.replaying = false;
+        response
+    }
+
+    /// Where `motion` goes from `pos`, and how an operator takes the text
+    /// it moves over; `None` if it cannot move.
+    fn motion(
+        &mut self,
+        text: &str,
+        pos: usize,
+        motion: Motion,
+        count: Option<usize>,
+        op: Option<Operator>,
+    ) -> Option<(usize, Span)> {
+        let n = count.unwrap_or(1);
+        let (line, col) = line_col(text, pos);
+        let last_line = text.matches('\n').count();
+        let target = match motion {
+            Motion::Left => {
+                let start = line_start(text, pos);
+                ((0..n).fold(pos, |p, _| if p > start { prev_char(text, p) } else { p }), Span::Exclusive)
+            }
+            Motion::Right => {
+                let end = line_end(text, pos);
+                ((0..n).fold(pos, |p, _| if p < end { next_char(text, p) } else { p }), Span::Exclusive)
+            }
+            Motion::Up | Motion::Down => {
+                let target = if motion == Motion::Up { line.checked_sub(n)? } else { line.saturating_add(n) };
+                if target > last_line {
+                    return None;
+                }
+                // Keep aiming for the column the first `j` / `k` started from.
+                let col = if op.is_some() { col } else { *self.goal.get_or_insert(col) };
+                (offset_at(text, target, col), Span::Linewise)
+            }
+            Motion::WordNext => {
+                let mut p = pos;
+                for step in 0..n {
+                    let next = word_next(text, p);
+                    let end = line_end(text, p);
+                    // An operator stops at the end of the last word's line
+                    // (`dw` on the last word keeps the line break).
+                    p = if op.is_some() && step == n - 1 && next > end && end > p { end } else { next };
+                }
+                (p, Span::Exclusive)
+            }
+            Motion::WordPrev => ((0..n).fold(pos, |p, _| word_prev(text, p)), Span::Exclusive),
+            Motion::WordEnd => ((0..n).fold(pos, |p, _| word_end(text, p)), Span::Inclusive),
+            Motion::LineStart => (line_start(text, pos), Span::Exclusive),
+            Motion::FirstNonBlank => (first_non_blank(text, pos), Span::Exclusive),
+            Motion::LineEnd => (offset_at(text, (line.saturating_add(n) - 1).min(last_line), usize::MAX), Span::Exclusive),
+            Motion::FileStart | Motion::FileEnd => {
+                let default = if motion == Motion::FileStart { 0 } else { last_line };
+                let target = count.map_or(default, |c| c.saturating_sub(1).min(last_line));
+                (first_non_blank(text, offset_at(text, target, 0)), Span::Linewise)
+            }
+            Motion::MatchBracket => (match_bracket(text, pos)?, Span::Inclusive),
+            Motion::Find { kind, ch } => {
+                self.last_find = Some((kind, ch));
+                find_char(text, pos, pos, kind, ch, n)?
+            }
+            Motion::RepeatFind { reverse } => {
+                let (kind, ch) = self.last_find?;
+                let kind = if reverse { flip(kind) } else { kind };
+                // Repeating `t` / `T` looks past the character it stopped at.
+                let from = match kind {
+                    't' => next_char(text, pos),
+                    'T' => prev_char(text, pos),
+                    _ => pos,
+                };
+                find_char(text, pos, from, kind, ch, n)?
+            }
+            Motion::SearchNext { reverse } => {
+                let pattern = self.last_search.clone()?;
+                match search(text, pos, &pattern, reverse) {
+                    Some(found) => (found, Span::Exclusive),
+                    None => {
+                        self.message = Some(format!("Pattern not found: {pattern}"));
+                        return None;
+                    }
+                }
+            }
+        };
+        Some(target)
+    }
+
+    fn operate(
+        &mut self,
+        text: &str,
+        op: Operator,
+        motion: Option<Motion>,
+        count: Option<usize>,
+        register: Option<char>,
+    ) -> Option<String> {
+        let pos = self.head;
+        let (a, b) = (self.anchor.min(pos), self.anchor.max(pos));
+        let range = match (self.mode, motion) {
+            (Mode::VisualLine, _) => Some(line_span(text, a, b)),
+            (Mode::Visual, _) => Some((a, next_char(text, b), false)),
+            (_, Some(motion)) => {
+                // `cw` on a word changes to its end, like `ce`.
+                let on_word = text[pos..].chars().next().is_some_and(|c| !c.is_whitespace());
+                let motion = if op == Operator::Change && motion == Motion::WordNext && on_word {
+                    Motion::WordEnd
+                } else {
+                    motion
+                };
+                self.motion(text, pos, motion, count, Some(op)).map(|(target, span)| {
+                    let (from, to) = (pos.min(target), pos.max(target));
+                    match span {
+                        Span::Exclusive => (from, to, false),
+                        Span::Inclusive => (from, next_char(text, to), false),
+                        Span::Linewise => line_span(text, from, to),
+                    }
+                })
+            }
+            (_, None) => {
+                let (line, _) = line_col(text, pos);
+                let last = (line.saturating_add(count.unwrap_or(1)) - 1).min(text.matches('\n').count());
+                Some(line_span(text, pos, offset_at(text, last, 0)))
+            }
+        };
+        self.mode = Mode::Normal;
+        let (start, end, linewise) = range?;
+        let content = &text[start..end];
+        match op {
+            Operator::Yank => {
+                self.store(register, content, linewise, true);
+                if !linewise {
+                    self.head = start;
+                }
+                self.head = clamp_cursor(text, self.head);
+                None
+            }
+            Operator::Delete | Operator::Change => {
+                self.store(register, content, linewise, false);
+                let mut start = start;
+                let mut replacement = String::new();
+                if linewise && op == Operator::Change {
+                    replacement.push_str(&text[start..first_non_blank(text, start)]);
+                    if content.ends_with('\n') {
+                        replacement.push('\n');
+                    }
+                } else if linewise && end == text.len() && !content.ends_with('\n') && start > 0 {
+                    // Deleting the last line also takes the line break before it.
+                    start -= 1;
+                }
+                let mut new = String::with_capacity(text.len());
+                new.push_str(&text[..start]);
+                new.push_str(&replacement);
+                new.push_str(&text[end..]);
+                self.head = if op == Operator::Change {
+                    self.mode = Mode::Insert;
+                    start + replacement.trim_end_matches('\n').len()
+                } else if linewise {
+                    first_non_blank(&new, start.min(new.len()))
+                } else {
+                    clamp_cursor(&new, start)
+                };
+                Some(new)
+            }
+            Operator::Indent | Operator::Outdent => {
+                let last = if end > start { prev_char(text, end) } else { start };
+                let (new, _) = if op == Operator::Indent {
+                    indent_block(text, start, last)
+                } else {
+                    outdent_block(text, start, last)
+                };
+                self.head = first_non_blank(&new, start.min(new.len()));
+                Some(new)
+            }
+        }
+    }
+
+    /// Put deleted or yanked text in the unnamed register, the named one if
+    /// given (upper case appends) and, for a yank, register `0`.
+    fn store(&mut self, register: Option<char>, content: &str, linewise: bool, yank: bool) {
+        let mut text = content.to_string();
+        if linewise && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let mut stored = Register { text, linewise };
+        if let Some(name) = register.filter(|c| *c != '"') {
+            if name.is_ascii_uppercase() {
+                let target = self.registers.entry(name.to_ascii_lowercase()).or_default();
+                target.text.push_str(&stored.text);
+                target.linewise |= linewise;
+                stored = target.clone();
+            } else {
+                self.registers.insert(name, stored.clone());
+            }
+        }
+        if yank {
+            self.registers.insert('0', stored.clone());
+        }
+        self.registers.insert('"', stored);
+    }
+
+    fn paste(&mut self, text: &str, before: bool, count: usize, register: Option<char>) -> Option<String> {
+        let name = register.map_or('"', |c| c.to_ascii_lowercase());
+        let register = self.registers.get(&name)?.clone();
+        if register.text.len().checked_mul(count).is_none_or(|len| len > MAX_PASTE) {
+            self.message = Some("Paste too large".to_string());
+            return None;
+        }
+        let content = register.text.repeat(count);
+        let pos = self.head;
+        let (at, insert) = if register.linewise {
+            let end = line_end(text, pos);
+            if before {
+                (line_start(text, pos), content)
+            } else if end < text.len() {
+                (end + 1, content)
+            } else {
+                (end, format!("\n{}", content.strip_suffix('\n').unwrap_or(&content)))
+            }
+        } else if before || pos == line_end(text, pos) {
+            (pos, content)
+        } else {
+            (next_char(text, pos), content)
+        };
+        let mut new = text.to_string();
+        new.insert_str(at, &insert);
+        self.head = if register.linewise {
+            first_non_blank(&new, at + usize::from(insert.starts_with('\n')))
+        } else {
+            prev_char(&new, at + insert.len()).max(at)
+        };
+        Some(new)
+    }
+
+    fn insert(&mut self, text: &str, at: InsertAt) -> Option<String> {
+        let pos = self.head;
+        let (start, end) = (line_start(text, pos), line_end(text, pos));
+        let indent = &text[start..first_non_blank(text, start)];
+        self.mode = Mode::Insert;
+        let (new, cursor) = match at {
+            InsertAt::Cursor => (None, pos),
+            InsertAt::After => (None, if pos < end { next_char(text, pos) } else { pos }),
+            InsertAt::LineStart => (None, first_non_blank(text, pos)),
+            InsertAt::LineEnd => (None, end),
+            InsertAt::Below => {
+                let mut new = text.to_string();
+                new.insert_str(end, &format!("\n{indent}"));
+                (Some(new), end + 1 + indent.len())
+            }
+            InsertAt::Above => {
+                let mut new = text.to_string();
+                new.insert_str(start, &format!("{indent}\n"));
+                (Some(new), start + indent.len())
+            }
+        };
+        self.head = cursor;
+        new
+    }
+
+    fn command_line_key(&mut self, text: &str, mut line: String, key: &str) -> Response {
+        match key {
+            "Escape" => {}
+            "Enter" => return self.run_command_line(text, &line),
+            "Backspace" => {
+                line.pop();
+                if !line.is_empty() {
+                    self.cmdline = Some(line);
+                }
+            }
+            _ if key.chars().count() == 1 => {
+                line.push_str(key);
+                self.cmdline = Some(line);
+            }
+            _ => self.cmdline = Some(line),
+        }
+        self.respond(text, None, None)
+    }
+
+    fn run_command_line(&mut self, text: &str, line: &str) -> Response {
+        if let Some(pattern) = line.strip_prefix('/') {
+            if !pattern.is_empty() {
+                self.last_search = Some(pattern.to_string());
+            }
+            let motion = Motion::SearchNext { reverse: false };
+            if let Some((target, _)) = self.motion(text, self.head, motion, None, None) {
+                self.head = clamp_cursor(text, target);
+            }
+            return self.respond(text, None, None);
+        }
+        let command = line.strip_prefix(':').unwrap_or(line).trim();
+        match command {
+            "w" | "write" | "wq" | "x" => return self.respond(text, None, Some(Command::Save)),
+            "" => {}
+            _ => match command.parse::<usize>() {
+                Ok(number) => {
+                    let last = text.matches('\n').count();
+                    self.mode = Mode::Normal;
+                    self.head = first_non_blank(text, offset_at(text, number.saturating_sub(1).min(last), 0));
+                }
+                Err(_) => self.message = Some(format!("Not an editor command: {command}")),
+            },
+        }
+        self.respond(text, None, None)
+    }
+}
+
+/// Keys Vim takes in normal and visual modes; the rest (modifiers, function
+/// keys, page scrolling) stay with the browser.
+fn is_vim_key(key: &str) -> bool {
+    key.chars().count() == 1
+        || matches!(
+            key,
+            "Escape"
+                | "Enter"
+                | "Backspace"
+                | "Delete"
+                | "Tab"
+                | "ArrowLeft"
+                | "ArrowRight"
+                | "ArrowUp"
+                | "ArrowDown"
+                | "Home"
+                | "End"
+                | "<C-r>"
+        )
+}
+
+fn single(key: &str) -> Option<char> {
+    let mut chars = key.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+fn operator(key: &str) -> Option<Operator> {
+    match key {
+        "d" => Some(Operator::Delete),
+        "c" => Some(Operator::Change),
+        "y" => Some(Operator::Yank),
+        ">" => Some(Operator::Indent),
+        "<" => Some(Operator::Outdent),
+        _ => None,
+    }
+}
+
+/// The largest count a command takes; longer ones are cut down to it.
+const MAX_COUNT: usize = 99_999;
+
+/// The most bytes one paste inserts.
+const MAX_PASTE: usize = 1 << 24;
+
+/// A count prefix (not starting with `0`, which is a motion), at most
+/// [`MAX_COUNT`].
+fn take_count(keys: &[String]) -> (Option<usize>, &[String]) {
+    let mut count: Option<usize> = None;
+    let mut used = 0;
+    while let Some(digit) = keys.get(used).and_then(|k| single(k)).and_then(|c| c.to_digit(10)) {
+        if digit == 0 && count.is_none() {
+            break;
+        }
+        count = Some((count.unwrap_or(0) * 10 + digit as usize).min(MAX_COUNT));
+        used += 1;
+    }
+    (count, &keys[used..])
+}
+
+fn parse(keys: &[String], visual: bool) -> Parse<Parsed> {
+    let mut rest = keys;
+    let mut register = None;
+    if rest.first().is_some_and(|k| k == "\"") {
+        match rest.get(1).map(|k| single(k)) {
+            None => return Parse::Incomplete,
+            Some(Some(c)) if c.is_ascii_alphanumeric() || c == '"' => {
+                register = Some(c);
+                rest = &rest[2..];
+            }
+            Some(_) => return Parse::Invalid,
+        }
+    }
+    let (count, rest) = take_count(rest);
+    let Some(first) = rest.first() else {
+        return Parse::Incomplete;
+    };
+    let done = |count, cmd| Parse::Done(Parsed { register, count, cmd });
+
+    if let Some(op) = operator(first) {
+        if visual {
+            return if rest.len() == 1 { done(count, Cmd::Operate(op, None)) } else { Parse::Invalid };
+        }
+        let (inner, tail) = take_count(&rest[1..]);
+        let count = match (count, inner) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(1).saturating_mul(b.unwrap_or(1)).min(MAX_COUNT)),
+        };
+        let Some(next) = tail.first() else {
+            return Parse::Incomplete;
+        };
+        if next == first {
+            return if tail.len() == 1 { done(count, Cmd::Operate(op, None)) } else { Parse::Invalid };
+        }
+        return match parse_motion(tail) {
+            Parse::Done((motion, used)) if used == tail.len() => done(count, Cmd::Operate(op, Some(motion))),
+            Parse::Incomplete => Parse::Incomplete,
+            _ => Parse::Invalid,
+        };
+    }
+
+    match parse_motion(rest) {
+        Parse::Done((motion, used)) if used == rest.len() => return done(count, Cmd::Move(motion)),
+        Parse::Incomplete => return Parse::Incomplete,
+        _ => {}
+    }
+    if rest.len() > 1 {
+        return Parse::Invalid;
+    }
+    let cmd = match (first.as_str(), visual) {
+        ("Escape", _) => Cmd::Escape,
+        ("v", _) => Cmd::Visual { linewise: false },
+        ("V", _) => Cmd::Visual { linewise: true },
+        ("/", _) => Cmd::CommandLine('/'),
+        (":", _) => Cmd::CommandLine(':'),
+        ("o", true) => Cmd::SwapEnds,
+        ("x" | "X" | "Delete", true) => Cmd::Operate(Operator::Delete, None),
+        ("i", false) => Cmd::Insert(InsertAt::Cursor),
+        ("a", false) => Cmd::Insert(InsertAt::After),
+        ("I", false) => Cmd::Insert(InsertAt::LineStart),
+        ("A", false) => Cmd::Insert(InsertAt::LineEnd),
+        ("o", false) => Cmd::Insert(InsertAt::Below),
+        ("O", false) => Cmd::Insert(InsertAt::Above),
+        ("x" | "Delete", false) => Cmd::Operate(Operator::Delete, Some(Motion::Right)),
+        ("X", false) => Cmd::Operate(Operator::Delete, Some(Motion::Left)),
+        ("s", false) => Cmd::Operate(Operator::Change, Some(Motion::Right)),
+        ("D", false) => Cmd::Operate(Operator::Delete, Some(Motion::LineEnd)),
+        ("C", false) => Cmd::Operate(Operator::Change, Some(Motion::LineEnd)),
+        ("Y", false) => Cmd::Operate(Operator::Yank, None),
+        ("p", false) => Cmd::Paste { before: false },
+        ("P", false) => Cmd::Paste { before: true },
+        ("u", false) => Cmd::Undo,
+        ("<C-r>", false) => Cmd::Redo,
+        (".", false) => Cmd::Repeat,
+        _ => return Parse::Invalid,
+    };
+    done(count, cmd)
+}
+
+/// A motion at the start of `keys`, with the number of keys it used.
+fn parse_motion(keys: &[String]) -> Parse<(Motion, usize)> {
+    let Some(key) = keys.first() else {
+        return Parse::Incomplete;
+    };
+    let motion = match key.as_str() {
+        "h" | "ArrowLeft" | "Backspace" => Motion::Left,
+        "l" | "ArrowRight" | " " => Motion::Right,
+        "j" | "ArrowDown" | "Enter" => Motion::Down,
+        "k" | "ArrowUp" => Motion::Up,
+        "w" => Motion::WordNext,
+        "b" => Motion::WordPrev,
+        "e" => Motion::WordEnd,
+        "0" | "Home" => Motion::LineStart,
+        "^" => Motion::FirstNonBlank,
+        "$" | "End" => Motion::LineEnd,
+        "G" => Motion::FileEnd,
+        "%" => Motion::MatchBracket,
+        ";" => Motion::RepeatFind { reverse: false },
+        "," => Motion::RepeatFind { reverse: true },
+        "n" => Motion::SearchNext { reverse: false },
+        "N" => Motion::SearchNext { reverse: true },
+        "g" => {
+            return match keys.get(1).map(String::as_str) {
+                None => Parse::Incomplete,
+                Some("g") => Parse::Done((Motion::FileStart, 2)),
+                Some(_) => Parse::Invalid,
+            }
+        }
+        "f" | "t" | "F" | "T" => {
+            let kind = key.chars().next().unwrap_or('f');
+            return match keys.get(1).map(|k| single(k)) {
+                None => Parse::Incomplete,
+                Some(Some(ch)) => Parse::Done((Motion::Find { kind, ch }, 2)),
+                Some(None) => Parse::Invalid,
+            };
+        }
+        _ => return Parse::Invalid,
+    };
+    Parse::Done((motion, 1))
+}
+
+fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+fn line_end(text: &str, pos: usize) -> usize {
+    text[pos..].find('\n').map_or(text.len(), |i| pos + i)
+}
+
+fn next_char(text: &str, pos: usize) -> usize {
+    text[pos..].chars().next().map_or(pos, |c| pos + c.len_utf8())
+}
+
+fn prev_char(text: &str, pos: usize) -> usize {
+    text[..pos].char_indices().next_back().map_or(0, |(i, _)| i)
+}
+
+fn first_non_blank(text: &str, pos: usize) -> usize {
+    let start = line_start(text, pos);
+    let line = &text[start..line_end(text, pos)];
+    start + (line.len() - line.trim_start_matches([' ', '\t']).len())
+}
+
+/// The normal-mode cursor sits on a character, never on a line break
+/// (unless the line is empty) or inside a multi-byte character.
+fn clamp_cursor(text: &str, pos: usize) -> usize {
+    let mut pos = pos.min(text.len());
+    while !text.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    if pos == line_end(text, pos) && pos > line_start(text, pos) {
+        prev_char(text, pos)
+    } else {
+        pos
+    }
+}
+
+/// Whole lines from the one holding `from` to the one holding `to`,
+/// including the last line break.
+fn line_span(text: &str, from: usize, to: usize) -> (usize, usize, bool) {
+    let end = line_end(text, to);
+    (line_start(text, from), if end < text.len() { end + 1 } else { end }, true)
+}
+
+/// Word characters, other non-blanks and blanks, as Vim's `w` tells words.
+fn class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        2
+    } else {
+        1
+    }
+}
+
+fn word_next(text: &str, pos: usize) -> usize {
+    let mut chars = text[pos..].char_indices().map(|(i, c)| (pos + i, c)).peekable();
+    let Some(&(_, first)) = chars.peek() else {
+        return text.len();
+    };
+    let start_class = class(first);
+    if start_class != 0 {
+        while chars.next_if(|&(_, c)| class(c) == start_class).is_some() {}
+    }
+    // Skip blanks; an empty line counts as a word.
+    let mut after_break = false;
+    for (i, c) in chars {
+        match c {
+            '\n' if after_break => return i,
+            '\n' => after_break = true,
+            c if c.is_whitespace() => after_break = false,
+            _ => return i,
+        }
+    }
+    text.len()
+}
+
+fn word_prev(text: &str, pos: usize) -> usize {
+    let chars: Vec<(usize, char)> = text[..pos].char_indices().collect();
+    let mut i = chars.len();
+    while i > 0 && chars[i - 1].1.is_whitespace() {
+        if chars[i - 1].1 == '\n' && i >= 2 && chars[i - 2].1 == '\n' {
+            return chars[i - 1].0;
+        }
+        i -= 1;
+    }
+    if i == 0 {
+        return 0;
+    }
+    let word_class = class(chars[i - 1].1);
+    while i > 0 && class(chars[i - 1].1) == word_class {
+        i -= 1;
+    }
+    chars[i].0
+}
+
+fn word_end(text: &str, pos: usize) -> usize {
+    let chars: Vec<(usize, char)> = text[pos..].char_indices().map(|(i, c)| (pos + i, c)).collect();
+    let mut i = 1;
+    while i < chars.len() && chars[i].1.is_whitespace() {
+        i += 1;
+    }
+    if i >= chars.len() {
+        return chars.last().map_or(pos, |c| c.0);
+    }
+    let word_class = class(chars[i].1);
+    while i + 1 < chars.len() && class(chars[i + 1].1) == word_class {
+        i += 1;
+    }
+    chars[i].0
+}
+
+/// `%`: the bracket matching the first one at or after `pos` on its line.
+fn match_bracket(text: &str, pos: usize) -> Option<usize> {
+    const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+    let (offset, bracket) = text[pos..line_end(text, pos)]
+        .char_indices()
+        .find(|(_, c)| PAIRS.iter().any(|&(o, c2)| *c == o || *c == c2))?;
+    let at = pos + offset;
+    let mut depth = 0;
+    if let Some(&(open, close)) = PAIRS.iter().find(|(o, _)| *o == bracket) {
+        for (i, c) in text[at..].char_indices() {
+            depth += i32::from(c == open) - i32::from(c == close);
+            if depth == 0 {
+                return Some(at + i);
+            }
+        }
+    } else {
+        let &(open, close) = PAIRS.iter().find(|(_, c)| *c == bracket)?;
+        for (i, c) in text[..=at].char_indices().rev() {
+            depth += i32::from(c == close) - i32::from(c == open);
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+fn flip(kind: char) -> char {
+    match kind {
+        'f' => 'F',
+        'F' => 'f',
+        't' => 'T',
+        _ => 't',
+    }
+}
+
+/// `f` / `t` / `F` / `T`: the `n`-th `ch` on the line, searched from
+/// `from`; `t` and `T` stop next to it.
+fn find_char(text: &str, pos: usize, from: usize, kind: char, ch: char, n: usize) -> Option<(usize, Span)> {
+    let (start, end) = (line_start(text, pos), line_end(text, pos));
+    if matches!(kind, 'f' | 't') {
+        let after = next_char(text, from).min(end);
+        let (i, _) = text[after..end].match_indices(ch).nth(n - 1)?;
+        let hit = after + i;
+        Some((if kind == 't' { prev_char(text, hit) } else { hit }, Span::Inclusive))
+    } else {
+        let (hit, _) = text[start..from.max(start)].rmatch_indices(ch).nth(n - 1)?;
+        let hit = start + hit;
+        Some((if kind == 'T' { next_char(text, hit) } else { hit }, Span::Exclusive))
+    }
+}
+
+/// Start of the next (or previous) match of `pattern`, wrapping around.
+fn search(text: &str, pos: usize, pattern: &str, backward: bool) -> Option<usize> {
+    let starts: Vec<usize> = find_matches(text, pattern).into_iter().map(|(s, _)| s).collect();
+    if backward {
+        starts.iter().rev().find(|&&s| s < pos).or(starts.last()).copied()
+    } else {
+        starts.iter().find(|&&s| s > pos).or(starts.first()).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A textarea stand-in: keys Vim passes on are typed into the text.
+    struct Buffer {
+        text: String,
+        selection: Range<usize>,
+        vim: Vim,
+        commands: Vec<Command>,
+    }
+
+    impl Buffer {
+        fn new(text: &str, cursor: usize) -> Self {
+            Self { text: text.to_string(), selection: cursor..cursor, vim: Vim::default(), commands: Vec::new() }
+        }
+
+        /// Press keys; `<Esc>`, `<CR>` and `<C-r>` name the special ones.
+        fn press(&mut self, keys: &str) -> &mut Self {
+            let mut rest = keys;
+            while let Some(c) = rest.chars().next() {
+                let (key, len) = match ["<Esc>", "<CR>", "<C-r>"].iter().find(|k| rest.starts_with(**k)) {
+                    Some(&"<Esc>") => ("Escape".to_string(), 5),
+                    Some(&"<CR>") => ("Enter".to_string(), 4),
+                    Some(k) => (k.to_string(), k.len()),
+                    None => (c.to_string(), c.len_utf8()),
+                };
+                rest = &rest[len..];
+                match self.vim.key(&self.text, self.selection.clone(), &key) {
+                    Some(response) => {
+                        if let Some(text) = response.text {
+                            self.text = text;
+                        }
+                        self.selection = response.selection;
+                        self.commands.extend(response.command);
+                    }
+                    None => {
+                        let at = self.selection.start;
+                        self.text.replace_range(self.selection.clone(), &key);
+                        self.selection = at + key.len()..at + key.len();
+                    }
+                }
+            }
+            self
+        }
+
+        fn cursor(&self) -> usize {
+            self.selection.start
+        }
+    }
+
+    #[test]
+    fn motions_move_the_cursor() {
+        let text = "#let area(w, h) = w * h\n\nfoo.bar baz";
+        let mut b = Buffer::new(text, 0);
+        assert_eq!(b.press("w").cursor(), 1);
+        assert_eq!(b.press("2w").cursor(), 9);
+        assert_eq!(b.press("e").cursor(), 10);
+        assert_eq!(b.press("b").cursor(), 9);
+        assert_eq!(b.press("%").cursor(), 14);
+        assert_eq!(b.press("%").cursor(), 9);
+        assert_eq!(b.press("fw").cursor(), 10);
+        assert_eq!(b.press("f=").cursor(), 16);
+        assert_eq!(b.press("th").cursor(), 21);
+        assert_eq!(b.press("0").cursor(), 0);
+        assert_eq!(b.press("$").cursor(), 22);
+        assert_eq!(b.press("Fw").cursor(), 18);
+        assert_eq!(b.press(",").cursor(), 18);
+        // `w` stops on the empty line, `j` / `k` keep the column.
+        assert_eq!(b.press("w").cursor(), 20);
+        assert_eq!(b.press("$w").cursor(), 24);
+        assert_eq!(b.press("w").cursor(), 25);
+        assert_eq!(b.press("G").cursor(), 25);
+        assert_eq!(b.press("gg").cursor(), 0);
+        assert_eq!(b.press("3G").cursor(), 25);
+        assert_eq!(b.press("ll").press("k").cursor(), 24);
+        assert_eq!(b.press("j").cursor(), 27);
+        assert_eq!(b.press("/baz<CR>").cursor(), 33);
+        assert_eq!(b.press("/w<CR>").cursor(), 10);
+        assert_eq!(b.press("n").cursor(), 18);
+        assert_eq!(b.press("N").cursor(), 10);
+    }
+
+    #[test]
+    fn operators_take_motions_counts_and_lines() {
+        let mut b = Buffer::new("one two three four\n", 0);
+        assert_eq!(b.press("dw").text, "two three four\n");
+        assert_eq!(b.press("2dw").text, "four\n");
+        assert_eq!(b.press("cwfive<Esc>").text, "five\n");
+        assert_eq!(b.cursor(), 3);
+        assert_eq!(b.press("0d$").text, "\n");
+
+        let mut b = Buffer::new("a\nb\nc\nd", 0);
+        assert_eq!(b.press("2dd").text, "c\nd");
+        assert_eq!(b.press("p").text, "c\na\nb\nd");
+        assert_eq!(b.cursor(), 2);
+        assert_eq!(b.press("G").press("dd").text, "c\na\nb");
+        assert_eq!(b.press("ggP").text, "d\nc\na\nb");
+        assert_eq!(b.press(">j").text, "  d\n  c\na\nb");
+        assert_eq!(b.press("<<").text, "d\n  c\na\nb");
+        assert_eq!(b.press("j3x").text, "d\n\na\nb");
+        assert_eq!(b.press("jccx<Esc>").text, "d\n\nx\nb");
+
+        let mut b = Buffer::new("alpha beta\n", 0);
+        assert_eq!(b.press("yeP").text, "alphaalpha beta\n");
+        assert_eq!(b.press("$Xp").text, "alphaalpha beat\n");
+    }
+
+    #[test]
+    fn registers_visual_mode_and_repeat() {
+        let mut b = Buffer::new("x = 1\ny = 2\nz = 3", 0);
+        b.press("\"ayy").press("j\"byy").press("G\"ap\"bp");
+        assert_eq!(b.text, "x = 1\ny = 2\nz = 3\nx = 1\ny = 2");
+        b.press("gg\"Ayyj\"aP");
+        assert_eq!(b.text, "x = 1\nx = 1\nx = 1\ny = 2\nz = 3\nx = 1\ny = 2");
+
+        let mut b = Buffer::new("one two three\n", 0);
+        assert_eq!(b.press("vld").text, "e two three\n");
+        assert_eq!(b.press("wve\"cy").text, "e two three\n");
+        assert_eq!(b.press("Vd").text, "");
+        assert_eq!(b.press("\"cP").text, "two");
+
+        let mut b = Buffer::new("a b c d\n", 0);
+        assert_eq!(b.press("dw..").text, "d\n");
+        let mut b = Buffer::new("- a\n- b\n", 0);
+        assert_eq!(b.press("A!<Esc>j.").text, "- a!\n- b!\n");
+        assert_eq!(b.press("k>>j.").text, "  - a!\n  - b!\n");
+    }
+
+    #[test]
+    fn commands_modes_and_status() {
+        let mut b = Buffer::new("text\nmore\n", 0);
+        b.press(":w<CR>");
+        assert_eq!(b.commands, vec![Command::Save]);
+        b.press("u<C-r>");
+        assert_eq!(b.commands, vec![Command::Save, Command::Undo, Command::Redo]);
+        assert_eq!(b.press(":2<CR>").cursor(), 5);
+        b.press(":q<CR>");
+        assert_eq!(b.vim.status(), "Not an editor command: q");
+        b.press("/zzz<CR>");
+        assert_eq!(b.vim.status(), "Pattern not found: zzz");
+        b.press("2d");
+        assert_eq!(b.vim.status(), "-- NORMAL -- 2d");
+        b.press("<Esc>i");
+        assert_eq!(b.vim.mode, Mode::Insert);
+        // Insert mode leaves keys to the textarea.
+        assert_eq!(b.press("ab<Esc>").text, "text\nabmore\n");
+        assert_eq!(b.cursor(), 6);
+        assert_eq!(b.vim.status(), "-- NORMAL --");
+    }
+
+    #[test]
+    fn huge_counts_are_capped() {
+        let nines = "9".repeat(20);
+        let mut b = Buffer::new("x\ny\n", 0);
+        b.press("yy").press(&format!("{nines}p"));
+        assert_eq!(b.text.matches("x\n").count(), MAX_COUNT + 1);
+        let mut b = Buffer::new("one\ntwo", 0);
+        assert_eq!(b.press(&format!("{nines}j")).cursor(), 0);
+        assert_eq!(b.press(&format!("{nines}$")).cursor(), 6);
+        assert_eq!(b.press(&format!("gg{nines}d{nines}d")).text, "");
+        // A paste past the size limit is refused.
+        let mut b = Buffer::new(&"a".repeat(1000), 0);
+        b.press("y$").press(&format!("{nines}P"));
+        assert_eq!(b.text.len(), 1000);
+        assert_eq!(b.vim.status(), "Paste too large");
+    }
+
+    #[test]
+    fn visual_mode_survives_the_text_changing() {
+        let mut b = Buffer::new("abc", 1);
+        b.press("v");
+        // Another file: offset 1 is inside the `é`.
+        b.text = "éx".to_string();
+        b.press("l");
+        assert_eq!(b.selection, 0..3);
+        b.text = String::new();
+        assert_eq!(b.press("d").text, "");
+    }
+}
@@ -0,0 +1,109 @@
+//! Soft-wrap geometry for the editor.
+//!
+//! With soft wrap on, a source line can take several visual rows. The
+//! editor font is monospace, so the rows follow from the column count alone.
+//! This mirrors `white-space: pre-wrap` with `overflow-wrap: anywhere` for
+//! Latin text: a row breaks before a word that would overflow it (words end
+//! at spaces and after a hyphen inside a word), spaces hang at the end of a
+//! row, and a word longer than a row is split. With `cols == usize::MAX` (no
+//! wrap) every line is one row.
+
+/// Char index at which each visual row of `line` starts (the first is 0).
+pub fn row_starts(line: &str, cols: usize) -> Vec<usize> {
+    let cols = cols.max(1);
+    let chars: Vec<char> = line.chars().collect();
+    let mut starts = vec![0];
+    let mut width = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let word_end = (i..chars.len())
+            .find(|&j| {
+                chars[j] == ' '
+                    || (j >= i + 2 && chars[j - 1] == '-' && chars[j - 2].is_alphanumeric() && !chars[j].is_ascii_digit())
+            })
+            .unwrap_or(chars.len());
+        let spaces_end = (word_end..chars.len()).find(|&j| chars[j] != ' ').unwrap_or(chars.len());
+        let mut word = word_end - i;
+        if width > 0 && width + word > cols {
+            starts.push(i);
+            width = 0;
+        }
+        // Split a word that is longer than the rest of the row.
+        let mut at = i;
+        while width + word > cols {
+            let take = cols - width;
+            at += take;
+            word -= take;
+            starts.push(at);
+            width = 0;
+        }
+        width += word + (spaces_end - word_end);
+        i = spaces_end;
+    }
+    starts
+}
+
+/// Visual `(row, column)` of byte `offset`, rows counted from the top of
+/// `text`.
+pub fn visual_position(text: &str, offset: usize, cols: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let rows_above: usize = text[..line_start].lines().map(|line| row_starts(line, cols).len()).sum();
+    let line = &text[line_start..];
+    let line = &line[..line.find('\n').unwrap_or(line.len())];
+    let col = before[line_start..].chars().count();
+    let starts = row_starts(line, cols);
+    let row = starts.partition_point(|&s| s <= col) - 1;
+    (rows_above + row, col - starts[row])
+}
+
+/// The logical `(line, column)` shown at visual `(row, column)`; a column
+/// past a row's end lands on its last character.
+pub fn logical_position(text: &str, row: usize, col: usize, cols: usize) -> (usize, usize) {
+    let mut remaining = row;
+    let mut last = (0, 0);
+    for (line_index, line) in text.split('\n').enumerate() {
+        let starts = row_starts(line, cols);
+        let len = line.chars().count();
+        if remaining < starts.len() {
+            let start = starts[remaining];
+            let end = starts.get(remaining + 1).map_or(len, |&next| next - 1);
+            return (line_index, (start + col).min(end.max(start)));
+        }
+        remaining -= starts.len();
+        last = (line_index, len);
+    }
+    last
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_break_between_words() {
+        assert_eq!(row_starts("", 10), vec![0]);
+        assert_eq!(row_starts("short line", 10), vec![0]);
+        assert_eq!(row_starts("one two three four", 10), vec![0, 8]);
+        assert_eq!(row_starts("one two three four", 9), vec![0, 8, 14]);
+        // Trailing spaces hang instead of starting a row.
+        assert_eq!(row_starts("one two       ", 7), vec![0]);
+        assert_eq!(row_starts("abcdefghijklmnopqrstuvwxy", 10), vec![0, 10, 20]);
+        assert_eq!(row_starts("ab abcdefghijkl", 10), vec![0, 3, 13]);
+        assert_eq!(row_starts("a well-known fact", 9), vec![0, 7, 13]);
+        assert_eq!(row_starts("page 10-12", 8), vec![0, 5]);
+        assert_eq!(row_starts("one two three four", usize::MAX), vec![0]);
+    }
+
+    #[test]
+    fn positions_map_both_ways() {
+        let text = "= Title\none two three four\nend";
+        let four = text.find("four").unwrap();
+        assert_eq!(visual_position(text, four, 9), (3, 0));
+        assert_eq!(visual_position(text, text.find("end").unwrap(), 9), (4, 0));
+        assert_eq!(visual_position(text, four, usize::MAX), (1, 14));
+        assert_eq!(logical_position(text, 3, 2, 9), (1, 16));
+        assert_eq!(logical_position(text, 2, 9, 9), (1, 13));
+        assert_eq!(logical_position(text, 9, 0, 9), (2, 3));
+    }
+}
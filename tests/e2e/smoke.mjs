@@ -8,6 +8,7 @@
 // Usage: BASE_URL=http://127.0.0.1:1420 node smoke.mjs
 
 import { chromium } from 'playwright';
+import { deflateRawSync } from 'node:zlib';
 
 const BASE = process.env.BASE_URL || 'http://127.0.0.1:1420';
 const results = [];
@@ -115,6 +116,61 @@ async function main() {
     } catch (e) {
       check('5. share-link #src= roundtrip', false, String(e).split('\n')[0]);
     }
+
+    // ---- Scenario 6: compressed multi-file #p= payload (v1 layout, upgraded on decode) ----
+    try {
+      const payload = {
+        version: 1,
+        files: [
+          { name: 'main.typ', content: '= Payload Main\n#include "part.typ"' },
+          { name: 'part.typ', content: 'Included part.' },
+        ],
+        bibliography: '',
+        settings: '',
+        inputs: {},
+      };
+      // Raw deflate + URL_SAFE_NO_PAD base64 (matches utils/share.rs).
+      const b64 = deflateRawSync(Buffer.from(JSON.stringify(payload), 'utf8'))
+        .toString('base64')
+        .replace(/\+/g, '-')
+        .replace(/\//g, '_')
+        .replace(/=+$/, '');
+      await page.goto(`${BASE}/#p=${b64}`);
+      await page.reload();
+      await waitForWasm(page);
+      await page.waitForTimeout(300);
+      const val = await page.locator('textarea.typst-editor').inputValue();
+      const tabs = await page.locator('text=part.typ').count();
+      check('6. share-link #p= multi-file payload', val.includes('Payload Main') && tabs > 0, `editor = "${val.slice(0, 30)}"`);
+    } catch (e) {
+      check('6. share-link #p= multi-file payload', false, String(e).split('\n')[0]);
+    }
+
+    // ---- Scenario 7: ?view=1 opens the read-only viewer ----
+    try {
+      const payload = {
+        version: 2,
+        files: [{ name: 'main.typ', content: '= Viewer Only' }],
+        bibliographies: [{ name: 'refs.bib', content: '' }],
+        settings: '',
+        inputs: {},
+      };
+      const b64 = deflateRawSync(Buffer.from(JSON.stringify(payload), 'utf8'))
+        .toString('base64')
+        .replace(/\+/g, '-')
+        .replace(/\//g, '_')
+        .replace(/=+$/, '');
+      await page.goto(`${BASE}/?view=1#p=${b64}`);
+      await waitForWasm(page);
+      await page.waitForSelector('.preview-page', { timeout: 30000 });
+      const editorVisible = await page.locator('textarea.typst-editor').isVisible();
+      const openBtn = await page.locator('text=Open in editor').count();
+      // The viewer keeps the fragment so reloads still show the shared document.
+      const keepsHash = page.url().includes('#p=');
+      check('7. ?view=1 read-only viewer', !editorVisible && openBtn > 0 && keepsHash, `editor visible = ${editorVisible}`);
+    } catch (e) {
+      check('7. ?view=1 read-only viewer', false, String(e).split('\n')[0]);
+    }
   } finally {
     await browser.close();
   }